@@ -1,5 +1,21 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 use std::process::Command;
 
+/// Sources holding the detection rules; cached audits are tied to their content
+const ANALYZER_SOURCES: &[&str] = &[
+    "src/security.rs",
+    "src/ast_security_analyzer.rs",
+    "src/taint_analyzer.rs",
+    "src/module_graph.rs",
+    "src/deobfuscation.rs",
+    "src/capabilities.rs",
+    "src/entropy.rs",
+    "src/artifacts.rs",
+    "src/registry_metadata.rs",
+    "src/provenance.rs",
+];
+
 fn main() {
     // Get git tag version
     let mut version_set = false;
@@ -52,6 +68,20 @@ fn main() {
         .to_string();
     println!("cargo:rustc-env=FNPM_BUILD_DATE={build_date}");
 
+    // Fingerprint the analyzers so cached audits are invalidated whenever
+    // the detection rules change
+    let mut hasher = DefaultHasher::new();
+    for source in ANALYZER_SOURCES {
+        println!("cargo:rerun-if-changed={source}");
+        if let Ok(content) = std::fs::read(source) {
+            content.hash(&mut hasher);
+        }
+    }
+    println!(
+        "cargo:rustc-env=FNPM_ANALYZER_FINGERPRINT={:016x}",
+        hasher.finish()
+    );
+
     // Re-run if git changes
     println!("cargo:rerun-if-changed=.git/HEAD");
     println!("cargo:rerun-if-changed=.git/refs/");
@@ -0,0 +1,768 @@
+// Known-vulnerability matching for resolved packages
+// `fnpm scan --advisories` downloads OSV's npm export (which carries the
+// GitHub advisory database) into the global cache once, then matches every
+// resolved name@version against it offline.
+
+use anyhow::{anyhow, Context, Result};
+use colored::*;
+use flate2::read::DeflateDecoder;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::tarball;
+
+pub const DEFAULT_ADVISORY_URL: &str =
+    "https://osv-vulnerabilities.storage.googleapis.com/npm/all.zip";
+
+/// Normalized bundle, relative to `global_cache_path`
+const BUNDLE_PATH: &str = "advisories/npm.json";
+
+/// The npm export is far larger than any package tarball
+const DOWNLOAD_TIMEOUT_SECS: u64 = 15 * 60;
+
+/// Largest bundle accepted; the export is a few hundred megabytes
+const MAX_DOWNLOAD_BYTES: u64 = 2 << 30;
+
+/// OSV bundle to download: `FNPM_ADVISORY_URL`, then OSV's npm export
+pub fn advisory_url() -> String {
+    std::env::var("FNPM_ADVISORY_URL")
+        .ok()
+        .filter(|url| !url.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_ADVISORY_URL.to_string())
+}
+
+/// Affected versions `introduced <= v < fixed` (or `<= last_affected`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AffectedRange {
+    pub introduced: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fixed: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_affected: Option<String>,
+}
+
+impl AffectedRange {
+    pub fn contains(&self, version: &Version) -> bool {
+        // OSV uses "0" for "every version before the fix"
+        let above = self.introduced == "0"
+            || Version::parse(&self.introduced).is_ok_and(|low| *version >= low);
+        let below = match (&self.fixed, &self.last_affected) {
+            (Some(fixed), _) => Version::parse(fixed).is_ok_and(|high| *version < high),
+            (None, Some(last)) => Version::parse(last).is_ok_and(|high| *version <= high),
+            (None, None) => true,
+        };
+        above && below
+    }
+}
+
+impl fmt::Display for AffectedRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut bounds = Vec::new();
+        if self.introduced != "0" {
+            bounds.push(format!(">={}", self.introduced));
+        }
+        if let Some(fixed) = &self.fixed {
+            bounds.push(format!("<{}", fixed));
+        } else if let Some(last) = &self.last_affected {
+            bounds.push(format!("<={}", last));
+        }
+        if bounds.is_empty() {
+            f.write_str("*")
+        } else {
+            f.write_str(&bounds.join(" "))
+        }
+    }
+}
+
+/// One OSV record, reduced to what matching and reporting need
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Advisory {
+    pub id: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub summary: String,
+    /// GitHub's rating (`LOW`..`CRITICAL`) when the record carries one
+    #[serde(default)]
+    pub severity: Option<String>,
+    #[serde(default)]
+    pub ranges: Vec<AffectedRange>,
+    /// Versions listed explicitly, for records without usable ranges
+    #[serde(default)]
+    pub versions: Vec<String>,
+}
+
+impl Advisory {
+    pub fn affects(&self, version: &str) -> bool {
+        if self.versions.iter().any(|v| v == version) {
+            return true;
+        }
+        Version::parse(version)
+            .is_ok_and(|parsed| self.ranges.iter().any(|range| range.contains(&parsed)))
+    }
+
+    /// Lowest fix for `version`: the fix of the range it falls in
+    fn fixed_in(&self, version: &str) -> Option<String> {
+        let parsed = Version::parse(version).ok()?;
+        self.ranges
+            .iter()
+            .filter(|range| range.contains(&parsed))
+            .filter_map(|range| range.fixed.as_deref())
+            .filter_map(|fixed| Version::parse(fixed).ok())
+            .min()
+            .map(|fixed| fixed.to_string())
+    }
+}
+
+/// A resolved package version covered by an advisory
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AdvisoryMatch {
+    pub package: String,
+    pub version: String,
+    pub id: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub summary: String,
+    #[serde(default)]
+    pub severity: Option<String>,
+    /// Affected ranges in npm syntax (`>=1.0.0 <1.2.3`)
+    #[serde(default)]
+    pub affected: Vec<String>,
+    #[serde(default)]
+    pub fixed_in: Option<String>,
+}
+
+impl AdvisoryMatch {
+    /// Advisory id with its CVE/GHSA aliases: `GHSA-xxxx (CVE-2021-1234)`
+    pub fn ids(&self) -> String {
+        if self.aliases.is_empty() {
+            self.id.clone()
+        } else {
+            format!("{} ({})", self.id, self.aliases.join(", "))
+        }
+    }
+}
+
+/// Offline copy of the npm advisories, keyed by package name
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AdvisoryDatabase {
+    pub fetched_at: String,
+    pub source: String,
+    pub packages: BTreeMap<String, Vec<Advisory>>,
+}
+
+impl AdvisoryDatabase {
+    pub fn path(cache_dir: &Path) -> PathBuf {
+        cache_dir.join(BUNDLE_PATH)
+    }
+
+    /// Bundle saved by a previous download, if any
+    pub fn load(cache_dir: &Path) -> Result<Option<Self>> {
+        let path = Self::path(cache_dir);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&path)?;
+        let database = serde_json::from_str(&content)
+            .with_context(|| format!("Corrupt advisory bundle at {}", path.display()))?;
+        Ok(Some(database))
+    }
+
+    pub fn save(&self, cache_dir: &Path) -> Result<()> {
+        let path = Self::path(cache_dir);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Download and normalize the OSV bundle at `url`
+    pub fn download(url: &str) -> Result<Self> {
+        let bytes = tarball::download(url, DOWNLOAD_TIMEOUT_SECS, MAX_DOWNLOAD_BYTES)
+            .with_context(|| format!("Could not download advisories from {}", url))?;
+        let mut database = Self::from_osv_zip(&bytes)?;
+        database.source = url.to_string();
+        Ok(database)
+    }
+
+    /// Cached bundle, downloading it on first use or when `refresh` is set
+    pub fn load_or_download(cache_dir: &Path, refresh: bool) -> Result<Self> {
+        if !refresh {
+            if let Some(database) = Self::load(cache_dir)? {
+                return Ok(database);
+            }
+        }
+
+        let url = advisory_url();
+        println!(
+            "{} Downloading advisories from {}...",
+            "📥".cyan(),
+            url.dimmed()
+        );
+        let database = Self::download(&url)?;
+        database.save(cache_dir)?;
+        println!(
+            "{} {} advisories for {} packages saved to {}",
+            "✅".green(),
+            database.len(),
+            database.packages.len(),
+            Self::path(cache_dir).display()
+        );
+        Ok(database)
+    }
+
+    /// Build the database from OSV's `all.zip`: one JSON record per entry
+    pub fn from_osv_zip(bytes: &[u8]) -> Result<Self> {
+        let mut database = Self {
+            fetched_at: chrono::Utc::now().to_rfc3339(),
+            ..Self::default()
+        };
+        for (name, content) in zip_entries(bytes)? {
+            if !name.ends_with(".json") {
+                continue;
+            }
+            // A malformed record shouldn't cost the rest of the bundle
+            if let Ok(record) = serde_json::from_slice::<Value>(&content) {
+                database.add_osv_record(&record);
+            }
+        }
+        Ok(database)
+    }
+
+    /// Index an OSV record under every npm package it affects
+    pub fn add_osv_record(&mut self, record: &Value) {
+        if record.get("withdrawn").is_some() {
+            return;
+        }
+        let text = |key: &str| record.get(key).and_then(|v| v.as_str()).unwrap_or("");
+        let id = text("id");
+        if id.is_empty() {
+            return;
+        }
+        let aliases: Vec<String> = record
+            .get("aliases")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|alias| alias.as_str().map(String::from))
+            .collect();
+        let summary = match text("summary") {
+            "" => text("details").lines().next().unwrap_or("").to_string(),
+            summary => summary.to_string(),
+        };
+        let severity = record
+            .pointer("/database_specific/severity")
+            .and_then(|v| v.as_str())
+            .map(String::from);
+
+        for affected in record
+            .get("affected")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+        {
+            let ecosystem = affected
+                .pointer("/package/ecosystem")
+                .and_then(|v| v.as_str());
+            let package = affected.pointer("/package/name").and_then(|v| v.as_str());
+            let (Some("npm"), Some(package)) = (ecosystem, package) else {
+                continue;
+            };
+
+            let advisory = Advisory {
+                id: id.to_string(),
+                aliases: aliases.clone(),
+                summary: summary.clone(),
+                severity: severity.clone(),
+                ranges: osv_ranges(affected),
+                versions: affected
+                    .get("versions")
+                    .and_then(|v| v.as_array())
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|v| v.as_str().map(String::from))
+                    .collect(),
+            };
+            if !advisory.ranges.is_empty() || !advisory.versions.is_empty() {
+                self.packages
+                    .entry(package.to_string())
+                    .or_default()
+                    .push(advisory);
+            }
+        }
+    }
+
+    /// Number of advisories across all packages
+    pub fn len(&self) -> usize {
+        self.packages.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.packages.is_empty()
+    }
+
+    /// Advisories covering `name@version`
+    pub fn check(&self, name: &str, version: &str) -> Vec<AdvisoryMatch> {
+        self.packages
+            .get(name)
+            .into_iter()
+            .flatten()
+            .filter(|advisory| advisory.affects(version))
+            .map(|advisory| AdvisoryMatch {
+                package: name.to_string(),
+                version: version.to_string(),
+                id: advisory.id.clone(),
+                aliases: advisory.aliases.clone(),
+                summary: advisory.summary.clone(),
+                severity: advisory.severity.clone(),
+                affected: advisory.ranges.iter().map(|r| r.to_string()).collect(),
+                fixed_in: advisory.fixed_in(version),
+            })
+            .collect()
+    }
+}
+
+/// SEMVER/ECOSYSTEM ranges of an OSV `affected` entry. Events come in order:
+/// each `introduced` opens a range, the next `fixed`/`last_affected` closes it.
+fn osv_ranges(affected: &Value) -> Vec<AffectedRange> {
+    let mut ranges = Vec::new();
+    for range in affected
+        .get("ranges")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+    {
+        let kind = range.get("type").and_then(|v| v.as_str());
+        if !matches!(kind, Some("SEMVER") | Some("ECOSYSTEM")) {
+            continue;
+        }
+        let mut open: Option<AffectedRange> = None;
+        for event in range
+            .get("events")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+        {
+            let field = |key: &str| event.get(key).and_then(|v| v.as_str()).map(String::from);
+            if let Some(introduced) = field("introduced") {
+                ranges.extend(open.take());
+                open = Some(AffectedRange {
+                    introduced,
+                    fixed: None,
+                    last_affected: None,
+                });
+            } else if let Some(mut current) = open.take() {
+                current.fixed = field("fixed");
+                current.last_affected = field("last_affected");
+                ranges.push(current);
+            }
+        }
+        ranges.extend(open);
+    }
+    ranges
+}
+
+/// A 16 or 32-bit zip field holding this says the value is in the ZIP64 records
+const ZIP64_SENTINEL: u64 = 0xFFFF_FFFF;
+
+/// Most bytes reserved for an entry before decompressing it; the sizes an
+/// archive declares are not trusted with an allocation
+const MAX_ENTRY_PREALLOCATION: usize = 1 << 20;
+
+/// Entries of a zip archive as `(name, content)`. Handles the stored and
+/// deflate methods and the ZIP64 records OSV exports use.
+fn zip_entries(bytes: &[u8]) -> Result<Vec<(String, Vec<u8>)>> {
+    const EOCD_SIGNATURE: u64 = 0x0605_4b50;
+    const ZIP64_EOCD_SIGNATURE: u64 = 0x0606_4b50;
+    const ZIP64_LOCATOR_SIGNATURE: u64 = 0x0706_4b50;
+    const CENTRAL_SIGNATURE: u64 = 0x0201_4b50;
+    const LOCAL_SIGNATURE: u64 = 0x0403_4b50;
+
+    let field = |offset: usize, len: usize| -> Result<u64> {
+        bytes
+            .get(offset..offset.saturating_add(len))
+            .map(|b| {
+                b.iter()
+                    .rev()
+                    .fold(0, |value, byte| value << 8 | *byte as u64)
+            })
+            .ok_or_else(|| anyhow!("Truncated zip archive"))
+    };
+    let u16_at = |offset: usize| field(offset, 2).map(|value| value as usize);
+    let u32_at = |offset: usize| field(offset, 4);
+    let u64_at = |offset: usize| field(offset, 8);
+    let position =
+        |value: u64| usize::try_from(value).map_err(|_| anyhow!("Zip archive too large"));
+
+    // The end-of-central-directory record sits in the last 64 KiB + 22 bytes
+    let search_from = bytes.len().saturating_sub(22 + u16::MAX as usize);
+    let eocd = (search_from..bytes.len().saturating_sub(21))
+        .rev()
+        .find(|&offset| u32_at(offset).ok() == Some(EOCD_SIGNATURE))
+        .ok_or_else(|| anyhow!("Not a zip archive"))?;
+    let mut count = u16_at(eocd + 10)? as u64;
+    let mut offset = u32_at(eocd + 16)?;
+    if count == u16::MAX as u64 || offset == ZIP64_SENTINEL {
+        // Too many entries or too large for the classic record: the real
+        // values are in the ZIP64 record the locator before it points to
+        let locator = eocd
+            .checked_sub(20)
+            .filter(|&at| u32_at(at).ok() == Some(ZIP64_LOCATOR_SIGNATURE))
+            .ok_or_else(|| anyhow!("Zip archive needs ZIP64 records but has none"))?;
+        let record = position(u64_at(locator + 8)?)?;
+        if u32_at(record)? != ZIP64_EOCD_SIGNATURE {
+            return Err(anyhow!("Corrupt ZIP64 end of central directory"));
+        }
+        count = u64_at(record + 32)?;
+        offset = u64_at(record + 48)?;
+    }
+    let mut offset = position(offset)?;
+
+    // Every central directory header takes at least 46 bytes
+    let mut entries = Vec::with_capacity(position(count)?.min(bytes.len() / 46));
+    for _ in 0..count {
+        if u32_at(offset)? != CENTRAL_SIGNATURE {
+            return Err(anyhow!("Corrupt zip central directory"));
+        }
+        let method = u16_at(offset + 10)?;
+        let mut compressed_size = u32_at(offset + 20)?;
+        let mut uncompressed_size = u32_at(offset + 24)?;
+        let name_len = u16_at(offset + 28)?;
+        let extra_len = u16_at(offset + 30)?;
+        let comment_len = u16_at(offset + 32)?;
+        let mut local = u32_at(offset + 42)?;
+        let name = bytes
+            .get(offset + 46..offset + 46 + name_len)
+            .map(|name| String::from_utf8_lossy(name).to_string())
+            .ok_or_else(|| anyhow!("Truncated zip archive"))?;
+        let extra = bytes
+            .get(offset + 46 + name_len..offset + 46 + name_len + extra_len)
+            .ok_or_else(|| anyhow!("Truncated zip archive"))?;
+        offset += 46 + name_len + extra_len + comment_len;
+
+        // Sentinel fields are replaced, in this order, by the ZIP64 extra field
+        let mut zip64 = zip64_extra(extra);
+        for value in [&mut uncompressed_size, &mut compressed_size, &mut local] {
+            if *value == ZIP64_SENTINEL {
+                *value = zip64
+                    .next()
+                    .ok_or_else(|| anyhow!("Zip entry {} is missing its ZIP64 sizes", name))?;
+            }
+        }
+        let local = position(local)?;
+
+        if u32_at(local)? != LOCAL_SIGNATURE {
+            return Err(anyhow!("Corrupt zip entry {}", name));
+        }
+        let data_start = local + 30 + u16_at(local + 26)? + u16_at(local + 28)?;
+        let data = position(compressed_size)
+            .ok()
+            .and_then(|size| bytes.get(data_start..data_start.checked_add(size)?))
+            .ok_or_else(|| anyhow!("Truncated zip entry {}", name))?;
+        let content = match method {
+            0 => data.to_vec(),
+            8 => {
+                let capacity = position(uncompressed_size)?.min(MAX_ENTRY_PREALLOCATION);
+                let mut content = Vec::with_capacity(capacity);
+                // One byte past the declared size is enough to catch a lie
+                DeflateDecoder::new(data)
+                    .take(uncompressed_size.saturating_add(1))
+                    .read_to_end(&mut content)
+                    .with_context(|| format!("Corrupt zip entry {}", name))?;
+                content
+            }
+            other => return Err(anyhow!("Unsupported zip method {} for {}", other, name)),
+        };
+        if content.len() as u64 != uncompressed_size {
+            return Err(anyhow!(
+                "Corrupt zip entry {}: {} bytes instead of {}",
+                name,
+                content.len(),
+                uncompressed_size
+            ));
+        }
+        entries.push((name, content));
+    }
+    Ok(entries)
+}
+
+/// Values of the ZIP64 extended information field (id 1) in a central
+/// directory header's extra data
+fn zip64_extra(extra: &[u8]) -> impl Iterator<Item = u64> + '_ {
+    let mut rest = extra;
+    let mut data: &[u8] = &[];
+    while rest.len() >= 4 {
+        let id = u16::from_le_bytes([rest[0], rest[1]]);
+        let len = (u16::from_le_bytes([rest[2], rest[3]]) as usize).min(rest.len() - 4);
+        if id == 1 {
+            data = &rest[4..4 + len];
+            break;
+        }
+        rest = &rest[4 + len..];
+    }
+    data.chunks_exact(8).map(|value| {
+        u64::from_le_bytes([
+            value[0], value[1], value[2], value[3], value[4], value[5], value[6], value[7],
+        ])
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::tarball::tests::MockRegistry;
+    use flate2::write::DeflateEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    /// Build a zip archive from `(name, content)` pairs; every other entry is
+    /// deflated so both methods get exercised
+    pub(crate) fn build_zip(files: &[(&str, &str)]) -> Vec<u8> {
+        build_zip_with(files, false)
+    }
+
+    /// `build_zip` with every size, offset and count in the ZIP64 records,
+    /// the way archives too large for the classic fields store them
+    fn build_zip64(files: &[(&str, &str)]) -> Vec<u8> {
+        build_zip_with(files, true)
+    }
+
+    fn build_zip_with(files: &[(&str, &str)], zip64: bool) -> Vec<u8> {
+        let sentinel = 0xFFFF_FFFFu32.to_le_bytes();
+        let mut archive = Vec::new();
+        let mut central = Vec::new();
+        for (index, (name, content)) in files.iter().enumerate() {
+            let (method, data): (u16, Vec<u8>) = if index % 2 == 0 {
+                (0, content.as_bytes().to_vec())
+            } else {
+                let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(content.as_bytes()).unwrap();
+                (8, encoder.finish().unwrap())
+            };
+            let local_offset = archive.len() as u32;
+            let sizes = [data.len() as u32, content.len() as u32];
+
+            archive.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+            archive.extend_from_slice(&[20, 0, 0, 0]);
+            archive.extend_from_slice(&method.to_le_bytes());
+            archive.extend_from_slice(&[0; 8]); // time, date, crc (unchecked)
+            sizes
+                .iter()
+                .for_each(|s| archive.extend_from_slice(&s.to_le_bytes()));
+            archive.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            archive.extend_from_slice(&[0, 0]);
+            archive.extend_from_slice(name.as_bytes());
+            archive.extend_from_slice(&data);
+
+            central.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+            central.extend_from_slice(&[20, 0, 20, 0, 0, 0]);
+            central.extend_from_slice(&method.to_le_bytes());
+            central.extend_from_slice(&[0; 8]);
+            if zip64 {
+                central.extend_from_slice(&sentinel);
+                central.extend_from_slice(&sentinel);
+            } else {
+                sizes
+                    .iter()
+                    .for_each(|s| central.extend_from_slice(&s.to_le_bytes()));
+            }
+            central.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            central.extend_from_slice(&(if zip64 { 28u16 } else { 0 }).to_le_bytes());
+            central.extend_from_slice(&[0; 10]); // comment, disk, attributes
+            if zip64 {
+                central.extend_from_slice(&sentinel);
+            } else {
+                central.extend_from_slice(&local_offset.to_le_bytes());
+            }
+            central.extend_from_slice(name.as_bytes());
+            if zip64 {
+                central.extend_from_slice(&[1, 0, 24, 0]);
+                for value in [sizes[1], sizes[0], local_offset] {
+                    central.extend_from_slice(&u64::from(value).to_le_bytes());
+                }
+            }
+        }
+
+        let central_offset = archive.len() as u32;
+        archive.extend_from_slice(&central);
+        if zip64 {
+            let record = archive.len() as u64;
+            archive.extend_from_slice(&0x0606_4b50u32.to_le_bytes());
+            archive.extend_from_slice(&44u64.to_le_bytes());
+            archive.extend_from_slice(&[45, 0, 45, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+            for value in [files.len(), files.len(), central.len()] {
+                archive.extend_from_slice(&(value as u64).to_le_bytes());
+            }
+            archive.extend_from_slice(&u64::from(central_offset).to_le_bytes());
+            archive.extend_from_slice(&0x0706_4b50u32.to_le_bytes());
+            archive.extend_from_slice(&[0; 4]);
+            archive.extend_from_slice(&record.to_le_bytes());
+            archive.extend_from_slice(&1u32.to_le_bytes());
+        }
+        let (count, size, offset) = if zip64 {
+            (u16::MAX, sentinel, sentinel)
+        } else {
+            (
+                files.len() as u16,
+                (central.len() as u32).to_le_bytes(),
+                central_offset.to_le_bytes(),
+            )
+        };
+        archive.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+        archive.extend_from_slice(&[0; 4]);
+        archive.extend_from_slice(&count.to_le_bytes());
+        archive.extend_from_slice(&count.to_le_bytes());
+        archive.extend_from_slice(&size);
+        archive.extend_from_slice(&offset);
+        archive.extend_from_slice(&[0, 0]);
+        archive
+    }
+
+    pub(crate) fn lodash_record() -> String {
+        serde_json::json!({
+            "id": "GHSA-jf85-cpcp-j695",
+            "aliases": ["CVE-2019-10744"],
+            "summary": "Prototype Pollution in lodash",
+            "database_specific": { "severity": "CRITICAL" },
+            "affected": [{
+                "package": { "ecosystem": "npm", "name": "lodash" },
+                "ranges": [{
+                    "type": "SEMVER",
+                    "events": [{ "introduced": "0" }, { "fixed": "4.17.12" }]
+                }]
+            }]
+        })
+        .to_string()
+    }
+
+    pub(crate) fn minimist_record() -> String {
+        serde_json::json!({
+            "id": "GHSA-xvch-5gv4-984h",
+            "aliases": ["CVE-2021-44906"],
+            "summary": "Prototype Pollution in minimist",
+            "database_specific": { "severity": "CRITICAL" },
+            "affected": [{
+                "package": { "ecosystem": "npm", "name": "minimist" },
+                "ranges": [{
+                    "type": "SEMVER",
+                    "events": [
+                        { "introduced": "0" }, { "fixed": "0.2.4" },
+                        { "introduced": "1.0.0" }, { "fixed": "1.2.6" }
+                    ]
+                }]
+            }]
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn zip_reader_handles_stored_and_deflated_entries() {
+        let zip = build_zip(&[("a.json", "{\"a\":1}"), ("b.json", "second entry")]);
+        let entries = zip_entries(&zip).unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                ("a.json".to_string(), b"{\"a\":1}".to_vec()),
+                ("b.json".to_string(), b"second entry".to_vec()),
+            ]
+        );
+        assert!(zip_entries(b"not a zip").is_err());
+    }
+
+    #[test]
+    fn zip_reader_follows_zip64_records() {
+        let files = [("a.json", "{\"a\":1}"), ("b.json", "second entry")];
+        assert_eq!(
+            zip_entries(&build_zip64(&files)).unwrap(),
+            zip_entries(&build_zip(&files)).unwrap()
+        );
+
+        // Sentinels without the records they point to
+        let mut zip = build_zip64(&files);
+        let locator = zip.len() - 22 - 20;
+        zip[locator..locator + 4].copy_from_slice(&[0; 4]);
+        let err = zip_entries(&zip).unwrap_err();
+        assert!(err.to_string().contains("ZIP64"), "{err}");
+    }
+
+    #[test]
+    fn zip_reader_rejects_entries_larger_than_declared() {
+        let mut zip = build_zip(&[("a.json", "stored"), ("b.json", "deflated entry")]);
+        // Declare 3 bytes for the deflated entry in its central header
+        let eocd = zip.len() - 22;
+        let central = u32::from_le_bytes(zip[eocd + 16..eocd + 20].try_into().unwrap()) as usize;
+        let second = central + 46 + "a.json".len();
+        zip[second + 24..second + 28].copy_from_slice(&3u32.to_le_bytes());
+        let err = zip_entries(&zip).unwrap_err();
+        assert!(err.to_string().contains("b.json"), "{err}");
+    }
+
+    #[test]
+    fn matches_versions_against_osv_ranges() {
+        let withdrawn = r#"{"id":"GHSA-old","withdrawn":"2022-01-01T00:00:00Z","affected":[{"package":{"ecosystem":"npm","name":"lodash"},"ranges":[{"type":"SEMVER","events":[{"introduced":"0"}]}]}]}"#;
+        let pypi = r#"{"id":"PYSEC-1","affected":[{"package":{"ecosystem":"PyPI","name":"minimist"},"versions":["1.2.5"]}]}"#;
+        let zip = build_zip(&[
+            ("GHSA-jf85-cpcp-j695.json", &lodash_record()),
+            ("GHSA-xvch-5gv4-984h.json", &minimist_record()),
+            ("GHSA-old.json", withdrawn),
+            ("PYSEC-1.json", pypi),
+        ]);
+        let database = AdvisoryDatabase::from_osv_zip(&zip).unwrap();
+        assert_eq!(database.len(), 2);
+
+        let matches = database.check("minimist", "1.2.5");
+        assert_eq!(matches.len(), 1);
+        let hit = &matches[0];
+        assert_eq!(hit.ids(), "GHSA-xvch-5gv4-984h (CVE-2021-44906)");
+        assert_eq!(hit.affected, vec!["<0.2.4", ">=1.0.0 <1.2.6"]);
+        assert_eq!(hit.fixed_in.as_deref(), Some("1.2.6"));
+        assert_eq!(
+            database.check("minimist", "0.1.0")[0].fixed_in.as_deref(),
+            Some("0.2.4")
+        );
+
+        assert!(database.check("minimist", "0.2.4").is_empty());
+        assert!(database.check("minimist", "1.2.6").is_empty());
+        assert!(database.check("lodash", "4.17.21").is_empty());
+        assert_eq!(database.check("lodash", "4.17.11").len(), 1);
+        assert!(database.check("left-pad", "1.0.0").is_empty());
+
+        let last = AffectedRange {
+            introduced: "2.0.0".to_string(),
+            fixed: None,
+            last_affected: Some("2.1.0".to_string()),
+        };
+        assert_eq!(last.to_string(), ">=2.0.0 <=2.1.0");
+        assert!(last.contains(&Version::parse("2.1.0").unwrap()));
+        assert!(!last.contains(&Version::parse("2.1.1").unwrap()));
+    }
+
+    #[test]
+    fn downloads_once_and_reuses_the_cached_bundle() {
+        let registry = MockRegistry::start();
+        registry.route(
+            "/npm/all.zip",
+            build_zip(&[("GHSA-jf85-cpcp-j695.json", &lodash_record())]),
+        );
+        let cache = TempDir::new().unwrap();
+        assert!(AdvisoryDatabase::load(cache.path()).unwrap().is_none());
+
+        let database =
+            AdvisoryDatabase::download(&format!("{}/npm/all.zip", registry.url)).unwrap();
+        database.save(cache.path()).unwrap();
+        let cached = AdvisoryDatabase::load_or_download(cache.path(), false).unwrap();
+        assert_eq!(cached.source, format!("{}/npm/all.zip", registry.url));
+        assert_eq!(cached.check("lodash", "4.17.4").len(), 1);
+
+        assert!(AdvisoryDatabase::download(&format!("{}/missing.zip", registry.url)).is_err());
+    }
+}
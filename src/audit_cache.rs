@@ -0,0 +1,262 @@
+//! On-disk cache of `PackageAudit` results, content-addressed by package,
+//! version, tarball integrity and analyzer version. Popular packages only
+//! need to be analyzed once per release of the detection rules. Registry
+//! verdicts (metadata signals, provenance) can change while the tarball stays
+//! the same, so they are never stored and are checked again on every scan.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::security::PackageAudit;
+
+/// Identifies the detection rules that produced an audit. Changes whenever
+/// the analyzer sources change, which invalidates every cached result.
+pub const ANALYZER_VERSION: &str = concat!(
+    env!("CARGO_PKG_VERSION"),
+    "+",
+    env!("FNPM_ANALYZER_FINGERPRINT")
+);
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CachedAudit {
+    pub name: String,
+    pub version: String,
+    pub integrity: String,
+    pub analyzer_version: String,
+    pub cached_at: DateTime<Utc>,
+    pub audit: PackageAudit,
+}
+
+impl CachedAudit {
+    /// Produced by the running analyzer (otherwise it will never be served)
+    pub fn is_current(&self) -> bool {
+        self.analyzer_version == ANALYZER_VERSION
+    }
+}
+
+pub struct AuditCache {
+    dir: PathBuf,
+}
+
+impl AuditCache {
+    /// Cache stored under `{global_cache_path}/audits`
+    pub fn new(global_cache_path: &str) -> Self {
+        Self::at(Path::new(global_cache_path).join("audits"))
+    }
+
+    pub fn at(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn key(name: &str, version: &str, integrity: &str) -> String {
+        let mut hasher = Sha256::new();
+        for part in [name, version, integrity, ANALYZER_VERSION] {
+            hasher.update(part.as_bytes());
+            hasher.update([0]);
+        }
+        hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    fn entry_path(&self, name: &str, version: &str, integrity: &str) -> PathBuf {
+        self.dir
+            .join(format!("{}.json", Self::key(name, version, integrity)))
+    }
+
+    /// Audit previously stored for this exact tarball, if any
+    pub fn get(&self, name: &str, version: &str, integrity: &str) -> Option<PackageAudit> {
+        let content = fs::read_to_string(self.entry_path(name, version, integrity)).ok()?;
+        let entry: CachedAudit = serde_json::from_str(&content).ok()?;
+        (entry.is_current() && entry.integrity == integrity).then_some(entry.audit)
+    }
+
+    /// Store `audit` without its metadata signals and provenance
+    pub fn put(
+        &self,
+        name: &str,
+        version: &str,
+        integrity: &str,
+        audit: &PackageAudit,
+    ) -> Result<()> {
+        #[derive(Serialize)]
+        struct Entry<'a> {
+            name: &'a str,
+            version: &'a str,
+            integrity: &'a str,
+            analyzer_version: &'a str,
+            cached_at: DateTime<Utc>,
+            audit: &'a PackageAudit,
+        }
+
+        let audit = PackageAudit {
+            metadata_signals: Vec::new(),
+            provenance: None,
+            ..audit.clone()
+        };
+        fs::create_dir_all(&self.dir)?;
+        let content = serde_json::to_string(&Entry {
+            name,
+            version,
+            integrity,
+            analyzer_version: ANALYZER_VERSION,
+            cached_at: Utc::now(),
+            audit: &audit,
+        })?;
+
+        // Write then rename so parallel scans never read a partial entry
+        let path = self.entry_path(name, version, integrity);
+        let staging = path.with_extension(format!("tmp-{}", uuid::Uuid::new_v4()));
+        fs::write(&staging, content)?;
+        if let Err(e) = fs::rename(&staging, &path) {
+            let _ = fs::remove_file(&staging);
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    fn entry_files(&self) -> Result<Vec<PathBuf>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                files.push(path);
+            }
+        }
+        Ok(files)
+    }
+
+    /// Every readable entry, sorted by package and version
+    pub fn list(&self) -> Result<Vec<CachedAudit>> {
+        let mut entries: Vec<CachedAudit> = self
+            .entry_files()?
+            .iter()
+            .filter_map(|path| fs::read_to_string(path).ok())
+            .filter_map(|content| serde_json::from_str(&content).ok())
+            .collect();
+        entries.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+        Ok(entries)
+    }
+
+    /// Remove entries written by another analyzer version, plus anything
+    /// unreadable. Returns how many files were deleted.
+    pub fn prune(&self) -> Result<usize> {
+        let mut removed = 0;
+        for path in self.entry_files()? {
+            let current = fs::read_to_string(&path)
+                .ok()
+                .and_then(|content| serde_json::from_str::<CachedAudit>(&content).ok())
+                .is_some_and(|entry| entry.is_current());
+            if !current {
+                fs::remove_file(&path)?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provenance::{ProvenanceReport, VerificationStatus};
+    use crate::registry_metadata::{MetadataSignal, SignalKind};
+    use crate::security::RiskLevel;
+    use tempfile::TempDir;
+
+    fn audit(name: &str) -> PackageAudit {
+        PackageAudit {
+            package_name: name.to_string(),
+            version: Some("1.0.0".to_string()),
+            has_scripts: true,
+            postinstall: Some("node build.js".to_string()),
+            risk_level: RiskLevel::Low,
+            risk_score: 10,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn round_trips_by_name_version_and_integrity() {
+        let dir = TempDir::new().unwrap();
+        let cache = AuditCache::at(dir.path().to_path_buf());
+
+        assert!(cache.get("pkg", "1.0.0", "sha512-a").is_none());
+        cache
+            .put("pkg", "1.0.0", "sha512-a", &audit("pkg"))
+            .unwrap();
+
+        let hit = cache.get("pkg", "1.0.0", "sha512-a").unwrap();
+        assert_eq!(hit.postinstall.as_deref(), Some("node build.js"));
+        assert_eq!(hit.risk_level, RiskLevel::Low);
+        assert!(cache.get("pkg", "1.0.0", "sha512-b").is_none());
+        assert!(cache.get("pkg", "1.0.1", "sha512-a").is_none());
+    }
+
+    #[test]
+    fn registry_verdicts_are_never_cached() {
+        let dir = TempDir::new().unwrap();
+        let cache = AuditCache::at(dir.path().to_path_buf());
+        let mut verified = audit("pkg");
+        verified.provenance = Some(ProvenanceReport {
+            signature: VerificationStatus::Verified,
+            attestation: VerificationStatus::Verified,
+            source_repository: None,
+            notes: Vec::new(),
+        });
+        verified.metadata_signals = vec![MetadataSignal {
+            kind: SignalKind::Deprecated,
+            description: "use pkg2".to_string(),
+        }];
+
+        cache.put("pkg", "1.0.0", "sha512-a", &verified).unwrap();
+        let hit = cache.get("pkg", "1.0.0", "sha512-a").unwrap();
+        assert!(hit.provenance.is_none());
+        assert!(hit.metadata_signals.is_empty());
+    }
+
+    #[test]
+    fn prune_removes_stale_and_corrupt_entries() {
+        let dir = TempDir::new().unwrap();
+        let cache = AuditCache::at(dir.path().to_path_buf());
+        cache
+            .put("fresh", "1.0.0", "sha512-a", &audit("fresh"))
+            .unwrap();
+
+        let stale = CachedAudit {
+            name: "stale".to_string(),
+            version: "1.0.0".to_string(),
+            integrity: "sha512-b".to_string(),
+            analyzer_version: "0.0.0+old".to_string(),
+            cached_at: Utc::now(),
+            audit: audit("stale"),
+        };
+        fs::write(
+            dir.path().join("stale.json"),
+            serde_json::to_string(&stale).unwrap(),
+        )
+        .unwrap();
+        fs::write(dir.path().join("corrupt.json"), "{").unwrap();
+
+        let names: Vec<_> = cache.list().unwrap().into_iter().map(|e| e.name).collect();
+        assert_eq!(names, vec!["fresh", "stale"]);
+
+        assert_eq!(cache.prune().unwrap(), 2);
+        let remaining = cache.list().unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert!(remaining[0].is_current());
+    }
+}
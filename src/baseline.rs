@@ -0,0 +1,421 @@
+//! Known findings that scans should stop reporting: a baseline of reviewed
+//! `SourceCodeIssue`s (`.fnpm/baseline.json`, written by
+//! `fnpm scan --write-baseline`) and hand-written suppressions with a reason
+//! (`.fnpm/suppressions.json`).
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use crate::package_manager::split_package_spec;
+use crate::policy::matches_pattern;
+use crate::security::{PackageAudit, SourceCodeIssue};
+
+pub const BASELINE_PATH: &str = ".fnpm/baseline.json";
+pub const SUPPRESSIONS_PATH: &str = ".fnpm/suppressions.json";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BaselineEntry {
+    pub fingerprint: String,
+    pub package: String,
+    pub version: Option<String>,
+    pub file: String,
+    pub issue_type: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Baseline {
+    #[serde(default)]
+    pub generated_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub findings: Vec<BaselineEntry>,
+}
+
+/// Hide findings matching every field that is set. Use `fingerprint` (from
+/// the baseline file) to target a single finding.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Suppression {
+    #[serde(default)]
+    pub fingerprint: Option<String>,
+    /// Package name or pattern (`*` wildcards)
+    #[serde(default)]
+    pub package: Option<String>,
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub issue_type: Option<String>,
+    /// File path inside the package, e.g. `lib/index.js`
+    #[serde(default)]
+    pub file: Option<String>,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SuppressionFile {
+    #[serde(default)]
+    pub suppressions: Vec<Suppression>,
+}
+
+/// Path of `path` inside its package, so the same file fingerprints the same
+/// whether it was scanned from node_modules or from a downloaded tarball.
+pub fn normalized_file(package: &str, version: Option<&str>, path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let mut prefixes = vec![format!("node_modules/{}/", package)];
+    if let Some(version) = version {
+        prefixes.push(format!("{}@{}/", package.replace('/', "+"), version));
+    }
+    prefixes
+        .iter()
+        .filter_map(|prefix| {
+            path.rfind(prefix.as_str())
+                .map(|i| &path[i + prefix.len()..])
+        })
+        .next()
+        .unwrap_or(&path)
+        .to_string()
+}
+
+/// Stable identity of a finding: package, version, file, issue type and
+/// snippet. Line numbers are left out so unrelated edits don't churn it.
+pub fn fingerprint(package: &str, version: Option<&str>, issue: &SourceCodeIssue) -> String {
+    let snippet = Sha256::digest(issue.code_snippet.as_deref().unwrap_or_default().trim());
+    let mut hasher = Sha256::new();
+    for part in [
+        package,
+        version.unwrap_or_default(),
+        &normalized_file(package, version, &issue.file_path),
+        &issue.issue_type,
+    ] {
+        hasher.update(part.as_bytes());
+        hasher.update([0]);
+    }
+    hasher.update(snippet);
+    hasher.finalize()[..8]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn package_of(audit: &PackageAudit) -> String {
+    split_package_spec(&audit.package_name).0
+}
+
+fn read_json<T: Default + for<'de> Deserialize<'de>>(path: &Path) -> Result<T> {
+    if !path.exists() {
+        return Ok(T::default());
+    }
+    let content = fs::read_to_string(path)?;
+    serde_json::from_str(&content).map_err(|e| anyhow!("Invalid {}: {}", path.display(), e))
+}
+
+impl Baseline {
+    /// Record every source code finding in `audits`
+    pub fn from_audits<'a>(audits: impl IntoIterator<Item = &'a PackageAudit>) -> Self {
+        let mut findings: Vec<BaselineEntry> = audits
+            .into_iter()
+            .flat_map(|audit| {
+                let package = package_of(audit);
+                let version = audit.version.clone();
+                audit
+                    .source_code_issues
+                    .iter()
+                    .map(move |issue| BaselineEntry {
+                        fingerprint: fingerprint(&package, version.as_deref(), issue),
+                        package: package.clone(),
+                        version: version.clone(),
+                        file: normalized_file(&package, version.as_deref(), &issue.file_path),
+                        issue_type: issue.issue_type.clone(),
+                    })
+            })
+            .collect();
+        findings.sort_by(|a, b| {
+            (
+                &a.package,
+                &a.version,
+                &a.file,
+                &a.issue_type,
+                &a.fingerprint,
+            )
+                .cmp(&(
+                    &b.package,
+                    &b.version,
+                    &b.file,
+                    &b.issue_type,
+                    &b.fingerprint,
+                ))
+        });
+        findings.dedup();
+
+        Self {
+            generated_at: Some(Utc::now()),
+            findings,
+        }
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self> {
+        read_json(path)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+impl Suppression {
+    fn matches(
+        &self,
+        package: &str,
+        version: Option<&str>,
+        file: &str,
+        issue: &SourceCodeIssue,
+        fp: &str,
+    ) -> bool {
+        self.fingerprint.as_deref().map_or(true, |f| f == fp)
+            && self
+                .package
+                .as_deref()
+                .map_or(true, |p| matches_pattern(p, package))
+            && self.version.as_deref().map_or(true, |v| Some(v) == version)
+            && self
+                .issue_type
+                .as_deref()
+                .map_or(true, |t| t == issue.issue_type)
+            && self.file.as_deref().map_or(true, |f| f == file)
+    }
+}
+
+/// Baseline fingerprints plus suppressions, loaded once per scan.
+#[derive(Debug, Default)]
+pub struct KnownFindings {
+    baseline: HashSet<String>,
+    suppressions: Vec<Suppression>,
+}
+
+impl KnownFindings {
+    /// Load `.fnpm/baseline.json` and `.fnpm/suppressions.json` (both optional)
+    pub fn load() -> Result<Self> {
+        Self::load_from(Path::new(BASELINE_PATH), Path::new(SUPPRESSIONS_PATH))
+    }
+
+    pub fn load_from(baseline: &Path, suppressions: &Path) -> Result<Self> {
+        let baseline = Baseline::load_from(baseline)?;
+        let suppressions: SuppressionFile = read_json(suppressions)?;
+
+        let targets_nothing = suppressions
+            .suppressions
+            .iter()
+            .find(|s| s.fingerprint.is_none() && s.package.is_none());
+        if let Some(suppression) = targets_nothing {
+            return Err(anyhow!(
+                "Suppression \"{}\" needs a fingerprint or a package",
+                suppression.reason
+            ));
+        }
+
+        Ok(Self {
+            baseline: baseline
+                .findings
+                .into_iter()
+                .map(|f| f.fingerprint)
+                .collect(),
+            suppressions: suppressions.suppressions,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.baseline.is_empty() && self.suppressions.is_empty()
+    }
+
+    /// Drop the findings of `audit` that are baselined or suppressed.
+    /// Returns how many were removed.
+    pub fn filter(&self, audit: &mut PackageAudit) -> usize {
+        let package = package_of(audit);
+        let version = audit.version.clone();
+        let before = audit.source_code_issues.len();
+
+        audit.source_code_issues.retain(|issue| {
+            let fp = fingerprint(&package, version.as_deref(), issue);
+            if self.baseline.contains(&fp) {
+                return false;
+            }
+            let file = normalized_file(&package, version.as_deref(), &issue.file_path);
+            !self
+                .suppressions
+                .iter()
+                .any(|s| s.matches(&package, version.as_deref(), &file, issue, &fp))
+        });
+
+        before - audit.source_code_issues.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::{IssueSeverity, RiskLevel};
+    use tempfile::TempDir;
+
+    fn issue(file: &str, issue_type: &str, line: usize, snippet: &str) -> SourceCodeIssue {
+        SourceCodeIssue {
+            file_path: file.to_string(),
+            line_number: line,
+            issue_type: issue_type.to_string(),
+            description: String::new(),
+            severity: IssueSeverity::Warning,
+            code_snippet: Some(snippet.to_string()),
+            data_flow: Vec::new(),
+        }
+    }
+
+    fn audit(name: &str, issues: Vec<SourceCodeIssue>) -> PackageAudit {
+        PackageAudit {
+            package_name: name.to_string(),
+            version: Some("5.0.0".to_string()),
+            source_code_issues: issues,
+            risk_level: RiskLevel::Low,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn fingerprint_ignores_scan_location_and_line_number() {
+        let installed = issue("node_modules/typescript/lib/tsc.js", "eval", 10, "eval(x)");
+        let tarball = issue(
+            "tarballs/typescript@5.0.0/lib/tsc.js",
+            "eval",
+            99,
+            " eval(x) ",
+        );
+        assert_eq!(
+            fingerprint("typescript", Some("5.0.0"), &installed),
+            fingerprint("typescript", Some("5.0.0"), &tarball)
+        );
+
+        let changed = issue("node_modules/typescript/lib/tsc.js", "eval", 10, "eval(y)");
+        assert_ne!(
+            fingerprint("typescript", Some("5.0.0"), &installed),
+            fingerprint("typescript", Some("5.0.0"), &changed)
+        );
+        assert_ne!(
+            fingerprint("typescript", Some("5.0.0"), &installed),
+            fingerprint("typescript", Some("5.0.1"), &installed)
+        );
+        assert_eq!(
+            normalized_file(
+                "@babel/core",
+                Some("7.0.0"),
+                "packages/@babel+core@7.0.0/lib/a.js"
+            ),
+            "lib/a.js"
+        );
+    }
+
+    #[test]
+    fn baseline_hides_only_known_findings() {
+        let dir = TempDir::new().unwrap();
+        let baseline_path = dir.path().join("baseline.json");
+
+        let reviewed = audit(
+            "typescript",
+            vec![issue(
+                "node_modules/typescript/lib/tsc.js",
+                "eval",
+                1,
+                "eval(x)",
+            )],
+        );
+        Baseline::from_audits([&reviewed])
+            .save(&baseline_path)
+            .unwrap();
+        let known =
+            KnownFindings::load_from(&baseline_path, &dir.path().join("none.json")).unwrap();
+
+        let mut rescanned = audit(
+            "typescript",
+            vec![
+                issue("node_modules/typescript/lib/tsc.js", "eval", 7, "eval(x)"),
+                issue("node_modules/typescript/lib/new.js", "eval", 1, "eval(z)"),
+            ],
+        );
+        assert_eq!(known.filter(&mut rescanned), 1);
+        assert_eq!(rescanned.source_code_issues.len(), 1);
+        assert_eq!(
+            rescanned.source_code_issues[0].file_path,
+            "node_modules/typescript/lib/new.js"
+        );
+    }
+
+    #[test]
+    fn suppressions_match_fields_or_fingerprints() {
+        let dir = TempDir::new().unwrap();
+        let target = issue(
+            "node_modules/esbuild/install.js",
+            "Command execution",
+            3,
+            "exec(x)",
+        );
+        let fp = fingerprint("esbuild", Some("5.0.0"), &target);
+        let suppressions = dir.path().join("suppressions.json");
+        fs::write(
+            &suppressions,
+            serde_json::json!({
+                "suppressions": [
+                    { "package": "@babel/*", "issue_type": "Dynamic function", "reason": "codegen" },
+                    { "fingerprint": fp, "reason": "installs the platform binary" }
+                ]
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let known = KnownFindings::load_from(&dir.path().join("none.json"), &suppressions).unwrap();
+
+        let mut esbuild = audit(
+            "esbuild",
+            vec![
+                target,
+                issue(
+                    "node_modules/esbuild/other.js",
+                    "Command execution",
+                    3,
+                    "exec(y)",
+                ),
+            ],
+        );
+        assert_eq!(known.filter(&mut esbuild), 1);
+
+        let mut babel = audit(
+            "@babel/core",
+            vec![
+                issue(
+                    "node_modules/@babel/core/a.js",
+                    "Dynamic function",
+                    1,
+                    "new Function()",
+                ),
+                issue("node_modules/@babel/core/a.js", "eval", 2, "eval(a)"),
+            ],
+        );
+        assert_eq!(known.filter(&mut babel), 1);
+        assert_eq!(babel.source_code_issues[0].issue_type, "eval");
+    }
+
+    #[test]
+    fn suppression_without_target_is_rejected() {
+        let dir = TempDir::new().unwrap();
+        let suppressions = dir.path().join("suppressions.json");
+        fs::write(
+            &suppressions,
+            r#"{"suppressions":[{"reason":"everything"}]}"#,
+        )
+        .unwrap();
+        assert!(KnownFindings::load_from(&dir.path().join("none.json"), &suppressions).is_err());
+    }
+}
@@ -0,0 +1,1537 @@
+// Sandbox for the lifecycle scripts of `allow_builds` packages
+// On Linux, `<manager> rebuild <pkg>` runs in fresh user, mount, IPC and
+// (unless the package is in `allow_builds_network`) network namespaces. The
+// whole filesystem is mounted read-only except for the package's own
+// directory, $HOME, /tmp and /dev/shm are replaced by empty private tmpfs
+// mounts and the environment is scrubbed. A seccomp filter hands fnpm the
+// syscalls worth a look: the ones no build script needs (new namespaces
+// included) are refused, and sockets and writes to read-only mounts are let
+// through to fail on their own. Each becomes a finding in
+// `.fnpm/build-violations.jsonl`, which `fnpm scan` adds to the audit of that
+// version. Where no filter exists for the CPU, builds don't run at all.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+use crate::security::{IssueSeverity, SourceCodeIssue};
+
+/// Findings of sandboxed builds, one JSON line per build
+pub const VIOLATIONS_LOG: &str = ".fnpm/build-violations.jsonl";
+
+/// Environment variables a build keeps. Everything else is dropped, so
+/// `NPM_TOKEN`, `GITHUB_TOKEN` and `AWS_*` never reach the scripts.
+const KEPT_ENV: &[&str] = &[
+    "PATH",
+    "LANG",
+    "LC_ALL",
+    "LC_CTYPE",
+    "TERM",
+    "TZ",
+    "USER",
+    "LOGNAME",
+    "SHELL",
+    "CC",
+    "CXX",
+    "MAKEFLAGS",
+    "JOBS",
+    "PYTHON",
+    "NODE_ENV",
+];
+
+fn is_secret(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    ["auth", "token", "password", "secret", "credential"]
+        .iter()
+        .any(|word| name.contains(word))
+}
+
+/// The environment a build runs with: `KEPT_ENV`, `npm_config_*` settings
+/// that aren't credentials, and `home` as HOME
+pub fn scrubbed_env(
+    vars: impl IntoIterator<Item = (String, String)>,
+    home: &Path,
+) -> Vec<(String, String)> {
+    let mut env: Vec<(String, String)> = vars
+        .into_iter()
+        .filter(|(name, _)| {
+            KEPT_ENV.contains(&name.as_str())
+                || name.to_ascii_lowercase().starts_with("npm_config_")
+        })
+        .filter(|(name, _)| !is_secret(name))
+        .collect();
+    env.push(("HOME".to_string(), home.display().to_string()));
+    env.sort();
+    env
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ViolationKind {
+    /// Made a syscall the seccomp filter refuses
+    BlockedSyscall,
+    /// Opened an internet socket without `allow_builds_network`
+    NetworkAccess,
+    /// Tried to write to a read-only mount: the project or the host
+    ProjectWrite,
+}
+
+/// Something a build script tried that the sandbox stopped
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SandboxViolation {
+    pub package: String,
+    pub kind: ViolationKind,
+    /// The syscall, its path and the process that made it
+    pub evidence: String,
+}
+
+impl SandboxViolation {
+    /// The violation as a finding, in the same shape as the analyzer's
+    pub fn to_issue(&self) -> SourceCodeIssue {
+        let (issue_type, description, severity) = match self.kind {
+            ViolationKind::BlockedSyscall => (
+                "sandbox_blocked_syscall",
+                "Build script made a syscall no build needs (ptrace, mount, new namespaces, bpf, kernel modules...) and was refused",
+                IssueSeverity::Critical,
+            ),
+            ViolationKind::NetworkAccess => (
+                "sandbox_network_access",
+                "Build script tried to use the network; add the package to allow_builds_network if that is expected",
+                IssueSeverity::Warning,
+            ),
+            ViolationKind::ProjectWrite => (
+                "sandbox_project_write",
+                "Build script tried to write outside its own package directory",
+                IssueSeverity::Critical,
+            ),
+        };
+        SourceCodeIssue {
+            file_path: format!("node_modules/{}/package.json", self.package),
+            line_number: 0,
+            issue_type: issue_type.to_string(),
+            description: description.to_string(),
+            severity,
+            code_snippet: Some(self.evidence.clone()),
+            data_flow: Vec::new(),
+        }
+    }
+}
+
+/// One sandboxed build in `VIOLATIONS_LOG`
+#[derive(Debug, Serialize, Deserialize)]
+struct ViolationLogEntry {
+    timestamp: String,
+    package: String,
+    #[serde(default)]
+    version: Option<String>,
+    findings: Vec<SourceCodeIssue>,
+}
+
+/// Append the findings of one build of `package` at `version` to `log`
+pub fn log_violations(
+    log: &Path,
+    package: &str,
+    version: Option<&str>,
+    violations: &[SandboxViolation],
+) -> Result<()> {
+    if let Some(parent) = log.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let entry = ViolationLogEntry {
+        timestamp: chrono::Local::now().to_rfc3339(),
+        package: package.to_string(),
+        version: version.map(String::from),
+        findings: violations.iter().map(SandboxViolation::to_issue).collect(),
+    };
+    let mut file = OpenOptions::new().create(true).append(true).open(log)?;
+    writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+    Ok(())
+}
+
+/// Findings logged for the builds of each `name@version`
+#[derive(Debug, Default)]
+pub struct BuildFindings {
+    by_version: BTreeMap<String, Vec<SourceCodeIssue>>,
+}
+
+impl BuildFindings {
+    /// Read `log`; a missing log has no findings and unreadable lines are skipped
+    pub fn load(log: &Path) -> Result<Self> {
+        let mut findings = Self::default();
+        let content = match fs::read_to_string(log) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(findings),
+            Err(e) => return Err(e.into()),
+        };
+        for entry in content
+            .lines()
+            .filter_map(|line| serde_json::from_str::<ViolationLogEntry>(line).ok())
+        {
+            let Some(version) = entry.version else {
+                continue;
+            };
+            let known = findings
+                .by_version
+                .entry(format!("{}@{}", entry.package, version))
+                .or_default();
+            // Every rebuild logs the same findings again
+            for finding in entry.findings {
+                if !known.iter().any(|issue| {
+                    issue.issue_type == finding.issue_type
+                        && issue.code_snippet == finding.code_snippet
+                }) {
+                    known.push(finding);
+                }
+            }
+        }
+        Ok(findings)
+    }
+
+    pub fn get(&self, name: &str, version: &str) -> &[SourceCodeIssue] {
+        self.by_version
+            .get(&format!("{}@{}", name, version))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_version.is_empty()
+    }
+}
+
+/// Whether builds can be sandboxed on this platform
+pub fn is_supported() -> bool {
+    cfg!(target_os = "linux")
+}
+
+/// Where and how a build may run
+pub struct BuildSandbox {
+    project_dir: PathBuf,
+    writable: Vec<PathBuf>,
+    network: bool,
+    home: PathBuf,
+    /// Whether `home` is a temp dir of ours, to remove afterwards
+    owns_home: bool,
+}
+
+/// HOME inside the sandbox: the real one, which the sandbox covers with an
+/// empty tmpfs, or /tmp when that would hide the project
+#[cfg(target_os = "linux")]
+fn sandbox_home(project_dir: &Path) -> Option<PathBuf> {
+    let home = std::env::var_os("HOME").map(PathBuf::from)?;
+    let usable = home.is_absolute()
+        && home.is_dir()
+        && home != Path::new("/")
+        && !home.starts_with(project_dir);
+    usable.then_some(home)
+}
+
+impl BuildSandbox {
+    /// A sandbox with a read-only `project_dir`, no network and an empty HOME
+    pub fn new(project_dir: &Path) -> Result<Self> {
+        let project_dir = project_dir.canonicalize()?;
+        #[cfg(target_os = "linux")]
+        let (home, owns_home) = (
+            sandbox_home(&project_dir).unwrap_or_else(|| PathBuf::from("/tmp")),
+            false,
+        );
+        #[cfg(not(target_os = "linux"))]
+        let (home, owns_home) = {
+            let home = std::env::temp_dir().join(format!("fnpm-build-{}", uuid::Uuid::new_v4()));
+            fs::create_dir_all(&home)?;
+            (home, true)
+        };
+        Ok(Self {
+            project_dir,
+            writable: Vec::new(),
+            network: false,
+            home,
+            owns_home,
+        })
+    }
+
+    /// Let the build write to `path` (skipped when it doesn't exist)
+    pub fn writable(mut self, path: &Path) -> Self {
+        if let Ok(path) = path.canonicalize() {
+            self.writable.push(path);
+        }
+        self
+    }
+
+    pub fn network(mut self, allowed: bool) -> Self {
+        self.network = allowed;
+        self
+    }
+
+    /// Run `program` inside the sandbox, from the project root, and collect
+    /// what it tried that the sandbox stopped. Violations are reported as
+    /// `package`'s.
+    pub fn run(&self, package: &str, program: &str, args: &[&str]) -> Result<SandboxRun> {
+        let mut cmd = Command::new(program);
+        cmd.args(args)
+            .current_dir(&self.project_dir)
+            .env_clear()
+            .envs(scrubbed_env(std::env::vars(), &self.home))
+            .env("FNPM_HOOK_ACTIVE", "1") // Prevent hook recursion
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let start_error = |e: std::io::Error| anyhow!("could not start the build sandbox: {}", e);
+
+        #[cfg(target_os = "linux")]
+        let (output, violations) = {
+            use std::os::unix::io::AsRawFd;
+            use std::os::unix::net::UnixStream;
+            use std::os::unix::process::CommandExt;
+
+            // The child sends its seccomp listener back over this pair
+            let (monitor_end, child_end) = UnixStream::pair()?;
+            let mut setup = linux::Setup::new(
+                &self.project_dir,
+                &self.writable,
+                &self.home,
+                self.network,
+                child_end.as_raw_fd(),
+            )?;
+            // SAFETY: `enter` only makes raw syscalls on memory prepared above
+            unsafe {
+                cmd.pre_exec(move || setup.enter());
+            }
+            let child = cmd.spawn().map_err(start_error)?;
+            drop(cmd);
+            drop(child_end);
+
+            let monitor = linux::Monitor::start(monitor_end, package, child.id());
+            let output = child.wait_with_output()?;
+            (output, monitor.finish())
+        };
+        #[cfg(not(target_os = "linux"))]
+        let (output, violations) = (cmd.output().map_err(start_error)?, Vec::new());
+
+        Ok(SandboxRun { output, violations })
+    }
+}
+
+/// A finished sandboxed run
+pub struct SandboxRun {
+    pub output: Output,
+    pub violations: Vec<SandboxViolation>,
+}
+
+impl Drop for BuildSandbox {
+    fn drop(&mut self) {
+        if self.owns_home {
+            let _ = fs::remove_dir_all(&self.home);
+        }
+    }
+}
+
+/// Result of one sandboxed build
+pub struct BuildOutcome {
+    pub success: bool,
+    /// Version of the package that was built
+    pub version: Option<String>,
+    pub violations: Vec<SandboxViolation>,
+}
+
+/// Run `<manager> <args> <package>` in a sandbox rooted at the current
+/// project. Its output is echoed to stderr once it finishes.
+pub fn run_build(
+    manager: &str,
+    args: &[&str],
+    package: &str,
+    network: bool,
+) -> Result<BuildOutcome> {
+    let node_modules = Path::new("node_modules");
+    let mut sandbox = BuildSandbox::new(Path::new("."))?
+        .network(network)
+        .writable(&node_modules.join(package))
+        .writable(&node_modules.join(".bin"));
+    if manager == "bun" {
+        // `bun pm trust` records the package in trustedDependencies
+        for file in ["package.json", "bun.lock", "bun.lockb"] {
+            sandbox = sandbox.writable(Path::new(file));
+        }
+    }
+
+    let mut args = args.to_vec();
+    args.push(package);
+    let run = sandbox.run(package, manager, &args)?;
+    eprint!("{}", String::from_utf8_lossy(&run.output.stdout));
+    eprint!("{}", String::from_utf8_lossy(&run.output.stderr));
+
+    let version = fs::read_to_string(node_modules.join(package).join("package.json"))
+        .ok()
+        .and_then(|manifest| serde_json::from_str::<serde_json::Value>(&manifest).ok())
+        .and_then(|manifest| manifest.get("version")?.as_str().map(String::from));
+    Ok(BuildOutcome {
+        success: run.output.status.success(),
+        version,
+        violations: run.violations,
+    })
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::ffi::{CString, OsStr};
+    use std::fs::{self, File};
+    use std::io;
+    use std::os::unix::ffi::{OsStrExt, OsStringExt};
+    use std::os::unix::fs::FileExt;
+    use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+    use std::os::unix::net::UnixStream;
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread::JoinHandle;
+    use std::time::Duration;
+
+    use super::{SandboxViolation, ViolationKind};
+
+    fn c_path(path: &Path) -> io::Result<CString> {
+        CString::new(path.as_os_str().as_bytes())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    const AUDIT_ARCH: Option<u32> = Some(0xC000_003E);
+    #[cfg(target_arch = "aarch64")]
+    const AUDIT_ARCH: Option<u32> = Some(0xC000_00B7);
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    const AUDIT_ARCH: Option<u32> = None;
+
+    /// Syscalls that let a process escape or inspect the sandbox, or the
+    /// machine. Build scripts never need them.
+    const BLOCKED_SYSCALLS: &[(libc::c_long, &str)] = &[
+        (libc::SYS_ptrace, "ptrace"),
+        (libc::SYS_process_vm_readv, "process_vm_readv"),
+        (libc::SYS_process_vm_writev, "process_vm_writev"),
+        (libc::SYS_mount, "mount"),
+        (libc::SYS_umount2, "umount2"),
+        (libc::SYS_pivot_root, "pivot_root"),
+        (libc::SYS_unshare, "unshare"),
+        (libc::SYS_setns, "setns"),
+        (libc::SYS_bpf, "bpf"),
+        (libc::SYS_perf_event_open, "perf_event_open"),
+        (libc::SYS_kexec_load, "kexec_load"),
+        (libc::SYS_init_module, "init_module"),
+        (libc::SYS_finit_module, "finit_module"),
+        (libc::SYS_delete_module, "delete_module"),
+        (libc::SYS_keyctl, "keyctl"),
+        (libc::SYS_add_key, "add_key"),
+        (libc::SYS_request_key, "request_key"),
+        (libc::SYS_userfaultfd, "userfaultfd"),
+        (libc::SYS_open_by_handle_at, "open_by_handle_at"),
+        (libc::SYS_reboot, "reboot"),
+        (libc::SYS_swapon, "swapon"),
+        (libc::SYS_swapoff, "swapoff"),
+    ];
+
+    /// `clone` flags creating namespaces, which would undo the sandbox's own
+    const NAMESPACE_FLAGS: u32 = (libc::CLONE_NEWNS
+        | libc::CLONE_NEWUSER
+        | libc::CLONE_NEWPID
+        | libc::CLONE_NEWNET
+        | libc::CLONE_NEWUTS
+        | libc::CLONE_NEWIPC
+        | libc::CLONE_NEWCGROUP) as u32;
+
+    /// `open` flags that make it a write
+    const WRITE_FLAGS: u32 = (libc::O_WRONLY | libc::O_RDWR | libc::O_CREAT | libc::O_TRUNC) as u32;
+
+    /// How a path syscall is told apart from a read
+    #[derive(Clone, Copy)]
+    enum Access {
+        /// Always changes its paths
+        Write,
+        /// `open` flags in this argument
+        Flags(usize),
+        /// `struct open_how` this argument points to (openat2)
+        How(usize),
+    }
+
+    /// Syscall changing the filesystem at the paths in its arguments
+    struct PathSyscall {
+        nr: libc::c_long,
+        name: &'static str,
+        /// (dirfd argument, path argument) of each path
+        paths: &'static [(Option<usize>, usize)],
+        access: Access,
+    }
+
+    const fn path_syscall(
+        nr: libc::c_long,
+        name: &'static str,
+        paths: &'static [(Option<usize>, usize)],
+        access: Access,
+    ) -> PathSyscall {
+        PathSyscall {
+            nr,
+            name,
+            paths,
+            access,
+        }
+    }
+
+    const PATH_SYSCALLS: &[PathSyscall] = &[
+        path_syscall(
+            libc::SYS_openat,
+            "openat",
+            &[(Some(0), 1)],
+            Access::Flags(2),
+        ),
+        path_syscall(
+            libc::SYS_openat2,
+            "openat2",
+            &[(Some(0), 1)],
+            Access::How(2),
+        ),
+        path_syscall(libc::SYS_mkdirat, "mkdirat", &[(Some(0), 1)], Access::Write),
+        path_syscall(
+            libc::SYS_unlinkat,
+            "unlinkat",
+            &[(Some(0), 1)],
+            Access::Write,
+        ),
+        path_syscall(
+            libc::SYS_renameat2,
+            "renameat2",
+            &[(Some(0), 1), (Some(2), 3)],
+            Access::Write,
+        ),
+        path_syscall(
+            libc::SYS_symlinkat,
+            "symlinkat",
+            &[(Some(1), 2)],
+            Access::Write,
+        ),
+        path_syscall(libc::SYS_linkat, "linkat", &[(Some(2), 3)], Access::Write),
+        path_syscall(libc::SYS_truncate, "truncate", &[(None, 0)], Access::Write),
+    ];
+
+    /// The pre-`*at` calls only some architectures still have
+    #[cfg(target_arch = "x86_64")]
+    const LEGACY_PATH_SYSCALLS: &[PathSyscall] = &[
+        path_syscall(libc::SYS_open, "open", &[(None, 0)], Access::Flags(1)),
+        path_syscall(libc::SYS_creat, "creat", &[(None, 0)], Access::Write),
+        path_syscall(libc::SYS_mkdir, "mkdir", &[(None, 0)], Access::Write),
+        path_syscall(libc::SYS_rmdir, "rmdir", &[(None, 0)], Access::Write),
+        path_syscall(libc::SYS_unlink, "unlink", &[(None, 0)], Access::Write),
+        path_syscall(
+            libc::SYS_rename,
+            "rename",
+            &[(None, 0), (None, 1)],
+            Access::Write,
+        ),
+        path_syscall(
+            libc::SYS_renameat,
+            "renameat",
+            &[(Some(0), 1), (Some(2), 3)],
+            Access::Write,
+        ),
+        path_syscall(libc::SYS_symlink, "symlink", &[(None, 1)], Access::Write),
+        path_syscall(libc::SYS_link, "link", &[(None, 1)], Access::Write),
+    ];
+    #[cfg(not(target_arch = "x86_64"))]
+    const LEGACY_PATH_SYSCALLS: &[PathSyscall] = &[];
+
+    /// Set in the number of an x32 syscall on x86_64
+    const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+
+    /// A syscall under numbers the tables here don't describe
+    fn foreign_abi(data: &libc::seccomp_data) -> bool {
+        Some(data.arch) != AUDIT_ARCH
+            || (cfg!(target_arch = "x86_64") && data.nr as u32 & X32_SYSCALL_BIT != 0)
+    }
+
+    fn path_syscalls() -> impl Iterator<Item = &'static PathSyscall> {
+        PATH_SYSCALLS.iter().chain(LEGACY_PATH_SYSCALLS)
+    }
+
+    fn statement(code: u32, k: u32) -> libc::sock_filter {
+        libc::sock_filter {
+            code: code as u16,
+            jt: 0,
+            jf: 0,
+            k,
+        }
+    }
+
+    fn jump(code: u32, k: u32, jt: u8, jf: u8) -> libc::sock_filter {
+        libc::sock_filter {
+            code: code as u16,
+            jt,
+            jf,
+            k,
+        }
+    }
+
+    /// Seccomp program passing syscalls of a foreign ABI, `BLOCKED_SYSCALLS`,
+    /// `clone` with namespace flags, internet sockets (unless the build may
+    /// use the network) and path writes to the monitor, and allowing
+    /// everything else. `clone3` keeps its flags in memory a filter can't
+    /// read, so it fails with ENOSYS and libc falls back to `clone`.
+    pub fn seccomp_filter(network: bool) -> Option<Vec<libc::sock_filter>> {
+        use libc::{
+            BPF_ABS, BPF_JEQ, BPF_JMP, BPF_JSET, BPF_K, BPF_LD, BPF_RET, BPF_W, SECCOMP_RET_ALLOW,
+            SECCOMP_RET_ERRNO, SECCOMP_RET_USER_NOTIF,
+        };
+        let arch = AUDIT_ARCH?;
+        let equals =
+            |value: libc::c_long, jt, jf| jump(BPF_JMP | BPF_JEQ | BPF_K, value as u32, jt, jf);
+        // Low half of an argument in struct seccomp_data (little endian)
+        let argument = |index: usize| statement(BPF_LD | BPF_W | BPF_ABS, 16 + 8 * index as u32);
+        let allow = statement(BPF_RET | BPF_K, SECCOMP_RET_ALLOW);
+        let notify = statement(BPF_RET | BPF_K, SECCOMP_RET_USER_NOTIF);
+
+        // Offsets of `nr` and `arch` in struct seccomp_data
+        let mut program = vec![
+            statement(BPF_LD | BPF_W | BPF_ABS, 4),
+            jump(BPF_JMP | BPF_JEQ | BPF_K, arch, 1, 0),
+            notify,
+            statement(BPF_LD | BPF_W | BPF_ABS, 0),
+        ];
+        if cfg!(target_arch = "x86_64") {
+            // x32 syscalls are the same calls under other numbers
+            program.push(jump(BPF_JMP | libc::BPF_JGE | BPF_K, X32_SYSCALL_BIT, 0, 1));
+            program.push(notify);
+        }
+        for (nr, _) in BLOCKED_SYSCALLS {
+            program.extend([equals(*nr, 0, 1), notify]);
+        }
+        program.extend([
+            equals(libc::SYS_clone3, 0, 1),
+            statement(BPF_RET | BPF_K, SECCOMP_RET_ERRNO | libc::ENOSYS as u32),
+            equals(libc::SYS_clone, 0, 4),
+            argument(0),
+            jump(BPF_JMP | BPF_JSET | BPF_K, NAMESPACE_FLAGS, 1, 0),
+            allow,
+            notify,
+        ]);
+        if !network {
+            program.extend([
+                equals(libc::SYS_socket, 0, 5),
+                argument(0),
+                equals(libc::AF_INET.into(), 2, 0),
+                equals(libc::AF_INET6.into(), 1, 0),
+                allow,
+                notify,
+            ]);
+        }
+        for syscall in path_syscalls() {
+            match syscall.access {
+                Access::Flags(index) => program.extend([
+                    equals(syscall.nr, 0, 4),
+                    argument(index),
+                    jump(BPF_JMP | BPF_JSET | BPF_K, WRITE_FLAGS, 1, 0),
+                    allow,
+                    notify,
+                ]),
+                Access::Write | Access::How(_) => {
+                    program.extend([equals(syscall.nr, 0, 1), notify])
+                }
+            }
+        }
+        program.push(allow);
+        Some(program)
+    }
+
+    /// Mount flags the kernel won't let an unprivileged remount drop, from
+    /// the options column of /proc/self/mountinfo
+    fn locked_flags(options: &str) -> libc::c_ulong {
+        let mut flags = 0;
+        let mut atime = libc::MS_STRICTATIME;
+        for option in options.split(',') {
+            match option {
+                "nosuid" => flags |= libc::MS_NOSUID,
+                "nodev" => flags |= libc::MS_NODEV,
+                "noexec" => flags |= libc::MS_NOEXEC,
+                "nodiratime" => flags |= libc::MS_NODIRATIME,
+                "noatime" => atime = libc::MS_NOATIME,
+                "relatime" => atime = libc::MS_RELATIME,
+                _ => {}
+            }
+        }
+        flags | atime
+    }
+
+    /// Undo the octal escapes mountinfo uses for spaces and the like
+    fn unescape(field: &str) -> PathBuf {
+        let bytes = field.as_bytes();
+        let mut out = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            let octal = bytes
+                .get(i + 1..i + 4)
+                .and_then(|digits| u8::from_str_radix(std::str::from_utf8(digits).ok()?, 8).ok());
+            match octal {
+                Some(byte) if bytes[i] == b'\\' => {
+                    out.push(byte);
+                    i += 4;
+                }
+                _ => {
+                    out.push(bytes[i]);
+                    i += 1;
+                }
+            }
+        }
+        PathBuf::from(std::ffi::OsString::from_vec(out))
+    }
+
+    /// Every mount point with its locked flags
+    fn host_mounts() -> io::Result<Vec<(PathBuf, libc::c_ulong)>> {
+        let mountinfo = std::fs::read_to_string("/proc/self/mountinfo")?;
+        Ok(mountinfo
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split(' ').skip(4);
+                let mount_point = unescape(fields.next()?);
+                Some((mount_point, locked_flags(fields.next()?)))
+            })
+            .collect())
+    }
+
+    /// Locked flags of the mount `path` is on
+    fn flags_of(path: &Path, mounts: &[(PathBuf, libc::c_ulong)]) -> libc::c_ulong {
+        mounts
+            .iter()
+            .filter(|(mount_point, _)| path.starts_with(mount_point))
+            .max_by_key(|(mount_point, _)| mount_point.as_os_str().len())
+            .map_or(0, |(_, flags)| *flags)
+    }
+
+    /// A host path mounted back over the private tmpfs mounts
+    struct Bind {
+        path: CString,
+        /// Directories to create first when `path` is inside a tmpfs
+        ancestors: Vec<CString>,
+        is_dir: bool,
+        /// Writable, with these locked flags; None keeps it read-only
+        writable: Option<libc::c_ulong>,
+    }
+
+    /// Everything the child needs, prepared before fork: nothing may be
+    /// allocated between fork and exec
+    pub struct Setup {
+        network: bool,
+        uid_map: Vec<u8>,
+        gid_map: Vec<u8>,
+        /// Mount points remounted read-only, with their locked flags
+        read_only: Vec<(CString, libc::c_ulong)>,
+        /// Directories covered by an empty tmpfs
+        private: Vec<CString>,
+        /// The project first, then the paths the build may write to
+        binds: Vec<Bind>,
+        /// `O_PATH` descriptors of `binds`, opened in the child
+        fds: Vec<libc::c_int>,
+        filter: Vec<libc::sock_filter>,
+        /// Where the child sends its seccomp listener to the monitor
+        notify_socket: RawFd,
+    }
+
+    // SAFETY: sock_filter is plain data
+    unsafe impl Send for Setup {}
+    unsafe impl Sync for Setup {}
+
+    impl Setup {
+        pub fn new(
+            project: &Path,
+            writable: &[PathBuf],
+            home: &Path,
+            network: bool,
+            notify_socket: RawFd,
+        ) -> io::Result<Self> {
+            // SAFETY: getuid and getgid cannot fail
+            let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+            let mounts = host_mounts()?;
+
+            // The kernel and device trees stay as they are; /dev/shm is private
+            let read_only = mounts
+                .iter()
+                .filter(|(mount_point, _)| {
+                    !mount_point.starts_with("/proc") && !mount_point.starts_with("/dev")
+                })
+                .map(|(mount_point, flags)| Ok((c_path(mount_point)?, *flags)))
+                .collect::<io::Result<_>>()?;
+
+            let mut private = vec![PathBuf::from("/tmp"), PathBuf::from("/dev/shm")];
+            private.push(home.to_path_buf());
+            private.sort();
+            private.dedup();
+            let private = private
+                .iter()
+                .filter(|dir| dir.is_dir())
+                .map(|dir| c_path(dir))
+                .collect::<io::Result<_>>()?;
+
+            let bind = |path: &Path, writable: bool| -> io::Result<Bind> {
+                Ok(Bind {
+                    path: c_path(path)?,
+                    ancestors: path
+                        .ancestors()
+                        .skip(1)
+                        .collect::<Vec<_>>()
+                        .into_iter()
+                        .rev()
+                        .skip(1)
+                        .map(c_path)
+                        .collect::<io::Result<_>>()?,
+                    is_dir: path.is_dir(),
+                    writable: writable.then(|| flags_of(path, &mounts)),
+                })
+            };
+            let mut binds = vec![bind(project, false)?];
+            for path in writable {
+                binds.push(bind(path, true)?);
+            }
+
+            Ok(Self {
+                network,
+                uid_map: format!("{uid} {uid} 1\n").into_bytes(),
+                gid_map: format!("{gid} {gid} 1\n").into_bytes(),
+                read_only,
+                private,
+                fds: vec![-1; binds.len()],
+                binds,
+                // Without a filter nothing would stop or report a build
+                // escaping the namespaces, so builds don't run at all
+                filter: seccomp_filter(network).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::Unsupported,
+                        "no seccomp filter for this CPU architecture",
+                    )
+                })?,
+                notify_socket,
+            })
+        }
+
+        /// Enter the sandbox. Runs in the forked child right before exec.
+        pub fn enter(&mut self) -> io::Result<()> {
+            // SAFETY: raw syscalls on pointers to memory owned by `self`
+            unsafe {
+                let mut namespaces = libc::CLONE_NEWUSER | libc::CLONE_NEWNS | libc::CLONE_NEWIPC;
+                if !self.network {
+                    namespaces |= libc::CLONE_NEWNET;
+                }
+                check(libc::unshare(namespaces))?;
+
+                write_file(b"/proc/self/setgroups\0", b"deny")?;
+                write_file(b"/proc/self/uid_map\0", &self.uid_map)?;
+                write_file(b"/proc/self/gid_map\0", &self.gid_map)?;
+
+                // Nothing done here may leak back to the host
+                mount(
+                    None,
+                    b"/\0".as_ptr().cast(),
+                    None,
+                    libc::MS_REC | libc::MS_PRIVATE,
+                )?;
+
+                // Keep hold of the project before tmpfs mounts can hide it
+                for (bind, fd) in self.binds.iter().zip(self.fds.iter_mut()) {
+                    *fd = libc::open(bind.path.as_ptr(), libc::O_PATH | libc::O_CLOEXEC);
+                    if *fd < 0 {
+                        return Err(io::Error::last_os_error());
+                    }
+                }
+
+                for (mount_point, flags) in &self.read_only {
+                    let remounted = mount(
+                        None,
+                        mount_point.as_ptr(),
+                        None,
+                        libc::MS_BIND | libc::MS_REMOUNT | libc::MS_RDONLY | flags,
+                    );
+                    match remounted {
+                        // Hidden below another mount or not reachable for
+                        // us: the build can't write there either
+                        Err(e)
+                            if matches!(
+                                e.raw_os_error(),
+                                Some(libc::ENOENT | libc::EACCES | libc::EINVAL)
+                            ) => {}
+                        result => result?,
+                    }
+                }
+
+                for dir in &self.private {
+                    mount(
+                        Some(b"tmpfs\0".as_ptr().cast()),
+                        dir.as_ptr(),
+                        Some(b"tmpfs\0".as_ptr().cast()),
+                        libc::MS_NOSUID | libc::MS_NODEV,
+                    )?;
+                }
+
+                let mut fd_path = [0u8; 32];
+                for (bind, fd) in self.binds.iter().zip(&self.fds) {
+                    // Recreate the way to it when it lives inside a tmpfs
+                    for dir in &bind.ancestors {
+                        libc::mkdir(dir.as_ptr(), 0o755);
+                    }
+                    if bind.is_dir {
+                        libc::mkdir(bind.path.as_ptr(), 0o755);
+                    } else {
+                        let created =
+                            libc::open(bind.path.as_ptr(), libc::O_CREAT | libc::O_CLOEXEC, 0o644);
+                        if created >= 0 {
+                            libc::close(created);
+                        }
+                    }
+                    let path = bind.path.as_ptr();
+                    mount(
+                        Some(proc_fd_path(*fd, &mut fd_path)),
+                        path,
+                        None,
+                        libc::MS_BIND | libc::MS_REC,
+                    )?;
+                    libc::close(*fd);
+                    if let Some(flags) = bind.writable {
+                        mount(None, path, None, libc::MS_BIND | libc::MS_REMOUNT | flags)?;
+                    }
+                }
+                // The working directory still points below the old mounts
+                check(libc::chdir(self.binds[0].path.as_ptr()))?;
+
+                check(libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0))?;
+                let program = libc::sock_fprog {
+                    len: self.filter.len() as u16,
+                    filter: self.filter.as_ptr() as *mut libc::sock_filter,
+                };
+                let listener = libc::syscall(
+                    libc::SYS_seccomp,
+                    libc::SECCOMP_SET_MODE_FILTER,
+                    libc::SECCOMP_FILTER_FLAG_NEW_LISTENER,
+                    &program as *const libc::sock_fprog,
+                );
+                if listener < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                let sent = send_fd(self.notify_socket, listener as RawFd);
+                libc::close(listener as RawFd);
+                sent?;
+            }
+            Ok(())
+        }
+    }
+
+    /// `/proc/self/fd/<fd>` written into `buf`, without allocating
+    fn proc_fd_path(fd: libc::c_int, buf: &mut [u8; 32]) -> *const libc::c_char {
+        const PREFIX: &[u8] = b"/proc/self/fd/";
+        buf[..PREFIX.len()].copy_from_slice(PREFIX);
+        let mut digits = [0u8; 10];
+        let mut len = 0;
+        let mut n = fd.max(0) as u32;
+        loop {
+            digits[len] = b'0' + (n % 10) as u8;
+            len += 1;
+            n /= 10;
+            if n == 0 {
+                break;
+            }
+        }
+        for i in 0..len {
+            buf[PREFIX.len() + i] = digits[len - 1 - i];
+        }
+        buf[PREFIX.len() + len] = 0;
+        buf.as_ptr().cast()
+    }
+
+    /// Send `fd` over the unix socket `socket`, without allocating
+    unsafe fn send_fd(socket: RawFd, fd: RawFd) -> io::Result<()> {
+        let mut byte = [0u8; 1];
+        let mut iov = libc::iovec {
+            iov_base: byte.as_mut_ptr().cast(),
+            iov_len: 1,
+        };
+        let mut control = [0u64; 4];
+        let mut message: libc::msghdr = std::mem::zeroed();
+        message.msg_iov = &mut iov;
+        message.msg_iovlen = 1;
+        message.msg_control = control.as_mut_ptr().cast();
+        message.msg_controllen = libc::CMSG_SPACE(std::mem::size_of::<RawFd>() as u32) as _;
+        let header = libc::CMSG_FIRSTHDR(&message);
+        (*header).cmsg_level = libc::SOL_SOCKET;
+        (*header).cmsg_type = libc::SCM_RIGHTS;
+        (*header).cmsg_len = libc::CMSG_LEN(std::mem::size_of::<RawFd>() as u32) as _;
+        std::ptr::write_unaligned(libc::CMSG_DATA(header).cast::<RawFd>(), fd);
+        if libc::sendmsg(socket, &message, 0) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// The descriptor `send_fd` sent, or None when the child closed the
+    /// socket first
+    fn receive_fd(socket: &UnixStream) -> Option<OwnedFd> {
+        // Never wait on a child that died before sending
+        socket
+            .set_read_timeout(Some(Duration::from_secs(10)))
+            .ok()?;
+        let mut byte = [0u8; 1];
+        let mut iov = libc::iovec {
+            iov_base: byte.as_mut_ptr().cast(),
+            iov_len: 1,
+        };
+        let mut control = [0u64; 4];
+        // SAFETY: recvmsg writes into the buffers set up here
+        unsafe {
+            let mut message: libc::msghdr = std::mem::zeroed();
+            message.msg_iov = &mut iov;
+            message.msg_iovlen = 1;
+            message.msg_control = control.as_mut_ptr().cast();
+            message.msg_controllen = std::mem::size_of_val(&control) as _;
+            if libc::recvmsg(socket.as_raw_fd(), &mut message, libc::MSG_CMSG_CLOEXEC) <= 0 {
+                return None;
+            }
+            let header = libc::CMSG_FIRSTHDR(&message);
+            if header.is_null() || (*header).cmsg_type != libc::SCM_RIGHTS {
+                return None;
+            }
+            let fd = std::ptr::read_unaligned(libc::CMSG_DATA(header).cast::<RawFd>());
+            Some(OwnedFd::from_raw_fd(fd))
+        }
+    }
+
+    // _IOWR('!', 0, struct seccomp_notif) and friends from linux/seccomp.h
+    const NOTIF_RECV: u32 = 0xC050_2100;
+    const NOTIF_SEND: u32 = 0xC018_2101;
+    const NOTIF_ID_VALID: u32 = 0x4008_2102;
+
+    /// Most violations kept per build; the rest repeat the same story
+    const MAX_VIOLATIONS: usize = 50;
+
+    /// Answers the seccomp notifications of a build and records what it
+    /// tried that the sandbox doesn't allow
+    pub struct Monitor {
+        stop: Arc<AtomicBool>,
+        thread: JoinHandle<Vec<SandboxViolation>>,
+    }
+
+    impl Monitor {
+        /// Wait for the listener on `socket` and watch the build of `package`
+        /// run by the package manager process `manager`
+        pub fn start(socket: UnixStream, package: &str, manager: u32) -> Self {
+            let stop = Arc::new(AtomicBool::new(false));
+            let stopped = Arc::clone(&stop);
+            let package = package.to_string();
+            let thread = std::thread::spawn(move || {
+                let Some(listener) = receive_fd(&socket) else {
+                    return Vec::new();
+                };
+                let mut recorder = Recorder {
+                    package,
+                    manager,
+                    violations: Vec::new(),
+                };
+                recorder.watch(&listener, &stopped);
+                recorder.violations
+            });
+            Self { stop, thread }
+        }
+
+        /// Stop once the build has exited and return what was recorded
+        pub fn finish(self) -> Vec<SandboxViolation> {
+            self.stop.store(true, Ordering::Relaxed);
+            self.thread.join().unwrap_or_default()
+        }
+    }
+
+    struct Recorder {
+        package: String,
+        /// The package manager itself isn't the package: npm checks for
+        /// updates, for one
+        manager: u32,
+        violations: Vec<SandboxViolation>,
+    }
+
+    impl Recorder {
+        fn watch(&mut self, listener: &OwnedFd, stop: &AtomicBool) {
+            loop {
+                let mut poll = libc::pollfd {
+                    fd: listener.as_raw_fd(),
+                    events: libc::POLLIN,
+                    revents: 0,
+                };
+                // SAFETY: poll on a descriptor we own
+                let ready = unsafe { libc::poll(&mut poll, 1, 50) };
+                if ready < 0 {
+                    if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted {
+                        continue;
+                    }
+                    return;
+                }
+                if poll.revents & libc::POLLIN != 0 {
+                    self.answer(listener.as_raw_fd());
+                } else if poll.revents & (libc::POLLHUP | libc::POLLERR) != 0
+                    || stop.load(Ordering::Relaxed)
+                {
+                    // No process is left under the filter, or the build
+                    // exited and left stragglers behind
+                    return;
+                }
+            }
+        }
+
+        fn answer(&mut self, listener: RawFd) {
+            // SAFETY: the ioctls read and write the structs passed to them
+            unsafe {
+                let mut request: libc::seccomp_notif = std::mem::zeroed();
+                if libc::ioctl(listener, NOTIF_RECV as _, &mut request) != 0 {
+                    // The process died before we got to it
+                    return;
+                }
+                let refused = self.inspect(listener, &request);
+                let mut response: libc::seccomp_notif_resp = std::mem::zeroed();
+                response.id = request.id;
+                match refused {
+                    Some(errno) => response.error = -errno,
+                    None => response.flags = libc::SECCOMP_USER_NOTIF_FLAG_CONTINUE as u32,
+                }
+                libc::ioctl(listener, NOTIF_SEND as _, &mut response);
+            }
+        }
+
+        /// Record what `request` is about. Returns the errno to refuse it
+        /// with, or None to let the kernel carry on with it.
+        fn inspect(&mut self, listener: RawFd, request: &libc::seccomp_notif) -> Option<i32> {
+            let nr = libc::c_long::from(request.data.nr);
+            let args = request.data.args;
+            let pid = request.pid;
+            if foreign_abi(&request.data) {
+                // Seen through these tables it would be some other syscall
+                self.record(
+                    ViolationKind::BlockedSyscall,
+                    format!("syscall {} of a foreign ABI by {}", nr, command_name(pid)),
+                );
+                return Some(libc::EPERM);
+            }
+            if thread_group(pid) == Some(self.manager) {
+                let blocked = nr == libc::SYS_clone
+                    || BLOCKED_SYSCALLS.iter().any(|(blocked, _)| *blocked == nr);
+                return blocked.then_some(libc::EPERM);
+            }
+
+            if let Some((_, name)) = BLOCKED_SYSCALLS.iter().find(|(blocked, _)| *blocked == nr) {
+                self.record(
+                    ViolationKind::BlockedSyscall,
+                    format!("{} by {}", name, command_name(pid)),
+                );
+                return Some(libc::EPERM);
+            }
+            if nr == libc::SYS_clone {
+                self.record(
+                    ViolationKind::BlockedSyscall,
+                    format!(
+                        "clone with namespace flags {:#x} by {}",
+                        args[0] as u32 & NAMESPACE_FLAGS,
+                        command_name(pid)
+                    ),
+                );
+                return Some(libc::EPERM);
+            }
+            if nr == libc::SYS_socket {
+                let family = if args[0] as i32 == libc::AF_INET6 {
+                    "AF_INET6"
+                } else {
+                    "AF_INET"
+                };
+                // The empty network namespace makes it fail by itself
+                self.record(
+                    ViolationKind::NetworkAccess,
+                    format!("socket({}) by {}", family, command_name(pid)),
+                );
+                return None;
+            }
+
+            let syscall = path_syscalls().find(|syscall| syscall.nr == nr)?;
+            if let Access::How(index) = syscall.access {
+                let flags = read_memory(pid, args[index], 8)?;
+                let flags = u64::from_ne_bytes(flags.try_into().ok()?);
+                if flags & u64::from(WRITE_FLAGS) == 0 {
+                    return None;
+                }
+            }
+            for (dirfd, path) in syscall.paths {
+                let Some(path) = read_path(pid, args[*path]) else {
+                    continue;
+                };
+                // The pid could have been reused while we read its memory
+                // SAFETY: NOTIF_ID_VALID only reads the id
+                if unsafe { libc::ioctl(listener, NOTIF_ID_VALID as _, &request.id) } != 0 {
+                    return None;
+                }
+                let dirfd = dirfd.map(|index| args[index] as i32);
+                if let Some(target) = read_only_target(pid, dirfd, &path) {
+                    self.record(
+                        ViolationKind::ProjectWrite,
+                        format!(
+                            "{} {} by {}",
+                            syscall.name,
+                            target.display(),
+                            command_name(pid)
+                        ),
+                    );
+                }
+            }
+            // The read-only mount refuses it by itself
+            None
+        }
+
+        fn record(&mut self, kind: ViolationKind, evidence: String) {
+            let seen = self
+                .violations
+                .iter()
+                .any(|v| v.kind == kind && v.evidence == evidence);
+            if !seen && self.violations.len() < MAX_VIOLATIONS {
+                self.violations.push(SandboxViolation {
+                    package: self.package.clone(),
+                    kind,
+                    evidence,
+                });
+            }
+        }
+    }
+
+    /// Process a thread belongs to; `pid` in a notification is the thread
+    fn thread_group(pid: u32) -> Option<u32> {
+        fs::read_to_string(format!("/proc/{}/status", pid))
+            .ok()?
+            .lines()
+            .find_map(|line| line.strip_prefix("Tgid:"))?
+            .trim()
+            .parse()
+            .ok()
+    }
+
+    fn command_name(pid: u32) -> String {
+        fs::read_to_string(format!("/proc/{}/comm", pid))
+            .map(|comm| comm.trim().to_string())
+            .unwrap_or_else(|_| format!("pid {}", pid))
+    }
+
+    /// `len` bytes at `address` in the memory of `pid`
+    fn read_memory(pid: u32, address: u64, len: usize) -> Option<Vec<u8>> {
+        let memory = File::open(format!("/proc/{}/mem", pid)).ok()?;
+        let mut buf = vec![0; len];
+        memory.read_exact_at(&mut buf, address).ok()?;
+        Some(buf)
+    }
+
+    /// NUL-terminated path at `address` in the memory of `pid`
+    fn read_path(pid: u32, address: u64) -> Option<PathBuf> {
+        let memory = File::open(format!("/proc/{}/mem", pid)).ok()?;
+        let mut path = Vec::new();
+        let mut chunk = [0u8; 256];
+        while path.len() < libc::PATH_MAX as usize {
+            let at = address + path.len() as u64;
+            // Never read across a page that may not be mapped
+            let len = chunk.len().min(4096 - (at % 4096) as usize);
+            let read = memory.read_at(&mut chunk[..len], at).ok()?;
+            if read == 0 {
+                return None;
+            }
+            match chunk[..read].iter().position(|byte| *byte == 0) {
+                Some(end) => {
+                    path.extend_from_slice(&chunk[..end]);
+                    return Some(PathBuf::from(OsStr::from_bytes(&path)));
+                }
+                None => path.extend_from_slice(&chunk[..read]),
+            }
+        }
+        None
+    }
+
+    /// Where `path` (relative to `dirfd`) points for `pid`, if that is on a
+    /// read-only mount. Looked up through /proc/<pid> so the build's own
+    /// mounts and symlinks decide, not ours.
+    fn read_only_target(pid: u32, dirfd: Option<i32>, path: &Path) -> Option<PathBuf> {
+        let (probe, shown) = match path.strip_prefix("/") {
+            Ok(relative) => (
+                PathBuf::from(format!("/proc/{}/root", pid)).join(relative),
+                path.to_path_buf(),
+            ),
+            Err(_) => {
+                let base = match dirfd {
+                    Some(fd) if fd != libc::AT_FDCWD => format!("/proc/{}/fd/{}", pid, fd),
+                    _ => format!("/proc/{}/cwd", pid),
+                };
+                let shown = fs::read_link(&base)
+                    .map(|dir| dir.join(path))
+                    .unwrap_or_else(|_| path.to_path_buf());
+                (PathBuf::from(base).join(path), shown)
+            }
+        };
+        let read_only = |path: &Path| -> Option<bool> {
+            let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+            // SAFETY: statvfs only writes into `stat`
+            let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+            if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+                return None;
+            }
+            Some(stat.f_flag & libc::ST_RDONLY != 0)
+        };
+        // A path still to be created is judged by its directory
+        let on_read_only = read_only(&probe).or_else(|| read_only(probe.parent()?))?;
+        on_read_only.then_some(shown)
+    }
+
+    fn check(result: libc::c_int) -> io::Result<()> {
+        if result != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    unsafe fn mount(
+        source: Option<*const libc::c_char>,
+        target: *const libc::c_char,
+        fstype: Option<*const libc::c_char>,
+        flags: libc::c_ulong,
+    ) -> io::Result<()> {
+        check(libc::mount(
+            source.unwrap_or(std::ptr::null()),
+            target,
+            fstype.unwrap_or(std::ptr::null()),
+            flags,
+            std::ptr::null(),
+        ))
+    }
+
+    unsafe fn write_file(path: &[u8], content: &[u8]) -> io::Result<()> {
+        let fd = libc::open(path.as_ptr().cast(), libc::O_WRONLY | libc::O_CLOEXEC);
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let written = libc::write(fd, content.as_ptr().cast(), content.len());
+        libc::close(fd);
+        if written != content.len() as isize {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Run `script` the way a package manager runs a build script: in a
+    /// child of the process the sandbox started
+    #[cfg(target_os = "linux")]
+    fn build(sandbox: &BuildSandbox, script: &str) -> Result<SandboxRun> {
+        sandbox.run(
+            "native",
+            "sh",
+            &["-c", "sh -c \"$1\"; exit $?", "manager", script],
+        )
+    }
+
+    #[test]
+    fn scrubbed_env_drops_credentials() {
+        let vars = [
+            ("PATH", "/usr/bin"),
+            ("NPM_TOKEN", "npm_abc"),
+            ("GITHUB_TOKEN", "ghp_abc"),
+            ("AWS_ACCESS_KEY_ID", "AKIA"),
+            ("AWS_SECRET_ACCESS_KEY", "secret"),
+            ("npm_config_build_from_source", "true"),
+            ("npm_config__authToken", "npm_abc"),
+            ("HOME", "/home/dev"),
+        ]
+        .map(|(k, v)| (k.to_string(), v.to_string()));
+
+        let env = scrubbed_env(vars, Path::new("/tmp/build-home"));
+        let names: Vec<&str> = env.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["HOME", "PATH", "npm_config_build_from_source"]);
+        assert_eq!(env[0].1, "/tmp/build-home");
+    }
+
+    #[test]
+    fn logged_violations_load_as_findings() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let log = temp.path().join(".fnpm/build-violations.jsonl");
+        assert!(BuildFindings::load(&log).unwrap().is_empty());
+
+        let violation = SandboxViolation {
+            package: "native".to_string(),
+            kind: ViolationKind::BlockedSyscall,
+            evidence: "ptrace by node".to_string(),
+        };
+        for _ in 0..2 {
+            log_violations(
+                &log,
+                "native",
+                Some("1.0.0"),
+                std::slice::from_ref(&violation),
+            )
+            .unwrap();
+        }
+        // Without a version there is nothing to attach it to
+        log_violations(&log, "other", None, std::slice::from_ref(&violation)).unwrap();
+
+        let content = fs::read_to_string(&log).unwrap();
+        assert_eq!(content.lines().count(), 3);
+        let entry: serde_json::Value =
+            serde_json::from_str(content.lines().next().unwrap()).unwrap();
+        assert_eq!(entry["version"], "1.0.0");
+        assert_eq!(
+            entry["findings"][0]["issue_type"],
+            "sandbox_blocked_syscall"
+        );
+        assert_eq!(entry["findings"][0]["severity"], "Critical");
+
+        let findings = BuildFindings::load(&log).unwrap();
+        assert_eq!(findings.get("native", "1.0.0").len(), 1);
+        assert!(findings.get("native", "2.0.0").is_empty());
+        assert!(findings.get("other", "1.0.0").is_empty());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn seccomp_filter_notifies_blocked_syscalls() {
+        let Some(filter) = linux::seccomp_filter(false) else {
+            return;
+        };
+        let last = filter.last().unwrap();
+        assert_eq!(last.k, libc::SECCOMP_RET_ALLOW);
+        assert!(filter.iter().any(|insn| insn.k == libc::SYS_ptrace as u32));
+        assert!(filter
+            .iter()
+            .any(|insn| insn.k == libc::SECCOMP_RET_USER_NOTIF));
+        // clone3 is refused outright; clone only with namespace flags
+        let clone3 = filter
+            .iter()
+            .position(|insn| insn.k == libc::SYS_clone3 as u32)
+            .unwrap();
+        assert_eq!(
+            filter[clone3 + 1].k,
+            libc::SECCOMP_RET_ERRNO | libc::ENOSYS as u32
+        );
+        let clone = filter
+            .iter()
+            .position(|insn| {
+                insn.code == (libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K) as u16
+                    && insn.k == libc::SYS_clone as u32
+            })
+            .unwrap();
+        assert_ne!(filter[clone + 2].k & libc::CLONE_NEWUSER as u32, 0);
+        // With network access sockets are never looked at
+        let networked = linux::seccomp_filter(true).unwrap();
+        assert!(!networked.iter().any(|insn| insn.k == libc::AF_INET6 as u32));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn sandbox_confines_writes_network_and_environment() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let project = temp.path().join("app");
+        let package = project.join("node_modules/native");
+        fs::create_dir_all(&package).unwrap();
+        // Next to the project, in the host's /tmp
+        fs::write(temp.path().join("secret"), "hunter2").unwrap();
+        let probe = format!("fnpm-sandbox-probe-{}", uuid::Uuid::new_v4());
+
+        let sandbox = BuildSandbox::new(&project).unwrap().writable(&package);
+        let script = format!(
+            "echo built > node_modules/native/out; \
+            echo evil > index.js && echo WROTE_PROJECT; \
+            cat ../secret && echo READ_SECRET; \
+            echo x > /etc/{probe} && echo WROTE_ROOT; \
+            echo x > \"$HOME/{probe}\" || echo HOME_NOT_WRITABLE; \
+            grep -c : /proc/net/dev; env"
+        );
+        let run = match build(&sandbox, &script) {
+            Ok(run) => run,
+            Err(e) => {
+                // Unprivileged user namespaces are disabled on this machine
+                eprintln!("skipping: {e}");
+                return;
+            }
+        };
+        let output = &run.output;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert_eq!(fs::read_to_string(package.join("out")).unwrap(), "built\n");
+        assert!(!project.join("index.js").exists());
+        assert!(!stdout.contains("WROTE_PROJECT"));
+        assert!(stderr.contains("Read-only file system"), "{stderr}");
+        // The rest of the host is read-only, /tmp and HOME are private
+        assert!(!stdout.contains("READ_SECRET"), "{stdout}");
+        assert!(!stdout.contains("WROTE_ROOT"), "{stdout}");
+        assert!(!Path::new("/etc").join(&probe).exists());
+        assert!(!stdout.contains("HOME_NOT_WRITABLE"), "{stdout}");
+        if let Some(home) = std::env::var_os("HOME") {
+            assert!(!Path::new(&home).join(&probe).exists());
+        }
+        // Only the loopback interface in a fresh network namespace
+        assert_eq!(stdout.lines().next(), Some("1"), "{stdout}");
+        for line in stdout.lines().skip(1) {
+            let name = line.split('=').next().unwrap();
+            assert!(
+                name == "HOME"
+                    || name == "FNPM_HOOK_ACTIVE"
+                    || name == "PWD"
+                    || name == "SHLVL"
+                    || name == "_"
+                    || KEPT_ENV.contains(&name)
+                    || name.to_ascii_lowercase().starts_with("npm_config_"),
+                "{name} leaked into the build"
+            );
+        }
+
+        let kinds: Vec<ViolationKind> = run.violations.iter().map(|v| v.kind).collect();
+        assert!(kinds.contains(&ViolationKind::ProjectWrite), "{kinds:?}");
+        assert!(run
+            .violations
+            .iter()
+            .any(|v| v.evidence.contains("index.js")));
+
+        // A new user namespace through clone is refused and recorded, where
+        // the host has a python3 outside HOME to make the raw syscall
+        let nr = libc::SYS_clone;
+        let flags = libc::CLONE_NEWUSER | libc::SIGCHLD;
+        let clone = format!(
+            "/usr/bin/python3 -c 'import ctypes, os; libc = ctypes.CDLL(None, use_errno=True); \
+            r = libc.syscall({nr}, {flags}, 0, 0, 0, 0); \
+            r == 0 and os._exit(0); print(\"CLONED\" if r > 0 else \"REFUSED\")' || true"
+        );
+        let run = build(&sandbox, &clone).unwrap();
+        let stdout = String::from_utf8_lossy(&run.output.stdout);
+        if !stdout.is_empty() {
+            assert_eq!(stdout.trim(), "REFUSED");
+            assert!(run
+                .violations
+                .iter()
+                .any(|v| v.kind == ViolationKind::BlockedSyscall && v.evidence.contains("clone")));
+        }
+
+        // A write whose error is swallowed is still seen
+        let run = build(&sandbox, "echo evil > index.js 2>/dev/null; true").unwrap();
+        assert!(run.output.status.success());
+        assert_eq!(run.violations.len(), 1, "{:?}", run.violations);
+        assert_eq!(run.violations[0].kind, ViolationKind::ProjectWrite);
+
+        // Words in the output are not violations
+        let run = build(&sandbox, "echo EROFS ENETUNREACH Bad system call").unwrap();
+        assert!(run.violations.is_empty(), "{:?}", run.violations);
+
+        let run = build(&sandbox, "unshare -U true").unwrap();
+        if Path::new("/usr/bin/unshare").exists() {
+            assert_eq!(run.violations[0].kind, ViolationKind::BlockedSyscall);
+            assert!(run.violations[0].evidence.starts_with("unshare by"));
+        }
+
+        if Path::new("/usr/bin/getent").exists() {
+            let run = build(&sandbox, "getent hosts example.com").unwrap();
+            assert!(run
+                .violations
+                .iter()
+                .any(|v| v.kind == ViolationKind::NetworkAccess));
+        }
+    }
+}
@@ -0,0 +1,433 @@
+// Capability manifest: what a package's code is able to do
+// Summarizes the APIs a package reaches for (network, filesystem writes,
+// child processes, native addons...) so two versions of the same package can
+// be compared. A patch release that suddenly gains network or exec access is
+// the classic sign of a hijacked maintainer account.
+
+use oxc_ast::ast::*;
+use oxc_ast_visit::walk;
+use oxc_ast_visit::Visit;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::deobfuscation::Deobfuscator;
+use crate::taint_analyzer::{
+    is_credential_path, ModuleAliases, EXEC_CONSTRUCTORS, NETWORK_CLIENTS, NETWORK_CONSTRUCTORS,
+    NETWORK_SINKS,
+};
+
+/// Risk added when a patch release gains a sensitive capability
+const PATCH_DRIFT_RISK_SCORE: u32 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Capability {
+    /// http/https/net/dgram/tls/dns, fetch and HTTP clients
+    Network,
+    FsWrite,
+    /// Reads of absolute, home-relative or credential paths
+    FsReadOutsidePackage,
+    ChildProcess,
+    Env,
+    /// `.node` binaries, binding.gyp or node-gyp builds
+    NativeAddon,
+    /// `vm`, `eval` and `new Function`
+    Vm,
+    WorkerThreads,
+    /// `require()`/`import()` of a name that isn't a constant
+    DynamicRequire,
+}
+
+impl Capability {
+    pub fn label(self) -> &'static str {
+        match self {
+            Capability::Network => "network",
+            Capability::FsWrite => "fs-write",
+            Capability::FsReadOutsidePackage => "fs-read-outside-package",
+            Capability::ChildProcess => "child_process",
+            Capability::Env => "env",
+            Capability::NativeAddon => "native-addon",
+            Capability::Vm => "vm",
+            Capability::WorkerThreads => "worker_threads",
+            Capability::DynamicRequire => "dynamic-require",
+        }
+    }
+
+    /// Capabilities a hijacked release needs to do damage
+    pub fn is_sensitive(self) -> bool {
+        matches!(self, Capability::Network | Capability::ChildProcess)
+    }
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+pub fn join(capabilities: &BTreeSet<Capability>) -> String {
+    capabilities
+        .iter()
+        .map(|c| c.label())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Capability granted by requiring/importing `module`, if any
+fn module_capability(module: &str) -> Option<Capability> {
+    let module = module.trim_start_matches("node:");
+    if module.ends_with(".node") {
+        return Some(Capability::NativeAddon);
+    }
+    let root = module.split('/').next().unwrap_or(module);
+    match root {
+        "http" | "https" | "http2" | "net" | "dgram" | "tls" | "dns" | "undici" | "node-fetch"
+        | "axios" | "got" | "ws" => Some(Capability::Network),
+        "child_process" => Some(Capability::ChildProcess),
+        "vm" => Some(Capability::Vm),
+        "worker_threads" => Some(Capability::WorkerThreads),
+        "bindings" | "node-gyp-build" | "node-addon-api" => Some(Capability::NativeAddon),
+        _ => None,
+    }
+}
+
+const FS_WRITE_METHODS: &[&str] = &[
+    "writeFile",
+    "writeFileSync",
+    "appendFile",
+    "appendFileSync",
+    "createWriteStream",
+    "mkdir",
+    "mkdirSync",
+    "rm",
+    "rmSync",
+    "rmdir",
+    "rmdirSync",
+    "unlink",
+    "unlinkSync",
+    "rename",
+    "renameSync",
+    "copyFile",
+    "copyFileSync",
+    "cp",
+    "cpSync",
+    "chmod",
+    "chmodSync",
+    "symlink",
+    "symlinkSync",
+];
+
+const FS_READ_METHODS: &[&str] = &[
+    "readFile",
+    "readFileSync",
+    "createReadStream",
+    "readdir",
+    "readdirSync",
+];
+
+/// Environment variables pointing at the user's home or profile
+const HOME_VARIABLES: &[&str] = &["HOME", "USERPROFILE", "APPDATA", "LOCALAPPDATA"];
+
+/// Lifecycle commands that build a native addon
+pub fn builds_native_addon(script: &str) -> bool {
+    ["node-gyp", "prebuild-install", "node-pre-gyp", "cmake-js"]
+        .iter()
+        .any(|tool| script.contains(tool))
+}
+
+struct CapabilityVisitor<'d, 's> {
+    deobfuscator: &'d Deobfuscator<'s>,
+    aliases: ModuleAliases,
+    found: BTreeSet<Capability>,
+}
+
+impl CapabilityVisitor<'_, '_> {
+    fn import(&mut self, module: &str) {
+        self.found.extend(module_capability(module));
+    }
+
+    /// `require(x)` / `import(x)`: a capability when `x` is constant,
+    /// otherwise the module loaded can't be known
+    fn load(&mut self, specifier: Option<&Expression<'_>>) {
+        match specifier.and_then(|s| self.deobfuscator.string_value(s)) {
+            Some(module) => self.import(&module),
+            None => {
+                self.found.insert(Capability::DynamicRequire);
+            }
+        }
+    }
+
+    /// Does a path expression leave the package (`~/.ssh`, `/etc/passwd`,
+    /// `os.homedir()`)?
+    fn points_outside(&self, expr: &Expression<'_>) -> bool {
+        if is_credential_path(expr) {
+            return true;
+        }
+        match expr.get_inner_expression() {
+            Expression::StringLiteral(lit) => lit.value.starts_with(['/', '~']),
+            Expression::TemplateLiteral(template) => {
+                template
+                    .quasis
+                    .first()
+                    .is_some_and(|quasi| quasi.value.raw.starts_with(['/', '~']))
+                    || template
+                        .expressions
+                        .first()
+                        .is_some_and(|first| self.points_outside(first))
+            }
+            Expression::BinaryExpression(binary) => self.points_outside(&binary.left),
+            Expression::CallExpression(call) => match self.aliases.qualify(&call.callee) {
+                Some(name) if name == "os.homedir" => true,
+                // path.join(os.homedir(), ...) / path.resolve('/etc', ...)
+                Some(name) if name.starts_with("path.") => call
+                    .arguments
+                    .first()
+                    .and_then(|arg| arg.as_expression())
+                    .is_some_and(|arg| self.points_outside(arg)),
+                _ => false,
+            },
+            expr => self
+                .aliases
+                .qualify(expr)
+                .and_then(|name| name.strip_prefix("process.env.").map(String::from))
+                .is_some_and(|variable| HOME_VARIABLES.contains(&variable.as_str())),
+        }
+    }
+
+    fn call(&mut self, call: &CallExpression<'_>, name: &str) {
+        let is_client_call = NETWORK_CLIENTS.iter().any(|client| {
+            name.strip_prefix(client)
+                .is_some_and(|m| m.starts_with('.'))
+        });
+        if NETWORK_SINKS.contains(&name) || is_client_call {
+            self.found.insert(Capability::Network);
+        } else if name == "eval" || name.starts_with("vm.") {
+            self.found.insert(Capability::Vm);
+        } else if name.starts_with("child_process.") {
+            self.found.insert(Capability::ChildProcess);
+        } else if name == "process.dlopen" {
+            self.found.insert(Capability::NativeAddon);
+        } else if let Some(method) = name
+            .strip_prefix("fs.")
+            .map(|rest| rest.rsplit('.').next().unwrap_or(rest))
+        {
+            if FS_WRITE_METHODS.contains(&method) {
+                self.found.insert(Capability::FsWrite);
+            } else if FS_READ_METHODS.contains(&method)
+                && call
+                    .arguments
+                    .first()
+                    .and_then(|arg| arg.as_expression())
+                    .is_some_and(|arg| self.points_outside(arg))
+            {
+                self.found.insert(Capability::FsReadOutsidePackage);
+            }
+        }
+    }
+}
+
+impl<'a> Visit<'a> for CapabilityVisitor<'_, '_> {
+    fn visit_import_declaration(&mut self, decl: &ImportDeclaration<'a>) {
+        self.aliases.record_import(decl);
+        self.import(&decl.source.value);
+        walk::walk_import_declaration(self, decl);
+    }
+
+    fn visit_import_expression(&mut self, expr: &ImportExpression<'a>) {
+        self.load(Some(&expr.source));
+        walk::walk_import_expression(self, expr);
+    }
+
+    fn visit_variable_declarator(&mut self, decl: &VariableDeclarator<'a>) {
+        if let Some(init) = &decl.init {
+            self.aliases.record_require(&decl.id, init);
+        }
+        walk::walk_variable_declarator(self, decl);
+    }
+
+    fn visit_call_expression(&mut self, call: &CallExpression<'a>) {
+        if matches!(&call.callee, Expression::Identifier(ident) if ident.name == "require") {
+            self.load(call.arguments.first().and_then(|arg| arg.as_expression()));
+        } else if let Some(name) = self.aliases.qualify(&call.callee) {
+            self.call(call, &name);
+        }
+        walk::walk_call_expression(self, call);
+    }
+
+    fn visit_new_expression(&mut self, expr: &NewExpression<'a>) {
+        if let Some(name) = self.aliases.qualify(&expr.callee) {
+            if NETWORK_CONSTRUCTORS.contains(&name.as_str()) {
+                self.found.insert(Capability::Network);
+            } else if EXEC_CONSTRUCTORS.contains(&name.as_str()) {
+                self.found.insert(Capability::Vm);
+            } else if name == "Worker" || name == "worker_threads.Worker" {
+                self.found.insert(Capability::WorkerThreads);
+            }
+        }
+        walk::walk_new_expression(self, expr);
+    }
+
+    fn visit_static_member_expression(&mut self, expr: &StaticMemberExpression<'a>) {
+        if expr.property.name == "env"
+            && matches!(&expr.object, Expression::Identifier(ident) if ident.name == "process")
+        {
+            self.found.insert(Capability::Env);
+        }
+        walk::walk_static_member_expression(self, expr);
+    }
+}
+
+/// Capabilities used by one parsed file
+pub fn analyze_program(
+    program: &Program<'_>,
+    deobfuscator: &Deobfuscator<'_>,
+) -> BTreeSet<Capability> {
+    let mut visitor = CapabilityVisitor {
+        deobfuscator,
+        aliases: ModuleAliases::default(),
+        found: BTreeSet::new(),
+    };
+    visitor.visit_program(program);
+    visitor.found
+}
+
+/// How the capabilities changed between the installed version of a package
+/// and the one about to replace it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CapabilityDrift {
+    pub from_version: String,
+    pub to_version: String,
+    pub gained: BTreeSet<Capability>,
+    pub lost: BTreeSet<Capability>,
+}
+
+impl CapabilityDrift {
+    /// `None` when both versions can do the same things
+    pub fn between(
+        from_version: &str,
+        from: &BTreeSet<Capability>,
+        to_version: &str,
+        to: &BTreeSet<Capability>,
+    ) -> Option<Self> {
+        let gained: BTreeSet<Capability> = to.difference(from).copied().collect();
+        let lost: BTreeSet<Capability> = from.difference(to).copied().collect();
+        if gained.is_empty() && lost.is_empty() {
+            return None;
+        }
+        Some(Self {
+            from_version: from_version.to_string(),
+            to_version: to_version.to_string(),
+            gained,
+            lost,
+        })
+    }
+
+    /// Same major and minor version, moving forward
+    pub fn is_patch_release(&self) -> bool {
+        match (
+            semver::Version::parse(&self.from_version),
+            semver::Version::parse(&self.to_version),
+        ) {
+            (Ok(from), Ok(to)) => from.major == to.major && from.minor == to.minor && to > from,
+            _ => false,
+        }
+    }
+
+    /// A patch release that gains network or exec access
+    pub fn is_suspicious(&self) -> bool {
+        self.is_patch_release() && self.gained.iter().any(|c| c.is_sensitive())
+    }
+
+    pub fn risk_score(&self) -> u32 {
+        if self.is_suspicious() {
+            PATCH_DRIFT_RISK_SCORE
+        } else {
+            0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oxc_allocator::Allocator;
+    use oxc_parser::Parser;
+    use oxc_span::SourceType;
+
+    fn capabilities(code: &str) -> Vec<&'static str> {
+        let allocator = Allocator::default();
+        let program = Parser::new(&allocator, code, SourceType::unambiguous())
+            .parse()
+            .program;
+        let deobfuscator = Deobfuscator::analyze(&program, code);
+        analyze_program(&program, &deobfuscator)
+            .into_iter()
+            .map(Capability::label)
+            .collect()
+    }
+
+    #[test]
+    fn detects_capabilities_from_imports_and_calls() {
+        assert_eq!(
+            capabilities(
+                "const { exec } = require('node:child_process');\nconst fs = require('fs');\nfs.writeFileSync('out.txt', process.env.TOKEN);\nfetch('https://x.example');\n"
+            ),
+            vec!["network", "fs-write", "child_process", "env"]
+        );
+        assert_eq!(
+            capabilities(
+                "import { Worker } from 'worker_threads';\nimport vm from 'vm';\nnew Worker('./w.js');\nconst addon = require('./build/Release/addon.node');\n"
+            ),
+            vec!["native-addon", "vm", "worker_threads"]
+        );
+        assert_eq!(
+            capabilities("const dgram = require('dgram');\nrequire(`./plugins/${name}`);\n"),
+            vec!["network", "dynamic-require"]
+        );
+        // Folded names count as constants
+        assert_eq!(
+            capabilities("require('chi' + 'ld_process');\n"),
+            vec!["child_process"]
+        );
+    }
+
+    #[test]
+    fn only_reads_outside_the_package_count() {
+        assert!(capabilities(
+            "const fs = require('fs');\nfs.readFileSync(__dirname + '/data.json');\n"
+        )
+        .is_empty());
+        assert_eq!(
+            capabilities(
+                "const fs = require('fs');\nconst os = require('os');\nconst path = require('path');\nfs.readFileSync(path.join(os.homedir(), '.gitconfig'));\n"
+            ),
+            vec!["fs-read-outside-package"]
+        );
+        assert_eq!(
+            capabilities(
+                "const { readFile } = require('fs/promises');\nreadFile('/etc/passwd');\n"
+            ),
+            vec!["fs-read-outside-package"]
+        );
+    }
+
+    #[test]
+    fn patch_release_gaining_network_is_suspicious() {
+        let old: BTreeSet<Capability> = [Capability::Env].into();
+        let new: BTreeSet<Capability> = [Capability::Env, Capability::Network].into();
+
+        let drift = CapabilityDrift::between("1.4.2", &old, "1.4.3", &new).unwrap();
+        assert_eq!(drift.gained, [Capability::Network].into());
+        assert!(drift.lost.is_empty());
+        assert!(drift.is_suspicious());
+        assert_eq!(drift.risk_score(), PATCH_DRIFT_RISK_SCORE);
+
+        // Same change in a minor release is reported but not flagged
+        let minor = CapabilityDrift::between("1.4.2", &old, "1.5.0", &new).unwrap();
+        assert!(!minor.is_suspicious());
+        assert_eq!(minor.risk_score(), 0);
+
+        assert!(CapabilityDrift::between("1.4.2", &old, "1.4.3", &old).is_none());
+    }
+}
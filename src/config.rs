@@ -16,6 +16,9 @@ pub struct Config {
     /// Maximum depth for transitive dependency scanning (0 = disabled, 1-5 = depth)
     #[serde(default = "default_transitive_scan_depth")]
     pub transitive_scan_depth: usize,
+    /// Number of packages fetched and analyzed in parallel during transitive scans (1-32)
+    #[serde(default = "default_scan_concurrency")]
+    pub scan_concurrency: usize,
     /// Minimum age (in minutes) a package version must have on the registry before
     /// it can be installed. Mirrors pnpm's `minimumReleaseAge`. Default: 1440 (1 day).
     /// Set to 0 to disable.
@@ -25,10 +28,33 @@ pub struct Config {
     /// range (git, http, file, github:, etc.). Mirrors pnpm's `blockExoticSubdeps`.
     #[serde(default = "default_block_exotic_subdeps")]
     pub block_exotic_subdeps: bool,
+    /// Reject `fnpm add` of names that look like typos of popular packages
+    /// (`lodahs`, `crossenv`) or that shadow a scoped package the project uses.
+    #[serde(default = "default_block_typosquats")]
+    pub block_typosquats: bool,
+    /// Names that are allowed through `block_typosquats` on purpose.
+    #[serde(default)]
+    pub typosquat_allowlist: Vec<String>,
+    /// Refuse to `fnpm add` a direct dependency unless its registry signature
+    /// and SLSA provenance attestation verify.
+    #[serde(default)]
+    pub require_provenance: bool,
+    /// Refuse `fnpm add` of versions with a known advisory in the offline
+    /// OSV bundle (downloaded by `fnpm scan --advisories`).
+    #[serde(default)]
+    pub block_known_vulnerable: bool,
     /// Allowlist of packages whose lifecycle scripts (preinstall/install/postinstall)
     /// fnpm is permitted to execute. Mirrors pnpm's `allowBuilds`. Empty = block all.
     #[serde(default)]
     pub allow_builds: Vec<String>,
+    /// `allow_builds` packages whose build scripts may use the network.
+    /// Every other build runs without network access.
+    #[serde(default)]
+    pub allow_builds_network: Vec<String>,
+    /// Install `fnpm add` packages into `.fnpm/staging` with scripts disabled,
+    /// audit exactly those files and only then move them into node_modules.
+    #[serde(default)]
+    pub quarantine: bool,
     /// Directory (relative to project root) where anti-corruption barrel adapters
     /// are generated by `fnpm add --adapter` or the post-install prompt.
     #[serde(default = "default_adapter_dir")]
@@ -99,6 +125,10 @@ fn default_transitive_scan_depth() -> usize {
     2
 }
 
+fn default_scan_concurrency() -> usize {
+    8
+}
+
 fn default_minimum_release_age_minutes() -> u64 {
     1440
 }
@@ -107,6 +137,10 @@ fn default_block_exotic_subdeps() -> bool {
     true
 }
 
+fn default_block_typosquats() -> bool {
+    true
+}
+
 fn default_adapter_dir() -> String {
     "src/adapters".to_string()
 }
@@ -128,9 +162,16 @@ impl Config {
             target_lockfile: None,
             security_audit: default_security_audit(),
             transitive_scan_depth: default_transitive_scan_depth(),
+            scan_concurrency: default_scan_concurrency(),
             minimum_release_age_minutes: default_minimum_release_age_minutes(),
             block_exotic_subdeps: default_block_exotic_subdeps(),
+            block_typosquats: default_block_typosquats(),
+            typosquat_allowlist: Vec::new(),
+            require_provenance: false,
+            block_known_vulnerable: false,
             allow_builds: Vec::new(),
+            allow_builds_network: Vec::new(),
+            quarantine: false,
             adapter_dir: default_adapter_dir(),
             adapter_prompt: default_adapter_prompt(),
             ai: AiConfig::default(),
@@ -144,9 +185,16 @@ impl Config {
             target_lockfile,
             security_audit: default_security_audit(),
             transitive_scan_depth: default_transitive_scan_depth(),
+            scan_concurrency: default_scan_concurrency(),
             minimum_release_age_minutes: default_minimum_release_age_minutes(),
             block_exotic_subdeps: default_block_exotic_subdeps(),
+            block_typosquats: default_block_typosquats(),
+            typosquat_allowlist: Vec::new(),
+            require_provenance: false,
+            block_known_vulnerable: false,
             allow_builds: Vec::new(),
+            allow_builds_network: Vec::new(),
+            quarantine: false,
             adapter_dir: default_adapter_dir(),
             adapter_prompt: default_adapter_prompt(),
             ai: AiConfig::default(),
@@ -211,6 +259,10 @@ impl Config {
         self.transitive_scan_depth = depth.min(5);
     }
 
+    pub fn get_scan_concurrency(&self) -> usize {
+        self.scan_concurrency.clamp(1, 32)
+    }
+
     pub fn get_minimum_release_age_minutes(&self) -> u64 {
         self.minimum_release_age_minutes
     }
@@ -219,10 +271,34 @@ impl Config {
         self.block_exotic_subdeps
     }
 
+    pub fn is_block_typosquats(&self) -> bool {
+        self.block_typosquats
+    }
+
+    pub fn get_typosquat_allowlist(&self) -> &[String] {
+        &self.typosquat_allowlist
+    }
+
+    pub fn is_require_provenance(&self) -> bool {
+        self.require_provenance
+    }
+
+    pub fn is_block_known_vulnerable(&self) -> bool {
+        self.block_known_vulnerable
+    }
+
     pub fn get_allow_builds(&self) -> &[String] {
         &self.allow_builds
     }
 
+    pub fn get_allow_builds_network(&self) -> &[String] {
+        &self.allow_builds_network
+    }
+
+    pub fn is_quarantine_enabled(&self) -> bool {
+        self.quarantine
+    }
+
     pub fn get_adapter_dir(&self) -> &str {
         &self.adapter_dir
     }
@@ -327,6 +403,30 @@ mod tests {
         assert_eq!(ai.url, "http://localhost:11434");
     }
 
+    #[test]
+    fn test_scan_concurrency_default_and_bounds() {
+        let json = r#"{"package_manager":"npm","global_cache_path":"/tmp/cache"}"#;
+        let mut config: Config = serde_json::from_str(json).unwrap();
+        assert_eq!(config.get_scan_concurrency(), 8);
+
+        config.scan_concurrency = 0;
+        assert_eq!(config.get_scan_concurrency(), 1);
+        config.scan_concurrency = 500;
+        assert_eq!(config.get_scan_concurrency(), 32);
+    }
+
+    #[test]
+    fn test_block_typosquats_defaults_to_on() {
+        let json = r#"{"package_manager":"npm","global_cache_path":"/tmp/cache"}"#;
+        let config: Config = serde_json::from_str(json).unwrap();
+        assert!(config.is_block_typosquats());
+        assert!(config.get_typosquat_allowlist().is_empty());
+
+        let json = r#"{"package_manager":"npm","global_cache_path":"/tmp/cache","typosquat_allowlist":["crossenv"]}"#;
+        let config: Config = serde_json::from_str(json).unwrap();
+        assert_eq!(config.get_typosquat_allowlist(), ["crossenv".to_string()]);
+    }
+
     #[test]
     fn test_get_config_path() {
         let path = Config::get_config_path().expect("Failed to get config path");
@@ -0,0 +1,527 @@
+// Deobfuscation pre-pass over the oxc AST
+// Folds constant string expressions ('ev' + 'al', "\x65\x76\x61\x6c",
+// String.fromCharCode(...), atob(...)) and resolves obfuscator-style string
+// tables (`_0x3f2a[0x12]`, rotated arrays read through accessor functions),
+// so the security visitor can match the names they hide.
+
+use base64::Engine;
+use oxc_ast::ast::*;
+use oxc_ast_visit::walk;
+use oxc_ast_visit::Visit;
+use oxc_span::GetSpan;
+use std::collections::HashMap;
+
+/// Nesting limit when folding an expression
+const MAX_DEPTH: usize = 32;
+
+/// Longest string folding may produce
+const MAX_LEN: usize = 4096;
+
+/// Suffix for findings that only matched once a hidden name was folded
+pub fn annotate(description: &str, revealed: &str) -> String {
+    format!(
+        "{} (revealed by deobfuscation: '{}')",
+        description, revealed
+    )
+}
+
+/// Marker searched for by the behavioral chain detection
+pub const REVEALED_MARKER: &str = "revealed by deobfuscation";
+
+/// Constant value of a folded expression
+#[derive(Debug, Clone, PartialEq)]
+enum Const {
+    Str(String),
+    Num(f64),
+    List(Vec<String>),
+}
+
+impl Const {
+    fn into_string(self) -> Option<String> {
+        match self {
+            Const::Str(s) => Some(s),
+            Const::Num(n) => Some(number_to_string(n)),
+            Const::List(items) => Some(items.join(",")),
+        }
+    }
+
+    /// Index into a string table: `0x12` or `'0x12'`
+    fn as_index(&self) -> Option<usize> {
+        match self {
+            Const::Num(n) if *n >= 0.0 && n.fract() == 0.0 => Some(*n as usize),
+            Const::Str(s) => parse_int(s).and_then(|n| usize::try_from(n).ok()),
+            _ => None,
+        }
+    }
+}
+
+fn number_to_string(n: f64) -> String {
+    if n.fract() == 0.0 && n.abs() < 1e15 {
+        format!("{}", n as i64)
+    } else {
+        format!("{}", n)
+    }
+}
+
+/// JavaScript `parseInt` for the decimal and hex forms obfuscators emit
+fn parse_int(s: &str) -> Option<i64> {
+    let s = s.trim();
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => i64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+/// Accessor function over a string table:
+/// `function _0x5678(i) { i = i - 0x1a; return _0x3f2a[i]; }`
+#[derive(Debug, Clone)]
+struct Accessor {
+    table: String,
+    offset: i64,
+}
+
+/// Constants collected from one file, in source order
+pub struct Deobfuscator<'s> {
+    source_text: &'s str,
+    /// Variables bound to a single constant string (`None` once reassigned)
+    strings: HashMap<String, Option<String>>,
+    tables: HashMap<String, Vec<String>>,
+    accessors: HashMap<String, Accessor>,
+}
+
+impl<'s> Deobfuscator<'s> {
+    /// Nothing collected yet: only literal expressions fold
+    pub fn new(source_text: &'s str) -> Self {
+        Self {
+            source_text,
+            strings: HashMap::new(),
+            tables: HashMap::new(),
+            accessors: HashMap::new(),
+        }
+    }
+
+    /// Collect constant bindings, string tables and their accessors
+    pub fn analyze(program: &Program<'_>, source_text: &'s str) -> Self {
+        let mut deobfuscator = Self::new(source_text);
+        deobfuscator.visit_program(program);
+        deobfuscator
+    }
+
+    /// String an expression evaluates to, if it is constant
+    pub fn string_value(&self, expr: &Expression<'_>) -> Option<String> {
+        match self.fold(expr, 0)? {
+            Const::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// String an expression evaluates to when it is written to hide it:
+    /// anything but a plain string literal without escapes.
+    pub fn reveal(&self, expr: &Expression<'_>) -> Option<String> {
+        if let Expression::StringLiteral(lit) = expr.get_inner_expression() {
+            let raw = self
+                .source_text
+                .get(lit.span.start as usize..lit.span.end as usize)
+                .unwrap_or_default();
+            return raw.contains('\\').then(|| lit.value.to_string());
+        }
+        self.string_value(expr)
+    }
+
+    fn fold(&self, expr: &Expression<'_>, depth: usize) -> Option<Const> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        let depth = depth + 1;
+        let value = match expr.get_inner_expression() {
+            Expression::StringLiteral(lit) => Const::Str(lit.value.to_string()),
+            Expression::NumericLiteral(num) => Const::Num(num.value),
+            Expression::TemplateLiteral(template) => {
+                let mut text = String::new();
+                for (i, quasi) in template.quasis.iter().enumerate() {
+                    text.push_str(quasi.value.cooked.as_deref()?);
+                    if let Some(expr) = template.expressions.get(i) {
+                        text.push_str(&self.fold(expr, depth)?.into_string()?);
+                    }
+                }
+                Const::Str(text)
+            }
+            Expression::BinaryExpression(binary) if binary.operator == BinaryOperator::Addition => {
+                match (
+                    self.fold(&binary.left, depth)?,
+                    self.fold(&binary.right, depth)?,
+                ) {
+                    (Const::Num(a), Const::Num(b)) => Const::Num(a + b),
+                    (left, right) => Const::Str(left.into_string()? + &right.into_string()?),
+                }
+            }
+            Expression::Identifier(ident) => {
+                Const::Str(self.strings.get(ident.name.as_str())?.clone()?)
+            }
+            Expression::ArrayExpression(array) => {
+                let mut items = Vec::new();
+                for element in &array.elements {
+                    match self.fold(element.as_expression()?, depth)? {
+                        Const::Str(s) => items.push(s),
+                        _ => return None,
+                    }
+                }
+                Const::List(items)
+            }
+            // _0x3f2a[0x12]
+            Expression::ComputedMemberExpression(member) => {
+                let Expression::Identifier(table) = member.object.get_inner_expression() else {
+                    return None;
+                };
+                let index = self.fold(&member.expression, depth)?.as_index()?;
+                Const::Str(self.tables.get(table.name.as_str())?.get(index)?.clone())
+            }
+            Expression::CallExpression(call) => self.fold_call(call, depth)?,
+            Expression::SequenceExpression(seq) => self.fold(seq.expressions.last()?, depth)?,
+            _ => return None,
+        };
+        match &value {
+            Const::Str(s) if s.len() > MAX_LEN => None,
+            _ => Some(value),
+        }
+    }
+
+    fn argument(&self, call: &CallExpression<'_>, index: usize, depth: usize) -> Option<Const> {
+        self.fold(call.arguments.get(index)?.as_expression()?, depth)
+    }
+
+    fn fold_call(&self, call: &CallExpression<'_>, depth: usize) -> Option<Const> {
+        if let Expression::Identifier(ident) = call.callee.get_inner_expression() {
+            if ident.name == "atob" {
+                let encoded = self.argument(call, 0, depth)?.into_string()?;
+                return decode_base64(&encoded).map(Const::Str);
+            }
+            // _0x5678('0x12')
+            let accessor = self.accessors.get(ident.name.as_str())?;
+            let index = self.argument(call, 0, depth)?.as_index()? as i64 - accessor.offset;
+            let table = self.tables.get(&accessor.table)?;
+            return Some(Const::Str(table.get(usize::try_from(index).ok()?)?.clone()));
+        }
+
+        let member = call.callee.get_inner_expression().as_member_expression()?;
+        let method = member.static_property_name()?;
+
+        if let Expression::Identifier(object) = member.object().get_inner_expression() {
+            match (object.name.as_str(), method) {
+                ("String", "fromCharCode") => {
+                    let mut text = String::new();
+                    for index in 0..call.arguments.len() {
+                        let Const::Num(code) = self.argument(call, index, depth)? else {
+                            return None;
+                        };
+                        text.push(char::from_u32(code as u32)?);
+                    }
+                    return Some(Const::Str(text));
+                }
+                // Buffer.from('ZXZhbA==', 'base64')
+                ("Buffer", "from") => {
+                    let data = self.argument(call, 0, depth)?.into_string()?;
+                    return match self.argument(call, 1, depth) {
+                        Some(Const::Str(encoding)) if encoding == "base64" => {
+                            decode_base64(&data).map(Const::Str)
+                        }
+                        Some(Const::Str(encoding)) if encoding == "hex" => {
+                            decode_hex(&data).map(Const::Str)
+                        }
+                        None => Some(Const::Str(data)),
+                        _ => None,
+                    };
+                }
+                _ => {}
+            }
+        }
+
+        let receiver = self.fold(member.object(), depth)?;
+        match (receiver, method) {
+            (Const::Str(s), "toString") => Some(Const::Str(s)),
+            (Const::Str(s), "split") => match self.argument(call, 0, depth)? {
+                Const::Str(sep) if sep.is_empty() => {
+                    Some(Const::List(s.chars().map(String::from).collect()))
+                }
+                Const::Str(sep) => Some(Const::List(s.split(&sep).map(String::from).collect())),
+                _ => None,
+            },
+            (Const::List(mut items), "reverse") => {
+                items.reverse();
+                Some(Const::List(items))
+            }
+            (Const::List(items), "join") => {
+                let sep = match self.argument(call, 0, depth) {
+                    Some(sep) => sep.into_string()?,
+                    None => ",".to_string(),
+                };
+                Some(Const::Str(items.join(&sep)))
+            }
+            _ => None,
+        }
+    }
+
+    /// `i = i - 0x1a` / `i -= 0x1a` at the top of an accessor
+    fn accessor_offset(body: &FunctionBody<'_>, param: &str) -> i64 {
+        for statement in &body.statements {
+            let Statement::ExpressionStatement(stmt) = statement else {
+                continue;
+            };
+            let Expression::AssignmentExpression(assign) = stmt.expression.get_inner_expression()
+            else {
+                continue;
+            };
+            if !matches!(&assign.left, AssignmentTarget::AssignmentTargetIdentifier(id) if id.name == param)
+            {
+                continue;
+            }
+            let offset = match (assign.operator, assign.right.get_inner_expression()) {
+                (AssignmentOperator::Subtraction, Expression::NumericLiteral(num)) => num.value,
+                (AssignmentOperator::Assign, Expression::BinaryExpression(binary))
+                    if binary.operator == BinaryOperator::Subtraction =>
+                {
+                    match (&binary.left, &binary.right) {
+                        (Expression::Identifier(id), Expression::NumericLiteral(num))
+                            if id.name == param =>
+                        {
+                            num.value
+                        }
+                        _ => continue,
+                    }
+                }
+                _ => continue,
+            };
+            return offset as i64;
+        }
+        0
+    }
+
+    /// Record `name` as an accessor if its body reads one of the tables
+    fn record_accessor(&mut self, name: &str, function: &Function<'_>) {
+        let (Some(body), Some(param)) = (&function.body, function.params.items.first()) else {
+            return;
+        };
+        let BindingPattern::BindingIdentifier(param) = &param.pattern else {
+            return;
+        };
+        let text = self
+            .source_text
+            .get(body.span.start as usize..body.span.end as usize)
+            .unwrap_or_default();
+        let mut tables: Vec<&String> = self.tables.keys().collect();
+        tables.sort_by_key(|table| std::cmp::Reverse(table.len()));
+        let Some(table) = tables
+            .into_iter()
+            .find(|table| text.contains(table.as_str()))
+        else {
+            return;
+        };
+        let accessor = Accessor {
+            table: table.clone(),
+            offset: Self::accessor_offset(body, &param.name),
+        };
+        self.accessors.insert(name.to_string(), accessor);
+    }
+
+    /// `(function (arr, n) { ... arr.push(arr.shift()) ... })(_0x3f2a, 0x1a3)`
+    fn apply_rotation(&mut self, call: &CallExpression<'_>) {
+        let is_function = matches!(
+            call.callee.get_inner_expression(),
+            Expression::FunctionExpression(_) | Expression::ArrowFunctionExpression(_)
+        );
+        let (true, [Argument::Identifier(table), Argument::NumericLiteral(count)]) =
+            (is_function, call.arguments.as_slice())
+        else {
+            return;
+        };
+        let span = call.callee.span();
+        let body = self
+            .source_text
+            .get(span.start as usize..span.end as usize)
+            .unwrap_or_default();
+        if !(body.contains("push") && body.contains("shift")) {
+            return;
+        }
+        if let Some(items) = self.tables.get_mut(table.name.as_str()) {
+            if !items.is_empty() {
+                let len = items.len();
+                items.rotate_left(count.value as usize % len);
+            }
+        }
+    }
+}
+
+fn decode_base64(encoded: &str) -> Option<String> {
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(encoded.trim())
+        .ok()?;
+    String::from_utf8(bytes).ok()
+}
+
+fn decode_hex(encoded: &str) -> Option<String> {
+    if encoded.len() % 2 != 0 {
+        return None;
+    }
+    let bytes = (0..encoded.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(encoded.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    String::from_utf8(bytes).ok()
+}
+
+impl<'a> Visit<'a> for Deobfuscator<'_> {
+    fn visit_variable_declarator(&mut self, decl: &VariableDeclarator<'a>) {
+        if let (BindingPattern::BindingIdentifier(ident), Some(init)) = (&decl.id, &decl.init) {
+            let name = ident.name.to_string();
+            match init.get_inner_expression() {
+                Expression::FunctionExpression(function) => self.record_accessor(&name, function),
+                init => match self.fold(init, 0) {
+                    Some(Const::List(items)) => {
+                        self.tables.insert(name, items);
+                    }
+                    Some(Const::Str(value)) => {
+                        // Declared twice with different values: not a constant
+                        let entry = self.strings.entry(name).or_insert(Some(value.clone()));
+                        if entry.as_deref() != Some(value.as_str()) {
+                            *entry = None;
+                        }
+                    }
+                    _ => {}
+                },
+            }
+        }
+        walk::walk_variable_declarator(self, decl);
+    }
+
+    fn visit_declaration(&mut self, decl: &Declaration<'a>) {
+        if let Declaration::FunctionDeclaration(function) = decl {
+            if let Some(id) = &function.id {
+                self.record_accessor(&id.name, function);
+            }
+        }
+        walk::walk_declaration(self, decl);
+    }
+
+    fn visit_assignment_expression(&mut self, expr: &AssignmentExpression<'a>) {
+        if let AssignmentTarget::AssignmentTargetIdentifier(ident) = &expr.left {
+            if let Some(value) = self.strings.get_mut(ident.name.as_str()) {
+                *value = None;
+            }
+        }
+        walk::walk_assignment_expression(self, expr);
+    }
+
+    fn visit_call_expression(&mut self, call: &CallExpression<'a>) {
+        self.apply_rotation(call);
+        walk::walk_call_expression(self, call);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oxc_allocator::Allocator;
+    use oxc_parser::Parser;
+    use oxc_span::SourceType;
+
+    /// Fold the initializer of the last `const result = ...` in `code`
+    fn folded(code: &str) -> Option<String> {
+        let allocator = Allocator::default();
+        let program = Parser::new(&allocator, code, SourceType::cjs())
+            .parse()
+            .program;
+        let deobfuscator = Deobfuscator::analyze(&program, code);
+        let Some(Statement::VariableDeclaration(decl)) = program.body.last() else {
+            panic!("last statement must be a declaration");
+        };
+        deobfuscator.reveal(decl.declarations[0].init.as_ref().unwrap())
+    }
+
+    #[test]
+    fn folds_concatenation_and_escapes() {
+        assert_eq!(
+            folded("const result = 'chi' + 'ld_pro' + `cess`;").as_deref(),
+            Some("child_process")
+        );
+        assert_eq!(
+            folded(r#"const result = "\x65\x76\x61\x6c";"#).as_deref(),
+            Some("eval")
+        );
+        assert_eq!(
+            folded(r#"const result = "\u0065val";"#).as_deref(),
+            Some("eval")
+        );
+        assert_eq!(
+            folded("const a = 'ev';\nconst result = `${a}al`;").as_deref(),
+            Some("eval")
+        );
+        // A plain literal hides nothing
+        assert_eq!(folded("const result = 'eval';"), None);
+    }
+
+    #[test]
+    fn folds_string_builders() {
+        assert_eq!(
+            folded("const result = String.fromCharCode(101, 118, 0x61, 108);").as_deref(),
+            Some("eval")
+        );
+        assert_eq!(
+            folded("const result = 'lave'.split('').reverse().join('');").as_deref(),
+            Some("eval")
+        );
+        assert_eq!(
+            folded("const result = atob('ZXZhbA==');").as_deref(),
+            Some("eval")
+        );
+        assert_eq!(
+            folded("const result = Buffer.from('6576616c', 'hex').toString();").as_deref(),
+            Some("eval")
+        );
+    }
+
+    #[test]
+    fn reassigned_variables_are_not_constant() {
+        assert_eq!(
+            folded("let a = 'ev';\na = other();\nconst result = a + 'al';"),
+            None
+        );
+    }
+
+    #[test]
+    fn resolves_rotated_string_tables_through_accessors() {
+        let code = r#"
+var _0x3f2a = ['child_process', 'exec', 'eval'];
+(function (_0x1, _0x2) {
+    var _0x3 = function (_0x4) {
+        while (--_0x4) {
+            _0x1['push'](_0x1['shift']());
+        }
+    };
+    _0x3(++_0x2);
+})(_0x3f2a, 0x1);
+var _0x5678 = function (_0x6, _0x7) {
+    _0x6 = _0x6 - 0x0;
+    var _0x8 = _0x3f2a[_0x6];
+    return _0x8;
+};
+const direct = _0x3f2a[0x0];
+const result = _0x5678('0x2');
+"#;
+        assert_eq!(folded(code).as_deref(), Some("child_process"));
+
+        let allocator = Allocator::default();
+        let program = Parser::new(&allocator, code, SourceType::cjs())
+            .parse()
+            .program;
+        let deobfuscator = Deobfuscator::analyze(&program, code);
+        let Some(Statement::VariableDeclaration(decl)) = program.body.get(3) else {
+            panic!("expected `const direct`");
+        };
+        assert_eq!(
+            deobfuscator
+                .string_value(decl.declarations[0].init.as_ref().unwrap())
+                .as_deref(),
+            Some("exec")
+        );
+    }
+}
@@ -0,0 +1,331 @@
+// Dependency edges recorded by the transitive, installed and lockfile scans
+// Nodes are keyed like `TransitiveScanResult.package_audits`, so `fnpm why`
+// can walk from the root dependencies down to any flagged package and
+// annotate each step with the audit of that package.
+
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashSet, VecDeque};
+
+use crate::package_manager::split_package_spec;
+use crate::security::{RiskLevel, TransitiveScanResult};
+
+/// Dependency paths beyond this are counted but not listed
+pub const MAX_PATHS: usize = 100;
+
+/// Which package depends on which, as seen by a scan
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DependencyGraph {
+    /// Direct dependencies of the project (or the package being added)
+    pub roots: BTreeSet<String>,
+    /// Package → the dependencies it pulled in
+    pub edges: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl DependencyGraph {
+    pub fn add_root(&mut self, key: &str) {
+        self.roots.insert(key.to_string());
+    }
+
+    pub fn add_edge(&mut self, parent: &str, child: &str) {
+        self.edges
+            .entry(parent.to_string())
+            .or_default()
+            .insert(child.to_string());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+
+    pub fn dependencies(&self, key: &str) -> impl Iterator<Item = &String> {
+        self.edges.get(key).into_iter().flatten()
+    }
+
+    /// Every package with a direct edge to `key`
+    pub fn dependents(&self, key: &str) -> Vec<&String> {
+        self.edges
+            .iter()
+            .filter(|(_, children)| children.contains(key))
+            .map(|(parent, _)| parent)
+            .collect()
+    }
+
+    /// Acyclic paths from a root to any node matching `target`, at most
+    /// `limit` of them. The second value is the number of paths found before
+    /// giving up, so callers can say how many were left out.
+    pub fn paths_to(
+        &self,
+        target: impl Fn(&str) -> bool,
+        limit: usize,
+    ) -> (Vec<Vec<String>>, usize) {
+        // Only walk through nodes the target can be reached from
+        let mut relevant: HashSet<&String> = HashSet::new();
+        let mut queue: VecDeque<&String> = self
+            .roots
+            .iter()
+            .chain(self.edges.keys())
+            .chain(self.edges.values().flatten())
+            .filter(|key| target(key))
+            .collect();
+        while let Some(key) = queue.pop_front() {
+            if relevant.insert(key) {
+                queue.extend(self.dependents(key));
+            }
+        }
+
+        let mut paths = Vec::new();
+        let mut found = 0;
+        for root in self.roots.iter().filter(|root| relevant.contains(root)) {
+            let mut path = vec![root];
+            self.walk(&mut path, &relevant, &target, limit, &mut paths, &mut found);
+        }
+        (paths, found)
+    }
+
+    fn walk<'a>(
+        &'a self,
+        path: &mut Vec<&'a String>,
+        relevant: &HashSet<&String>,
+        target: &impl Fn(&str) -> bool,
+        limit: usize,
+        paths: &mut Vec<Vec<String>>,
+        found: &mut usize,
+    ) {
+        let current = path[path.len() - 1];
+        if target(current) {
+            *found += 1;
+            if paths.len() < limit {
+                paths.push(path.iter().map(|key| key.to_string()).collect());
+            }
+            return;
+        }
+        // Counting stops well past the limit so huge graphs stay fast
+        if *found > limit * 10 {
+            return;
+        }
+        for child in self.dependencies(current) {
+            if relevant.contains(child) && !path.contains(&child) {
+                path.push(child);
+                self.walk(path, relevant, target, limit, paths, found);
+                path.pop();
+            }
+        }
+    }
+}
+
+/// One package along a dependency path
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PathStep {
+    pub name: String,
+    pub version: Option<String>,
+    /// None when the package wasn't audited (depth limit or scan failure)
+    pub risk_level: Option<RiskLevel>,
+}
+
+impl PathStep {
+    fn label(&self) -> String {
+        match &self.version {
+            Some(version) => format!("{}@{}", self.name, version),
+            None => self.name.clone(),
+        }
+    }
+}
+
+/// Answer to `fnpm why <package>`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WhyReport {
+    pub package: String,
+    pub paths: Vec<Vec<PathStep>>,
+    /// Paths found in total; more than `paths.len()` when some were left out
+    pub total_paths: usize,
+}
+
+/// Does scan key `key` refer to `package` (`name` or `name@version`)?
+fn key_matches(key: &str, package: &str, result: &TransitiveScanResult) -> bool {
+    let (name, spec) = split_package_spec(package);
+    let (key_name, _) = split_package_spec(key);
+    if key_name != name {
+        return false;
+    }
+    // No version asked for ("latest" is split_package_spec's default)
+    if spec == "latest" && !package.ends_with("@latest") {
+        return true;
+    }
+    step(key, result).version.as_deref() == Some(spec.as_str())
+}
+
+fn step(key: &str, result: &TransitiveScanResult) -> PathStep {
+    let audit = result.package_audits.get(key);
+    let (name, spec) = split_package_spec(key);
+    let from_key = (key.trim_start_matches('@').contains('@')).then_some(spec);
+    PathStep {
+        name,
+        version: audit.and_then(|a| a.version.clone()).or(from_key),
+        risk_level: audit.map(|a| a.risk_level),
+    }
+}
+
+/// Every dependency path from a root of `result` to `package`
+pub fn explain(result: &TransitiveScanResult, package: &str) -> WhyReport {
+    let graph = &result.dependency_graph;
+    let (paths, total_paths) = graph.paths_to(|key| key_matches(key, package, result), MAX_PATHS);
+    WhyReport {
+        package: package.to_string(),
+        paths: paths
+            .iter()
+            .map(|path| path.iter().map(|key| step(key, result)).collect())
+            .collect(),
+        total_paths,
+    }
+}
+
+/// Paths merged into a tree, so shared prefixes print once
+struct TreeNode<'a> {
+    step: &'a PathStep,
+    children: Vec<TreeNode<'a>>,
+}
+
+fn insert<'a>(nodes: &mut Vec<TreeNode<'a>>, path: &'a [PathStep]) {
+    let Some((first, rest)) = path.split_first() else {
+        return;
+    };
+    let index = match nodes.iter().position(|node| node.step == first) {
+        Some(index) => index,
+        None => {
+            nodes.push(TreeNode {
+                step: first,
+                children: Vec::new(),
+            });
+            nodes.len() - 1
+        }
+    };
+    insert(&mut nodes[index].children, rest);
+}
+
+fn print_tree(nodes: &[TreeNode], prefix: &str) {
+    for (index, node) in nodes.iter().enumerate() {
+        let last = index + 1 == nodes.len();
+        let risk = match node.step.risk_level {
+            Some(level) => level.color(),
+            None => "not scanned".bright_black().to_string(),
+        };
+        println!(
+            "{}{} {} {}",
+            prefix,
+            if last { "└─" } else { "├─" },
+            node.step.label().bright_white(),
+            risk
+        );
+        let child_prefix = format!("{}{}", prefix, if last { "   " } else { "│  " });
+        print_tree(&node.children, &child_prefix);
+    }
+}
+
+/// Print `report` as a tree rooted at the project's direct dependencies
+pub fn print_why(report: &WhyReport) {
+    if report.paths.is_empty() {
+        println!(
+            "{} {} is not in the scanned dependency graph",
+            "ℹ".cyan(),
+            report.package.bright_white()
+        );
+        return;
+    }
+
+    println!(
+        "{} {} is pulled in by {} path(s):\n",
+        "🔗".cyan(),
+        report.package.bright_white().bold(),
+        report.total_paths.to_string().bright_white()
+    );
+    let mut roots = Vec::new();
+    for path in &report.paths {
+        insert(&mut roots, path);
+    }
+    print_tree(&roots, "");
+    if report.total_paths > report.paths.len() {
+        println!(
+            "\n{} {} more path(s) not shown",
+            "…".bright_black(),
+            report.total_paths - report.paths.len()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result() -> TransitiveScanResult {
+        let mut result: TransitiveScanResult = serde_json::from_str(
+            r#"{"total_packages":0,"scanned_packages":0,"high_risk_count":0,"medium_risk_count":0,"packages_with_scripts":0,"max_depth_reached":0,"package_audits":{}}"#,
+        )
+        .unwrap();
+        let graph = &mut result.dependency_graph;
+        graph.add_root("express@4.18.2");
+        graph.add_root("webpack@5.0.0");
+        graph.add_edge("express@4.18.2", "body-parser@1.20.1");
+        graph.add_edge("body-parser@1.20.1", "qs@6.11.0");
+        graph.add_edge("express@4.18.2", "qs@6.11.0");
+        graph.add_edge("webpack@5.0.0", "watchpack@2.0.0");
+        graph.add_edge("watchpack@2.0.0", "qs@6.5.0");
+        // A cycle must not loop forever
+        graph.add_edge("qs@6.11.0", "body-parser@1.20.1");
+
+        for (key, risk) in [
+            ("express@4.18.2", RiskLevel::Safe),
+            ("body-parser@1.20.1", RiskLevel::Low),
+            ("qs@6.11.0", RiskLevel::High),
+        ] {
+            let audit = crate::security::PackageAudit {
+                package_name: "x".to_string(),
+                version: Some(key.rsplit('@').next().unwrap().to_string()),
+                risk_level: risk,
+                ..Default::default()
+            };
+            result.package_audits.insert(key.to_string(), audit);
+        }
+        result
+    }
+
+    #[test]
+    fn explains_every_path_with_versions_and_risk() {
+        let result = result();
+        let report = explain(&result, "qs");
+        assert_eq!(report.total_paths, 3);
+        let labels: Vec<Vec<String>> = report
+            .paths
+            .iter()
+            .map(|path| path.iter().map(PathStep::label).collect())
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                vec!["express@4.18.2", "body-parser@1.20.1", "qs@6.11.0"],
+                vec!["express@4.18.2", "qs@6.11.0"],
+                vec!["webpack@5.0.0", "watchpack@2.0.0", "qs@6.5.0"],
+            ]
+        );
+        assert_eq!(report.paths[1][1].risk_level, Some(RiskLevel::High));
+        assert_eq!(report.paths[2][1].risk_level, None);
+
+        let pinned = explain(&result, "qs@6.5.0");
+        assert_eq!(pinned.total_paths, 1);
+        assert!(explain(&result, "left-pad").paths.is_empty());
+        print_why(&report);
+    }
+
+    #[test]
+    fn limits_listed_paths_but_counts_the_rest() {
+        let mut graph = DependencyGraph::default();
+        for i in 0..5 {
+            graph.add_root(&format!("root-{i}"));
+            graph.add_edge(&format!("root-{i}"), "leaf");
+        }
+        let (paths, found) = graph.paths_to(|key| key == "leaf", 2);
+        assert_eq!(paths.len(), 2);
+        assert_eq!(found, 5);
+        assert_eq!(graph.dependents("leaf").len(), 5);
+    }
+}
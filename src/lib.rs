@@ -1,13 +1,34 @@
 pub mod adapt;
 pub mod adapter;
+pub mod advisories;
 pub mod ai_review;
+pub mod artifacts;
 pub mod ast_analyzer;
 pub mod ast_debug;
 pub mod ast_security_analyzer;
+pub mod audit_cache;
+pub mod baseline;
+pub mod build_sandbox;
+pub mod capabilities;
 pub mod config;
+pub mod deobfuscation;
+pub mod dependency_graph;
+pub mod entropy;
+pub mod lockfile;
+pub mod module_graph;
 pub mod package_manager;
 pub mod package_managers;
+pub mod policy;
+pub mod provenance;
+pub mod quarantine;
+pub mod registry_metadata;
+pub mod sarif;
+pub mod sbom;
 pub mod security;
+pub mod taint_analyzer;
+pub mod tarball;
+pub mod typosquat;
+pub mod watch;
 
 pub use ast_analyzer::{
     AnalysisReport, DockerfileAnalyzer, JsAnalyzer, PackageJsonAnalyzer, YamlAnalyzer,
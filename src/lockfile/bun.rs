@@ -0,0 +1,279 @@
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+use super::{
+    manifest_dependencies, resolve_nested, split_descriptor, LockedDependency, LockedPackage,
+    LockfileGraph,
+};
+
+/// bun.lock is JSON with trailing commas (and possibly comments).
+fn strip_jsonc(content: &str) -> String {
+    let chars: Vec<char> = content.chars().collect();
+    let mut out = String::with_capacity(content.len());
+    let mut in_string = false;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if in_string {
+            out.push(c);
+            if c == '\\' && i + 1 < chars.len() {
+                out.push(chars[i + 1]);
+                i += 1;
+            } else if c == '"' {
+                in_string = false;
+            }
+        } else if c == '"' {
+            in_string = true;
+            out.push(c);
+        } else if c == '/' && chars.get(i + 1) == Some(&'/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            continue;
+        } else if c == ',' {
+            let next = chars[i + 1..].iter().find(|c| !c.is_whitespace());
+            if !matches!(next, Some('}') | Some(']')) {
+                out.push(c);
+            }
+        } else {
+            out.push(c);
+        }
+        i += 1;
+    }
+    out
+}
+
+/// Split a `a/@scope/b` package key into `["a", "@scope/b"]`.
+fn install_path(key: &str) -> Vec<String> {
+    let mut segments = Vec::new();
+    let mut parts = key.split('/');
+    while let Some(part) = parts.next() {
+        if part.starts_with('@') {
+            segments.push(format!("{}/{}", part, parts.next().unwrap_or_default()));
+        } else {
+            segments.push(part.to_string());
+        }
+    }
+    segments
+}
+
+pub(super) fn parse(content: &str) -> Result<LockfileGraph> {
+    let lock: Value = serde_json::from_str(&strip_jsonc(content))?;
+
+    let workspaces = lock
+        .get("workspaces")
+        .and_then(|w| w.as_object())
+        .ok_or_else(|| anyhow!("bun.lock is missing workspaces"))?;
+    if workspaces.len() > 1 {
+        return Err(anyhow!("bun workspaces are not supported"));
+    }
+    let root = workspaces
+        .get("")
+        .ok_or_else(|| anyhow!("bun.lock is missing the root workspace"))?;
+
+    let empty = serde_json::Map::new();
+    let entries = lock
+        .get("packages")
+        .and_then(|p| p.as_object())
+        .unwrap_or(&empty);
+
+    // Install path -> (name, version, entry)
+    let mut installed: BTreeMap<Vec<String>, (String, String, &Value)> = BTreeMap::new();
+    for (key, entry) in entries {
+        let ident = entry
+            .get(0)
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Invalid bun.lock entry: {}", key))?;
+        let (name, version) =
+            split_descriptor(ident).ok_or_else(|| anyhow!("Invalid bun.lock entry: {}", key))?;
+        if version.starts_with("workspace:") || version.starts_with("link:") {
+            return Err(anyhow!(
+                "Linked packages / workspaces are not supported: {}",
+                key
+            ));
+        }
+        installed.insert(
+            install_path(key),
+            (name.to_string(), version.to_string(), entry),
+        );
+    }
+
+    let resolve_all = |from: &[String], deps: BTreeMap<String, String>| {
+        deps.into_iter()
+            .filter_map(|(name, range)| {
+                let (_, version, _) =
+                    resolve_nested(from, &name, |candidate| installed.get(candidate))?;
+                Some((name, LockedDependency::new(range, version.clone())))
+            })
+            .collect::<BTreeMap<_, _>>()
+    };
+
+    let mut graph = LockfileGraph::default();
+    graph.root.name = root.get("name").and_then(|v| v.as_str()).map(String::from);
+    graph.root.dependencies = resolve_all(&[], manifest_dependencies(root, "dependencies"));
+    graph.root.dev_dependencies = resolve_all(&[], manifest_dependencies(root, "devDependencies"));
+    graph.root.optional_dependencies =
+        resolve_all(&[], manifest_dependencies(root, "optionalDependencies"));
+
+    for (path, (name, version, entry)) in &installed {
+        let info = entry.get(2).cloned().unwrap_or(Value::Null);
+        let mut package = LockedPackage::new(name.clone(), version.clone());
+        package.integrity = entry.get(3).and_then(|v| v.as_str()).map(String::from);
+        package.dependencies = resolve_all(path, manifest_dependencies(&info, "dependencies"));
+        package.optional_dependencies =
+            resolve_all(path, manifest_dependencies(&info, "optionalDependencies"));
+        graph.insert(package);
+    }
+
+    graph.compute_flags();
+    Ok(graph)
+}
+
+fn ranges(deps: &BTreeMap<String, LockedDependency>) -> Value {
+    deps.iter()
+        .map(|(name, dep)| (name.clone(), Value::String(dep.range.clone())))
+        .collect::<serde_json::Map<_, _>>()
+        .into()
+}
+
+/// Inline JSON in bun's style: `{ "a": "1" }` and `["x", "y"]`.
+fn inline(value: &Value) -> String {
+    match value {
+        Value::Object(map) if map.is_empty() => "{}".to_string(),
+        Value::Object(map) => format!(
+            "{{ {} }}",
+            map.iter()
+                .map(|(k, v)| format!("{}: {}", Value::String(k.clone()), inline(v)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Value::Array(items) => format!(
+            "[{}]",
+            items.iter().map(inline).collect::<Vec<_>>().join(", ")
+        ),
+        other => other.to_string(),
+    }
+}
+
+pub(super) fn render(graph: &LockfileGraph) -> Result<String> {
+    let mut out = String::from("{\n  \"lockfileVersion\": 1,\n  \"workspaces\": {\n    \"\": {\n");
+    if let Some(name) = &graph.root.name {
+        out.push_str(&format!(
+            "      \"name\": {},\n",
+            Value::String(name.clone())
+        ));
+    }
+    for (label, deps) in [
+        ("dependencies", &graph.root.dependencies),
+        ("devDependencies", &graph.root.dev_dependencies),
+        ("optionalDependencies", &graph.root.optional_dependencies),
+    ] {
+        if deps.is_empty() {
+            continue;
+        }
+        out.push_str(&format!("      \"{}\": {{\n", label));
+        for (name, dep) in deps {
+            out.push_str(&format!(
+                "        {}: {},\n",
+                Value::String(name.clone()),
+                Value::String(dep.range.clone())
+            ));
+        }
+        out.push_str("      },\n");
+    }
+    out.push_str("    },\n  },\n  \"packages\": {\n");
+
+    let entries: Vec<String> = graph
+        .hoisted_layout()
+        .into_iter()
+        .map(|(path, id)| {
+            let package = &graph.packages[&id];
+            let mut info = serde_json::Map::new();
+            if !package.dependencies.is_empty() {
+                info.insert("dependencies".to_string(), ranges(&package.dependencies));
+            }
+            if !package.optional_dependencies.is_empty() {
+                info.insert(
+                    "optionalDependencies".to_string(),
+                    ranges(&package.optional_dependencies),
+                );
+            }
+            let tuple = json!([
+                id,
+                "",
+                Value::Object(info),
+                package.integrity.clone().unwrap_or_default()
+            ]);
+            format!(
+                "    {}: {},\n",
+                Value::String(path.join("/")),
+                inline(&tuple)
+            )
+        })
+        .collect();
+    out.push_str(&entries.join("\n"));
+    out.push_str("  }\n}\n");
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOCK: &str = r#"{
+  "lockfileVersion": 1,
+  "workspaces": {
+    "": {
+      "name": "app",
+      "dependencies": {
+        "a": "^1.0.0",
+        "c": "^1.0.0",
+      },
+      "devDependencies": {
+        "@types/d": "^1.0.0",
+      },
+    },
+  },
+  "packages": {
+    "@types/d": ["@types/d@1.0.0", "", {}, "sha512-ddd"],
+
+    "a": ["a@1.0.0", "", { "dependencies": { "c": "^2.0.0" } }, "sha512-aaa"],
+
+    "a/c": ["c@2.0.0", "", {}, "sha512-c2"],
+
+    "c": ["c@1.0.0", "", {}, "sha512-c1"],
+  }
+}
+"#;
+
+    #[test]
+    fn strip_jsonc_removes_trailing_commas_outside_strings() {
+        let stripped = strip_jsonc(r#"{ "a": [1, 2,], "b": "x,}", } // note"#);
+        let value: Value = serde_json::from_str(&stripped).unwrap();
+        assert_eq!(value["b"], "x,}");
+        assert_eq!(value["a"], json!([1, 2]));
+    }
+
+    #[test]
+    fn parses_nested_install_paths() {
+        let graph = parse(LOCK).unwrap();
+        assert_eq!(graph.packages.len(), 4);
+        assert_eq!(
+            graph.get("a", "1.0.0").unwrap().dependencies["c"].version,
+            "2.0.0"
+        );
+        assert!(graph.get("@types/d", "1.0.0").unwrap().dev);
+        assert_eq!(
+            install_path("a/@s/b"),
+            vec!["a".to_string(), "@s/b".to_string()]
+        );
+    }
+
+    #[test]
+    fn render_round_trips() {
+        let graph = parse(LOCK).unwrap();
+        assert_eq!(render(&graph).unwrap(), LOCK);
+    }
+}
@@ -0,0 +1,311 @@
+//! Compare two resolved lockfile graphs, e.g. the working tree against a
+//! git revision, in terms reviewers care about: which packages were
+//! added, removed, upgraded or downgraded and who pulled them in.
+
+use anyhow::{anyhow, Result};
+use colored::*;
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::process::Command;
+
+use super::{package_id, parse_lockfile, LockfileGraph, LockfileKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Upgraded,
+    Downgraded,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PackageChange {
+    pub name: String,
+    pub kind: ChangeKind,
+    pub old_version: Option<String>,
+    pub new_version: Option<String>,
+    /// Direct dependency of the project (otherwise transitive)
+    pub direct: bool,
+    /// Top-level dependencies that pull this package in
+    pub introduced_by: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LockfileDiff {
+    pub changes: Vec<PackageChange>,
+}
+
+impl LockfileDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn count(&self, kind: ChangeKind) -> usize {
+        self.changes.iter().filter(|c| c.kind == kind).count()
+    }
+
+    /// Versions that are present now but were not before.
+    pub fn introduced(&self) -> Vec<&PackageChange> {
+        self.changes
+            .iter()
+            .filter(|c| c.new_version.is_some())
+            .collect()
+    }
+}
+
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    match (semver::Version::parse(a), semver::Version::parse(b)) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        _ => a.cmp(b),
+    }
+}
+
+fn versions_by_name(graph: &LockfileGraph) -> BTreeMap<&str, BTreeSet<&str>> {
+    let mut versions: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for package in graph.packages.values() {
+        versions
+            .entry(package.name.as_str())
+            .or_default()
+            .insert(package.version.as_str());
+    }
+    versions
+}
+
+/// Compute the package-level changes between two lockfile graphs.
+pub fn diff_lockfiles(old: &LockfileGraph, new: &LockfileGraph) -> LockfileDiff {
+    let old_versions = versions_by_name(old);
+    let new_versions = versions_by_name(new);
+    let old_owners = old.introduced_by();
+    let new_owners = new.introduced_by();
+
+    let names: BTreeSet<&str> = old_versions
+        .keys()
+        .chain(new_versions.keys())
+        .copied()
+        .collect();
+    let empty = BTreeSet::new();
+    let mut changes = Vec::new();
+
+    for name in names {
+        let before = old_versions.get(name).unwrap_or(&empty);
+        let after = new_versions.get(name).unwrap_or(&empty);
+
+        let mut removed: Vec<&str> = before.difference(after).copied().collect();
+        let mut added: Vec<&str> = after.difference(before).copied().collect();
+        removed.sort_by(|a, b| compare_versions(a, b));
+        added.sort_by(|a, b| compare_versions(a, b));
+
+        let change = |kind, old_version: Option<&str>, new_version: Option<&str>| {
+            // Attribute a change to the graph that still contains the package
+            let (graph, owners, version) = match new_version {
+                Some(version) => (new, &new_owners, version),
+                None => (old, &old_owners, old_version.unwrap_or_default()),
+            };
+            PackageChange {
+                name: name.to_string(),
+                kind,
+                old_version: old_version.map(String::from),
+                new_version: new_version.map(String::from),
+                direct: graph.is_direct(name),
+                introduced_by: owners
+                    .get(&package_id(name, version))
+                    .map(|set| set.iter().filter(|o| *o != name).cloned().collect())
+                    .unwrap_or_default(),
+            }
+        };
+
+        // Pair old and new versions of the same package as version bumps
+        let paired = removed.len().min(added.len());
+        for (old_version, new_version) in removed.iter().zip(added.iter()) {
+            let kind = match compare_versions(old_version, new_version) {
+                Ordering::Greater => ChangeKind::Downgraded,
+                _ => ChangeKind::Upgraded,
+            };
+            changes.push(change(kind, Some(old_version), Some(new_version)));
+        }
+        for version in &removed[paired..] {
+            changes.push(change(ChangeKind::Removed, Some(version), None));
+        }
+        for version in &added[paired..] {
+            changes.push(change(ChangeKind::Added, None, Some(version)));
+        }
+    }
+
+    LockfileDiff { changes }
+}
+
+/// Read a file as it was at `git_ref`. Returns `None` when the file didn't
+/// exist in that revision.
+pub fn git_show(git_ref: &str, path: &str) -> Result<Option<String>> {
+    let verify = Command::new("git")
+        .args(["rev-parse", "--verify", "--quiet"])
+        .arg(format!("{}^{{commit}}", git_ref))
+        .output()?;
+    if !verify.status.success() {
+        return Err(anyhow!("Unknown git revision: {}", git_ref));
+    }
+
+    let output = Command::new("git")
+        .arg("show")
+        .arg(format!("{}:./{}", git_ref, path))
+        .output()?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    Ok(Some(String::from_utf8_lossy(&output.stdout).into_owned()))
+}
+
+/// Parse the `kind` lockfile as committed at `git_ref`. A lockfile that
+/// didn't exist yet yields an empty graph.
+pub fn load_lockfile_at_revision(git_ref: &str, kind: LockfileKind) -> Result<LockfileGraph> {
+    let Some(content) = git_show(git_ref, kind.filename())? else {
+        return Ok(LockfileGraph::default());
+    };
+    let manifest =
+        git_show(git_ref, "package.json")?.and_then(|content| serde_json::from_str(&content).ok());
+    parse_lockfile(kind, &content, manifest.as_ref())
+}
+
+/// Print a human-readable summary of the diff.
+pub fn print_diff(diff: &LockfileDiff, lockfile: &str, git_ref: &str) {
+    println!(
+        "\n{} {} {} {}",
+        "📦 Lockfile changes in".cyan().bold(),
+        lockfile.bright_white(),
+        "since".cyan().bold(),
+        git_ref.bright_white()
+    );
+
+    if diff.is_empty() {
+        println!("   {}", "No dependency changes".green());
+        return;
+    }
+    println!();
+
+    for change in &diff.changes {
+        let (marker, label) = match change.kind {
+            ChangeKind::Added => ("+".green().bold(), "added".green()),
+            ChangeKind::Removed => ("-".red().bold(), "removed".red()),
+            ChangeKind::Upgraded => ("↑".cyan().bold(), "upgraded".cyan()),
+            ChangeKind::Downgraded => ("↓".yellow().bold(), "downgraded".yellow()),
+        };
+        let versions = match (&change.old_version, &change.new_version) {
+            (Some(old), Some(new)) => format!("{} → {}", old, new),
+            (Some(version), None) | (None, Some(version)) => version.clone(),
+            (None, None) => String::new(),
+        };
+        let origin = if change.direct {
+            "direct".to_string()
+        } else if change.introduced_by.is_empty() {
+            "transitive".to_string()
+        } else {
+            format!("transitive via {}", change.introduced_by.join(", "))
+        };
+
+        println!(
+            "  {} {:<11} {} {} {}",
+            marker,
+            label,
+            change.name.bright_white(),
+            versions,
+            format!("({})", origin).bright_black()
+        );
+    }
+
+    println!(
+        "\n{} {} added, {} removed, {} upgraded, {} downgraded",
+        "Summary:".bold(),
+        diff.count(ChangeKind::Added),
+        diff.count(ChangeKind::Removed),
+        diff.count(ChangeKind::Upgraded),
+        diff.count(ChangeKind::Downgraded)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lockfile::{LockedDependency, LockedPackage};
+
+    type Deps<'a> = &'a [(&'a str, &'a str)];
+
+    fn graph(direct: Deps, packages: &[(&str, &str, Deps)]) -> LockfileGraph {
+        let mut graph = LockfileGraph::default();
+        for (name, version) in direct {
+            graph.root.dependencies.insert(
+                name.to_string(),
+                LockedDependency::new(format!("^{}", version), *version),
+            );
+        }
+        for (name, version, deps) in packages {
+            let mut package = LockedPackage::new(*name, *version);
+            for (dep, dep_version) in *deps {
+                package.dependencies.insert(
+                    dep.to_string(),
+                    LockedDependency::new(*dep_version, *dep_version),
+                );
+            }
+            graph.insert(package);
+        }
+        graph
+    }
+
+    #[test]
+    fn classifies_changes() {
+        let old = graph(
+            &[("express", "4.0.0"), ("left-pad", "1.0.0")],
+            &[
+                ("express", "4.0.0", &[("ms", "2.0.0")]),
+                ("ms", "2.0.0", &[]),
+                ("left-pad", "1.0.0", &[]),
+            ],
+        );
+        let new = graph(
+            &[("express", "4.1.0")],
+            &[
+                ("express", "4.1.0", &[("ms", "1.9.0"), ("debug", "4.0.0")]),
+                ("ms", "1.9.0", &[]),
+                ("debug", "4.0.0", &[]),
+            ],
+        );
+
+        let diff = diff_lockfiles(&old, &new);
+        let find = |name: &str| diff.changes.iter().find(|c| c.name == name).unwrap();
+
+        assert_eq!(find("express").kind, ChangeKind::Upgraded);
+        assert!(find("express").direct);
+        assert_eq!(find("ms").kind, ChangeKind::Downgraded);
+        assert_eq!(find("ms").introduced_by, vec!["express".to_string()]);
+        assert_eq!(find("debug").kind, ChangeKind::Added);
+        assert!(!find("debug").direct);
+        assert_eq!(find("left-pad").kind, ChangeKind::Removed);
+        assert!(find("left-pad").direct);
+        assert_eq!(diff.introduced().len(), 3);
+    }
+
+    #[test]
+    fn unchanged_graphs_produce_empty_diff() {
+        let old = graph(&[("a", "1.0.0")], &[("a", "1.0.0", &[])]);
+        assert!(diff_lockfiles(&old, &old.clone()).is_empty());
+    }
+
+    #[test]
+    fn extra_versions_are_reported_as_added() {
+        let old = graph(&[("a", "1.0.0")], &[("a", "1.0.0", &[])]);
+        let new = graph(
+            &[("a", "1.0.0"), ("b", "1.0.0")],
+            &[
+                ("a", "1.0.0", &[]),
+                ("b", "1.0.0", &[("a", "2.0.0")]),
+                ("a", "2.0.0", &[]),
+            ],
+        );
+        let diff = diff_lockfiles(&old, &new);
+        let a = diff.changes.iter().find(|c| c.name == "a").unwrap();
+        assert_eq!(a.kind, ChangeKind::Added);
+        assert_eq!(a.new_version.as_deref(), Some("2.0.0"));
+        assert_eq!(a.introduced_by, vec!["b".to_string()]);
+    }
+}
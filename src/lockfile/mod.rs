@@ -0,0 +1,590 @@
+//! Native lockfile parsing and generation.
+//!
+//! Every supported lockfile format is read into one common resolved
+//! dependency graph ([`LockfileGraph`]) and can be written back out in any
+//! other format except yarn berry's. Berry lockfiles record checksums of
+//! the zip archives in yarn's own cache, which fnpm doesn't build, so a
+//! berry target is still synced by yarn. This lets fnpm keep the team's
+//! lockfile in sync without the target package manager being installed,
+//! and gives the security commands an exact `name@version` view of the
+//! project.
+
+mod bun;
+pub mod diff;
+mod npm;
+mod pnpm;
+mod yarn;
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fs;
+use std::path::Path;
+
+/// Lockfile formats fnpm can read and write natively. Yarn berry is read only.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LockfileKind {
+    /// `package-lock.json` (lockfileVersion 2 and 3)
+    Npm,
+    /// `yarn.lock` (classic v1; berry is parsed but never written)
+    Yarn,
+    /// `pnpm-lock.yaml` (lockfileVersion 6 and 9)
+    Pnpm,
+    /// `bun.lock` (text lockfile, not the binary `bun.lockb`)
+    Bun,
+}
+
+impl LockfileKind {
+    pub const ALL: [LockfileKind; 4] = [
+        LockfileKind::Npm,
+        LockfileKind::Yarn,
+        LockfileKind::Pnpm,
+        LockfileKind::Bun,
+    ];
+
+    pub fn from_filename(filename: &str) -> Option<Self> {
+        match filename {
+            "package-lock.json" => Some(LockfileKind::Npm),
+            "yarn.lock" => Some(LockfileKind::Yarn),
+            "pnpm-lock.yaml" => Some(LockfileKind::Pnpm),
+            "bun.lock" => Some(LockfileKind::Bun),
+            _ => None,
+        }
+    }
+
+    pub fn for_package_manager(pm: &str) -> Option<Self> {
+        match pm {
+            "npm" => Some(LockfileKind::Npm),
+            "yarn" => Some(LockfileKind::Yarn),
+            "pnpm" => Some(LockfileKind::Pnpm),
+            "bun" => Some(LockfileKind::Bun),
+            _ => None,
+        }
+    }
+
+    pub fn filename(&self) -> &'static str {
+        match self {
+            LockfileKind::Npm => "package-lock.json",
+            LockfileKind::Yarn => "yarn.lock",
+            LockfileKind::Pnpm => "pnpm-lock.yaml",
+            LockfileKind::Bun => "bun.lock",
+        }
+    }
+
+    pub fn package_manager(&self) -> &'static str {
+        match self {
+            LockfileKind::Npm => "npm",
+            LockfileKind::Yarn => "yarn",
+            LockfileKind::Pnpm => "pnpm",
+            LockfileKind::Bun => "bun",
+        }
+    }
+}
+
+/// A dependency edge: the range that was requested and the version it resolved to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedDependency {
+    pub range: String,
+    pub version: String,
+}
+
+impl LockedDependency {
+    pub fn new(range: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            range: range.into(),
+            version: version.into(),
+        }
+    }
+}
+
+/// The project itself (the lockfile root / importer).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RootProject {
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub dependencies: BTreeMap<String, LockedDependency>,
+    pub dev_dependencies: BTreeMap<String, LockedDependency>,
+    pub optional_dependencies: BTreeMap<String, LockedDependency>,
+}
+
+impl RootProject {
+    /// All direct dependencies regardless of their group.
+    pub fn all_dependencies(&self) -> impl Iterator<Item = (&String, &LockedDependency)> {
+        self.dependencies
+            .iter()
+            .chain(self.optional_dependencies.iter())
+            .chain(self.dev_dependencies.iter())
+    }
+}
+
+/// One resolved package version in the graph.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LockedPackage {
+    pub name: String,
+    pub version: String,
+    pub resolved: Option<String>,
+    pub integrity: Option<String>,
+    pub dependencies: BTreeMap<String, LockedDependency>,
+    pub optional_dependencies: BTreeMap<String, LockedDependency>,
+    /// Only reachable through devDependencies
+    pub dev: bool,
+    /// Only reachable through optionalDependencies
+    pub optional: bool,
+    pub has_install_script: bool,
+}
+
+impl LockedPackage {
+    pub fn new(name: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            version: version.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn id(&self) -> String {
+        package_id(&self.name, &self.version)
+    }
+
+    /// Regular and optional dependencies of this package.
+    pub fn all_dependencies(&self) -> impl Iterator<Item = (&String, &LockedDependency)> {
+        self.dependencies
+            .iter()
+            .chain(self.optional_dependencies.iter())
+    }
+
+    /// Tarball URL, falling back to the conventional npm registry location.
+    pub fn tarball_url(&self) -> String {
+        self.resolved
+            .clone()
+            .unwrap_or_else(|| default_tarball_url(&self.name, &self.version))
+    }
+}
+
+/// Resolved dependency graph shared by all lockfile formats.
+/// Packages are keyed by `name@version`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LockfileGraph {
+    pub root: RootProject,
+    pub packages: BTreeMap<String, LockedPackage>,
+}
+
+/// Key used for packages in [`LockfileGraph::packages`].
+pub fn package_id(name: &str, version: &str) -> String {
+    format!("{}@{}", name, version)
+}
+
+/// Package id a dependency edge points to. Aliased edges
+/// (`"foo": "npm:bar@^1"`) point at the real package name.
+pub fn dependency_id(name: &str, dep: &LockedDependency) -> String {
+    let real_name = dep
+        .range
+        .strip_prefix("npm:")
+        .and_then(split_descriptor)
+        .map(|(real, _)| real)
+        .unwrap_or(name);
+    package_id(real_name, &dep.version)
+}
+
+/// Split `name@range` (scoped names included) into its two halves.
+pub fn split_descriptor(descriptor: &str) -> Option<(&str, &str)> {
+    let idx = descriptor.get(1..)?.find('@')? + 1;
+    Some((&descriptor[..idx], &descriptor[idx + 1..]))
+}
+
+/// Conventional registry tarball URL for a package version.
+pub fn default_tarball_url(name: &str, version: &str) -> String {
+    let basename = name.rsplit('/').next().unwrap_or(name);
+    format!(
+        "https://registry.npmjs.org/{}/-/{}-{}.tgz",
+        name, basename, version
+    )
+}
+
+impl LockfileGraph {
+    pub fn get(&self, name: &str, version: &str) -> Option<&LockedPackage> {
+        self.packages.get(&package_id(name, version))
+    }
+
+    pub fn insert(&mut self, package: LockedPackage) {
+        self.packages.insert(package.id(), package);
+    }
+
+    /// All locked versions of `name`, in key order.
+    pub fn versions_of(&self, name: &str) -> Vec<&LockedPackage> {
+        self.packages.values().filter(|p| p.name == name).collect()
+    }
+
+    /// Is `name` a direct dependency of the project?
+    pub fn is_direct(&self, name: &str) -> bool {
+        self.root.dependencies.contains_key(name)
+            || self.root.dev_dependencies.contains_key(name)
+            || self.root.optional_dependencies.contains_key(name)
+    }
+
+    /// Package ids reachable from the given direct dependencies.
+    pub fn reachable_from<'a, I>(&self, roots: I) -> BTreeSet<String>
+    where
+        I: IntoIterator<Item = (&'a String, &'a LockedDependency)>,
+    {
+        let mut seen = BTreeSet::new();
+        let mut queue: VecDeque<String> = roots
+            .into_iter()
+            .map(|(name, dep)| dependency_id(name, dep))
+            .collect();
+
+        while let Some(id) = queue.pop_front() {
+            if !seen.insert(id.clone()) {
+                continue;
+            }
+            if let Some(package) = self.packages.get(&id) {
+                for (name, dep) in package.all_dependencies() {
+                    queue.push_back(dependency_id(name, dep));
+                }
+            }
+        }
+
+        seen.retain(|id| self.packages.contains_key(id));
+        seen
+    }
+
+    /// Map every package id to the direct dependencies that pull it in.
+    pub fn introduced_by(&self) -> BTreeMap<String, BTreeSet<String>> {
+        let mut owners: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        for (name, dep) in self.root.all_dependencies() {
+            for id in self.reachable_from([(name, dep)]) {
+                owners.entry(id).or_default().insert(name.clone());
+            }
+        }
+        owners
+    }
+
+    /// Recompute `dev`/`optional` flags from reachability. Formats that do
+    /// not record these flags (yarn, bun, pnpm v9) call this after parsing.
+    pub fn compute_flags(&mut self) {
+        let prod = self.reachable_from(self.root.dependencies.iter());
+        let optional = self.reachable_from(self.root.optional_dependencies.iter());
+
+        // Packages only reachable through an optionalDependencies edge
+        let mut required = prod.clone();
+        let mut queue: VecDeque<String> = prod.iter().cloned().collect();
+        while let Some(id) = queue.pop_front() {
+            if let Some(package) = self.packages.get(&id) {
+                for (name, dep) in &package.dependencies {
+                    let child = dependency_id(name, dep);
+                    if self.packages.contains_key(&child) && required.insert(child.clone()) {
+                        queue.push_back(child);
+                    }
+                }
+            }
+        }
+
+        for (id, package) in self.packages.iter_mut() {
+            package.dev = !prod.contains(id) && !optional.contains(id);
+            package.optional = !package.dev && !required.contains(id);
+        }
+    }
+
+    /// Lay the graph out as a `node_modules` tree, hoisting every package as
+    /// high as possible without changing what each dependent resolves to.
+    /// Returns install paths (as name segments) mapped to package ids.
+    pub(crate) fn hoisted_layout(&self) -> BTreeMap<Vec<String>, String> {
+        let mut placements: BTreeMap<Vec<String>, String> = BTreeMap::new();
+        let mut queue: VecDeque<Vec<String>> = VecDeque::new();
+
+        for (name, dep) in self.root.all_dependencies() {
+            let path = vec![name.clone()];
+            if placements.contains_key(&path) {
+                continue;
+            }
+            placements.insert(path.clone(), dependency_id(name, dep));
+            queue.push_back(path);
+        }
+
+        while let Some(path) = queue.pop_front() {
+            let Some(package) = placements.get(&path).and_then(|id| self.packages.get(id)) else {
+                continue;
+            };
+
+            for (name, dep) in package.all_dependencies() {
+                let wanted = dependency_id(name, dep);
+                if !self.packages.contains_key(&wanted) {
+                    continue;
+                }
+
+                // Walk up from the dependent like Node's resolver does
+                let mut found = None;
+                for depth in (0..=path.len()).rev() {
+                    let mut candidate = path[..depth].to_vec();
+                    candidate.push(name.clone());
+                    if let Some(id) = placements.get(&candidate) {
+                        found = Some(id.clone());
+                        break;
+                    }
+                }
+
+                let target = match found {
+                    Some(id) if id == wanted => continue,
+                    Some(_) => {
+                        let mut nested = path.clone();
+                        nested.push(name.clone());
+                        nested
+                    }
+                    None => vec![name.clone()],
+                };
+
+                placements.insert(target.clone(), wanted);
+                queue.push_back(target);
+            }
+        }
+
+        placements
+    }
+}
+
+/// Resolve a dependency from a nested install path by walking up the tree.
+/// `lookup` receives candidate paths from the deepest to the root.
+pub(crate) fn resolve_nested<T, F>(path: &[String], name: &str, lookup: F) -> Option<T>
+where
+    F: Fn(&[String]) -> Option<T>,
+{
+    for depth in (0..=path.len()).rev() {
+        let mut candidate = path[..depth].to_vec();
+        candidate.push(name.to_string());
+        if let Some(found) = lookup(&candidate) {
+            return Some(found);
+        }
+    }
+    None
+}
+
+/// Read the dependency groups from a `package.json` value.
+pub(crate) fn manifest_dependencies(manifest: &Value, field: &str) -> BTreeMap<String, String> {
+    manifest
+        .get(field)
+        .and_then(|d| d.as_object())
+        .map(|deps| {
+            deps.iter()
+                .filter_map(|(name, range)| range.as_str().map(|r| (name.clone(), r.to_string())))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Parse lockfile `content` of the given kind. `manifest` is the project's
+/// `package.json`, needed by formats that do not record the root importer
+/// (yarn) and used to fill in the project name elsewhere.
+pub fn parse_lockfile(
+    kind: LockfileKind,
+    content: &str,
+    manifest: Option<&Value>,
+) -> Result<LockfileGraph> {
+    let mut graph = match kind {
+        LockfileKind::Npm => npm::parse(content)?,
+        LockfileKind::Yarn => yarn::parse(content, manifest)?,
+        LockfileKind::Pnpm => pnpm::parse(content)?,
+        LockfileKind::Bun => bun::parse(content)?,
+    };
+
+    if let Some(manifest) = manifest {
+        if graph.root.name.is_none() {
+            graph.root.name = manifest
+                .get("name")
+                .and_then(|v| v.as_str())
+                .map(String::from);
+        }
+        if graph.root.version.is_none() {
+            graph.root.version = manifest
+                .get("version")
+                .and_then(|v| v.as_str())
+                .map(String::from);
+        }
+    }
+
+    Ok(graph)
+}
+
+/// Render `graph` in the given lockfile format.
+pub fn render_lockfile(kind: LockfileKind, graph: &LockfileGraph) -> Result<String> {
+    match kind {
+        LockfileKind::Npm => npm::render(graph),
+        LockfileKind::Yarn => Ok(yarn::render(graph)),
+        LockfileKind::Pnpm => Ok(pnpm::render(graph)),
+        LockfileKind::Bun => bun::render(graph),
+    }
+}
+
+/// Read the project's `package.json`, if any.
+pub fn read_manifest(project_dir: &Path) -> Option<Value> {
+    fs::read_to_string(project_dir.join("package.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+}
+
+/// Load and parse the `kind` lockfile in `project_dir`.
+pub fn load_lockfile(project_dir: &Path, kind: LockfileKind) -> Result<LockfileGraph> {
+    let path = project_dir.join(kind.filename());
+    let content =
+        fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let manifest = read_manifest(project_dir);
+    parse_lockfile(kind, &content, manifest.as_ref())
+        .with_context(|| format!("Failed to parse {}", kind.filename()))
+}
+
+/// Find the first lockfile fnpm can parse in `project_dir`.
+pub fn detect_lockfile(project_dir: &Path) -> Option<LockfileKind> {
+    LockfileKind::ALL
+        .into_iter()
+        .find(|kind| project_dir.join(kind.filename()).exists())
+}
+
+/// Whether the project in `project_dir` uses yarn 2+ (berry), whose
+/// lockfile format fnpm doesn't write.
+pub fn is_yarn_berry_project(project_dir: &Path) -> bool {
+    yarn::is_berry_project(project_dir)
+}
+
+/// Translate the `from` lockfile in `project_dir` into the `to` format,
+/// writing it next to the source. Fails for a yarn berry target, which the
+/// caller leaves to yarn.
+pub fn translate_lockfile(project_dir: &Path, from: LockfileKind, to: LockfileKind) -> Result<()> {
+    if from == to {
+        return Err(anyhow!("Source and target lockfile are the same"));
+    }
+    if to == LockfileKind::Yarn && is_yarn_berry_project(project_dir) {
+        return Err(anyhow!(
+            "yarn.lock is a yarn berry lockfile, which fnpm reads but can't write"
+        ));
+    }
+    let graph = load_lockfile(project_dir, from)?;
+    let rendered = render_lockfile(to, &graph)?;
+    fs::write(project_dir.join(to.filename()), rendered)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_graph() -> LockfileGraph {
+        let mut graph = LockfileGraph::default();
+        graph.root.name = Some("app".to_string());
+        graph
+            .root
+            .dependencies
+            .insert("a".to_string(), LockedDependency::new("^1.0.0", "1.0.0"));
+        graph
+            .root
+            .dependencies
+            .insert("c".to_string(), LockedDependency::new("^1.0.0", "1.0.0"));
+        graph
+            .root
+            .dev_dependencies
+            .insert("d".to_string(), LockedDependency::new("^1.0.0", "1.0.0"));
+
+        let mut a = LockedPackage::new("a", "1.0.0");
+        a.dependencies
+            .insert("c".to_string(), LockedDependency::new("^2.0.0", "2.0.0"));
+        graph.insert(a);
+        graph.insert(LockedPackage::new("c", "1.0.0"));
+        graph.insert(LockedPackage::new("c", "2.0.0"));
+        graph.insert(LockedPackage::new("d", "1.0.0"));
+        graph
+    }
+
+    #[test]
+    fn split_descriptor_handles_scopes() {
+        assert_eq!(
+            split_descriptor("lodash@^4.0.0"),
+            Some(("lodash", "^4.0.0"))
+        );
+        assert_eq!(
+            split_descriptor("@babel/core@7.0.0"),
+            Some(("@babel/core", "7.0.0"))
+        );
+        assert_eq!(split_descriptor("lodash"), None);
+    }
+
+    #[test]
+    fn default_tarball_url_strips_scope_from_basename() {
+        assert_eq!(
+            default_tarball_url("@types/node", "20.0.0"),
+            "https://registry.npmjs.org/@types/node/-/node-20.0.0.tgz"
+        );
+    }
+
+    #[test]
+    fn hoisted_layout_nests_conflicting_versions() {
+        let layout = sample_graph().hoisted_layout();
+        assert_eq!(layout.get(&vec!["c".to_string()]).unwrap(), "c@1.0.0");
+        assert_eq!(
+            layout.get(&vec!["a".to_string(), "c".to_string()]).unwrap(),
+            "c@2.0.0"
+        );
+        assert_eq!(layout.len(), 4);
+    }
+
+    #[test]
+    fn compute_flags_marks_dev_only_packages() {
+        let mut graph = sample_graph();
+        graph.compute_flags();
+        assert!(graph.get("d", "1.0.0").unwrap().dev);
+        assert!(!graph.get("a", "1.0.0").unwrap().dev);
+        assert!(!graph.get("c", "2.0.0").unwrap().dev);
+    }
+
+    #[test]
+    fn lockfile_kind_round_trips_filenames() {
+        for kind in LockfileKind::ALL {
+            assert_eq!(LockfileKind::from_filename(kind.filename()), Some(kind));
+            assert_eq!(
+                LockfileKind::for_package_manager(kind.package_manager()),
+                Some(kind)
+            );
+        }
+        assert_eq!(LockfileKind::from_filename("bun.lockb"), None);
+    }
+
+    #[test]
+    fn translate_refuses_to_overwrite_berry_lockfile() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let dir = temp.path();
+        fs::write(
+            dir.join("package.json"),
+            r#"{"name":"app","dependencies":{"a":"^1.0.0"}}"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("package-lock.json"),
+            npm::render(&sample_graph()).unwrap(),
+        )
+        .unwrap();
+        let berry = "__metadata:\n  version: 8\n  cacheKey: 10\n";
+        fs::write(dir.join("yarn.lock"), berry).unwrap();
+
+        let err = translate_lockfile(dir, LockfileKind::Npm, LockfileKind::Yarn).unwrap_err();
+        assert!(err.to_string().contains("berry"), "{err}");
+        assert_eq!(fs::read_to_string(dir.join("yarn.lock")).unwrap(), berry);
+
+        // A project pinned to berry has no yarn.lock to look at yet
+        fs::remove_file(dir.join("yarn.lock")).unwrap();
+        fs::write(
+            dir.join("package.json"),
+            r#"{"name":"app","packageManager":"yarn@4.1.0"}"#,
+        )
+        .unwrap();
+        assert!(translate_lockfile(dir, LockfileKind::Npm, LockfileKind::Yarn).is_err());
+        assert!(!dir.join("yarn.lock").exists());
+
+        fs::write(
+            dir.join("package.json"),
+            r#"{"name":"app","packageManager":"yarn@1.22.19"}"#,
+        )
+        .unwrap();
+        translate_lockfile(dir, LockfileKind::Npm, LockfileKind::Yarn).unwrap();
+        assert!(fs::read_to_string(dir.join("yarn.lock"))
+            .unwrap()
+            .contains("# yarn lockfile v1"));
+    }
+}
@@ -0,0 +1,293 @@
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use super::{
+    manifest_dependencies, resolve_nested, LockedDependency, LockedPackage, LockfileGraph,
+};
+
+/// Split a `node_modules/a/node_modules/@s/b` key into `["a", "@s/b"]`.
+fn install_path(key: &str) -> Option<Vec<String>> {
+    let rest = key.strip_prefix("node_modules/")?;
+    Some(rest.split("/node_modules/").map(String::from).collect())
+}
+
+fn path_key(path: &[String]) -> String {
+    path.iter()
+        .map(|segment| format!("node_modules/{}", segment))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+pub(super) fn parse(content: &str) -> Result<LockfileGraph> {
+    let lock: Value = serde_json::from_str(content)?;
+    let lockfile_version = lock
+        .get("lockfileVersion")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(1);
+    let entries = match lock.get("packages").and_then(|p| p.as_object()) {
+        Some(entries) if lockfile_version >= 2 => entries,
+        _ => {
+            return Err(anyhow!(
+                "package-lock.json lockfileVersion {} is not supported (need 2 or 3)",
+                lockfile_version
+            ))
+        }
+    };
+
+    // Install path -> (name, version) for every entry in the tree
+    let mut installed: BTreeMap<Vec<String>, (String, String)> = BTreeMap::new();
+    for (key, entry) in entries {
+        if key.is_empty() {
+            continue;
+        }
+        if entry.get("link").and_then(|v| v.as_bool()).unwrap_or(false) {
+            return Err(anyhow!(
+                "Linked packages / workspaces are not supported: {}",
+                key
+            ));
+        }
+        let path = install_path(key)
+            .ok_or_else(|| anyhow!("Workspace packages are not supported: {}", key))?;
+        let name = entry
+            .get("name")
+            .and_then(|v| v.as_str())
+            .map(String::from)
+            .unwrap_or_else(|| path.last().cloned().unwrap_or_default());
+        let version = entry
+            .get("version")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing version for {}", key))?
+            .to_string();
+        installed.insert(path, (name, version));
+    }
+
+    let resolve = |from: &[String], name: &str, range: &str| {
+        resolve_nested(from, name, |candidate| installed.get(candidate).cloned())
+            .map(|(_, version)| LockedDependency::new(range, version))
+    };
+    let resolve_all = |from: &[String], deps: BTreeMap<String, String>| {
+        deps.into_iter()
+            .filter_map(|(name, range)| resolve(from, &name, &range).map(|dep| (name, dep)))
+            .collect::<BTreeMap<_, _>>()
+    };
+
+    let mut graph = LockfileGraph::default();
+    if let Some(root) = entries.get("") {
+        graph.root.name = root.get("name").and_then(|v| v.as_str()).map(String::from);
+        graph.root.version = root
+            .get("version")
+            .and_then(|v| v.as_str())
+            .map(String::from);
+        graph.root.dependencies = resolve_all(&[], manifest_dependencies(root, "dependencies"));
+        graph.root.dev_dependencies =
+            resolve_all(&[], manifest_dependencies(root, "devDependencies"));
+        graph.root.optional_dependencies =
+            resolve_all(&[], manifest_dependencies(root, "optionalDependencies"));
+    }
+
+    for (key, entry) in entries {
+        let Some(path) = install_path(key) else {
+            continue;
+        };
+        let (name, version) = installed[&path].clone();
+        let flag = |field: &str| entry.get(field).and_then(|v| v.as_bool()).unwrap_or(false);
+
+        let mut package = LockedPackage::new(name, version);
+        package.resolved = entry
+            .get("resolved")
+            .and_then(|v| v.as_str())
+            .map(String::from);
+        package.integrity = entry
+            .get("integrity")
+            .and_then(|v| v.as_str())
+            .map(String::from);
+        package.dependencies = resolve_all(&path, manifest_dependencies(entry, "dependencies"));
+        package.optional_dependencies =
+            resolve_all(&path, manifest_dependencies(entry, "optionalDependencies"));
+        package.dev = flag("dev") || flag("devOptional");
+        package.optional = flag("optional");
+        package.has_install_script = flag("hasInstallScript");
+        graph.insert(package);
+    }
+
+    Ok(graph)
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NpmLockfile {
+    name: Option<String>,
+    version: Option<String>,
+    lockfile_version: u32,
+    requires: bool,
+    packages: BTreeMap<String, NpmEntry>,
+}
+
+#[derive(Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct NpmEntry {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    resolved: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    integrity: Option<String>,
+    #[serde(skip_serializing_if = "is_false")]
+    dev: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    optional: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    has_install_script: bool,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    dependencies: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    dev_dependencies: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    optional_dependencies: BTreeMap<String, String>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+fn ranges(deps: &BTreeMap<String, LockedDependency>) -> BTreeMap<String, String> {
+    deps.iter()
+        .map(|(name, dep)| (name.clone(), dep.range.clone()))
+        .collect()
+}
+
+pub(super) fn render(graph: &LockfileGraph) -> Result<String> {
+    let mut packages = BTreeMap::new();
+    packages.insert(
+        String::new(),
+        NpmEntry {
+            name: graph.root.name.clone(),
+            version: graph.root.version.clone(),
+            dependencies: ranges(&graph.root.dependencies),
+            dev_dependencies: ranges(&graph.root.dev_dependencies),
+            optional_dependencies: ranges(&graph.root.optional_dependencies),
+            ..Default::default()
+        },
+    );
+
+    for (path, id) in graph.hoisted_layout() {
+        let package = &graph.packages[&id];
+        let installed_as = path.last().map(String::as_str).unwrap_or_default();
+        packages.insert(
+            path_key(&path),
+            NpmEntry {
+                // Aliased installs (`"foo": "npm:bar@1"`) record the real name
+                name: (installed_as != package.name).then(|| package.name.clone()),
+                version: Some(package.version.clone()),
+                resolved: Some(package.tarball_url()),
+                integrity: package.integrity.clone(),
+                dev: package.dev,
+                optional: package.optional,
+                has_install_script: package.has_install_script,
+                dependencies: ranges(&package.dependencies),
+                optional_dependencies: ranges(&package.optional_dependencies),
+                ..Default::default()
+            },
+        );
+    }
+
+    let lockfile = NpmLockfile {
+        name: graph.root.name.clone(),
+        version: graph.root.version.clone(),
+        lockfile_version: 3,
+        requires: true,
+        packages,
+    };
+
+    Ok(serde_json::to_string_pretty(&lockfile)? + "\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOCK: &str = r#"{
+  "name": "app",
+  "version": "1.0.0",
+  "lockfileVersion": 3,
+  "requires": true,
+  "packages": {
+    "": {
+      "name": "app",
+      "version": "1.0.0",
+      "dependencies": {
+        "a": "^1.0.0",
+        "c": "^1.0.0"
+      },
+      "devDependencies": {
+        "@types/d": "^1.0.0"
+      }
+    },
+    "node_modules/@types/d": {
+      "version": "1.0.0",
+      "resolved": "https://registry.npmjs.org/@types/d/-/d-1.0.0.tgz",
+      "integrity": "sha512-ddd",
+      "dev": true
+    },
+    "node_modules/a": {
+      "version": "1.0.0",
+      "resolved": "https://registry.npmjs.org/a/-/a-1.0.0.tgz",
+      "integrity": "sha512-aaa",
+      "hasInstallScript": true,
+      "dependencies": {
+        "c": "^2.0.0"
+      }
+    },
+    "node_modules/a/node_modules/c": {
+      "version": "2.0.0",
+      "resolved": "https://registry.npmjs.org/c/-/c-2.0.0.tgz",
+      "integrity": "sha512-c2"
+    },
+    "node_modules/c": {
+      "version": "1.0.0",
+      "resolved": "https://registry.npmjs.org/c/-/c-1.0.0.tgz",
+      "integrity": "sha512-c1"
+    }
+  }
+}
+"#;
+
+    #[test]
+    fn parses_nested_resolution() {
+        let graph = parse(LOCK).unwrap();
+        assert_eq!(graph.packages.len(), 4);
+        assert_eq!(graph.root.dependencies["c"].version, "1.0.0");
+        assert_eq!(graph.root.dev_dependencies["@types/d"].version, "1.0.0");
+
+        let a = graph.get("a", "1.0.0").unwrap();
+        assert_eq!(a.dependencies["c"].version, "2.0.0");
+        assert!(a.has_install_script);
+        assert!(graph.get("@types/d", "1.0.0").unwrap().dev);
+    }
+
+    #[test]
+    fn render_round_trips() {
+        let graph = parse(LOCK).unwrap();
+        let rendered = render(&graph).unwrap();
+        assert_eq!(rendered, LOCK);
+        assert_eq!(parse(&rendered).unwrap(), graph);
+    }
+
+    #[test]
+    fn rejects_lockfile_v1() {
+        let err = parse(r#"{"lockfileVersion": 1, "dependencies": {}}"#).unwrap_err();
+        assert!(err.to_string().contains("lockfileVersion 1"));
+    }
+
+    #[test]
+    fn rejects_workspace_links() {
+        let lock = r#"{"lockfileVersion": 3, "packages": {
+            "": {}, "node_modules/pkg": {"resolved": "packages/pkg", "link": true}
+        }}"#;
+        assert!(parse(lock).is_err());
+    }
+}
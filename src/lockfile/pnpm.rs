@@ -0,0 +1,390 @@
+use anyhow::{anyhow, Result};
+use serde_yaml::{Mapping, Value};
+use std::collections::BTreeMap;
+
+use super::{
+    dependency_id, package_id, split_descriptor, LockedDependency, LockedPackage, LockfileGraph,
+};
+
+fn str_field<'a>(value: &'a Value, field: &str) -> Option<&'a str> {
+    value.get(field).and_then(|v| v.as_str())
+}
+
+/// `1.0.0(react@18.0.0)` -> `1.0.0`
+fn strip_peer_suffix(value: &str) -> &str {
+    value.split('(').next().unwrap_or(value)
+}
+
+/// Parse a `name@version` package key (v6 keys carry a leading `/`).
+fn parse_key(key: &str) -> Option<(String, String)> {
+    let key = strip_peer_suffix(key.strip_prefix('/').unwrap_or(key));
+    let (name, version) = split_descriptor(key)?;
+    Some((name.to_string(), version.to_string()))
+}
+
+/// Resolve a dependency reference to `(real name, version)`. References are
+/// plain versions, or `name@version` (`/name@version` in v6) for aliases.
+fn parse_reference(name: &str, reference: &str) -> Option<(String, String)> {
+    if reference.starts_with("link:") || reference.starts_with("file:") {
+        return None;
+    }
+    let reference = strip_peer_suffix(reference);
+    if reference.contains('@') {
+        parse_key(reference)
+    } else {
+        Some((name.to_string(), reference.to_string()))
+    }
+}
+
+fn dependency(name: &str, specifier: Option<&str>, reference: &str) -> Option<LockedDependency> {
+    let (real_name, version) = parse_reference(name, reference)?;
+    let range = match specifier {
+        Some(specifier) => specifier.to_string(),
+        None if real_name != name => format!("npm:{}@{}", real_name, version),
+        // pnpm does not keep the requested range for transitive dependencies
+        None => version.clone(),
+    };
+    Some(LockedDependency::new(range, version))
+}
+
+fn transitive_dependencies(value: Option<&Value>) -> BTreeMap<String, LockedDependency> {
+    value
+        .and_then(|v| v.as_mapping())
+        .map(|deps| {
+            deps.iter()
+                .filter_map(|(name, reference)| {
+                    let name = name.as_str()?;
+                    Some((
+                        name.to_string(),
+                        dependency(name, None, reference.as_str()?)?,
+                    ))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn importer_dependencies(value: Option<&Value>) -> BTreeMap<String, LockedDependency> {
+    value
+        .and_then(|v| v.as_mapping())
+        .map(|deps| {
+            deps.iter()
+                .filter_map(|(name, entry)| {
+                    let name = name.as_str()?;
+                    let dep = dependency(
+                        name,
+                        str_field(entry, "specifier"),
+                        str_field(entry, "version")?,
+                    )?;
+                    Some((name.to_string(), dep))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub(super) fn parse(content: &str) -> Result<LockfileGraph> {
+    let document: Value = serde_yaml::from_str(content)?;
+    let lockfile_version = document
+        .get("lockfileVersion")
+        .and_then(|v| match v {
+            Value::String(s) => s.parse::<f64>().ok(),
+            Value::Number(n) => n.as_f64(),
+            _ => None,
+        })
+        .unwrap_or(0.0);
+    if lockfile_version < 6.0 {
+        return Err(anyhow!(
+            "pnpm-lock.yaml lockfileVersion {} is not supported (need 6 or 9)",
+            lockfile_version
+        ));
+    }
+
+    let importer = match document.get("importers").and_then(|i| i.as_mapping()) {
+        Some(importers) => {
+            if importers.len() > 1 {
+                return Err(anyhow!("pnpm workspaces are not supported"));
+            }
+            importers.get(".").cloned().unwrap_or(Value::Null)
+        }
+        None => document.clone(),
+    };
+
+    let mut graph = LockfileGraph::default();
+    graph.root.dependencies = importer_dependencies(importer.get("dependencies"));
+    graph.root.dev_dependencies = importer_dependencies(importer.get("devDependencies"));
+    graph.root.optional_dependencies = importer_dependencies(importer.get("optionalDependencies"));
+
+    let empty = Mapping::new();
+    let packages = document
+        .get("packages")
+        .and_then(|p| p.as_mapping())
+        .unwrap_or(&empty);
+    // v9 moved the dependency edges into `snapshots`, one per peer variant
+    let snapshots = document
+        .get("snapshots")
+        .and_then(|s| s.as_mapping())
+        .unwrap_or(packages);
+
+    for (key, entry) in packages {
+        let Some((name, version)) = key.as_str().and_then(parse_key) else {
+            continue;
+        };
+        let mut package = LockedPackage::new(name, version);
+        if let Some(resolution) = entry.get("resolution") {
+            package.integrity = str_field(resolution, "integrity").map(String::from);
+            package.resolved = str_field(resolution, "tarball").map(String::from);
+        }
+        package.has_install_script = entry
+            .get("requiresBuild")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        graph.insert(package);
+    }
+
+    for (key, snapshot) in snapshots {
+        let Some((name, version)) = key.as_str().and_then(parse_key) else {
+            continue;
+        };
+        if let Some(package) = graph.packages.get_mut(&package_id(&name, &version)) {
+            if package.dependencies.is_empty() && package.optional_dependencies.is_empty() {
+                package.dependencies = transitive_dependencies(snapshot.get("dependencies"));
+                package.optional_dependencies =
+                    transitive_dependencies(snapshot.get("optionalDependencies"));
+            }
+        }
+    }
+
+    graph.compute_flags();
+    Ok(graph)
+}
+
+/// Quote a scalar only when YAML would not read it back as the same string.
+fn yaml_str(value: &str) -> String {
+    let plain = !value.is_empty()
+        && !value.starts_with(|c: char| "@*&!|>'\"%`#,[]{}?:- ".contains(c))
+        && matches!(serde_yaml::from_str::<Value>(value), Ok(Value::String(ref s)) if s == value);
+    if plain {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "''"))
+    }
+}
+
+/// Reference written for an edge: the version, or `name@version` for aliases.
+fn reference(name: &str, dep: &LockedDependency) -> String {
+    let id = dependency_id(name, dep);
+    if id == package_id(name, &dep.version) {
+        dep.version.clone()
+    } else {
+        id
+    }
+}
+
+/// Render a `pnpm-lock.yaml` in the lockfileVersion 9 layout.
+pub(super) fn render(graph: &LockfileGraph) -> String {
+    let mut out = String::from("lockfileVersion: '9.0'\n\n");
+    out.push_str("settings:\n  autoInstallPeers: true\n  excludeLinksFromLockfile: false\n\n");
+    out.push_str("importers:\n\n  .:\n");
+
+    for (label, deps) in [
+        ("dependencies", &graph.root.dependencies),
+        ("optionalDependencies", &graph.root.optional_dependencies),
+        ("devDependencies", &graph.root.dev_dependencies),
+    ] {
+        if deps.is_empty() {
+            continue;
+        }
+        out.push_str(&format!("    {}:\n", label));
+        for (name, dep) in deps {
+            out.push_str(&format!("      {}:\n", yaml_str(name)));
+            out.push_str(&format!("        specifier: {}\n", yaml_str(&dep.range)));
+            out.push_str(&format!(
+                "        version: {}\n",
+                yaml_str(&reference(name, dep))
+            ));
+        }
+    }
+
+    if graph.packages.is_empty() {
+        return out;
+    }
+
+    out.push_str("\npackages:\n");
+    for (id, package) in &graph.packages {
+        out.push_str(&format!("\n  {}:\n", yaml_str(id)));
+        match (&package.integrity, &package.resolved) {
+            (Some(integrity), _) => {
+                out.push_str(&format!("    resolution: {{integrity: {}}}\n", integrity))
+            }
+            (None, resolved) => out.push_str(&format!(
+                "    resolution: {{tarball: {}}}\n",
+                resolved.clone().unwrap_or_else(|| package.tarball_url())
+            )),
+        }
+        if package.has_install_script {
+            out.push_str("    requiresBuild: true\n");
+        }
+    }
+
+    out.push_str("\nsnapshots:\n");
+    for (id, package) in &graph.packages {
+        let key = yaml_str(id);
+        if package.dependencies.is_empty()
+            && package.optional_dependencies.is_empty()
+            && !package.optional
+        {
+            out.push_str(&format!("\n  {}: {{}}\n", key));
+            continue;
+        }
+        out.push_str(&format!("\n  {}:\n", key));
+        for (label, deps) in [
+            ("dependencies", &package.dependencies),
+            ("optionalDependencies", &package.optional_dependencies),
+        ] {
+            if deps.is_empty() {
+                continue;
+            }
+            out.push_str(&format!("    {}:\n", label));
+            for (name, dep) in deps {
+                out.push_str(&format!(
+                    "      {}: {}\n",
+                    yaml_str(name),
+                    yaml_str(&reference(name, dep))
+                ));
+            }
+        }
+        if package.optional {
+            out.push_str("    optional: true\n");
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOCK_V9: &str = r#"lockfileVersion: '9.0'
+
+settings:
+  autoInstallPeers: true
+  excludeLinksFromLockfile: false
+
+importers:
+
+  .:
+    dependencies:
+      a:
+        specifier: ^1.0.0
+        version: 1.0.0
+      c:
+        specifier: ^1.0.0
+        version: 1.0.0
+    devDependencies:
+      '@types/d':
+        specifier: ^1.0.0
+        version: 1.0.0
+
+packages:
+
+  '@types/d@1.0.0':
+    resolution: {integrity: sha512-ddd}
+
+  a@1.0.0:
+    resolution: {integrity: sha512-aaa}
+
+  c@1.0.0:
+    resolution: {integrity: sha512-c1}
+
+  c@2.0.0:
+    resolution: {integrity: sha512-c2}
+
+snapshots:
+
+  '@types/d@1.0.0': {}
+
+  a@1.0.0:
+    dependencies:
+      c: 2.0.0
+
+  c@1.0.0: {}
+
+  c@2.0.0: {}
+"#;
+
+    #[test]
+    fn parses_v9_lockfile() {
+        let graph = parse(LOCK_V9).unwrap();
+        assert_eq!(graph.packages.len(), 4);
+        assert_eq!(graph.root.dev_dependencies["@types/d"].range, "^1.0.0");
+        assert_eq!(
+            graph.get("a", "1.0.0").unwrap().dependencies["c"].version,
+            "2.0.0"
+        );
+        assert!(graph.get("@types/d", "1.0.0").unwrap().dev);
+        assert_eq!(
+            graph.get("c", "1.0.0").unwrap().integrity.as_deref(),
+            Some("sha512-c1")
+        );
+    }
+
+    #[test]
+    fn render_round_trips_v9_lockfile() {
+        let graph = parse(LOCK_V9).unwrap();
+        assert_eq!(render(&graph), LOCK_V9);
+    }
+
+    #[test]
+    fn parses_v6_lockfile_with_peer_suffixes() {
+        let lock = r#"lockfileVersion: '6.0'
+
+dependencies:
+  a:
+    specifier: ^1.0.0
+    version: 1.0.0(react@18.0.0)
+  alias:
+    specifier: npm:b@^2.0.0
+    version: /b@2.0.0
+
+packages:
+
+  /a@1.0.0(react@18.0.0):
+    resolution: {integrity: sha512-aaa}
+    requiresBuild: true
+    dependencies:
+      react: 18.0.0
+    dev: false
+
+  /b@2.0.0:
+    resolution: {integrity: sha512-bbb}
+    dev: false
+
+  /react@18.0.0:
+    resolution: {integrity: sha512-rrr}
+    dev: false
+"#;
+        let graph = parse(lock).unwrap();
+        assert_eq!(graph.root.dependencies["a"].version, "1.0.0");
+        assert_eq!(graph.root.dependencies["alias"].range, "npm:b@^2.0.0");
+        let a = graph.get("a", "1.0.0").unwrap();
+        assert!(a.has_install_script);
+        assert_eq!(a.dependencies["react"].version, "18.0.0");
+        assert!(graph.get("b", "2.0.0").is_some());
+    }
+
+    #[test]
+    fn rejects_old_lockfile_versions() {
+        assert!(parse("lockfileVersion: 5.4\n").is_err());
+    }
+
+    #[test]
+    fn yaml_str_quotes_only_when_needed() {
+        assert_eq!(yaml_str("^1.0.0"), "^1.0.0");
+        assert_eq!(yaml_str("1.0.0"), "1.0.0");
+        assert_eq!(yaml_str("@types/d@1.0.0"), "'@types/d@1.0.0'");
+        assert_eq!(yaml_str("*"), "'*'");
+        assert_eq!(yaml_str("1.0"), "'1.0'");
+    }
+}
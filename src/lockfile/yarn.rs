@@ -0,0 +1,522 @@
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+use serde_yaml::Mapping;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+use super::{
+    dependency_id, manifest_dependencies, split_descriptor, LockedDependency, LockedPackage,
+    LockfileGraph,
+};
+
+const HEADER: &str =
+    "# THIS IS AN AUTOGENERATED FILE. DO NOT EDIT THIS FILE DIRECTLY.\n# yarn lockfile v1\n";
+
+/// A parsed `yarn.lock` entry, shared by the classic and berry readers.
+#[derive(Debug, Default)]
+struct YarnEntry {
+    descriptors: Vec<String>,
+    version: String,
+    resolved: Option<String>,
+    integrity: Option<String>,
+    dependencies: BTreeMap<String, String>,
+    optional_dependencies: BTreeMap<String, String>,
+}
+
+/// Whether `content` is a berry (yarn 2+) lockfile rather than classic v1.
+fn is_berry_lockfile(content: &str) -> bool {
+    content.contains("__metadata:")
+}
+
+/// Whether the project in `project_dir` uses berry (yarn 2+): its
+/// `yarn.lock` is a berry lockfile, it has a `.yarnrc.yml`, or
+/// `packageManager` pins yarn 2 or later.
+pub(super) fn is_berry_project(project_dir: &Path) -> bool {
+    if let Ok(content) = fs::read_to_string(project_dir.join("yarn.lock")) {
+        return is_berry_lockfile(&content);
+    }
+    if project_dir.join(".yarnrc.yml").exists() {
+        return true;
+    }
+    super::read_manifest(project_dir)
+        .and_then(|manifest| {
+            let pinned = manifest
+                .get("packageManager")?
+                .as_str()?
+                .strip_prefix("yarn@")?;
+            let major = pinned.split('.').next()?.parse::<u64>().ok()?;
+            Some(major >= 2)
+        })
+        .unwrap_or(false)
+}
+
+pub(super) fn parse(content: &str, manifest: Option<&Value>) -> Result<LockfileGraph> {
+    if is_berry_lockfile(content) {
+        parse_berry(content, manifest)
+    } else {
+        let manifest = manifest
+            .ok_or_else(|| anyhow!("yarn.lock needs package.json to resolve the project root"))?;
+        let entries = parse_classic(content)?;
+        build_graph(
+            entries,
+            |deps| {
+                deps.iter()
+                    .map(|(name, range)| (name.clone(), range.clone()))
+                    .collect()
+            },
+            root_groups(manifest),
+        )
+    }
+}
+
+fn root_groups(manifest: &Value) -> [BTreeMap<String, String>; 3] {
+    [
+        manifest_dependencies(manifest, "dependencies"),
+        manifest_dependencies(manifest, "devDependencies"),
+        manifest_dependencies(manifest, "optionalDependencies"),
+    ]
+}
+
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        serde_json::from_str(value).unwrap_or_else(|_| value[1..value.len() - 1].to_string())
+    } else {
+        value.to_string()
+    }
+}
+
+/// Split `key value` where the key may be quoted.
+fn split_pair(line: &str) -> Option<(String, String)> {
+    let line = line.trim();
+    if let Some(rest) = line.strip_prefix('"') {
+        let end = rest.find('"')?;
+        Some((rest[..end].to_string(), unquote(&rest[end + 1..])))
+    } else {
+        let (key, value) = line.split_once(' ')?;
+        Some((key.to_string(), unquote(value)))
+    }
+}
+
+fn parse_classic(content: &str) -> Result<Vec<YarnEntry>> {
+    let mut entries = Vec::new();
+    let mut current: Option<YarnEntry> = None;
+    let mut section: Option<String> = None;
+
+    for (index, raw) in content.lines().enumerate() {
+        if raw.trim().is_empty() || raw.trim_start().starts_with('#') {
+            continue;
+        }
+        let indent = raw.len() - raw.trim_start().len();
+
+        if indent == 0 {
+            let header = raw
+                .trim_end()
+                .strip_suffix(':')
+                .ok_or_else(|| anyhow!("yarn.lock line {}: expected an entry header", index + 1))?;
+            if let Some(entry) = current.take() {
+                entries.push(entry);
+            }
+            current = Some(YarnEntry {
+                descriptors: header.split(", ").map(unquote).collect(),
+                ..Default::default()
+            });
+            section = None;
+            continue;
+        }
+
+        let entry = current
+            .as_mut()
+            .ok_or_else(|| anyhow!("yarn.lock line {}: field outside of an entry", index + 1))?;
+
+        if indent == 2 {
+            let line = raw.trim();
+            if let Some(name) = line.strip_suffix(':') {
+                section = Some(name.to_string());
+                continue;
+            }
+            section = None;
+            let Some((key, value)) = split_pair(line) else {
+                continue;
+            };
+            match key.as_str() {
+                "version" => entry.version = value,
+                "resolved" => entry.resolved = Some(value),
+                "integrity" => entry.integrity = Some(value),
+                _ => {}
+            }
+        } else if let Some((name, range)) = split_pair(raw) {
+            match section.as_deref() {
+                Some("dependencies") => {
+                    entry.dependencies.insert(name, range);
+                }
+                Some("optionalDependencies") => {
+                    entry.optional_dependencies.insert(name, range);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    if let Some(entry) = current {
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+fn parse_berry(content: &str, manifest: Option<&Value>) -> Result<LockfileGraph> {
+    let document: Mapping = serde_yaml::from_str(content)?;
+    let as_map = |value: Option<&serde_yaml::Value>| -> BTreeMap<String, String> {
+        value
+            .and_then(|v| v.as_mapping())
+            .map(|deps| {
+                deps.iter()
+                    .filter_map(|(k, v)| Some((k.as_str()?.to_string(), yaml_scalar(v)?)))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    let mut entries = Vec::new();
+    let mut workspace_deps = BTreeMap::new();
+    for (key, value) in &document {
+        let Some(key) = key.as_str() else {
+            continue;
+        };
+        if key == "__metadata" {
+            continue;
+        }
+        let descriptors: Vec<String> = key.split(", ").map(String::from).collect();
+        let dependencies = as_map(value.get("dependencies"));
+
+        if descriptors.iter().any(|d| d.contains("@workspace:")) {
+            if descriptors.iter().any(|d| d.ends_with("@workspace:.")) {
+                workspace_deps = dependencies;
+                continue;
+            }
+            return Err(anyhow!("Yarn workspaces are not supported: {}", key));
+        }
+
+        // Optional dependencies are regular dependencies flagged in dependenciesMeta
+        let optional_names: BTreeSet<String> = value
+            .get("dependenciesMeta")
+            .and_then(|m| m.as_mapping())
+            .map(|meta| {
+                meta.iter()
+                    .filter(|(_, v)| v.get("optional").and_then(|o| o.as_bool()) == Some(true))
+                    .filter_map(|(k, _)| k.as_str().map(String::from))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let (optional_dependencies, dependencies) = dependencies
+            .into_iter()
+            .partition(|(name, _)| optional_names.contains(name));
+
+        entries.push(YarnEntry {
+            descriptors,
+            version: value
+                .get("version")
+                .and_then(yaml_scalar)
+                .unwrap_or_default(),
+            resolved: None,
+            integrity: None,
+            dependencies,
+            optional_dependencies,
+        });
+    }
+
+    let roots = match manifest {
+        Some(manifest) => root_groups(manifest),
+        None => [workspace_deps, BTreeMap::new(), BTreeMap::new()],
+    };
+    build_graph(
+        entries,
+        |deps| {
+            deps.iter()
+                .map(|(n, r)| (n.clone(), berry_range(r)))
+                .collect()
+        },
+        roots,
+    )
+}
+
+fn yaml_scalar(value: &serde_yaml::Value) -> Option<String> {
+    match value {
+        serde_yaml::Value::String(s) => Some(s.clone()),
+        serde_yaml::Value::Number(n) => Some(n.to_string()),
+        serde_yaml::Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// Berry descriptors always carry a protocol; bare ranges mean `npm:`.
+fn berry_range(range: &str) -> String {
+    if range.contains(':') {
+        range.to_string()
+    } else {
+        format!("npm:{}", range)
+    }
+}
+
+/// Strip berry's implicit `npm:` protocol so ranges match package.json.
+fn plain_range(range: &str) -> String {
+    match range.strip_prefix("npm:") {
+        Some(rest) if split_descriptor(rest).is_none() => rest.to_string(),
+        _ => range.to_string(),
+    }
+}
+
+fn build_graph<F>(
+    entries: Vec<YarnEntry>,
+    lookup_ranges: F,
+    roots: [BTreeMap<String, String>; 3],
+) -> Result<LockfileGraph>
+where
+    F: Fn(&BTreeMap<String, String>) -> BTreeMap<String, String>,
+{
+    // Descriptor (`name@range`) -> (package name, version)
+    let mut by_descriptor: BTreeMap<String, (String, String)> = BTreeMap::new();
+    let mut packages = Vec::new();
+
+    for entry in &entries {
+        let first = entry
+            .descriptors
+            .first()
+            .ok_or_else(|| anyhow!("yarn.lock entry without descriptors"))?;
+        let (alias, range) =
+            split_descriptor(first).ok_or_else(|| anyhow!("Invalid descriptor: {}", first))?;
+        // `foo@npm:bar@^1` is an alias for bar
+        let name = range
+            .strip_prefix("npm:")
+            .and_then(split_descriptor)
+            .map(|(real, _)| real)
+            .unwrap_or(alias)
+            .to_string();
+
+        for descriptor in &entry.descriptors {
+            by_descriptor.insert(descriptor.clone(), (name.clone(), entry.version.clone()));
+        }
+        packages.push((name, entry));
+    }
+
+    let resolve = |deps: &BTreeMap<String, String>| -> BTreeMap<String, LockedDependency> {
+        lookup_ranges(deps)
+            .into_iter()
+            .filter_map(|(name, lookup)| {
+                let (_, version) = by_descriptor.get(&format!("{}@{}", name, lookup))?;
+                Some((
+                    name,
+                    LockedDependency::new(plain_range(&lookup), version.clone()),
+                ))
+            })
+            .collect()
+    };
+
+    let mut graph = LockfileGraph::default();
+    let [dependencies, dev_dependencies, optional_dependencies] = roots;
+    graph.root.dependencies = resolve(&dependencies);
+    graph.root.dev_dependencies = resolve(&dev_dependencies);
+    graph.root.optional_dependencies = resolve(&optional_dependencies);
+
+    for (name, entry) in packages {
+        let mut package = LockedPackage::new(name, entry.version.clone());
+        package.resolved = entry.resolved.clone();
+        package.integrity = entry.integrity.clone();
+        package.dependencies = resolve(&entry.dependencies);
+        package.optional_dependencies = resolve(&entry.optional_dependencies);
+        graph.insert(package);
+    }
+
+    graph.compute_flags();
+    Ok(graph)
+}
+
+/// Quote a key or value the way yarn's own lockfile writer does.
+fn maybe_wrap(value: &str) -> String {
+    let needs_quotes = value.starts_with("true")
+        || value.starts_with("false")
+        || value
+            .chars()
+            .any(|c| matches!(c, ':' | ' ' | '\t' | '\n' | '\\' | '"' | ',' | '[' | ']'))
+        || !value.starts_with(|c: char| c.is_ascii_alphabetic());
+    if needs_quotes {
+        serde_json::to_string(value).unwrap_or_else(|_| format!("\"{}\"", value))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Render a classic (v1) `yarn.lock`. Berry reads it but rewrites it in its
+/// own format, so it is never written over a berry project's lockfile.
+pub(super) fn render(graph: &LockfileGraph) -> String {
+    let mut descriptors: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    let edges = graph
+        .root
+        .all_dependencies()
+        .chain(graph.packages.values().flat_map(|p| p.all_dependencies()));
+    for (name, dep) in edges {
+        descriptors
+            .entry(dependency_id(name, dep))
+            .or_default()
+            .insert(format!("{}@{}", name, dep.range));
+    }
+
+    let mut blocks: Vec<(String, String)> = Vec::new();
+    for (id, package) in &graph.packages {
+        let Some(keys) = descriptors.get(id) else {
+            continue;
+        };
+        let mut keys: Vec<String> = keys.iter().map(|k| maybe_wrap(k)).collect();
+        keys.sort();
+        let header = keys.join(", ");
+
+        let mut block = format!("{}:\n", header);
+        block.push_str(&format!("  version {}\n", maybe_wrap(&package.version)));
+        block.push_str(&format!(
+            "  resolved {}\n",
+            maybe_wrap(&package.tarball_url())
+        ));
+        if let Some(integrity) = &package.integrity {
+            block.push_str(&format!("  integrity {}\n", maybe_wrap(integrity)));
+        }
+        for (label, deps) in [
+            ("dependencies", &package.dependencies),
+            ("optionalDependencies", &package.optional_dependencies),
+        ] {
+            if deps.is_empty() {
+                continue;
+            }
+            block.push_str(&format!("  {}:\n", label));
+            for (name, dep) in deps {
+                block.push_str(&format!(
+                    "    {} {}\n",
+                    maybe_wrap(name),
+                    maybe_wrap(&dep.range)
+                ));
+            }
+        }
+        blocks.push((header, block));
+    }
+    blocks.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut output = format!("{}\n\n", HEADER);
+    output.push_str(
+        &blocks
+            .into_iter()
+            .map(|(_, block)| block)
+            .collect::<Vec<_>>()
+            .join("\n"),
+    );
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOCK: &str = r#"# THIS IS AN AUTOGENERATED FILE. DO NOT EDIT THIS FILE DIRECTLY.
+# yarn lockfile v1
+
+
+"@types/d@^1.0.0":
+  version "1.0.0"
+  resolved "https://registry.npmjs.org/@types/d/-/d-1.0.0.tgz"
+  integrity sha512-ddd
+
+a@^1.0.0:
+  version "1.0.0"
+  resolved "https://registry.npmjs.org/a/-/a-1.0.0.tgz"
+  integrity sha512-aaa
+  dependencies:
+    c "^2.0.0"
+
+c@^1.0.0:
+  version "1.0.0"
+  resolved "https://registry.npmjs.org/c/-/c-1.0.0.tgz"
+  integrity sha512-c1
+
+c@^2.0.0:
+  version "2.0.0"
+  resolved "https://registry.npmjs.org/c/-/c-2.0.0.tgz"
+  integrity sha512-c2
+"#;
+
+    fn manifest() -> Value {
+        serde_json::json!({
+            "name": "app",
+            "dependencies": { "a": "^1.0.0", "c": "^1.0.0" },
+            "devDependencies": { "@types/d": "^1.0.0" }
+        })
+    }
+
+    #[test]
+    fn parses_classic_lockfile() {
+        let graph = parse(LOCK, Some(&manifest())).unwrap();
+        assert_eq!(graph.packages.len(), 4);
+        assert_eq!(graph.root.dependencies["c"].version, "1.0.0");
+        assert_eq!(
+            graph.get("a", "1.0.0").unwrap().dependencies["c"].version,
+            "2.0.0"
+        );
+        assert!(graph.get("@types/d", "1.0.0").unwrap().dev);
+        assert!(!graph.get("c", "2.0.0").unwrap().dev);
+    }
+
+    #[test]
+    fn render_round_trips_classic_lockfile() {
+        let graph = parse(LOCK, Some(&manifest())).unwrap();
+        assert_eq!(render(&graph), LOCK);
+    }
+
+    #[test]
+    fn classic_lockfile_requires_manifest() {
+        assert!(parse(LOCK, None).is_err());
+    }
+
+    #[test]
+    fn parses_berry_lockfile() {
+        let lock = r#"__metadata:
+  version: 6
+  cacheKey: 8
+
+"app@workspace:.":
+  version: 0.0.0-use.local
+  resolution: "app@workspace:."
+  dependencies:
+    a: ^1.0.0
+  languageName: unknown
+  linkType: soft
+
+"a@npm:^1.0.0":
+  version: 1.0.0
+  resolution: "a@npm:1.0.0"
+  dependencies:
+    b: "npm:^2.0.0"
+  checksum: abc
+  languageName: node
+  linkType: hard
+
+"b@npm:^2.0.0":
+  version: 2.1.0
+  resolution: "b@npm:2.1.0"
+  languageName: node
+  linkType: hard
+"#;
+        let graph = parse(lock, None).unwrap();
+        assert_eq!(graph.root.dependencies["a"].range, "^1.0.0");
+        assert_eq!(graph.root.dependencies["a"].version, "1.0.0");
+        assert_eq!(
+            graph.get("a", "1.0.0").unwrap().dependencies["b"].version,
+            "2.1.0"
+        );
+    }
+
+    #[test]
+    fn maybe_wrap_matches_yarn_quoting() {
+        assert_eq!(maybe_wrap("lodash@^4.0.0"), "lodash@^4.0.0");
+        assert_eq!(maybe_wrap("@babel/core@^7.0.0"), "\"@babel/core@^7.0.0\"");
+        assert_eq!(maybe_wrap("1.0.0"), "\"1.0.0\"");
+        assert_eq!(maybe_wrap("a@>= 1"), "\"a@>= 1\"");
+    }
+}
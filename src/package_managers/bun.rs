@@ -167,7 +167,7 @@ impl PackageManager for BunManager {
         }
 
         print_lifecycle_scripts_warning("bun");
-        run_allowed_builds("bun", config.get_allow_builds())?;
+        run_allowed_builds("bun", &config)?;
         Ok(())
     }
 
@@ -214,7 +214,7 @@ impl PackageManager for BunManager {
         }
 
         print_lifecycle_scripts_warning("bun");
-        run_allowed_builds("bun", config.get_allow_builds())?;
+        run_allowed_builds("bun", &config)?;
         Ok(())
     }
 
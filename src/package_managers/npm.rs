@@ -179,7 +179,7 @@ impl PackageManager for NpmManager {
         }
 
         print_lifecycle_scripts_warning("npm");
-        run_allowed_builds("npm", config.get_allow_builds())?;
+        run_allowed_builds("npm", &config)?;
         Ok(())
     }
 
@@ -208,7 +208,7 @@ impl PackageManager for NpmManager {
         }
 
         print_lifecycle_scripts_warning("npm");
-        run_allowed_builds("npm", config.get_allow_builds())?;
+        run_allowed_builds("npm", &config)?;
         Ok(())
     }
 
@@ -176,7 +176,7 @@ impl PackageManager for PnpmManager {
         }
 
         print_lifecycle_scripts_warning("pnpm");
-        run_allowed_builds("pnpm", config.get_allow_builds())?;
+        run_allowed_builds("pnpm", &config)?;
         Ok(())
     }
 
@@ -201,7 +201,7 @@ impl PackageManager for PnpmManager {
         }
 
         print_lifecycle_scripts_warning("pnpm");
-        run_allowed_builds("pnpm", config.get_allow_builds())?;
+        run_allowed_builds("pnpm", &config)?;
         Ok(())
     }
 
@@ -121,7 +121,7 @@ impl PackageManager for YarnManager {
         }
 
         print_lifecycle_scripts_warning("yarn");
-        run_allowed_builds("yarn", config.get_allow_builds())?;
+        run_allowed_builds("yarn", &config)?;
         Ok(())
     }
 
@@ -149,7 +149,7 @@ impl PackageManager for YarnManager {
         }
 
         print_lifecycle_scripts_warning("yarn");
-        run_allowed_builds("yarn", config.get_allow_builds())?;
+        run_allowed_builds("yarn", &config)?;
         Ok(())
     }
 
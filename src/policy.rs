@@ -0,0 +1,435 @@
+//! Declarative security policy (`.fnpm/policy.json`) used to turn audit
+//! results into a pass/warn/fail verdict without asking anyone, so CI can
+//! gate merges on `fnpm scan --non-interactive`.
+
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::package_manager::split_package_spec;
+use crate::security::{AttackChainType, PackageAudit, RiskLevel};
+use crate::tarball;
+
+/// Exit code when the policy only produced warnings
+pub const EXIT_POLICY_WARN: i32 = 2;
+/// Exit code when at least one package violates the policy
+pub const EXIT_POLICY_FAIL: i32 = 3;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Policy {
+    /// Packages at or above this risk level fail the policy
+    #[serde(default = "default_fail_on")]
+    pub fail_on: RiskLevel,
+    /// Packages at or above this risk level (but below `fail_on`) are reported
+    #[serde(default = "default_warn_on")]
+    pub warn_on: RiskLevel,
+    /// Packages that fail regardless of their risk level
+    #[serde(default)]
+    pub deny: Vec<DenyRule>,
+    /// Reviewed packages that are allowed despite their findings, until `expires`
+    #[serde(default)]
+    pub exceptions: Vec<PolicyException>,
+}
+
+/// Matches by package name pattern (`*` wildcards), by attack chain, or
+/// both when both are set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DenyRule {
+    #[serde(default)]
+    pub package: Option<String>,
+    #[serde(default)]
+    pub attack_chain: Option<AttackChainType>,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyException {
+    /// Package name or pattern (`*` wildcards)
+    pub package: String,
+    /// Only this version or semver range, when set
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Last day (inclusive) the exception applies
+    pub expires: NaiveDate,
+    pub justification: String,
+}
+
+fn default_fail_on() -> RiskLevel {
+    RiskLevel::High
+}
+
+fn default_warn_on() -> RiskLevel {
+    RiskLevel::Medium
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self {
+            fail_on: default_fail_on(),
+            warn_on: default_warn_on(),
+            deny: Vec::new(),
+            exceptions: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verdict {
+    Pass,
+    Warn,
+    Fail,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Finding {
+    pub package: String,
+    pub version: Option<String>,
+    pub risk_level: RiskLevel,
+    pub verdict: Verdict,
+    pub reason: String,
+    /// Justification of the exception that waived this finding
+    pub waived_by: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PolicyReport {
+    pub findings: Vec<Finding>,
+}
+
+impl PolicyReport {
+    pub fn verdict(&self) -> Verdict {
+        self.findings
+            .iter()
+            .map(|f| f.verdict)
+            .max()
+            .unwrap_or(Verdict::Pass)
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        match self.verdict() {
+            Verdict::Pass => 0,
+            Verdict::Warn => EXIT_POLICY_WARN,
+            Verdict::Fail => EXIT_POLICY_FAIL,
+        }
+    }
+}
+
+/// Returned by commands that stop because of the policy; `main` turns it
+/// into the matching exit code.
+#[derive(Debug)]
+pub struct PolicyViolation {
+    pub exit_code: i32,
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Security policy violated")
+    }
+}
+
+impl std::error::Error for PolicyViolation {}
+
+/// `*` matches any run of characters; everything else is literal.
+pub(crate) fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+fn version_matches(range: Option<&str>, version: Option<&str>) -> bool {
+    let Some(range) = range else {
+        return true;
+    };
+    match version.map(semver::Version::parse) {
+        Some(Ok(version)) => tarball::satisfies_range(&version, range),
+        Some(Err(_)) => version == Some(range),
+        None => false,
+    }
+}
+
+impl Policy {
+    /// Load `.fnpm/policy.json`, or the default policy when there is none.
+    pub fn load() -> Result<Self> {
+        Self::load_from(Path::new(".fnpm/policy.json"))
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)?;
+        serde_json::from_str(&content)
+            .map_err(|e| anyhow!("Invalid policy file {}: {}", path.display(), e))
+    }
+
+    fn deny_reason(&self, name: &str, audit: &PackageAudit) -> Option<String> {
+        self.deny.iter().find_map(|rule| {
+            if rule.package.is_none() && rule.attack_chain.is_none() {
+                return None;
+            }
+            let name_matches = rule
+                .package
+                .as_deref()
+                .map_or(true, |pattern| matches_pattern(pattern, name));
+            let chain_matches = rule.attack_chain.as_ref().map_or(true, |chain| {
+                audit
+                    .behavioral_chains
+                    .iter()
+                    .any(|c| &c.chain_type == chain)
+            });
+            (name_matches && chain_matches).then(|| {
+                let target = match (&rule.package, &rule.attack_chain) {
+                    (Some(pattern), Some(chain)) => format!("{} with {:?}", pattern, chain),
+                    (Some(pattern), None) => pattern.clone(),
+                    (None, Some(chain)) => format!("{:?}", chain),
+                    (None, None) => unreachable!(),
+                };
+                match &rule.reason {
+                    Some(reason) => format!("denied ({}): {}", target, reason),
+                    None => format!("denied ({})", target),
+                }
+            })
+        })
+    }
+
+    /// Judge a single audit. Returns `None` when there is nothing to report.
+    pub fn evaluate_audit(&self, audit: &PackageAudit, today: NaiveDate) -> Option<Finding> {
+        let (name, _) = split_package_spec(&audit.package_name);
+        let version = audit.version.as_deref();
+
+        let (verdict, reason) = if let Some(reason) = self.deny_reason(&name, audit) {
+            (Verdict::Fail, reason)
+        } else if audit.risk_level >= self.fail_on {
+            (Verdict::Fail, format!("risk level {:?}", audit.risk_level))
+        } else if audit.risk_level >= self.warn_on {
+            (Verdict::Warn, format!("risk level {:?}", audit.risk_level))
+        } else {
+            return None;
+        };
+
+        let mut finding = Finding {
+            package: name.clone(),
+            version: version.map(String::from),
+            risk_level: audit.risk_level,
+            verdict,
+            reason,
+            waived_by: None,
+        };
+
+        let exception = self.exceptions.iter().find(|e| {
+            matches_pattern(&e.package, &name) && version_matches(e.version.as_deref(), version)
+        });
+        match exception {
+            Some(exception) if exception.expires >= today => {
+                finding.verdict = Verdict::Pass;
+                finding.waived_by = Some(exception.justification.clone());
+            }
+            Some(exception) => {
+                finding.reason = format!(
+                    "{} (exception expired on {})",
+                    finding.reason, exception.expires
+                );
+            }
+            None => {}
+        }
+
+        Some(finding)
+    }
+
+    pub fn evaluate<'a>(
+        &self,
+        audits: impl IntoIterator<Item = &'a PackageAudit>,
+        today: NaiveDate,
+    ) -> PolicyReport {
+        PolicyReport {
+            findings: audits
+                .into_iter()
+                .filter_map(|audit| self.evaluate_audit(audit, today))
+                .collect(),
+        }
+    }
+}
+
+/// Print the policy outcome for humans (CI logs).
+pub fn print_report(report: &PolicyReport) {
+    println!("\n{}", "📜 Security policy".cyan().bold());
+
+    if report.findings.is_empty() {
+        println!("   {}", "No findings".green());
+    }
+
+    for finding in &report.findings {
+        let label = match (finding.verdict, &finding.waived_by) {
+            (_, Some(_)) => "WAIVED".bright_black().bold(),
+            (Verdict::Fail, None) => "FAIL".red().bold(),
+            (Verdict::Warn, None) => "WARN".yellow().bold(),
+            (Verdict::Pass, None) => "PASS".green().bold(),
+        };
+        let package = match &finding.version {
+            Some(version) => format!("{}@{}", finding.package, version),
+            None => finding.package.clone(),
+        };
+        println!(
+            "   {:<6} {} {}",
+            label,
+            package.bright_white(),
+            format!("— {}", finding.reason).bright_black()
+        );
+        if let Some(justification) = &finding.waived_by {
+            println!(
+                "          {}",
+                format!("↳ {}", justification).bright_black()
+            );
+        }
+    }
+
+    let verdict = match report.verdict() {
+        Verdict::Pass => "passed".green().bold(),
+        Verdict::Warn => "passed with warnings".yellow().bold(),
+        Verdict::Fail => "failed".red().bold(),
+    };
+    println!("   {} {}", "Policy".bold(), verdict);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::{BehavioralChain, IssueSeverity};
+
+    fn audit(name: &str, version: &str, risk_level: RiskLevel) -> PackageAudit {
+        PackageAudit {
+            package_name: name.to_string(),
+            version: Some(version.to_string()),
+            risk_level,
+            ..Default::default()
+        }
+    }
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 6, 1).unwrap()
+    }
+
+    #[test]
+    fn pattern_matching() {
+        assert!(matches_pattern("lodash", "lodash"));
+        assert!(!matches_pattern("lodash", "lodash.merge"));
+        assert!(matches_pattern("lodash*", "lodash.merge"));
+        assert!(matches_pattern("@evil/*", "@evil/pkg"));
+        assert!(matches_pattern("*-stream", "event-stream"));
+        assert!(matches_pattern("a*b*c", "a-x-b-y-c"));
+        assert!(!matches_pattern("a*b*c", "a-x-c"));
+        assert!(!matches_pattern("ab*ab", "ab"));
+    }
+
+    #[test]
+    fn thresholds_decide_the_verdict() {
+        let policy = Policy::default();
+        let audits = [
+            audit("ok", "1.0.0", RiskLevel::Low),
+            audit("meh", "1.0.0", RiskLevel::Medium),
+        ];
+        let report = policy.evaluate(&audits, today());
+        assert_eq!(report.findings.len(), 1);
+        assert_eq!(report.verdict(), Verdict::Warn);
+        assert_eq!(report.exit_code(), EXIT_POLICY_WARN);
+
+        let bad = [audit("bad", "1.0.0", RiskLevel::Critical)];
+        assert_eq!(policy.evaluate(&bad, today()).exit_code(), EXIT_POLICY_FAIL);
+    }
+
+    #[test]
+    fn deny_rules_match_names_and_attack_chains() {
+        let policy: Policy = serde_json::from_str(
+            r#"{
+                "deny": [
+                    { "package": "event-*", "reason": "compromised" },
+                    { "attack_chain": "CredentialTheft" }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let finding = policy
+            .evaluate_audit(&audit("event-stream", "3.3.6", RiskLevel::Safe), today())
+            .unwrap();
+        assert_eq!(finding.verdict, Verdict::Fail);
+        assert!(finding.reason.contains("compromised"));
+
+        let mut stealer = audit("innocent", "1.0.0", RiskLevel::Low);
+        stealer.behavioral_chains.push(BehavioralChain {
+            chain_type: AttackChainType::CredentialTheft,
+            description: String::new(),
+            evidence: Vec::new(),
+            severity: IssueSeverity::Critical,
+            risk_score: 50,
+            entry_point: None,
+        });
+        let finding = policy.evaluate_audit(&stealer, today()).unwrap();
+        assert_eq!(finding.verdict, Verdict::Fail);
+        assert!(finding.reason.contains("CredentialTheft"));
+    }
+
+    #[test]
+    fn exceptions_waive_findings_until_they_expire() {
+        let policy: Policy = serde_json::from_str(
+            r#"{
+                "fail_on": "medium",
+                "exceptions": [
+                    { "package": "esbuild", "version": "^0.20.0", "expires": "2025-06-01",
+                      "justification": "postinstall downloads the platform binary" }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let esbuild = audit("esbuild", "0.20.2", RiskLevel::Medium);
+        let waived = policy.evaluate_audit(&esbuild, today()).unwrap();
+        assert_eq!(waived.verdict, Verdict::Pass);
+        assert!(waived.waived_by.is_some());
+
+        let later = NaiveDate::from_ymd_opt(2025, 6, 2).unwrap();
+        let expired = policy.evaluate_audit(&esbuild, later).unwrap();
+        assert_eq!(expired.verdict, Verdict::Fail);
+        assert!(expired.reason.contains("expired"));
+
+        let other_version = audit("esbuild", "0.21.0", RiskLevel::Medium);
+        assert_eq!(
+            policy
+                .evaluate_audit(&other_version, today())
+                .unwrap()
+                .verdict,
+            Verdict::Fail
+        );
+    }
+
+    #[test]
+    fn missing_policy_file_uses_defaults() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let policy = Policy::load_from(&dir.path().join("policy.json")).unwrap();
+        assert_eq!(policy.fail_on, RiskLevel::High);
+        assert_eq!(policy.warn_on, RiskLevel::Medium);
+
+        let broken = dir.path().join("broken.json");
+        fs::write(&broken, "{ nope").unwrap();
+        assert!(Policy::load_from(&broken).is_err());
+    }
+}
@@ -0,0 +1,592 @@
+// npm registry signatures and Sigstore provenance attestations
+// Checks that a version was signed by the registry (ECDSA over
+// `name@version:integrity`, as `npm audit signatures` does) and that its SLSA
+// provenance attestation describes the exact tarball being installed and is
+// signed by the key in its signing certificate.
+//
+// The certificate chain up to the Sigstore root and the transparency-log
+// inclusion proof are not checked yet, so anyone can produce a consistent
+// bundle for a tarball. Such an attestation is reported as `self-signed` and
+// never counts as provenance.
+
+use anyhow::Result;
+use base64::Engine;
+use ring::signature::{UnparsedPublicKey, ECDSA_P256_SHA256_ASN1};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+use crate::tarball;
+
+/// Where the registry publishes the keys it signs versions with
+pub const KEYS_PATH: &str = "/-/npm/v1/keys";
+
+const SLSA_PREDICATE_PREFIX: &str = "https://slsa.dev/provenance/";
+
+/// Risk added when a signature or attestation doesn't match the tarball
+const MISMATCH_RISK_SCORE: u32 = 100;
+
+/// DER header of a P-256 SubjectPublicKeyInfo; the 65-byte uncompressed
+/// point follows it
+const P256_SPKI_PREFIX: &[u8] = &[
+    0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x08, 0x2a,
+    0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00,
+];
+const P256_POINT_LEN: usize = 65;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VerificationStatus {
+    Verified,
+    /// Attestation signed by the key in its own certificate, which is not
+    /// chained to the Sigstore root: proves nothing about who built it
+    SelfSigned,
+    /// Nothing to check against: no signature, no attestation, unknown key
+    Unverified,
+    /// A signature or attestation exists but doesn't match the tarball
+    Mismatched,
+}
+
+impl VerificationStatus {
+    pub fn label(self) -> &'static str {
+        match self {
+            VerificationStatus::Verified => "verified",
+            VerificationStatus::SelfSigned => "self-signed",
+            VerificationStatus::Unverified => "unverified",
+            VerificationStatus::Mismatched => "mismatched",
+        }
+    }
+}
+
+impl fmt::Display for VerificationStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProvenanceReport {
+    /// Registry signature over `name@version:integrity`
+    pub signature: VerificationStatus,
+    /// SLSA provenance attestation of the tarball
+    pub attestation: VerificationStatus,
+    /// Repository the provenance says the version was built from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_repository: Option<String>,
+    /// Why a check did not verify
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+}
+
+impl ProvenanceReport {
+    /// Worst of the two checks
+    pub fn status(&self) -> VerificationStatus {
+        if self.signature == VerificationStatus::Mismatched
+            || self.attestation == VerificationStatus::Mismatched
+        {
+            VerificationStatus::Mismatched
+        } else if self.signature == VerificationStatus::Verified
+            && self.attestation == VerificationStatus::Verified
+        {
+            VerificationStatus::Verified
+        } else {
+            VerificationStatus::Unverified
+        }
+    }
+
+    /// Built in CI with a provenance attestation that matches the tarball.
+    /// A self-signed attestation never qualifies.
+    pub fn has_provenance(&self) -> bool {
+        self.attestation == VerificationStatus::Verified
+            && self.signature != VerificationStatus::Mismatched
+    }
+
+    pub fn risk_score(&self) -> u32 {
+        if self.status() == VerificationStatus::Mismatched {
+            MISMATCH_RISK_SCORE
+        } else {
+            0
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RegistryKey {
+    pub keyid: String,
+    /// Base64 DER SubjectPublicKeyInfo
+    pub key: String,
+    #[serde(default)]
+    pub expires: Option<String>,
+}
+
+/// Signing keys published by a registry at `/-/npm/v1/keys`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RegistryKeys {
+    pub keys: Vec<RegistryKey>,
+}
+
+impl RegistryKeys {
+    pub fn fetch(registry: &str) -> Result<Self> {
+        let document = tarball::fetch_json(&format!("{}{}", registry, KEYS_PATH), "signing keys")?;
+        Ok(serde_json::from_value(document)?)
+    }
+
+    fn find(&self, keyid: &str) -> Option<&RegistryKey> {
+        self.keys.iter().find(|key| key.keyid == keyid)
+    }
+}
+
+fn decode_base64(data: &str) -> Option<Vec<u8>> {
+    base64::engine::general_purpose::STANDARD.decode(data).ok()
+}
+
+/// Uncompressed P-256 point from an SPKI, or from a certificate containing one
+fn p256_public_key(der: &[u8]) -> Option<&[u8]> {
+    let start = der
+        .windows(P256_SPKI_PREFIX.len())
+        .position(|window| window == P256_SPKI_PREFIX)?
+        + P256_SPKI_PREFIX.len();
+    der.get(start..start + P256_POINT_LEN)
+}
+
+fn verify_p256(public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+    UnparsedPublicKey::new(&ECDSA_P256_SHA256_ASN1, public_key)
+        .verify(message, signature)
+        .is_ok()
+}
+
+/// DSSE pre-authentication encoding: what an envelope's signature covers
+fn pae(payload_type: &str, payload: &[u8]) -> Vec<u8> {
+    let mut encoded = format!(
+        "DSSEv1 {} {} {} ",
+        payload_type.len(),
+        payload_type,
+        payload.len()
+    )
+    .into_bytes();
+    encoded.extend_from_slice(payload);
+    encoded
+}
+
+/// Hex SHA-512 of the tarball from its SRI integrity string
+fn sha512_hex(integrity: &str) -> Option<String> {
+    let digest = integrity
+        .split_whitespace()
+        .find_map(|entry| entry.strip_prefix("sha512-"))
+        .and_then(decode_base64)?;
+    Some(digest.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// Package URL an attestation subject uses for `name@version`
+fn purl(name: &str, version: &str) -> String {
+    format!("pkg:npm/{}@{}", name.replacen('@', "%40", 1), version)
+}
+
+fn check_signature(
+    keys: Option<&RegistryKeys>,
+    name: &str,
+    version: &str,
+    dist: &Value,
+    published: Option<&str>,
+) -> (VerificationStatus, Option<String>) {
+    let unverified = |note: &str| (VerificationStatus::Unverified, Some(note.to_string()));
+    let mismatched = |note: String| (VerificationStatus::Mismatched, Some(note));
+
+    let Some(signatures) = dist
+        .get("signatures")
+        .and_then(|s| s.as_array())
+        .filter(|s| !s.is_empty())
+    else {
+        return unverified("no registry signature");
+    };
+    let Some(integrity) = dist.get("integrity").and_then(|i| i.as_str()) else {
+        return unverified("no integrity for the registry signature to cover");
+    };
+    let Some(keys) = keys else {
+        return unverified("registry signing keys unavailable");
+    };
+    let message = format!("{}@{}:{}", name, version, integrity);
+
+    for signature in signatures {
+        let keyid = signature
+            .get("keyid")
+            .and_then(|k| k.as_str())
+            .unwrap_or("");
+        let Some(key) = keys.find(keyid) else {
+            continue;
+        };
+        // Both are RFC 3339 timestamps in UTC, which sort as strings
+        if let (Some(expires), Some(published)) = (&key.expires, published) {
+            if expires.as_str() < published {
+                return mismatched(format!("signed with key {} after it expired", keyid));
+            }
+        }
+        let public_key = decode_base64(&key.key);
+        let sig = signature
+            .get("sig")
+            .and_then(|s| s.as_str())
+            .and_then(decode_base64);
+        return match (public_key.as_deref().and_then(p256_public_key), sig) {
+            (Some(public_key), Some(sig)) if verify_p256(public_key, message.as_bytes(), &sig) => {
+                (VerificationStatus::Verified, None)
+            }
+            _ => mismatched(format!("registry signature doesn't cover {}", message)),
+        };
+    }
+    unverified("signed with a key the registry doesn't list")
+}
+
+/// SLSA provenance statement and its envelope from an attestations document
+fn provenance_bundle(attestations: &Value) -> Option<&Value> {
+    attestations
+        .get("attestations")?
+        .as_array()?
+        .iter()
+        .find(|attestation| {
+            attestation
+                .get("predicateType")
+                .and_then(|p| p.as_str())
+                .is_some_and(|p| p.starts_with(SLSA_PREDICATE_PREFIX))
+        })?
+        .get("bundle")
+}
+
+fn source_repository(statement: &Value) -> Option<String> {
+    let predicate = statement.get("predicate")?;
+    predicate
+        .pointer("/buildDefinition/externalParameters/workflow/repository")
+        .or_else(|| predicate.pointer("/invocation/configSource/uri"))
+        .and_then(|r| r.as_str())
+        .map(String::from)
+}
+
+/// Check an attestation bundle against `name@version` and its integrity.
+/// Returns the status and the source repository it names. A bundle that is
+/// consistent is at best `SelfSigned`, since its certificate is not verified.
+pub fn check_attestation(
+    bundle: &Value,
+    name: &str,
+    version: &str,
+    integrity: Option<&str>,
+) -> (VerificationStatus, Option<String>, Option<String>) {
+    let unverified = |note: &str| (VerificationStatus::Unverified, None, Some(note.to_string()));
+    let mismatched = |note: &str| (VerificationStatus::Mismatched, None, Some(note.to_string()));
+
+    let Some(envelope) = bundle.get("dsseEnvelope") else {
+        return unverified("attestation has no DSSE envelope");
+    };
+    let payload_type = envelope
+        .get("payloadType")
+        .and_then(|t| t.as_str())
+        .unwrap_or("");
+    let Some(payload) = envelope
+        .get("payload")
+        .and_then(|p| p.as_str())
+        .and_then(decode_base64)
+    else {
+        return unverified("attestation payload is not base64");
+    };
+    let Ok(statement) = serde_json::from_slice::<Value>(&payload) else {
+        return unverified("attestation payload is not an in-toto statement");
+    };
+
+    let Some(expected) = integrity.and_then(sha512_hex) else {
+        return unverified("no sha512 integrity to compare the attestation with");
+    };
+    let subject = purl(name, version);
+    let names_tarball = statement
+        .get("subject")
+        .and_then(|s| s.as_array())
+        .into_iter()
+        .flatten()
+        .any(|s| {
+            let named = s.get("name").and_then(|n| n.as_str()).is_some_and(|n| {
+                n == subject || n.replacen("%40", "@", 1) == subject.replacen("%40", "@", 1)
+            });
+            let digest = s.pointer("/digest/sha512").and_then(|d| d.as_str());
+            named && digest == Some(expected.as_str())
+        });
+    if !names_tarball {
+        return mismatched("attestation describes a different tarball");
+    }
+
+    let material = bundle.get("verificationMaterial");
+    let certificate = material
+        .and_then(|m| m.pointer("/x509CertificateChain/certificates/0/rawBytes"))
+        .or_else(|| material.and_then(|m| m.pointer("/certificate/rawBytes")))
+        .and_then(|c| c.as_str())
+        .and_then(decode_base64);
+    let Some(certificate) = certificate else {
+        return unverified("attestation has no signing certificate");
+    };
+    let Some(public_key) = p256_public_key(&certificate) else {
+        return unverified("signing certificate has no P-256 key");
+    };
+    let signature = envelope
+        .pointer("/signatures/0/sig")
+        .and_then(|s| s.as_str())
+        .and_then(decode_base64);
+    match signature {
+        Some(sig) if verify_p256(public_key, &pae(payload_type, &payload), &sig) => (
+            VerificationStatus::SelfSigned,
+            source_repository(&statement),
+            Some("signing certificate is not verified against the Sigstore root".to_string()),
+        ),
+        _ => mismatched("attestation signature doesn't match its certificate"),
+    }
+}
+
+/// Verify `version` of `name` using the registry's `keys` and the
+/// attestation bundle the packument links to
+pub fn verify(
+    keys: Option<&RegistryKeys>,
+    packument: &Value,
+    name: &str,
+    version: &str,
+) -> ProvenanceReport {
+    let dist = packument
+        .pointer(&format!("/versions/{}/dist", version.replace('/', "~1")))
+        .cloned()
+        .unwrap_or(Value::Null);
+    let published = packument
+        .get("time")
+        .and_then(|t| t.get(version))
+        .and_then(|t| t.as_str());
+    let mut notes = Vec::new();
+
+    let (signature, note) = check_signature(keys, name, version, &dist, published);
+    notes.extend(note);
+
+    let integrity = dist.get("integrity").and_then(|i| i.as_str());
+    let (attestation, source_repository) =
+        match dist.pointer("/attestations/url").and_then(|u| u.as_str()) {
+            None => {
+                notes.push("no provenance attestation".to_string());
+                (VerificationStatus::Unverified, None)
+            }
+            Some(url) => match tarball::fetch_json(url, "attestations") {
+                Err(e) => {
+                    notes.push(e.to_string());
+                    (VerificationStatus::Unverified, None)
+                }
+                Ok(document) => match provenance_bundle(&document) {
+                    None => {
+                        notes.push("no SLSA provenance among the attestations".to_string());
+                        (VerificationStatus::Unverified, None)
+                    }
+                    Some(bundle) => {
+                        let (status, repository, note) =
+                            check_attestation(bundle, name, version, integrity);
+                        notes.extend(note);
+                        (status, repository)
+                    }
+                },
+            },
+        };
+
+    ProvenanceReport {
+        signature,
+        attestation,
+        source_repository,
+        notes,
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use ring::rand::SystemRandom;
+    use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+    use serde_json::json;
+
+    /// P-256 key for signing test fixtures
+    pub(crate) struct TestKey {
+        pair: EcdsaKeyPair,
+        rng: SystemRandom,
+    }
+
+    impl TestKey {
+        pub(crate) fn generate() -> Self {
+            let rng = SystemRandom::new();
+            let pkcs8 =
+                EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+            let pair =
+                EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref(), &rng)
+                    .unwrap();
+            Self { pair, rng }
+        }
+
+        /// Base64 DER SubjectPublicKeyInfo
+        pub(crate) fn spki(&self) -> String {
+            let mut der = P256_SPKI_PREFIX.to_vec();
+            der.extend_from_slice(self.pair.public_key().as_ref());
+            base64::engine::general_purpose::STANDARD.encode(der)
+        }
+
+        pub(crate) fn sign(&self, message: &[u8]) -> String {
+            let signature = self.pair.sign(&self.rng, message).unwrap();
+            base64::engine::general_purpose::STANDARD.encode(signature.as_ref())
+        }
+
+        pub(crate) fn registry_keys(&self) -> Value {
+            json!({ "keys": [{ "keyid": "SHA256:test", "keytype": "ecdsa-sha2-nistp256", "key": self.spki(), "expires": null }] })
+        }
+
+        pub(crate) fn registry_signature(
+            &self,
+            name: &str,
+            version: &str,
+            integrity: &str,
+        ) -> Value {
+            let message = format!("{}@{}:{}", name, version, integrity);
+            json!([{ "keyid": "SHA256:test", "sig": self.sign(message.as_bytes()) }])
+        }
+
+        /// Attestations document with a SLSA provenance for the tarball
+        pub(crate) fn attestations(&self, name: &str, version: &str, integrity: &str) -> Value {
+            let statement = json!({
+                "_type": "https://in-toto.io/Statement/v1",
+                "subject": [{
+                    "name": purl(name, version),
+                    "digest": { "sha512": sha512_hex(integrity).unwrap() }
+                }],
+                "predicateType": "https://slsa.dev/provenance/v1",
+                "predicate": {
+                    "buildDefinition": {
+                        "externalParameters": {
+                            "workflow": { "repository": "https://github.com/acme/widget" }
+                        }
+                    }
+                }
+            });
+            let payload = statement.to_string();
+            let payload_type = "application/vnd.in-toto+json";
+            // Certificate stand-in: any DER holding the signer's SPKI
+            let mut certificate = vec![0x30, 0x82, 0x01, 0x00];
+            certificate.extend(decode_base64(&self.spki()).unwrap());
+            json!({
+                "attestations": [{
+                    "predicateType": "https://slsa.dev/provenance/v1",
+                    "bundle": {
+                        "verificationMaterial": {
+                            "x509CertificateChain": { "certificates": [{
+                                "rawBytes": base64::engine::general_purpose::STANDARD.encode(certificate)
+                            }] }
+                        },
+                        "dsseEnvelope": {
+                            "payload": base64::engine::general_purpose::STANDARD.encode(&payload),
+                            "payloadType": payload_type,
+                            "signatures": [{ "sig": self.sign(&pae(payload_type, payload.as_bytes())) }]
+                        }
+                    }
+                }]
+            })
+        }
+    }
+
+    const INTEGRITY: &str = "sha512-z4PhNX7vuL3xVChQ1m2AB9Yg5AULVxXcg/SpIdNs6c5H0NE8XYXysP+DGNKHfuwvY7kxvUdBeoGlODJ6+SfaPg==";
+
+    fn bundle(document: &Value) -> &Value {
+        provenance_bundle(document).unwrap()
+    }
+
+    #[test]
+    fn registry_signature_over_name_version_and_integrity() {
+        let key = TestKey::generate();
+        let keys: RegistryKeys = serde_json::from_value(key.registry_keys()).unwrap();
+        let dist = json!({
+            "integrity": INTEGRITY,
+            "signatures": key.registry_signature("@acme/widget", "1.2.0", INTEGRITY),
+        });
+        let status = |keys: &RegistryKeys, version: &str, dist: &Value| {
+            check_signature(Some(keys), "@acme/widget", version, dist, None).0
+        };
+        assert_eq!(status(&keys, "1.2.0", &dist), VerificationStatus::Verified);
+        // Same signature presented for another version
+        assert_eq!(
+            status(&keys, "1.2.1", &dist),
+            VerificationStatus::Mismatched
+        );
+        assert_eq!(
+            status(&RegistryKeys::default(), "1.2.0", &dist),
+            VerificationStatus::Unverified
+        );
+        assert_eq!(
+            status(&keys, "1.2.0", &json!({})),
+            VerificationStatus::Unverified
+        );
+
+        // Key that expired before the version was published
+        let mut expired = keys.clone();
+        expired.keys[0].expires = Some("2023-01-01T00:00:00.000Z".to_string());
+        let (status, note) = check_signature(
+            Some(&expired),
+            "@acme/widget",
+            "1.2.0",
+            &dist,
+            Some("2024-06-01T00:00:00.000Z"),
+        );
+        assert_eq!(status, VerificationStatus::Mismatched);
+        assert_eq!(
+            note.as_deref(),
+            Some("signed with key SHA256:test after it expired")
+        );
+    }
+
+    #[test]
+    fn attestation_must_name_the_tarball_and_match_its_certificate() {
+        let key = TestKey::generate();
+        let document = key.attestations("@acme/widget", "1.2.0", INTEGRITY);
+
+        let (status, repository, _) =
+            check_attestation(bundle(&document), "@acme/widget", "1.2.0", Some(INTEGRITY));
+        // Consistent, but the certificate could have been made by anyone
+        assert_eq!(status, VerificationStatus::SelfSigned);
+        assert_eq!(
+            repository.as_deref(),
+            Some("https://github.com/acme/widget")
+        );
+
+        // Provenance of a different tarball
+        let other = "sha512-AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA==";
+        let (status, _, note) =
+            check_attestation(bundle(&document), "@acme/widget", "1.2.0", Some(other));
+        assert_eq!(status, VerificationStatus::Mismatched);
+        assert_eq!(
+            note.as_deref(),
+            Some("attestation describes a different tarball")
+        );
+
+        // Envelope signed by someone other than the certificate holder
+        let mut forged = document.clone();
+        let impostor = TestKey::generate();
+        forged["attestations"][0]["bundle"]["dsseEnvelope"]["signatures"][0]["sig"] =
+            json!(impostor.sign(b"anything"));
+        let (status, _, _) =
+            check_attestation(bundle(&forged), "@acme/widget", "1.2.0", Some(INTEGRITY));
+        assert_eq!(status, VerificationStatus::Mismatched);
+    }
+
+    #[test]
+    fn report_status_is_the_worst_of_both_checks() {
+        let report = |signature, attestation| ProvenanceReport {
+            signature,
+            attestation,
+            source_repository: None,
+            notes: Vec::new(),
+        };
+        use VerificationStatus::*;
+        assert_eq!(report(Verified, Verified).status(), Verified);
+        assert!(report(Verified, Verified).has_provenance());
+        assert_eq!(report(Verified, Unverified).status(), Unverified);
+        assert!(!report(Verified, Unverified).has_provenance());
+        assert_eq!(report(Verified, SelfSigned).status(), Unverified);
+        assert!(!report(Verified, SelfSigned).has_provenance());
+        assert_eq!(report(Mismatched, Verified).status(), Mismatched);
+        assert_eq!(
+            report(Mismatched, Verified).risk_score(),
+            MISMATCH_RISK_SCORE
+        );
+        assert_eq!(report(Unverified, Unverified).risk_score(), 0);
+    }
+}
@@ -0,0 +1,622 @@
+// Quarantined installs for `fnpm add --quarantine`
+// Packages are installed into `.fnpm/staging` with lifecycle scripts
+// disabled, so the audit runs on exactly the files that will end up in the
+// project. Only an approved staging tree is promoted: node_modules is swapped
+// in with a rename and the manifest and lockfile are replaced, with the
+// previous state restored if any step fails. The package manager then relinks
+// the promoted tree, whose `.bin` shims and metadata still point into staging.
+// Projects whose installs read files outside the manifest (local and workspace
+// dependencies) can't be staged and are refused.
+
+use anyhow::{anyhow, Context, Result};
+use colored::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::lockfile;
+use crate::package_manager::split_package_spec;
+
+/// Staging project, relative to the project root
+pub const STAGING_DIR: &str = ".fnpm/staging";
+
+/// Files an install reads and rewrites; copied into staging and promoted back
+pub const PROJECT_FILES: &[&str] = &[
+    "package.json",
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "bun.lock",
+    "bun.lockb",
+];
+
+/// Where promotion keeps the project's previous files until it succeeds
+const BACKUP_DIR: &str = ".previous";
+
+/// Registry and package manager settings the staging install must honor
+const SETTINGS_FILES: &[&str] = &[".npmrc", ".yarnrc", ".yarnrc.yml"];
+
+/// Directories an install may read: yarn's pinned release and plugins, and
+/// the patches applied by yarn, pnpm and patch-package
+const SETTINGS_DIRS: &[&str] = &[
+    ".yarn/releases",
+    ".yarn/plugins",
+    ".yarn/patches",
+    "patches",
+];
+
+/// Dependency fields of package.json
+const DEPENDENCY_FIELDS: &[&str] = &[
+    "dependencies",
+    "devDependencies",
+    "optionalDependencies",
+    "peerDependencies",
+];
+
+/// Whether the dependency range `range` points at files in or next to the
+/// project, which a staging install would resolve against the wrong directory
+fn is_local_range(range: &str) -> bool {
+    [
+        "file:",
+        "link:",
+        "portal:",
+        "workspace:",
+        "./",
+        "../",
+        "/",
+        "~/",
+    ]
+    .iter()
+    .any(|prefix| range.starts_with(prefix))
+        || range == "."
+        || range == ".."
+}
+
+/// `is_local_range` for a package argument: a path, or `name@range`
+fn is_local_package(package: &str) -> bool {
+    is_local_range(package) || is_local_range(&split_package_spec(package).1)
+}
+
+/// Why the project in `project_dir` can't be installed in staging, if it can't
+fn unsupported_project(project_dir: &Path) -> Option<String> {
+    if project_dir.join("pnpm-workspace.yaml").exists() {
+        return Some("it is a pnpm workspace".to_string());
+    }
+    if lockfile::is_yarn_berry_project(project_dir) {
+        return Some("yarn berry can't install without running scripts".to_string());
+    }
+    let manifest: serde_json::Value = fs::read_to_string(project_dir.join("package.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())?;
+    if manifest.get("workspaces").is_some() {
+        return Some("it declares workspaces".to_string());
+    }
+    for field in DEPENDENCY_FIELDS {
+        let Some(deps) = manifest.get(field).and_then(|deps| deps.as_object()) else {
+            continue;
+        };
+        for (name, spec) in deps {
+            let spec = spec.as_str().unwrap_or_default();
+            if is_local_range(spec) {
+                return Some(format!("{} depends on {}", name, spec));
+            }
+        }
+    }
+    None
+}
+
+/// Copy the directory tree `source` to `dest`, following no symlinks
+fn copy_tree(source: &Path, dest: &Path) -> Result<()> {
+    fs::create_dir_all(dest)?;
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let target = dest.join(entry.file_name());
+        if file_type.is_dir() {
+            copy_tree(&entry.path(), &target)?;
+        } else if file_type.is_file() {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Command installing `packages` into the current directory without running
+/// any lifecycle script
+pub fn install_command(manager: &str, packages: &[String], dev: bool) -> Result<Command> {
+    let (subcommand, dev_flag) = match manager {
+        "npm" => ("install", "--save-dev"),
+        "yarn" => ("add", "--dev"),
+        "pnpm" => ("add", "--save-dev"),
+        "bun" => ("add", "--dev"),
+        _ => {
+            return Err(anyhow!(
+                "Quarantined installs are not supported with {}",
+                manager
+            ))
+        }
+    };
+
+    let mut cmd = Command::new(manager);
+    cmd.args([subcommand, "--ignore-scripts"]);
+    if dev {
+        cmd.arg(dev_flag);
+    }
+    cmd.args(packages).env("FNPM_HOOK_ACTIVE", "1"); // Prevent hook recursion
+    Ok(cmd)
+}
+
+/// Command relinking an installed tree in place from the package manager's
+/// cache, without running any lifecycle script
+pub fn relink_command(manager: &str) -> Result<Command> {
+    let args: &[&str] = match manager {
+        // Recreates every `.bin` link from the packages' own manifests
+        "npm" => &["rebuild", "--ignore-scripts"],
+        "yarn" => &[
+            "install",
+            "--offline",
+            "--frozen-lockfile",
+            "--ignore-scripts",
+        ],
+        "pnpm" => &[
+            "install",
+            "--offline",
+            "--frozen-lockfile",
+            "--ignore-scripts",
+        ],
+        "bun" => &["install", "--frozen-lockfile", "--ignore-scripts"],
+        _ => {
+            return Err(anyhow!(
+                "Quarantined installs are not supported with {}",
+                manager
+            ))
+        }
+    };
+    let mut cmd = Command::new(manager);
+    cmd.args(args).env("FNPM_HOOK_ACTIVE", "1");
+    Ok(cmd)
+}
+
+/// Relink the promoted node_modules of `project_dir`: `.bin` shims and
+/// metadata such as pnpm's `.modules.yaml` still hold staging paths
+pub fn relink(manager: &str, project_dir: &Path) -> Result<()> {
+    let mut cmd = relink_command(manager)?;
+    let status = cmd.current_dir(project_dir).status();
+    if !status.is_ok_and(|status| status.success()) {
+        let args: Vec<String> = cmd
+            .get_args()
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect();
+        return Err(anyhow!(
+            "Could not relink node_modules after promotion; run `{} {}` before using its binaries",
+            manager,
+            args.join(" ")
+        ));
+    }
+    Ok(())
+}
+
+/// The staging copy of a project
+pub struct Staging {
+    project_dir: PathBuf,
+    dir: PathBuf,
+}
+
+impl Staging {
+    /// Start a fresh staging tree holding copies of the project's manifest,
+    /// lockfiles, package manager settings and patches
+    pub fn prepare(project_dir: &Path) -> Result<Self> {
+        if let Some(reason) = unsupported_project(project_dir) {
+            return Err(anyhow!(
+                "Quarantined installs can't stage this project: {}. Install without --quarantine",
+                reason
+            ));
+        }
+        let dir = project_dir.join(STAGING_DIR);
+        if dir.join(BACKUP_DIR).exists() {
+            return Err(anyhow!(
+                "A failed promotion left the previous project files in {}. Restore or delete them first",
+                dir.join(BACKUP_DIR).display()
+            ));
+        }
+        if dir.exists() {
+            fs::remove_dir_all(&dir)
+                .with_context(|| format!("Could not clear {}", dir.display()))?;
+        }
+        fs::create_dir_all(&dir)?;
+
+        for file in PROJECT_FILES.iter().chain(SETTINGS_FILES) {
+            let source = project_dir.join(file);
+            if source.is_file() {
+                fs::copy(&source, dir.join(file))?;
+            }
+        }
+        for settings in SETTINGS_DIRS {
+            let source = project_dir.join(settings);
+            if source.is_dir() {
+                copy_tree(&source, &dir.join(settings))
+                    .with_context(|| format!("Could not copy {} into staging", settings))?;
+            }
+        }
+
+        Ok(Self {
+            project_dir: project_dir.to_path_buf(),
+            dir,
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Install `packages` (and the rest of the project's dependencies) into
+    /// the staging node_modules
+    pub fn install(&self, manager: &str, packages: &[String], dev: bool) -> Result<()> {
+        if let Some(local) = packages.iter().find(|package| is_local_package(package)) {
+            return Err(anyhow!(
+                "Quarantined installs can't stage the local package {}. Install without --quarantine",
+                local
+            ));
+        }
+        let status = install_command(manager, packages, dev)?
+            .current_dir(&self.dir)
+            .status()?;
+        if !status.success() {
+            return Err(anyhow!(
+                "Failed to install {} into {}",
+                packages.join(" "),
+                STAGING_DIR
+            ));
+        }
+        Ok(())
+    }
+
+    /// Move the staged node_modules, manifest and lockfile into the project.
+    /// On failure everything already moved is put back and the project is
+    /// left as it was.
+    pub fn promote(self) -> Result<()> {
+        let backup = self.dir.join(BACKUP_DIR);
+        fs::create_dir_all(&backup)?;
+
+        let mut promotion = Promotion {
+            project_dir: &self.project_dir,
+            backup: &backup,
+            node_modules_backed_up: false,
+            staged_in_place: false,
+            files_replaced: Vec::new(),
+        };
+        match promotion.run(&self.dir) {
+            Ok(()) => {
+                // The old tree is no longer needed; a leftover is harmless
+                let _ = fs::remove_dir_all(&self.dir);
+                Ok(())
+            }
+            Err(e) => match promotion.rollback() {
+                Ok(()) => {
+                    let _ = fs::remove_dir_all(&backup);
+                    Err(e.context("Promotion failed, the project was left unchanged"))
+                }
+                Err(rollback) => Err(e.context(format!(
+                    "Promotion failed and could not be rolled back ({}). The previous files are in {}",
+                    rollback,
+                    backup.display()
+                ))),
+            },
+        }
+    }
+
+    /// Throw the staging tree away
+    pub fn discard(self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// What `Staging::promote` has changed so far, to undo it
+struct Promotion<'a> {
+    project_dir: &'a Path,
+    backup: &'a Path,
+    node_modules_backed_up: bool,
+    staged_in_place: bool,
+    /// Project files overwritten, and whether they existed before
+    files_replaced: Vec<(&'static str, bool)>,
+}
+
+impl Promotion<'_> {
+    fn run(&mut self, staging: &Path) -> Result<()> {
+        let staged_modules = staging.join("node_modules");
+        if !staged_modules.is_dir() {
+            return Err(anyhow!("Nothing was installed into {}", STAGING_DIR));
+        }
+
+        let node_modules = self.project_dir.join("node_modules");
+        if node_modules.exists() {
+            fs::rename(&node_modules, self.backup.join("node_modules"))?;
+            self.node_modules_backed_up = true;
+        }
+        fs::rename(&staged_modules, &node_modules)?;
+        self.staged_in_place = true;
+
+        for file in PROJECT_FILES {
+            let staged = staging.join(file);
+            if !staged.exists() {
+                continue;
+            }
+            let target = self.project_dir.join(file);
+            let existed = target.exists();
+            if existed {
+                fs::copy(&target, self.backup.join(file))?;
+            }
+            self.files_replaced.push((file, existed));
+            // Copy next to the target first so the swap itself is a rename
+            let partial = self.project_dir.join(format!(".{}.fnpm-staged", file));
+            if let Err(e) = fs::copy(&staged, &partial).and_then(|_| fs::rename(&partial, &target))
+            {
+                let _ = fs::remove_file(&partial);
+                return Err(anyhow!(e).context(format!("Could not replace {}", file)));
+            }
+        }
+        Ok(())
+    }
+
+    fn rollback(&self) -> Result<()> {
+        for (file, existed) in &self.files_replaced {
+            let target = self.project_dir.join(file);
+            if *existed {
+                fs::copy(self.backup.join(file), &target)?;
+            } else if target.exists() {
+                fs::remove_file(&target)?;
+            }
+        }
+
+        let node_modules = self.project_dir.join("node_modules");
+        if self.staged_in_place {
+            fs::remove_dir_all(&node_modules)?;
+        }
+        if self.node_modules_backed_up {
+            fs::rename(self.backup.join("node_modules"), node_modules)?;
+        }
+        Ok(())
+    }
+}
+
+/// Explain what happens to a rejected staging tree
+pub fn print_discarded(packages: &[String]) {
+    println!(
+        "{} {} {}",
+        "🗑".bright_black(),
+        packages.join(" ").bright_white(),
+        "was not installed: the staging tree was discarded and node_modules is untouched"
+            .bright_black()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+    use tempfile::TempDir;
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn project() -> TempDir {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        write(&root.join("package.json"), r#"{"dependencies":{"a":"1"}}"#);
+        write(&root.join("package-lock.json"), "old lock");
+        write(&root.join(".npmrc"), "registry=http://mirror.example/");
+        write(&root.join("node_modules/a/package.json"), "old a");
+        temp
+    }
+
+    #[test]
+    fn install_command_never_runs_scripts() {
+        for manager in ["npm", "yarn", "pnpm", "bun"] {
+            let cmd = install_command(manager, &["left-pad".to_string()], true).unwrap();
+            let args: Vec<&OsStr> = cmd.get_args().collect();
+            assert_eq!(cmd.get_program(), OsStr::new(manager));
+            assert!(args.contains(&OsStr::new("--ignore-scripts")), "{manager}");
+            assert_eq!(args.last(), Some(&OsStr::new("left-pad")));
+        }
+        assert!(install_command("deno", &[], false).is_err());
+    }
+
+    #[test]
+    fn promote_swaps_in_the_staged_tree() {
+        let temp = project();
+        let root = temp.path();
+        let staging = Staging::prepare(root).unwrap();
+        assert_eq!(
+            fs::read_to_string(staging.dir().join(".npmrc")).unwrap(),
+            "registry=http://mirror.example/"
+        );
+
+        // What the package manager would have done in staging
+        write(
+            &staging.dir().join("package.json"),
+            r#"{"dependencies":{"a":"1","b":"2"}}"#,
+        );
+        write(&staging.dir().join("package-lock.json"), "new lock");
+        write(&staging.dir().join("node_modules/a/package.json"), "new a");
+        write(&staging.dir().join("node_modules/b/package.json"), "b");
+
+        staging.promote().unwrap();
+        assert!(fs::read_to_string(root.join("package.json"))
+            .unwrap()
+            .contains("\"b\""));
+        assert_eq!(
+            fs::read_to_string(root.join("package-lock.json")).unwrap(),
+            "new lock"
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("node_modules/a/package.json")).unwrap(),
+            "new a"
+        );
+        assert!(root.join("node_modules/b").exists());
+        assert!(!root.join(STAGING_DIR).exists());
+    }
+
+    #[test]
+    fn failed_promotion_restores_the_project() {
+        let temp = project();
+        let root = temp.path();
+        let staging = Staging::prepare(root).unwrap();
+        write(
+            &staging.dir().join("package.json"),
+            r#"{"dependencies":{}}"#,
+        );
+        write(&staging.dir().join("node_modules/b/package.json"), "b");
+        // A lockfile that can't be copied breaks promotion half way
+        fs::create_dir_all(staging.dir().join("yarn.lock")).unwrap();
+
+        let err = staging.promote().unwrap_err();
+        assert!(format!("{err:#}").contains("left unchanged"), "{err:#}");
+        assert_eq!(
+            fs::read_to_string(root.join("package.json")).unwrap(),
+            r#"{"dependencies":{"a":"1"}}"#
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("node_modules/a/package.json")).unwrap(),
+            "old a"
+        );
+        assert!(!root.join("node_modules/b").exists());
+        assert!(!root.join("yarn.lock").exists());
+    }
+
+    #[test]
+    fn failed_first_install_leaves_no_node_modules() {
+        let temp = project();
+        let root = temp.path();
+        fs::remove_dir_all(root.join("node_modules")).unwrap();
+        let staging = Staging::prepare(root).unwrap();
+        write(&staging.dir().join("node_modules/b/package.json"), "b");
+        fs::create_dir_all(staging.dir().join("bun.lock")).unwrap();
+
+        assert!(staging.promote().is_err());
+        assert!(!root.join("node_modules").exists());
+        // The backup is gone once the rollback succeeded
+        assert!(Staging::prepare(root).is_ok());
+    }
+
+    #[test]
+    fn prepare_copies_patches_and_refuses_local_dependencies() {
+        let temp = project();
+        let root = temp.path();
+        write(&root.join(".yarn/releases/yarn-4.1.0.cjs"), "yarn");
+        write(&root.join("patches/a+1.0.0.patch"), "diff");
+        let staging = Staging::prepare(root).unwrap();
+        assert!(staging.dir().join(".yarn/releases/yarn-4.1.0.cjs").exists());
+        assert!(staging.dir().join("patches/a+1.0.0.patch").exists());
+
+        let err = staging
+            .install("npm", &["../shared".to_string()], false)
+            .unwrap_err();
+        assert!(err.to_string().contains("../shared"), "{err}");
+        assert!(staging
+            .install("npm", &["b@file:../b".to_string()], false)
+            .is_err());
+        staging.discard();
+
+        for manifest in [
+            r#"{"dependencies":{"shared":"file:../shared"}}"#,
+            r#"{"devDependencies":{"@app/ui":"workspace:*"}}"#,
+            r#"{"dependencies":{"lib":"link:./lib"}}"#,
+            r#"{"workspaces":["packages/*"]}"#,
+        ] {
+            write(&root.join("package.json"), manifest);
+            let err = Staging::prepare(root).err().expect(manifest);
+            assert!(err.to_string().contains("--quarantine"), "{err}");
+        }
+        assert!(!root.join(STAGING_DIR).exists());
+    }
+
+    #[test]
+    fn local_packages_are_told_apart_from_registry_ones() {
+        for local in [
+            "./a",
+            "../a",
+            "/abs/a",
+            "a@file:../a",
+            "@s/a@link:../a",
+            ".",
+        ] {
+            assert!(is_local_package(local), "{local}");
+        }
+        for remote in ["a", "a@^1.0.0", "@types/node", "@s/a@2", "a@npm:b@1"] {
+            assert!(!is_local_package(remote), "{remote}");
+        }
+    }
+
+    #[test]
+    fn relink_command_never_runs_scripts() {
+        for manager in ["npm", "yarn", "pnpm", "bun"] {
+            let cmd = relink_command(manager).unwrap();
+            let args: Vec<&OsStr> = cmd.get_args().collect();
+            assert!(args.contains(&OsStr::new("--ignore-scripts")), "{manager}");
+        }
+        assert!(relink_command("deno").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn promoted_bin_entries_point_into_the_project() {
+        let temp = project();
+        let root = temp.path();
+        let staging = Staging::prepare(root).unwrap();
+        let staged = staging.dir().join("node_modules");
+        write(
+            &staged.join("b/package.json"),
+            r#"{"name":"b","version":"1.0.0","bin":{"b":"cli.js"}}"#,
+        );
+        write(&staged.join("b/cli.js"), "#!/usr/bin/env node\n");
+        fs::create_dir_all(staged.join(".bin")).unwrap();
+        // Relative, as npm links it: still valid once promoted
+        std::os::unix::fs::symlink("../b/cli.js", staged.join(".bin/b")).unwrap();
+        // Absolute into staging, as some managers write their shims
+        std::os::unix::fs::symlink(staged.join("b/cli.js"), staged.join(".bin/b-abs")).unwrap();
+        write(
+            &staging.dir().join("package.json"),
+            r#"{"dependencies":{"b":"1.0.0"}}"#,
+        );
+
+        staging.promote().unwrap();
+        let bin = root.join("node_modules/.bin");
+        assert_eq!(
+            fs::canonicalize(bin.join("b")).unwrap(),
+            fs::canonicalize(root.join("node_modules/b/cli.js")).unwrap()
+        );
+        // Staging is gone, so the absolute link is dangling until relinked
+        assert!(fs::metadata(bin.join("b-abs")).is_err());
+
+        let npm_available = Command::new("npm")
+            .arg("--version")
+            .output()
+            .is_ok_and(|output| output.status.success());
+        if !npm_available {
+            return;
+        }
+        fs::remove_file(bin.join("b")).unwrap();
+        std::os::unix::fs::symlink(
+            root.join(STAGING_DIR).join("node_modules/b/cli.js"),
+            bin.join("b"),
+        )
+        .unwrap();
+        relink("npm", root).unwrap();
+        assert_eq!(
+            fs::canonicalize(bin.join("b")).unwrap(),
+            fs::canonicalize(root.join("node_modules/b/cli.js")).unwrap()
+        );
+    }
+
+    #[test]
+    fn promote_refuses_an_empty_staging_tree() {
+        let temp = project();
+        let root = temp.path();
+        let staging = Staging::prepare(root).unwrap();
+        assert!(staging.promote().is_err());
+        assert!(root.join("node_modules/a").exists());
+    }
+}
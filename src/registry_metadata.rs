@@ -0,0 +1,411 @@
+// Publish-metadata risk signals from a registry packument
+// Looks at who published a version and how, compared with the version
+// published before it: a new maintainer, dropped provenance, a first install
+// script or a sudden jump in size are what account takeovers look like from
+// the registry's side, before a single line of code is read.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// Hooks npm runs on `npm install`
+const INSTALL_HOOKS: &[&str] = &["preinstall", "install", "postinstall"];
+
+/// A version at least this many times larger than the previous one...
+const SIZE_JUMP_FACTOR: u64 = 4;
+/// ...that also grew by at least this many bytes is flagged
+const SIZE_JUMP_MIN_BYTES: u64 = 256 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SignalKind {
+    /// Maintainers added or removed since the previous version
+    MaintainerChange,
+    /// Published by an account that never published this package before
+    NewPublisher,
+    /// Earlier versions had provenance attestations, this one doesn't
+    ProvenanceDropped,
+    NoRepository,
+    Deprecated,
+    /// preinstall/install/postinstall that the previous version didn't have
+    NewInstallScript,
+    SizeJump,
+}
+
+impl SignalKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            SignalKind::MaintainerChange => "maintainer-change",
+            SignalKind::NewPublisher => "new-publisher",
+            SignalKind::ProvenanceDropped => "provenance-dropped",
+            SignalKind::NoRepository => "no-repository",
+            SignalKind::Deprecated => "deprecated",
+            SignalKind::NewInstallScript => "new-install-script",
+            SignalKind::SizeJump => "size-jump",
+        }
+    }
+
+    pub fn risk_score(self) -> u32 {
+        match self {
+            SignalKind::ProvenanceDropped => 40,
+            SignalKind::NewInstallScript => 30,
+            SignalKind::NewPublisher => 25,
+            SignalKind::MaintainerChange | SignalKind::SizeJump => 15,
+            SignalKind::NoRepository | SignalKind::Deprecated => 5,
+        }
+    }
+}
+
+impl fmt::Display for SignalKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetadataSignal {
+    pub kind: SignalKind,
+    pub description: String,
+}
+
+impl MetadataSignal {
+    fn new(kind: SignalKind, description: String) -> Self {
+        Self { kind, description }
+    }
+
+    pub fn risk_score(&self) -> u32 {
+        self.kind.risk_score()
+    }
+}
+
+/// Sum of the risk added by `signals`
+pub fn risk_score(signals: &[MetadataSignal]) -> u32 {
+    signals.iter().map(MetadataSignal::risk_score).sum()
+}
+
+/// One version's entry in the packument
+struct Release<'a> {
+    version: &'a str,
+    manifest: &'a Value,
+}
+
+impl Release<'_> {
+    fn publisher(&self) -> Option<&str> {
+        self.manifest
+            .get("_npmUser")
+            .and_then(|user| user.get("name"))
+            .and_then(|name| name.as_str())
+    }
+
+    fn maintainers(&self) -> BTreeSet<&str> {
+        self.manifest
+            .get("maintainers")
+            .and_then(|m| m.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|m| m.get("name").and_then(|n| n.as_str()).or(m.as_str()))
+            .collect()
+    }
+
+    fn has_provenance(&self) -> bool {
+        self.manifest
+            .get("dist")
+            .and_then(|dist| dist.get("attestations"))
+            .is_some_and(|attestations| !attestations.is_null())
+    }
+
+    fn install_hooks(&self) -> Vec<&'static str> {
+        let scripts = self.manifest.get("scripts");
+        let mut hooks: Vec<&'static str> = INSTALL_HOOKS
+            .iter()
+            .copied()
+            .filter(|hook| scripts.and_then(|s| s.get(*hook)).is_some())
+            .collect();
+        // Abbreviated packuments only carry the flag
+        let flagged = self.manifest.get("hasInstallScript") == Some(&Value::Bool(true));
+        if hooks.is_empty() && flagged {
+            hooks.push("install");
+        }
+        hooks
+    }
+
+    fn unpacked_size(&self) -> Option<u64> {
+        self.manifest
+            .get("dist")
+            .and_then(|dist| dist.get("unpackedSize"))
+            .and_then(|size| size.as_u64())
+    }
+}
+
+fn has_repository(value: Option<&Value>) -> bool {
+    match value {
+        Some(Value::String(url)) => !url.trim().is_empty(),
+        Some(Value::Object(repo)) => repo
+            .get("url")
+            .and_then(|url| url.as_str())
+            .is_some_and(|url| !url.trim().is_empty()),
+        _ => false,
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    } else {
+        format!("{} KB", bytes / 1024)
+    }
+}
+
+/// Versions published before `version`, oldest first. Uses the packument's
+/// `time` map; without it falls back to semver order.
+fn earlier_releases<'a>(packument: &'a Value, version: &str) -> Vec<Release<'a>> {
+    let Some(versions) = packument.get("versions").and_then(|v| v.as_object()) else {
+        return Vec::new();
+    };
+    let time = packument.get("time");
+    let published = |v: &str| {
+        time.and_then(|t| t.get(v))
+            .and_then(|t| t.as_str())
+            .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+    };
+
+    let mut earlier: Vec<(&String, &Value)> = match published(version) {
+        Some(at) => versions
+            .iter()
+            .filter(|(v, _)| published(v).is_some_and(|t| t < at))
+            .collect(),
+        None => {
+            let Ok(current) = semver::Version::parse(version) else {
+                return Vec::new();
+            };
+            versions
+                .iter()
+                .filter(|(v, _)| semver::Version::parse(v).is_ok_and(|v| v < current))
+                .collect()
+        }
+    };
+    earlier.sort_by_key(|(v, _)| (published(v), semver::Version::parse(v).ok()));
+    earlier
+        .into_iter()
+        .map(|(version, manifest)| Release { version, manifest })
+        .collect()
+}
+
+/// Risk signals for `version` of the package described by `packument`
+pub fn analyze(packument: &Value, version: &str) -> Vec<MetadataSignal> {
+    let Some(manifest) = packument.get("versions").and_then(|v| v.get(version)) else {
+        return Vec::new();
+    };
+    let current = Release { version, manifest };
+    let earlier = earlier_releases(packument, version);
+    let mut signals = Vec::new();
+
+    if let Some(reason) = manifest.get("deprecated").and_then(|d| d.as_str()) {
+        signals.push(MetadataSignal::new(
+            SignalKind::Deprecated,
+            format!("{} is deprecated: {}", version, reason),
+        ));
+    }
+    if !has_repository(manifest.get("repository")) && !has_repository(packument.get("repository")) {
+        signals.push(MetadataSignal::new(
+            SignalKind::NoRepository,
+            "no repository link: the published code can't be traced to its source".to_string(),
+        ));
+    }
+
+    let Some(previous) = earlier.last() else {
+        return signals;
+    };
+
+    let before = previous.maintainers();
+    let now = current.maintainers();
+    if !before.is_empty() && !now.is_empty() && before != now {
+        let changes: Vec<String> = now
+            .difference(&before)
+            .map(|name| format!("+{}", name))
+            .chain(before.difference(&now).map(|name| format!("-{}", name)))
+            .collect();
+        signals.push(MetadataSignal::new(
+            SignalKind::MaintainerChange,
+            format!(
+                "maintainers changed since {}: {}",
+                previous.version,
+                changes.join(" ")
+            ),
+        ));
+    }
+
+    if let Some(publisher) = current.publisher() {
+        let known = earlier
+            .iter()
+            .any(|release| release.publisher() == Some(publisher));
+        let published_before = earlier.iter().any(|release| release.publisher().is_some());
+        if published_before && !known {
+            signals.push(MetadataSignal::new(
+                SignalKind::NewPublisher,
+                format!(
+                    "first version published by '{}', who never published {} before",
+                    publisher,
+                    packument
+                        .get("name")
+                        .and_then(|n| n.as_str())
+                        .unwrap_or("this package")
+                ),
+            ));
+        }
+    }
+
+    if !current.has_provenance() {
+        if let Some(attested) = earlier
+            .iter()
+            .rev()
+            .find(|release| release.has_provenance())
+        {
+            signals.push(MetadataSignal::new(
+                SignalKind::ProvenanceDropped,
+                format!(
+                    "published without provenance, but {} had build attestations",
+                    attested.version
+                ),
+            ));
+        }
+    }
+
+    let hooks = current.install_hooks();
+    if !hooks.is_empty() && previous.install_hooks().is_empty() {
+        signals.push(MetadataSignal::new(
+            SignalKind::NewInstallScript,
+            format!(
+                "{} script first appears in {} ({} had none)",
+                hooks.join("/"),
+                version,
+                previous.version
+            ),
+        ));
+    }
+
+    if let (Some(size), Some(previous_size)) = (current.unpacked_size(), previous.unpacked_size()) {
+        if previous_size > 0
+            && size >= previous_size * SIZE_JUMP_FACTOR
+            && size - previous_size >= SIZE_JUMP_MIN_BYTES
+        {
+            signals.push(MetadataSignal::new(
+                SignalKind::SizeJump,
+                format!(
+                    "unpacked size grew {}x since {} ({} → {})",
+                    size / previous_size,
+                    previous.version,
+                    format_size(previous_size),
+                    format_size(size)
+                ),
+            ));
+        }
+    }
+
+    signals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn kinds(signals: &[MetadataSignal]) -> Vec<&'static str> {
+        signals.iter().map(|s| s.kind.label()).collect()
+    }
+
+    fn release(publisher: &str, maintainers: &[&str], size: u64) -> Value {
+        json!({
+            "_npmUser": { "name": publisher },
+            "maintainers": maintainers.iter().map(|m| json!({ "name": m })).collect::<Vec<_>>(),
+            "repository": { "type": "git", "url": "git+https://github.com/acme/widget.git" },
+            "dist": { "unpackedSize": size },
+        })
+    }
+
+    #[test]
+    fn hijacked_patch_release_raises_every_signal() {
+        let mut attested = release("alice", &["alice"], 40_000);
+        attested["dist"]["attestations"] =
+            json!({ "provenance": { "predicateType": "https://slsa.dev/provenance/v1" } });
+        let mut hijacked = release("mallory", &["alice", "mallory"], 900_000);
+        hijacked["scripts"] = json!({ "postinstall": "node setup.js" });
+
+        let packument = json!({
+            "name": "widget",
+            "time": {
+                "1.0.0": "2024-01-01T00:00:00.000Z",
+                "1.0.1": "2024-06-01T00:00:00.000Z",
+                "1.0.2": "2025-03-01T00:00:00.000Z",
+            },
+            "versions": {
+                "1.0.0": release("alice", &["alice"], 38_000),
+                "1.0.1": attested,
+                "1.0.2": hijacked,
+            }
+        });
+
+        let signals = analyze(&packument, "1.0.2");
+        assert_eq!(
+            kinds(&signals),
+            vec![
+                "maintainer-change",
+                "new-publisher",
+                "provenance-dropped",
+                "new-install-script",
+                "size-jump"
+            ]
+        );
+        assert_eq!(
+            signals[0].description,
+            "maintainers changed since 1.0.1: +mallory"
+        );
+        assert!(signals[2]
+            .description
+            .contains("1.0.1 had build attestations"));
+        assert!(signals[4].description.contains("22x since 1.0.1"));
+        assert_eq!(risk_score(&signals), 15 + 25 + 40 + 30 + 15);
+
+        // The release before it was fine
+        assert!(analyze(&packument, "1.0.1").is_empty());
+    }
+
+    #[test]
+    fn deprecated_version_without_repository_is_flagged() {
+        let packument = json!({
+            "versions": {
+                "2.0.0": {
+                    "deprecated": "use widget-ng instead",
+                    "dist": {},
+                }
+            }
+        });
+        let signals = analyze(&packument, "2.0.0");
+        assert_eq!(kinds(&signals), vec!["deprecated", "no-repository"]);
+        assert_eq!(
+            signals[0].description,
+            "2.0.0 is deprecated: use widget-ng instead"
+        );
+
+        assert!(analyze(&packument, "9.9.9").is_empty());
+    }
+
+    #[test]
+    fn falls_back_to_semver_order_without_publish_times() {
+        let mut second = release("alice", &["alice"], 41_000);
+        second["hasInstallScript"] = json!(true);
+        let packument = json!({
+            "versions": {
+                "1.1.0": second,
+                "1.0.0": release("alice", &["alice"], 40_000),
+            }
+        });
+        let signals = analyze(&packument, "1.1.0");
+        assert_eq!(kinds(&signals), vec!["new-install-script"]);
+        assert_eq!(
+            signals[0].description,
+            "install script first appears in 1.1.0 (1.0.0 had none)"
+        );
+    }
+}
